@@ -0,0 +1,115 @@
+use crate::parsing::ast::expression::{Atom, BinOp, ExprTail, Expression};
+
+/// An expression's chain of binary operators. The parser nests each operator's right side in
+/// the tail of the left one, so `a + b * c ? x : y` is split into the operands `a`, `b` and `c`,
+/// the operators `+` and `*`, and the branches of the `?:` whose condition is the rest.
+pub struct Chain<'a> {
+    /// Each operand along with the calls, member accesses and other tails that bind tighter
+    /// than any operator
+    pub operands: Vec<Expression>,
+    pub ops: Vec<&'a BinOp>,
+    /// The branches of a `?:` that ends the chain
+    pub ternary: Option<(&'a Expression, &'a Expression)>,
+}
+
+impl<'a> Chain<'a> {
+    /// A unary operator or cast is parsed with the whole rest of the chain as its operand, so it
+    /// is moved back onto the first operand, as C reads it: `(long)a * b` converts just `a`.
+    pub fn split(expression: &'a Expression) -> Self {
+        let mut chain = Chain { operands: Vec::new(), ops: Vec::new(), ternary: None };
+        chain.push(expression);
+        chain
+    }
+
+    fn push(&mut self, expression: &'a Expression) {
+        if let Atom::UnaryOperation { value, .. } | Atom::TypeCast { value, .. } = &expression.atom {
+            let start = self.operands.len();
+            self.push(value);
+            let first = Box::new(std::mem::replace(&mut self.operands[start], Expression::new(Atom::Int(0), ExprTail::None)));
+            let atom = match &expression.atom {
+                Atom::UnaryOperation { op, .. } => Atom::UnaryOperation { op: op.clone(), value: first },
+                Atom::TypeCast { typ, .. } => Atom::TypeCast { typ: typ.clone(), value: first },
+                _ => unreachable!(),
+            };
+            // The operand took the rest of the chain with it
+            self.operands[start] = Expression::new(atom, ExprTail::None);
+            return;
+        }
+        let mut operand = Expression::new(expression.atom.clone(), ExprTail::None);
+        let mut tail = &expression.tail;
+        loop {
+            match tail {
+                ExprTail::None => break,
+                ExprTail::BinaryOp { op, right, .. } => {
+                    self.operands.push(operand);
+                    self.ops.push(op);
+                    return self.push(right);
+                },
+                ExprTail::TernaryConditional { second, third, .. } => {
+                    self.ternary = Some((second, third));
+                    break;
+                },
+                ExprTail::Call { next, .. } | ExprTail::MemberAccess { next, .. } | ExprTail::PointerAccess { next, .. }
+                    | ExprTail::Index { next, .. } | ExprTail::Slice { next, .. } | ExprTail::SafeAccess { next, .. }
+                    | ExprTail::Coalesce { next, .. } => {
+                    operand = Expression::new(operand.atom, operand.tail.append(without_next(tail)));
+                    tail = next;
+                },
+            }
+        }
+        self.operands.push(operand);
+    }
+
+    /// Combines the operands by the precedence of the operators between them, after turning
+    /// each into a `T` with `operand`: `a + b * c` is `combine(a, +, combine(b, *, c))`. A
+    /// fallible `T` like `Result` lets `combine` pass on the first error.
+    pub fn regroup<T>(self, operand: impl FnMut(Expression) -> T, mut combine: impl FnMut(T, &BinOp, T) -> T) -> T {
+        let mut operands = self.operands.into_iter().map(operand);
+        let mut values = vec![operands.next().unwrap()];
+        let mut pending: Vec<&BinOp> = Vec::new();
+        let mut reduce = |values: &mut Vec<T>, pending: &mut Vec<&BinOp>| {
+            let right = values.pop().unwrap();
+            let left = values.pop().unwrap();
+            values.push(combine(left, pending.pop().unwrap(), right));
+        };
+        for (op, operand) in self.ops.into_iter().zip(operands) {
+            while pending.last().is_some_and(|top| precedence(top) >= precedence(op)) {
+                reduce(&mut values, &mut pending);
+            }
+            pending.push(op);
+            values.push(operand);
+        }
+        while !pending.is_empty() {
+            reduce(&mut values, &mut pending);
+        }
+        values.pop().unwrap()
+    }
+}
+
+/// The first link of a tail, without the rest of the chain
+pub(crate) fn without_next(tail: &ExprTail) -> ExprTail {
+    let mut head = tail.clone();
+    match &mut head {
+        ExprTail::Call { next, .. } | ExprTail::BinaryOp { next, .. } | ExprTail::MemberAccess { next, .. }
+            | ExprTail::PointerAccess { next, .. } | ExprTail::Index { next, .. } | ExprTail::Slice { next, .. }
+            | ExprTail::TernaryConditional { next, .. } | ExprTail::SafeAccess { next, .. }
+            | ExprTail::Coalesce { next, .. } => **next = ExprTail::None,
+        ExprTail::None => (),
+    }
+    head
+}
+
+pub(crate) fn precedence(op: &BinOp) -> u8 {
+    match op {
+        BinOp::Mul | BinOp::Div | BinOp::Mod => 10,
+        BinOp::Add | BinOp::Sub => 9,
+        BinOp::LeftShift | BinOp::RightShift => 8,
+        BinOp::IsLT | BinOp::IsLTE | BinOp::IsGT | BinOp::IsGTE => 7,
+        BinOp::IsEqual | BinOp::IsNotEqual => 6,
+        BinOp::BitAnd => 5,
+        BinOp::BitXor => 4,
+        BinOp::BitOr => 3,
+        BinOp::LogicAnd => 2,
+        BinOp::LogicOr => 1,
+    }
+}
@@ -0,0 +1,569 @@
+use std::{collections::HashMap, fmt};
+
+use crate::{codegen::simple::SimpleCodeGen, parsing::ast::{attribute::Attribute, enumm::Enum, expression::{Atom, BinOp, ExprTail, Expression, UnaryOp}, program::Program, sstruct::{Struct, StructMember}, types::{Type, TypeType}}};
+
+use super::{chain::Chain, symbols::SymbolTable};
+
+/// The sizes of builtin types that `sizeof` assumes, since the transpiler doesn't
+/// know which compiler the output will be built with
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum DataModel {
+    /// 32-bit `int`, `long` and pointers, as on 32-bit x86
+    Ilp32,
+    /// 32-bit `int` and `long` with 64-bit pointers, as on 64-bit Windows
+    Llp64,
+    /// 32-bit `int` with 64-bit `long` and pointers, as on 64-bit Linux and macOS
+    #[default]
+    Lp64,
+}
+
+impl DataModel {
+    fn pointer_size(&self) -> u64 {
+        match self {
+            DataModel::Ilp32 => 4,
+            DataModel::Llp64 | DataModel::Lp64 => 8,
+        }
+    }
+
+    fn long_size(&self) -> u64 {
+        match self {
+            DataModel::Ilp32 | DataModel::Llp64 => 4,
+            DataModel::Lp64 => 8,
+        }
+    }
+
+    fn long_double_size(&self) -> u64 {
+        match self {
+            DataModel::Ilp32 => 12,
+            DataModel::Llp64 => 8,
+            DataModel::Lp64 => 16,
+        }
+    }
+
+    /// 32-bit x86 only aligns struct fields to 4 bytes, even 8-byte ones
+    fn max_field_align(&self) -> u64 {
+        match self {
+            DataModel::Ilp32 => 4,
+            DataModel::Llp64 | DataModel::Lp64 => 16,
+        }
+    }
+}
+
+/// The value of a constant expression. Integers remember the size and signedness of
+/// their C type, so that arithmetic wraps and overflows where C's would.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ConstValue {
+    Int {
+        value: i128,
+        bits: u32,
+        unsigned: bool,
+    },
+    Float(f64),
+}
+
+impl ConstValue {
+    pub fn int(value: i128) -> Self {
+        ConstValue::Int { value, bits: 32, unsigned: false }
+    }
+
+    pub fn is_true(&self) -> bool {
+        match self {
+            ConstValue::Int { value, .. } => *value != 0,
+            ConstValue::Float(value) => *value != 0.0,
+        }
+    }
+
+    /// A literal with this value, or `None` if no literal can spell it
+    pub fn to_atom(&self) -> Option<Atom> {
+        match self {
+            ConstValue::Int { value, .. } => {
+                if let Ok(value) = i32::try_from(*value) {
+                    Some(Atom::Int(value))
+                } else {
+                    i64::try_from(*value).ok().map(Atom::TrueLong)
+                }
+            },
+            ConstValue::Float(value) => Some(Atom::Double(*value)),
+        }
+    }
+}
+
+impl fmt::Display for ConstValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConstValue::Int { value, .. } => write!(f, "{}", value),
+            ConstValue::Float(value) => write!(f, "{:?}", value),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConstError {
+    /// The expression can't be worked out while transpiling, e.g. because it reads a variable
+    NotConstant(String),
+    /// The expression is constant but has no valid value, e.g. because it divides by zero
+    Invalid(String),
+}
+
+impl fmt::Display for ConstError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConstError::NotConstant(reason) | ConstError::Invalid(reason) => write!(f, "{}", reason),
+        }
+    }
+}
+
+impl std::error::Error for ConstError {}
+
+type ConstResult<T> = Result<T, ConstError>;
+
+fn not_constant<T>(reason: String) -> ConstResult<T> {
+    Err(ConstError::NotConstant(reason))
+}
+
+fn invalid<T>(reason: String) -> ConstResult<T> {
+    Err(ConstError::Invalid(reason))
+}
+
+#[derive(Clone, Copy)]
+struct Layout {
+    size: u64,
+    align: u64,
+}
+
+enum Scalar {
+    Integer {
+        size: u64,
+        unsigned: bool,
+    },
+    Floating(u64),
+    Bool,
+}
+
+/// Evaluates constant expressions at transpile time: integer and floating point arithmetic,
+/// casts, `sizeof` under a `DataModel`, enum variants and module-level constants.
+/// Constants and struct layouts have to be added in declaration order, as C requires.
+pub struct ConstEvaluator {
+    data_model: DataModel,
+    symbols: SymbolTable,
+    /// Enum variants and module-level constants, keyed by mangled name
+    constants: HashMap<String, ConstValue>,
+    /// The layouts of structs and unions declared so far, keyed by mangled name
+    layouts: HashMap<String, Layout>,
+}
+
+impl ConstEvaluator {
+    pub fn new(program: &Program, data_model: DataModel) -> Self {
+        ConstEvaluator {
+            data_model,
+            symbols: SymbolTable::collect(program),
+            constants: HashMap::new(),
+            layouts: HashMap::new(),
+        }
+    }
+
+    pub fn define(&mut self, name: String, value: ConstValue) {
+        self.constants.insert(name, value);
+    }
+
+    /// Works out the layout of a struct or union so that `sizeof` can be used on it.
+    /// Structs with fields of unknown size are skipped, and just can't be measured.
+    pub fn declare_struct(&mut self, name: String, s: &Struct) {
+        if let Ok(layout) = self.struct_layout(s) {
+            self.layouts.insert(name, layout);
+        }
+    }
+
+    /// Computes the value of each variant of an enum, defining them as constants.
+    /// Variants without an explicit value are one more than the previous one.
+    pub fn evaluate_enum(&mut self, e: &Enum) -> anyhow::Result<Vec<i32>> {
+        let mut values = Vec::new();
+        let mut next = 0;
+        for entry in &e.entries {
+            let value = match &entry.value {
+                Some(expression) => match self.evaluate(expression) {
+                    Ok(ConstValue::Int { value, .. }) => value,
+                    Ok(ConstValue::Float(_)) => anyhow::bail!("The value of enum variant '{}' must be an integer", entry.name),
+                    Err(e) => anyhow::bail!("Cannot compute the value of enum variant '{}': {}", entry.name, e),
+                },
+                None => next,
+            };
+            let Ok(value) = i32::try_from(value) else {
+                anyhow::bail!("The value of enum variant '{}' ({}) doesn't fit in an int", entry.name, value);
+            };
+            self.define(entry.name.clone(), ConstValue::int(value as i128));
+            values.push(value);
+            next = value as i128 + 1;
+        }
+        Ok(values)
+    }
+
+    pub fn evaluate(&self, expression: &Expression) -> ConstResult<ConstValue> {
+        let chain = Chain::split(expression);
+        let ternary = chain.ternary;
+        let value = chain.regroup(|operand| self.operand_value(&operand), |left, op, right| self.binary(left?, op, right?))?;
+
+        // Only the chosen branch is evaluated, so the other may be invalid
+        match ternary {
+            Some((second, third)) => self.evaluate(if value.is_true() { second } else { third }),
+            None => Ok(value),
+        }
+    }
+
+    /// Converts a value to the given type, as a cast or an initialization would
+    pub fn convert(&self, value: ConstValue, typ: &Type) -> ConstResult<ConstValue> {
+        let resolved = self.symbols.resolve_typedefs(typ);
+        if resolved.pointer_layers() > 0 {
+            return not_constant(format!("conversions to pointer type '{}' aren't constant", typ.describe()));
+        }
+        let Some(scalar) = self.scalar(&resolved) else {
+            return not_constant(format!("'{}' isn't an arithmetic type", typ.describe()));
+        };
+        Ok(match (scalar, value) {
+            (Scalar::Floating(_), ConstValue::Int { value, .. }) => ConstValue::Float(value as f64),
+            (Scalar::Floating(_), float) => float,
+            (Scalar::Bool, value) => ConstValue::Int { value: value.is_true() as i128, bits: 8, unsigned: true },
+            (Scalar::Integer { size, unsigned }, ConstValue::Int { value, .. }) => Self::wrap(value, size as u32 * 8, unsigned),
+            (Scalar::Integer { size, unsigned }, ConstValue::Float(value)) => {
+                let truncated = value.trunc();
+                let bits = size as u32 * 8;
+                if !truncated.is_finite() || !Self::fits(truncated as i128, bits, unsigned) {
+                    return invalid(format!("{:?} is out of range for '{}'", value, typ.describe()));
+                }
+                ConstValue::Int { value: truncated as i128, bits, unsigned }
+            },
+        })
+    }
+
+    pub fn is_enum(&self, typ: &Type) -> bool {
+        let resolved = self.symbols.resolve_typedefs(typ);
+        resolved.pointer_layers() == 0 && (matches!(resolved.typetype, TypeType::Enum) || self.symbols.is_enum(&resolved.name))
+    }
+
+    pub fn size_of(&self, typ: &Type) -> ConstResult<u64> {
+        Ok(self.layout(typ)?.size)
+    }
+
+    // == Expressions ==
+
+    /// The value of an operand of a chain of binary operators. The atom is only evaluated
+    /// without a tail, so that e.g. a call explains why it isn't constant.
+    fn operand_value(&self, operand: &Expression) -> ConstResult<ConstValue> {
+        match &operand.tail {
+            ExprTail::None => self.atom_value(&operand.atom),
+            // Operators were split off by `Chain::split`
+            ExprTail::BinaryOp { .. } | ExprTail::TernaryConditional { .. } => unreachable!(),
+            ExprTail::Call { .. } => not_constant(format!("function calls such as '{}' aren't constant", operand.generate())),
+            ExprTail::MemberAccess { .. } | ExprTail::PointerAccess { .. } | ExprTail::Index { .. } => {
+                not_constant(format!("'{}' reads from memory, so it isn't constant", operand.generate()))
+            },
+            ExprTail::Slice { .. } => not_constant(String::from("slices aren't constant")),
+            ExprTail::SafeAccess { member, .. } => not_constant(format!("'?->{}' reads from memory, so it isn't constant", member)),
+            ExprTail::Coalesce { .. } => not_constant(String::from("'??' checks a pointer at run time, so it isn't constant")),
+        }
+    }
+
+    fn atom_value(&self, atom: &Atom) -> ConstResult<ConstValue> {
+        match atom {
+            // Character constants are `int`s in C
+            Atom::Char(c) => Ok(ConstValue::int(*c as i128)),
+            Atom::Short(v) => Ok(ConstValue::Int { value: *v as i128, bits: 16, unsigned: false }),
+            Atom::Int(v) | Atom::Octal(v) => Ok(ConstValue::int(*v as i128)),
+            Atom::TrueLong(v) => Ok(ConstValue::Int { value: *v as i128, bits: self.data_model.long_size() as u32 * 8, unsigned: false }),
+            Atom::Float(v) => Ok(ConstValue::Float(*v as f64)),
+            Atom::Double(v) => Ok(ConstValue::Float(*v)),
+            Atom::Boolean(v) => Ok(ConstValue::int(*v as i128)),
+            Atom::Identifier(ident) => match self.constants.get(&ident.generate()) {
+                Some(value) => Ok(*value),
+                None => not_constant(format!("'{}' isn't an enum variant or a module-level constant", ident.generate())),
+            },
+            Atom::TypeCast { typ, value } => self.convert(self.evaluate(value)?, typ),
+            Atom::UnaryOperation { op, value } => self.unary(op, self.evaluate(value)?),
+            Atom::SizeOf(typ) => Ok(ConstValue::Int { value: self.size_of(typ)? as i128, bits: self.data_model.pointer_size() as u32 * 8, unsigned: true }),
+            Atom::Wrapped(inner) => self.evaluate(inner),
+            Atom::String(_) => not_constant(String::from("a string is a pointer, not a constant number")),
+            Atom::InitList(_) => not_constant(String::from("an initializer list isn't a single number")),
+            Atom::Lambda(_) => not_constant(String::from("a lambda isn't a number")),
+            Atom::VaArg { .. } => not_constant(String::from("'va_arg' reads an argument at run time")),
+            Atom::Interpolated(_) => not_constant(String::from("an interpolated string is built at run time")),
+            Atom::Assign { target, .. } => not_constant(format!("'{}' is assigned at run time", target)),
+            Atom::Embed(_) | Atom::Bytes(_) => not_constant(String::from("an embedded file isn't a single number")),
+            Atom::MacroCall { .. } => unreachable!("macros are expanded before constants are evaluated"),
+        }
+    }
+
+    fn unary(&self, op: &UnaryOp, value: ConstValue) -> ConstResult<ConstValue> {
+        match (op, value) {
+            (UnaryOp::LogicNot, value) => Ok(ConstValue::int(!value.is_true() as i128)),
+            (UnaryOp::Plus, ConstValue::Float(v)) => Ok(ConstValue::Float(v)),
+            (UnaryOp::Minus, ConstValue::Float(v)) => Ok(ConstValue::Float(-v)),
+            (UnaryOp::Plus, int) => Ok(Self::promote(int)),
+            (UnaryOp::Minus, int) => {
+                let ConstValue::Int { value, bits, unsigned } = Self::promote(int) else { unreachable!() };
+                Self::checked(-value, bits, unsigned, "negation")
+            },
+            (UnaryOp::BitNot, ConstValue::Int { .. }) => {
+                let ConstValue::Int { value, bits, unsigned } = Self::promote(value) else { unreachable!() };
+                Ok(Self::wrap(!value, bits, unsigned))
+            },
+            (UnaryOp::BitNot, ConstValue::Float(_)) => invalid(String::from("'~' needs an integer operand")),
+            (UnaryOp::AddressOf | UnaryOp::Dereference, _) => not_constant(String::from("pointers aren't constant numbers")),
+            (UnaryOp::Increment | UnaryOp::Decrement, _) => not_constant(String::from("'++' and '--' modify a variable")),
+        }
+    }
+
+    fn binary(&self, left: ConstValue, op: &BinOp, right: ConstValue) -> ConstResult<ConstValue> {
+        match op {
+            BinOp::LogicAnd => return Ok(ConstValue::int((left.is_true() && right.is_true()) as i128)),
+            BinOp::LogicOr => return Ok(ConstValue::int((left.is_true() || right.is_true()) as i128)),
+            BinOp::LeftShift | BinOp::RightShift => return self.shift(left, op, right),
+            _ => (),
+        }
+
+        let (l, r, bits, unsigned) = match (left, right) {
+            (ConstValue::Int { .. }, ConstValue::Int { .. }) => Self::usual_conversion(left, right),
+            (l, r) => return self.float_binary(Self::as_float(l), op, Self::as_float(r)),
+        };
+        let compare = |result: bool| Ok(ConstValue::int(result as i128));
+        match op {
+            BinOp::Add => Self::checked(l + r, bits, unsigned, "addition"),
+            BinOp::Sub => Self::checked(l - r, bits, unsigned, "subtraction"),
+            BinOp::Mul => Self::checked(l * r, bits, unsigned, "multiplication"),
+            BinOp::Div | BinOp::Mod if r == 0 => invalid(String::from("division by zero")),
+            BinOp::Div => Self::checked(l / r, bits, unsigned, "division"),
+            BinOp::Mod => Self::checked(l % r, bits, unsigned, "remainder"),
+            BinOp::BitAnd => Ok(Self::wrap(l & r, bits, unsigned)),
+            BinOp::BitOr => Ok(Self::wrap(l | r, bits, unsigned)),
+            BinOp::BitXor => Ok(Self::wrap(l ^ r, bits, unsigned)),
+            BinOp::IsEqual => compare(l == r),
+            BinOp::IsNotEqual => compare(l != r),
+            BinOp::IsLT => compare(l < r),
+            BinOp::IsLTE => compare(l <= r),
+            BinOp::IsGT => compare(l > r),
+            BinOp::IsGTE => compare(l >= r),
+            BinOp::LogicAnd | BinOp::LogicOr | BinOp::LeftShift | BinOp::RightShift => unreachable!(),
+        }
+    }
+
+    fn float_binary(&self, l: f64, op: &BinOp, r: f64) -> ConstResult<ConstValue> {
+        let compare = |result: bool| Ok(ConstValue::int(result as i128));
+        match op {
+            BinOp::Add => Ok(ConstValue::Float(l + r)),
+            BinOp::Sub => Ok(ConstValue::Float(l - r)),
+            BinOp::Mul => Ok(ConstValue::Float(l * r)),
+            BinOp::Div if r == 0.0 => invalid(String::from("division by zero")),
+            BinOp::Div => Ok(ConstValue::Float(l / r)),
+            BinOp::IsEqual => compare(l == r),
+            BinOp::IsNotEqual => compare(l != r),
+            BinOp::IsLT => compare(l < r),
+            BinOp::IsLTE => compare(l <= r),
+            BinOp::IsGT => compare(l > r),
+            BinOp::IsGTE => compare(l >= r),
+            _ => invalid(format!("'{}' needs integer operands", op.generate())),
+        }
+    }
+
+    /// The result of a shift has the promoted type of its left operand
+    fn shift(&self, left: ConstValue, op: &BinOp, right: ConstValue) -> ConstResult<ConstValue> {
+        let (ConstValue::Int { .. }, ConstValue::Int { value: amount, .. }) = (left, right) else {
+            return invalid(format!("'{}' needs integer operands", op.generate()));
+        };
+        let ConstValue::Int { value, bits, unsigned } = Self::promote(left) else { unreachable!() };
+        if amount < 0 || amount >= bits as i128 {
+            return invalid(format!("cannot shift a {}-bit value by {}", bits, amount));
+        }
+        match op {
+            BinOp::LeftShift if unsigned => Ok(Self::wrap(value << amount, bits, true)),
+            BinOp::LeftShift if value < 0 => invalid(String::from("left shift of a negative value")),
+            BinOp::LeftShift => Self::checked(value << amount, bits, false, "left shift"),
+            _ => Ok(ConstValue::Int { value: value >> amount, bits, unsigned }),
+        }
+    }
+
+    // == Integer conversions ==
+
+    /// Integers smaller than `int` are promoted to `int` before arithmetic
+    fn promote(value: ConstValue) -> ConstValue {
+        match value {
+            ConstValue::Int { value, bits, .. } if bits < 32 => ConstValue::int(value),
+            other => other,
+        }
+    }
+
+    /// C's usual arithmetic conversions for two integers, giving both values
+    /// converted to the common type along with its size and signedness
+    fn usual_conversion(left: ConstValue, right: ConstValue) -> (i128, i128, u32, bool) {
+        let (ConstValue::Int { bits: l_bits, unsigned: l_unsigned, .. }, ConstValue::Int { bits: r_bits, unsigned: r_unsigned, .. }) = (Self::promote(left), Self::promote(right)) else {
+            unreachable!()
+        };
+        let (bits, unsigned) = if l_bits != r_bits {
+            if l_bits > r_bits { (l_bits, l_unsigned) } else { (r_bits, r_unsigned) }
+        } else {
+            (l_bits, l_unsigned || r_unsigned)
+        };
+        let value = |v: ConstValue| match Self::wrap(Self::int_value(v), bits, unsigned) {
+            ConstValue::Int { value, .. } => value,
+            ConstValue::Float(_) => unreachable!(),
+        };
+        (value(left), value(right), bits, unsigned)
+    }
+
+    fn int_value(value: ConstValue) -> i128 {
+        match value {
+            ConstValue::Int { value, .. } => value,
+            ConstValue::Float(v) => v as i128,
+        }
+    }
+
+    fn as_float(value: ConstValue) -> f64 {
+        match value {
+            ConstValue::Int { value, .. } => value as f64,
+            ConstValue::Float(v) => v,
+        }
+    }
+
+    fn fits(value: i128, bits: u32, unsigned: bool) -> bool {
+        if unsigned {
+            value >= 0 && value < (1i128 << bits)
+        } else {
+            value >= -(1i128 << (bits - 1)) && value < (1i128 << (bits - 1))
+        }
+    }
+
+    /// Unsigned arithmetic wraps around, but signed overflow is undefined in C
+    fn checked(value: i128, bits: u32, unsigned: bool, operation: &str) -> ConstResult<ConstValue> {
+        if !unsigned && !Self::fits(value, bits, false) {
+            return invalid(format!("{} overflows a {}-bit signed integer", operation, bits));
+        }
+        Ok(Self::wrap(value, bits, unsigned))
+    }
+
+    /// Truncates a value to the given number of bits, as two's complement
+    fn wrap(value: i128, bits: u32, unsigned: bool) -> ConstValue {
+        let modulus = 1i128 << bits;
+        let mut wrapped = value.rem_euclid(modulus);
+        if !unsigned && wrapped >= modulus / 2 {
+            wrapped -= modulus;
+        }
+        ConstValue::Int { value: wrapped, bits, unsigned }
+    }
+
+    // == Sizes ==
+
+    fn layout(&self, typ: &Type) -> ConstResult<Layout> {
+        let resolved = self.symbols.resolve_typedefs(typ);
+        let pointer = self.data_model.pointer_size();
+        if resolved.pointer_layers() > 0 {
+            return Ok(Layout { size: pointer, align: pointer });
+        }
+        let name = resolved.name.generate();
+        match &resolved.typetype {
+            // Closures are an `mc::Closure`, a function pointer and an environment pointer
+            TypeType::Closure(_) => return Ok(Layout { size: pointer * 2, align: pointer }),
+            TypeType::FunctionPointer(_) => return Ok(Layout { size: pointer, align: pointer }),
+            TypeType::Enum => return Ok(Layout { size: 4, align: 4 }),
+            _ if self.symbols.enums.contains(&name) => return Ok(Layout { size: 4, align: 4 }),
+            _ => (),
+        }
+        if let Some(layout) = self.layouts.get(&name) {
+            return Ok(*layout);
+        }
+        let size = match self.scalar(&resolved) {
+            Some(Scalar::Integer { size, .. } | Scalar::Floating(size)) => size,
+            Some(Scalar::Bool) => 1,
+            None if self.symbols.structs.contains_key(&name) => {
+                return not_constant(format!("the size of '{}' isn't known where it is used", typ.describe()));
+            },
+            None => return not_constant(format!("the size of '{}' isn't known to the transpiler", typ.describe())),
+        };
+        Ok(Layout { size, align: size.min(self.data_model.max_field_align()) })
+    }
+
+    /// Lays out fields in order with C's padding rules. `@packed` drops the padding by
+    /// aligning every field to 1 byte, while `@aligned(n)` raises an alignment to `n`.
+    fn struct_layout(&self, s: &Struct) -> ConstResult<Layout> {
+        let packed = s.attributes.contains(&Attribute::Packed);
+        let mut size = 0;
+        let mut align = 1;
+        for member in &s.members {
+            let member_layout = match member {
+                StructMember::Field(field) => {
+                    let layout = self.layout(&field.typ)?;
+                    let field_packed = packed || field.attributes.contains(&Attribute::Packed);
+                    let natural = if field_packed { 1 } else { layout.align };
+                    Layout { size: layout.size * field.array_size.unwrap_or(1) as u64, align: natural.max(Self::aligned(&field.attributes)) }
+                },
+                StructMember::AnonStruct(inner) => {
+                    let layout = self.struct_layout(inner)?;
+                    Layout { size: layout.size, align: if packed { 1 } else { layout.align } }
+                },
+                StructMember::Directive(_) => return not_constant(String::from("preprocessor directives decide which fields it has")),
+            };
+            align = align.max(member_layout.align);
+            size = if s.is_union {
+                size.max(member_layout.size)
+            } else {
+                size.next_multiple_of(member_layout.align) + member_layout.size
+            };
+        }
+        let align = align.max(Self::aligned(&s.attributes));
+        Ok(Layout { size: size.next_multiple_of(align), align })
+    }
+
+    /// The alignment asked for by `@aligned(n)`, or 1 if there is none
+    fn aligned(attributes: &[Attribute]) -> u64 {
+        attributes.iter().find_map(|a| match a {
+            Attribute::Aligned(n) => Some(*n as u64),
+            _ => None,
+        }).unwrap_or(1)
+    }
+
+    /// Classifies a builtin arithmetic type, which must already have its typedefs resolved
+    fn scalar(&self, resolved: &Type) -> Option<Scalar> {
+        match resolved.typetype {
+            TypeType::Simple => (),
+            TypeType::Enum => return Some(Scalar::Integer { size: 4, unsigned: false }),
+            _ => return None,
+        }
+        let name = resolved.name.generate();
+        if self.symbols.enums.contains(&name) {
+            return Some(Scalar::Integer { size: 4, unsigned: false });
+        }
+        let pointer = self.data_model.pointer_size();
+        let unsigned = name.starts_with('u') || name == "size_t";
+        let integer = |size| Some(Scalar::Integer { size, unsigned });
+        match name.as_str() {
+            "_Bool" | "bool" => return Some(Scalar::Bool),
+            "int8_t" | "uint8_t" => return integer(1),
+            "int16_t" | "uint16_t" => return integer(2),
+            "int32_t" | "uint32_t" => return integer(4),
+            "int64_t" | "uint64_t" => return integer(8),
+            "size_t" | "ssize_t" | "usize" | "ptrdiff_t" | "intptr_t" | "uintptr_t" => return integer(pointer),
+            _ => (),
+        }
+
+        // Builtin types, possibly spelled with several words like `unsigned long long int`
+        let words = name.split(' ').collect::<Vec<&str>>();
+        if !words.iter().all(|w| ["unsigned", "signed", "short", "long", "int", "char", "float", "double"].contains(w)) {
+            return None;
+        }
+        let unsigned = words.contains(&"unsigned");
+        let longs = words.iter().filter(|w| **w == "long").count();
+        Some(if words.contains(&"float") {
+            Scalar::Floating(4)
+        } else if words.contains(&"double") {
+            Scalar::Floating(if longs > 0 { self.data_model.long_double_size() } else { 8 })
+        } else if words.contains(&"char") {
+            Scalar::Integer { size: 1, unsigned }
+        } else if words.contains(&"short") {
+            Scalar::Integer { size: 2, unsigned }
+        } else {
+            let size = match longs {
+                0 => 4,
+                1 => self.data_model.long_size(),
+                _ => 8,
+            };
+            Scalar::Integer { size, unsigned }
+        })
+    }
+}
@@ -0,0 +1,6 @@
+pub mod chain;
+pub mod consteval;
+pub mod nullability;
+pub mod scopes;
+pub mod symbols;
+pub mod typing;
@@ -0,0 +1,692 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{codegen::simple::SimpleCodeGen, parsing::ast::{expression::{Atom, BinOp, ExprTail, Expression, StringPart, UnaryOp}, function::{Function, Parameter}, identifier::Identifier, lambda::Lambda, program::Program, statement::{ForIterable, IdentifierExpression, Statement}, toplevel::TopLevel, types::Type}};
+use anyhow::{bail, Result};
+
+use super::{chain::{without_next, Chain}, scopes::Scoped, typing::{is_null_constant, ExpressionTyper}};
+
+/// Names of nullable variables
+type Names = HashSet<String>;
+
+/// Checks that pointers declared `T?*` can't be null where they are dereferenced with `->`, `*`
+/// or `[]`, or called.
+///
+/// The check follows the flow of each function: a nullable local variable or parameter is known
+/// not to be null after a test like `p != NULL` or `p`, inside the branch where the test passed,
+/// on the right of `&&` or `||`, in a branch of `?:`, and after an `if` whose other branches
+/// return, break, continue or abort. Assigning to the variable forgets what is known about it,
+/// and so does a loop assigning it anywhere in its body. Other nullable values, like fields and
+/// results of calls, have to be stored in a local variable before they are tested.
+///
+/// With `non_null_pointers`, plain `T*` pointers can't be null either, so values that may be
+/// null can't be stored in them, returned as them or passed to them, including through a branch
+/// of `?:` or an element of an initializer list.
+pub struct NullChecker {
+    typer: ExpressionTyper,
+    /// Parameters of every function, keyed by mangled name
+    functions: HashMap<String, Vec<Parameter>>,
+    non_null_pointers: bool,
+    /// Nullable local variables that can't be null at the current point
+    checked: Names,
+    /// Local variables in scope, innermost scope last, each with whether the variable it
+    /// shadows was checked
+    locals: Vec<HashMap<String, bool>>,
+    /// The return types of the function and lambdas being checked, innermost last
+    return_types: Vec<Type>,
+    /// Labels that the current function jumps to with `goto`, where nothing is known
+    goto_targets: Names,
+}
+
+/// An expression's chain of binary operators, regrouped by precedence
+enum Tree {
+    Operand(Box<Expression>),
+    Binary(Box<Tree>, BinOp, Box<Tree>),
+}
+
+impl NullChecker {
+    pub fn new(program: &Program, non_null_pointers: bool) -> Self {
+        let mut functions = HashMap::new();
+        Self::collect_functions(&program.statements, &String::new(), &mut functions);
+        NullChecker {
+            typer: ExpressionTyper::new(program),
+            functions,
+            non_null_pointers,
+            checked: HashSet::new(),
+            locals: Vec::new(),
+            return_types: Vec::new(),
+            goto_targets: HashSet::new(),
+        }
+    }
+
+    fn collect_functions(toplevels: &Vec<TopLevel>, name_prefix: &String, functions: &mut HashMap<String, Vec<Parameter>>) {
+        for t in toplevels {
+            match t {
+                TopLevel::Function(Function { header, .. }) | TopLevel::FunctionHeader(header) => {
+                    functions.insert(format!("{}{}", name_prefix, header.name), header.params.clone());
+                },
+                TopLevel::Module { name, body } => Self::collect_functions(body, &format!("mod__{}__", name), functions),
+                _ => (),
+            }
+        }
+    }
+
+    pub fn check_program(mut self, program: &Program) -> Result<()> {
+        self.check_toplevels(&program.statements)
+    }
+
+    fn check_toplevels(&mut self, toplevels: &Vec<TopLevel>) -> Result<()> {
+        for t in toplevels {
+            match t {
+                TopLevel::Function(func) => {
+                    self.push_scope();
+                    for param in &func.header.params {
+                        self.declare(&param.name, &param.typ, None);
+                    }
+                    self.return_types.push(func.header.return_type.clone());
+                    self.goto_targets = goto_targets(&func.body);
+                    let result = self.check_body(&func.body);
+                    self.return_types.pop();
+                    self.pop_scope();
+                    self.checked.clear();
+                    result?;
+                },
+                TopLevel::VarDeclaration { typ, name, array_size, right: Some(right) } => {
+                    self.check_expression(right)?;
+                    self.check_initializer(typ, name, *array_size, right)?;
+                },
+                TopLevel::Module { body, .. } => self.check_toplevels(body)?,
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+
+    // == Scopes ==
+
+    /// Declares a local variable, which is checked if it's nullable and `value` can't be null
+    fn declare_with_value(&mut self, name: &String, typ: &Type, array_size: Option<usize>, value: Option<&Expression>) {
+        self.typer.declare(name, typ, array_size);
+        let was_checked = self.checked.contains(name);
+        self.locals.last_mut().unwrap().entry(name.clone()).or_insert(was_checked);
+        let is_checked = self.typer.is_nullable(typ) && value.is_some_and(|v| self.is_non_null(v));
+        self.set_checked(name, is_checked);
+    }
+
+    fn set_checked(&mut self, name: &String, is_checked: bool) {
+        if is_checked {
+            self.checked.insert(name.clone());
+        } else {
+            self.checked.remove(name);
+        }
+    }
+
+    fn is_local(&self, name: &String) -> bool {
+        self.locals.iter().any(|scope| scope.contains_key(name))
+    }
+
+    /// Runs `check` with `facts` known, then forgets them again
+    fn with_facts(&mut self, facts: Names, check: impl FnOnce(&mut Self) -> Result<()>) -> Result<()> {
+        let saved = self.checked.clone();
+        self.checked.extend(facts);
+        let result = check(self);
+        self.checked = saved;
+        result
+    }
+
+    // == Statements ==
+
+    fn check_body(&mut self, body: &[Statement]) -> Result<()> {
+        self.scoped(|s| body.iter().try_for_each(|statement| s.check_statement(statement)))
+    }
+
+    fn check_statement(&mut self, statement: &Statement) -> Result<()> {
+        match statement {
+            Statement::VarDec { typ, name, array_size, right } => {
+                if let Some(right) = right {
+                    self.check_expression(right)?;
+                    self.check_initializer(typ, name, *array_size, right)?;
+                }
+                self.declare_with_value(name, typ, *array_size, right.as_ref());
+            },
+            Statement::Expression(expression) => self.check_expression(expression)?,
+            Statement::VarAssign { identifier, right } => {
+                self.check_expression(right)?;
+                let target = self.check_target(identifier)?;
+                if let Ok(typ) = self.typer.type_of(&target) {
+                    if self.breaks_non_null(&typ, right) {
+                        bail!("Cannot assign '{}', which may be null, to '{}' of type '{}'", right.generate(), target.generate(), typ.describe());
+                    }
+                }
+                if let IdentifierExpression::Standard(name) = identifier {
+                    if self.is_local(name) {
+                        let is_checked = self.is_non_null(right);
+                        self.set_checked(name, is_checked);
+                    }
+                }
+            },
+            Statement::BinOpVarAssign { identifier, right, .. } => {
+                self.check_expression(right)?;
+                self.check_target(identifier)?;
+            },
+            Statement::IncDec { identifier, .. } => {
+                self.check_target(identifier)?;
+            },
+            Statement::Return(Some(value)) => {
+                self.check_expression(value)?;
+                let return_type = self.return_types.last().unwrap().clone();
+                if self.breaks_non_null(&return_type, value) {
+                    bail!("Cannot return '{}', which may be null, from a function returning '{}'", value.generate(), return_type.describe());
+                }
+            },
+            Statement::If { base, elseifs, tail } => {
+                let entry = self.checked.clone();
+                // What is known at the end of each branch that carries on after the `if`
+                let mut ends = Vec::new();
+                // What is known when the next condition is tested, since the earlier ones failed
+                let mut remaining = entry.clone();
+                for branch in std::iter::once(base).chain(elseifs) {
+                    self.checked = remaining.clone();
+                    self.check_expression(&branch.condition)?;
+                    let (when_true, when_false) = self.expression_facts(&branch.condition);
+                    self.checked.extend(when_true);
+                    self.check_body(&branch.body)?;
+                    if !diverges(&branch.body) {
+                        ends.push(self.checked.clone());
+                    }
+                    remaining.extend(when_false);
+                }
+                self.checked = remaining;
+                match tail {
+                    Some(tail) => {
+                        self.check_body(tail)?;
+                        if !diverges(tail) {
+                            ends.push(self.checked.clone());
+                        }
+                    },
+                    None => ends.push(self.checked.clone()),
+                }
+                // If no branch carries on, nothing after the `if` is reached
+                self.checked = ends.into_iter().reduce(|a, b| a.intersection(&b).cloned().collect()).unwrap_or(entry);
+            },
+            Statement::While(condition_body) => {
+                self.enter_loop(&[&condition_body.body]);
+                let entry = self.checked.clone();
+                self.check_expression(&condition_body.condition)?;
+                let (when_true, _) = self.expression_facts(&condition_body.condition);
+                self.checked.extend(when_true);
+                self.check_body(&condition_body.body)?;
+                self.checked = entry;
+            },
+            Statement::DoWhile { condition, body } => {
+                self.enter_loop(&[body]);
+                let entry = self.checked.clone();
+                self.check_body(body)?;
+                self.checked = entry.clone();
+                self.check_expression(condition)?;
+                self.checked = entry;
+            },
+            Statement::For { init, condition, increment, body } => {
+                self.scoped(|s| s.check_for(init, condition, increment, body))?;
+            },
+            Statement::EnumFor { typ, name, body, .. } => {
+                self.enter_loop(&[body]);
+                let entry = self.checked.clone();
+                self.scoped(|s| {
+                    s.declare(name, typ, None);
+                    s.check_body(body)
+                })?;
+                self.checked = entry;
+            },
+            Statement::ForIn { typ, name, iterable, body } => {
+                match iterable {
+                    ForIterable::Range { start, end } => {
+                        self.check_expression(start)?;
+                        self.check_expression(end)?;
+                    },
+                    ForIterable::Array { array, .. } => self.check_expression(array)?,
+                    ForIterable::Slice { slice, .. } => self.check_expression(slice)?,
+                }
+                self.enter_loop(&[body]);
+                let entry = self.checked.clone();
+                self.scoped(|s| {
+                    s.declare(name, typ, None);
+                    s.check_body(body)
+                })?;
+                self.checked = entry;
+            },
+            Statement::Switch { value, cases, default, .. } => {
+                self.check_expression(value)?;
+                let mut bodies = cases.iter().map(|c| &c.body).collect::<Vec<_>>();
+                bodies.extend(default);
+                // Cases can fall through into each other, so each starts from what they all keep
+                self.enter_loop(&bodies);
+                let entry = self.checked.clone();
+                for body in bodies {
+                    self.checked = entry.clone();
+                    self.check_body(body)?;
+                }
+                self.checked = entry;
+            },
+            Statement::Labeled { label, statement } => {
+                if self.goto_targets.contains(label) {
+                    self.checked.clear();
+                }
+                self.check_statement(statement)?;
+            },
+            Statement::Label(label) if self.goto_targets.contains(label) => self.checked.clear(),
+            _ => (),
+        }
+        Ok(())
+    }
+
+    fn check_for(&mut self, init: &Statement, condition: &Expression, increment: &Statement, body: &Vec<Statement>) -> Result<()> {
+        self.check_statement(init)?;
+        self.enter_loop(&[body, &vec![increment.clone()]]);
+        let entry = self.checked.clone();
+        self.check_expression(condition)?;
+        let (when_true, _) = self.expression_facts(condition);
+        self.checked.extend(when_true);
+        self.check_body(body)?;
+        self.checked = entry.clone();
+        self.check_statement(increment)?;
+        self.checked = entry;
+        Ok(())
+    }
+
+    /// Forgets the variables that a loop assigns, since they may have changed when it repeats
+    fn enter_loop(&mut self, bodies: &[&Vec<Statement>]) {
+        for body in bodies {
+            visit_statements(body, &mut |s| match s {
+                Statement::VarAssign { identifier: IdentifierExpression::Standard(name), .. }
+                    | Statement::BinOpVarAssign { identifier: IdentifierExpression::Standard(name), .. }
+                    | Statement::IncDec { identifier: IdentifierExpression::Standard(name), .. } => {
+                    self.checked.remove(name);
+                },
+                _ => (),
+            });
+        }
+    }
+
+    /// Checks the target of an assignment, returning it as an expression.
+    /// `*p = value` dereferences `p`.
+    fn check_target(&mut self, identifier: &IdentifierExpression) -> Result<Expression> {
+        match identifier {
+            IdentifierExpression::Standard(name) => Ok(Expression::identifier(Identifier::Plain(name.clone()))),
+            IdentifierExpression::Pointer(pointer) => {
+                self.check_expression(pointer)?;
+                self.require_non_null(pointer, "dereference")?;
+                Ok(Expression::new(Atom::UnaryOperation { op: UnaryOp::Dereference, value: Box::new(pointer.clone()) }, ExprTail::None))
+            },
+        }
+    }
+
+    // == Expressions ==
+
+    fn check_expression(&mut self, expression: &Expression) -> Result<()> {
+        let (tree, ternary) = split(expression);
+        self.check_tree(&tree)?;
+        if let Some((second, third)) = ternary {
+            let (when_true, when_false) = self.facts(&tree);
+            self.with_facts(when_true, |s| s.check_expression(second))?;
+            self.with_facts(when_false, |s| s.check_expression(third))?;
+        }
+        Ok(())
+    }
+
+    /// The right of `&&` is only evaluated if the left is true, and the right of `||` if it's false
+    fn check_tree(&mut self, tree: &Tree) -> Result<()> {
+        match tree {
+            Tree::Operand(operand) => self.check_operand(operand),
+            Tree::Binary(left, op, right) => {
+                self.check_tree(left)?;
+                let facts = match op {
+                    BinOp::LogicAnd => self.facts(left).0,
+                    BinOp::LogicOr => self.facts(left).1,
+                    _ => HashSet::new(),
+                };
+                self.with_facts(facts, |s| s.check_tree(right))
+            },
+        }
+    }
+
+    fn check_operand(&mut self, operand: &Expression) -> Result<()> {
+        self.check_atom(&operand.atom)?;
+        let mut prefix = Expression::new(operand.atom.clone(), ExprTail::None);
+        let mut tail = &operand.tail;
+        loop {
+            let next = match tail {
+                ExprTail::None => return Ok(()),
+                ExprTail::PointerAccess { next, .. } => {
+                    self.require_non_null(&prefix, "use '->' on")?;
+                    next
+                },
+                ExprTail::Index { inner, next } => {
+                    self.check_expression(inner)?;
+                    self.require_non_null(&prefix, "index")?;
+                    next
+                },
+                ExprTail::Slice { start, end, next } => {
+                    self.check_expression(start)?;
+                    self.check_expression(end)?;
+                    next
+                },
+                ExprTail::Call { body, next, .. } => {
+                    body.iter().try_for_each(|a| self.check_expression(a))?;
+                    self.require_non_null(&prefix, "call")?;
+                    self.check_arguments(&prefix, body)?;
+                    next
+                },
+                ExprTail::MemberAccess { next, .. } => next,
+                ExprTail::SafeAccess { member, next } => {
+                    // The rest of the chain only runs when the pointer isn't null, so it's
+                    // checked as if `?->` were `->`
+                    let access = ExprTail::PointerAccess { member: member.clone(), next: Box::new(ExprTail::None) };
+                    prefix = Expression::new(prefix.atom, prefix.tail.append(access));
+                    tail = next;
+                    continue;
+                },
+                ExprTail::Coalesce { fallback, next } => {
+                    self.check_expression(fallback)?;
+                    next
+                },
+                // Operators were split off by `Chain::split`
+                ExprTail::BinaryOp { .. } | ExprTail::TernaryConditional { .. } => unreachable!(),
+            };
+            prefix = Expression::new(prefix.atom, prefix.tail.append(without_next(tail)));
+            tail = next;
+        }
+    }
+
+    fn check_arguments(&self, callee: &Expression, args: &[Expression]) -> Result<()> {
+        let name = match (&callee.atom, &callee.tail) {
+            (Atom::Identifier(name), ExprTail::None) => name.generate(),
+            _ => return Ok(()),
+        };
+        let Some(params) = self.functions.get(&name) else { return Ok(()) };
+        for (arg, param) in args.iter().zip(params) {
+            if self.breaks_non_null(&param.typ, arg) {
+                bail!("Cannot pass '{}', which may be null, to the parameter '{} {}' of '{}'", arg.generate(), param.typ.describe(), param.name, name);
+            }
+        }
+        Ok(())
+    }
+
+    fn check_atom(&mut self, atom: &Atom) -> Result<()> {
+        match atom {
+            Atom::UnaryOperation { op: UnaryOp::Dereference, value } => {
+                self.check_expression(value)?;
+                self.require_non_null(value, "dereference")
+            },
+            Atom::UnaryOperation { value, .. } | Atom::Wrapped(value) | Atom::TypeCast { value, .. } | Atom::VaArg { list: value, .. } => {
+                self.check_expression(value)
+            },
+            Atom::InitList(values) => values.iter().try_for_each(|v| self.check_expression(v)),
+            Atom::Interpolated(parts) => parts.iter().try_for_each(|p| match p {
+                StringPart::Value { value, .. } => self.check_expression(value),
+                StringPart::Text(_) => Ok(()),
+            }),
+            Atom::Lambda(lambda) => self.check_lambda(lambda),
+            _ => Ok(()),
+        }
+    }
+
+    /// A lambda is checked on its own, except that values it captures by copy are still checked
+    fn check_lambda(&mut self, lambda: &Lambda) -> Result<()> {
+        let outer_checked = std::mem::take(&mut self.checked);
+        let outer_locals = std::mem::take(&mut self.locals);
+        let outer_targets = std::mem::replace(&mut self.goto_targets, goto_targets(&lambda.body));
+        self.push_scope();
+        for capture in &lambda.captures {
+            self.locals.last_mut().unwrap().insert(capture.name.clone(), false);
+            if !capture.by_reference && outer_checked.contains(&capture.name) {
+                self.checked.insert(capture.name.clone());
+            }
+        }
+        for param in &lambda.params {
+            self.declare(&param.name, &param.typ, None);
+        }
+        self.return_types.push(lambda.return_type.clone());
+        let result = self.check_body(&lambda.body);
+        self.return_types.pop();
+        self.pop_scope();
+        self.checked = outer_checked;
+        self.locals = outer_locals;
+        self.goto_targets = outer_targets;
+        result
+    }
+
+    fn require_non_null(&self, value: &Expression, operation: &str) -> Result<()> {
+        if !self.may_be_null(value) {
+            return Ok(());
+        }
+        let hint = match self.narrowable(value) {
+            Some(_) => "compare it against NULL first",
+            None => "store it in a local variable and compare that against NULL first",
+        };
+        bail!("Cannot {} '{}', since it may be null ({})", operation, value.generate(), hint)
+    }
+
+    /// Whether storing `value` in something of type `typ` could put null in a non-null pointer
+    fn breaks_non_null(&self, typ: &Type, value: &Expression) -> bool {
+        self.non_null_pointers && self.typer.is_pointer(typ) && !self.typer.is_nullable(typ) && self.may_be_null(value)
+    }
+
+    /// Checks that initializing a variable doesn't put null in a non-null pointer, including
+    /// through the elements of an initializer list
+    fn check_initializer(&self, typ: &Type, name: &String, array_size: Option<usize>, value: &Expression) -> Result<()> {
+        if let (Atom::InitList(values), ExprTail::None) = (&value.atom, &value.tail) {
+            if let Some((element, element_type)) = self.null_in_init_list(typ, array_size, values) {
+                bail!("Cannot initialize '{} {}' with '{}', since '{}' may be null and initializes a '{}'", typ.describe(), name, value.generate(), element.generate(), element_type.describe());
+            }
+        } else if self.breaks_non_null(typ, value) {
+            bail!("Cannot initialize '{} {}' with '{}', which may be null", typ.describe(), name, value.generate());
+        }
+        Ok(())
+    }
+
+    /// The element of an initializer list that may put null in a non-null pointer, and the type
+    /// it initializes. Elements are matched to the array's elements or the struct's fields in
+    /// order; the check stops at the first one that relies on braces being left out.
+    fn null_in_init_list<'a>(&self, typ: &Type, array_size: Option<usize>, values: &'a [Expression]) -> Option<(&'a Expression, Type)> {
+        let targets = match array_size {
+            Some(_) => vec![(typ.clone(), None); values.len()],
+            None => self.typer.initialized_fields(typ)?,
+        };
+        for (value, (target, target_size)) in values.iter().zip(targets) {
+            match (&value.atom, &value.tail) {
+                (Atom::InitList(inner), ExprTail::None) => {
+                    if let Some(found) = self.null_in_init_list(&target, target_size, inner) {
+                        return Some(found);
+                    }
+                },
+                // An array or struct without its own braces takes several elements
+                _ if target_size.is_some() || self.typer.initialized_fields(&target).is_some() => return None,
+                _ if self.breaks_non_null(&target, value) => return Some((value, target)),
+                _ => (),
+            }
+        }
+        None
+    }
+
+    // == Nullability ==
+
+    /// Whether a value may be null: it's a null constant, or nullable and not checked
+    fn may_be_null(&self, value: &Expression) -> bool {
+        self.may_be_null_knowing(value, &self.checked)
+    }
+
+    /// `may_be_null` with the variables in `checked` known not to be null, which the branches
+    /// of a `?:` add their condition's facts to
+    fn may_be_null_knowing(&self, value: &Expression, checked: &Names) -> bool {
+        if is_null_constant(value) {
+            return true;
+        }
+        if let (tree, Some((second, third))) = split(value) {
+            let (when_true, when_false) = self.facts(&tree);
+            return self.may_be_null_knowing(second, &(checked | &when_true)) || self.may_be_null_knowing(third, &(checked | &when_false));
+        }
+        match (&value.atom, &value.tail) {
+            (Atom::Identifier(Identifier::Plain(name)), ExprTail::None) if checked.contains(name) => false,
+            (Atom::Wrapped(inner), ExprTail::None) => self.may_be_null_knowing(inner, checked),
+            _ => self.typer.type_of(value).is_ok_and(|t| self.typer.is_nullable(&t)),
+        }
+    }
+
+    /// Whether a value is known not to be null. Plain pointers only count with `non_null_pointers`.
+    fn is_non_null(&self, value: &Expression) -> bool {
+        match (&value.atom, &value.tail) {
+            (Atom::UnaryOperation { op: UnaryOp::AddressOf, .. }, ExprTail::None) | (Atom::String(_), ExprTail::None) => true,
+            (Atom::Identifier(Identifier::Plain(name)), ExprTail::None) if self.checked.contains(name) => true,
+            (Atom::Wrapped(inner), ExprTail::None) => self.is_non_null(inner),
+            _ if matches!(self.typer.array_of(value), Ok(Some(_))) => true,
+            _ => self.non_null_pointers && !self.may_be_null(value) && self.typer.type_of(value).is_ok_and(|t| self.typer.is_pointer(&t)),
+        }
+    }
+
+    /// The name of a nullable local variable, which tests can check
+    fn narrowable(&self, value: &Expression) -> Option<String> {
+        match (&value.atom, &value.tail) {
+            (Atom::Identifier(Identifier::Plain(name)), ExprTail::None) if self.is_local(name) => {
+                let typ = self.typer.type_of(value).ok()?;
+                self.typer.is_nullable(&typ).then(|| name.clone())
+            },
+            (Atom::Wrapped(inner), ExprTail::None) => self.narrowable(inner),
+            _ => None,
+        }
+    }
+
+    /// The variables known not to be null when a condition is true, and when it's false
+    fn facts(&self, tree: &Tree) -> (Names, Names) {
+        match tree {
+            Tree::Operand(operand) => {
+                if let Some(name) = self.narrowable(operand) {
+                    return (HashSet::from([name]), HashSet::new());
+                }
+                match (&operand.atom, &operand.tail) {
+                    (Atom::Wrapped(inner), ExprTail::None) => self.expression_facts(inner),
+                    (Atom::UnaryOperation { op: UnaryOp::LogicNot, value }, ExprTail::None) => {
+                        let (when_true, when_false) = self.expression_facts(value);
+                        (when_false, when_true)
+                    },
+                    _ => (HashSet::new(), HashSet::new()),
+                }
+            },
+            Tree::Binary(left, BinOp::LogicAnd, right) => {
+                let ((left_true, left_false), (right_true, right_false)) = (self.facts(left), self.facts(right));
+                (&left_true | &right_true, &left_false & &right_false)
+            },
+            Tree::Binary(left, BinOp::LogicOr, right) => {
+                let ((left_true, left_false), (right_true, right_false)) = (self.facts(left), self.facts(right));
+                (&left_true & &right_true, &left_false | &right_false)
+            },
+            Tree::Binary(left, op @ (BinOp::IsEqual | BinOp::IsNotEqual), right) => {
+                let variable = match (left.as_ref(), right.as_ref()) {
+                    (Tree::Operand(value), Tree::Operand(null)) | (Tree::Operand(null), Tree::Operand(value)) if is_null_constant(null) => {
+                        self.narrowable(value)
+                    },
+                    _ => None,
+                };
+                let facts = variable.into_iter().collect::<Names>();
+                match op {
+                    BinOp::IsNotEqual => (facts, HashSet::new()),
+                    _ => (HashSet::new(), facts),
+                }
+            },
+            Tree::Binary(..) => (HashSet::new(), HashSet::new()),
+        }
+    }
+
+    fn expression_facts(&self, expression: &Expression) -> (Names, Names) {
+        match split(expression) {
+            (tree, None) => self.facts(&tree),
+            (_, Some(_)) => (HashSet::new(), HashSet::new()),
+        }
+    }
+}
+
+impl Scoped for NullChecker {
+    fn push_scope(&mut self) {
+        self.typer.push_scope();
+        self.locals.push(HashMap::new());
+    }
+
+    /// Restores what was known about the variables that the scope's locals shadowed
+    fn pop_scope(&mut self) {
+        self.typer.pop_scope();
+        for (name, was_checked) in self.locals.pop().unwrap() {
+            if was_checked {
+                self.checked.insert(name);
+            } else {
+                self.checked.remove(&name);
+            }
+        }
+    }
+
+    fn declare(&mut self, name: &str, typ: &Type, array_size: Option<usize>) {
+        self.declare_with_value(&String::from(name), typ, array_size, None);
+    }
+}
+
+/// Splits an expression into its binary operators, grouped by precedence, and the branches
+/// of a `?:` if it ends with one. The operator chain is then the condition.
+fn split(expression: &Expression) -> (Tree, Option<(&Expression, &Expression)>) {
+    let chain = Chain::split(expression);
+    let ternary = chain.ternary;
+    let tree = chain.regroup(|operand| Tree::Operand(Box::new(operand)), |left, op, right| Tree::Binary(Box::new(left), op.clone(), Box::new(right)));
+    (tree, ternary)
+}
+
+/// Whether a body never reaches its end, because it returns, jumps away or aborts
+fn diverges(body: &[Statement]) -> bool {
+    body.iter().any(|s| match s {
+        Statement::Return(_) | Statement::Break(_) | Statement::Continue(_) | Statement::Goto(_) => true,
+        Statement::Expression(Expression { atom: Atom::Identifier(Identifier::Plain(name)), tail: ExprTail::Call { .. } }) => {
+            name == "abort" || name == "exit"
+        },
+        Statement::If { base, elseifs, tail: Some(tail) } => {
+            diverges(&base.body) && elseifs.iter().all(|c| diverges(&c.body)) && diverges(tail)
+        },
+        _ => false,
+    })
+}
+
+fn goto_targets(body: &[Statement]) -> Names {
+    let mut targets = HashSet::new();
+    visit_statements(body, &mut |s| {
+        if let Statement::Goto(label) = s {
+            targets.insert(label.clone());
+        }
+    });
+    targets
+}
+
+/// Calls `visit` on every statement in `body`, including the ones nested in other statements
+fn visit_statements(body: &[Statement], visit: &mut dyn FnMut(&Statement)) {
+    for statement in body {
+        visit(statement);
+        match statement {
+            Statement::If { base, elseifs, tail } => {
+                visit_statements(&base.body, visit);
+                elseifs.iter().for_each(|c| visit_statements(&c.body, visit));
+                if let Some(tail) = tail {
+                    visit_statements(tail, visit);
+                }
+            },
+            Statement::While(condition_body) => visit_statements(&condition_body.body, visit),
+            Statement::DoWhile { body, .. } | Statement::EnumFor { body, .. } | Statement::ForIn { body, .. } => visit_statements(body, visit),
+            Statement::For { init, increment, body, .. } => {
+                visit(init);
+                visit(increment);
+                visit_statements(body, visit);
+            },
+            Statement::Switch { cases, default, .. } => {
+                cases.iter().for_each(|c| visit_statements(&c.body, visit));
+                if let Some(default) = default {
+                    visit_statements(default, visit);
+                }
+            },
+            Statement::Labeled { statement, .. } => visit_statements(std::slice::from_ref(statement), visit),
+            _ => (),
+        }
+    }
+}
@@ -0,0 +1,163 @@
+use crate::parsing::ast::{expression::Expression, statement::{CaseStatement, ConditionBody, ForIterable, IdentifierExpression, Statement}, types::Type};
+use anyhow::Result;
+
+/// Something that keeps track of the local variables in scope while it walks a function body.
+/// Every block has a scope of its own, and so does each loop: a `for` loop because the
+/// declaration in its init statement is only visible inside the loop, and a `for`-in or enum
+/// loop for its variable.
+pub trait Scoped {
+    fn push_scope(&mut self);
+
+    fn pop_scope(&mut self);
+
+    /// Declares a local variable, or the variable of a loop, in the innermost scope
+    fn declare(&mut self, name: &str, typ: &Type, array_size: Option<usize>);
+
+    /// Runs `visit` in a scope of its own, which is closed again even if it fails
+    fn scoped<T>(&mut self, visit: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> where Self: Sized {
+        self.push_scope();
+        let result = visit(self);
+        self.pop_scope();
+        result
+    }
+}
+
+/// A pass that rewrites function bodies one statement at a time. The walk is shared:
+/// `walk_statement` passes every expression of a statement to `lower_expression` and lowers
+/// the bodies nested in it, each in its own scope. A pass implements `lower_expression` and the
+/// `Scoped` hooks, and overrides `lower_statement` for the statements it handles itself,
+/// leaving the rest to `walk_statement`.
+pub trait ScopedLowering: Scoped + Sized {
+    fn lower_expression(&mut self, expression: Expression) -> Result<Expression>;
+
+    fn lower_statement(&mut self, statement: Statement) -> Result<Statement> {
+        self.walk_statement(statement)
+    }
+
+    /// Lowers the statements of a block, inside the block's scope
+    fn lower_statements(&mut self, body: Vec<Statement>) -> Result<Vec<Statement>> {
+        body.into_iter().map(|s| self.lower_statement(s)).collect()
+    }
+
+    fn lower_body(&mut self, body: Vec<Statement>) -> Result<Vec<Statement>> {
+        self.scoped(|s| s.lower_statements(body))
+    }
+
+    /// Lowers the statements after a `case` or `default` label
+    fn lower_case_body(&mut self, body: Vec<Statement>) -> Result<Vec<Statement>> {
+        self.lower_body(body)
+    }
+
+    /// Lowers a `for` loop, inside the scope of its init statement
+    fn lower_for(&mut self, init: Statement, condition: Expression, increment: Statement, body: Vec<Statement>) -> Result<Statement> {
+        Ok(Statement::For {
+            init: Box::new(self.lower_statement(init)?),
+            condition: self.lower_expression(condition)?,
+            increment: Box::new(self.lower_statement(increment)?),
+            body: self.lower_body(body)?,
+        })
+    }
+
+    /// Lowers what the loop variable `typ name` iterates over, before the variable is declared
+    fn lower_iterable(&mut self, _typ: &Type, _name: &String, iterable: ForIterable) -> Result<ForIterable> {
+        self.walk_iterable(iterable)
+    }
+
+    /// Lowers the expressions of what a loop iterates over
+    fn walk_iterable(&mut self, iterable: ForIterable) -> Result<ForIterable> {
+        Ok(match iterable {
+            ForIterable::Range { start, end } => {
+                ForIterable::Range { start: Box::new(self.lower_expression(*start)?), end: Box::new(self.lower_expression(*end)?) }
+            },
+            ForIterable::Array { array, length, by_reference } => {
+                ForIterable::Array { array: self.lower_expression(array)?, length, by_reference }
+            },
+            ForIterable::Slice { slice, by_reference } => {
+                ForIterable::Slice { slice: self.lower_expression(slice)?, by_reference }
+            },
+        })
+    }
+
+    /// Lowers the body of a loop with the variable `typ name`
+    fn lower_loop_body(&mut self, typ: &Type, name: &str, body: Vec<Statement>) -> Result<Vec<Statement>> {
+        self.scoped(|s| {
+            s.declare(name, typ, None);
+            s.lower_body(body)
+        })
+    }
+
+    fn lower_condition_body(&mut self, condition_body: ConditionBody) -> Result<ConditionBody> {
+        Ok(ConditionBody { condition: self.lower_expression(condition_body.condition)?, body: self.lower_body(condition_body.body)? })
+    }
+
+    fn lower_identifier_expression(&mut self, identifier: IdentifierExpression) -> Result<IdentifierExpression> {
+        match identifier {
+            IdentifierExpression::Pointer(expression) => Ok(IdentifierExpression::Pointer(self.lower_expression(expression)?)),
+            standard => Ok(standard),
+        }
+    }
+
+    /// Lowers the expressions of a statement and the bodies nested in it, declaring the
+    /// variables it introduces
+    fn walk_statement(&mut self, statement: Statement) -> Result<Statement> {
+        match statement {
+            Statement::VarDec { typ, name, array_size, right } => {
+                let right = right.map(|r| self.lower_expression(r)).transpose()?;
+                self.declare(&name, &typ, array_size);
+                Ok(Statement::VarDec { typ, name, array_size, right })
+            },
+            Statement::Expression(expression) => Ok(Statement::Expression(self.lower_expression(expression)?)),
+            Statement::VarAssign { identifier, right } => {
+                Ok(Statement::VarAssign { identifier: self.lower_identifier_expression(identifier)?, right: self.lower_expression(right)? })
+            },
+            Statement::BinOpVarAssign { identifier, op, right } => {
+                Ok(Statement::BinOpVarAssign { identifier: self.lower_identifier_expression(identifier)?, op, right: self.lower_expression(right)? })
+            },
+            Statement::IncDec { identifier, is_inc } => {
+                Ok(Statement::IncDec { identifier: self.lower_identifier_expression(identifier)?, is_inc })
+            },
+            Statement::Return(expression) => Ok(Statement::Return(expression.map(|e| self.lower_expression(e)).transpose()?)),
+            Statement::If { base, elseifs, tail } => {
+                Ok(Statement::If {
+                    base: self.lower_condition_body(base)?,
+                    elseifs: elseifs.into_iter().map(|c| self.lower_condition_body(c)).collect::<Result<_, _>>()?,
+                    tail: tail.map(|t| self.lower_body(t)).transpose()?,
+                })
+            },
+            Statement::While(condition_body) => Ok(Statement::While(self.lower_condition_body(condition_body)?)),
+            Statement::DoWhile { condition, body } => {
+                let body = self.lower_body(body)?;
+                Ok(Statement::DoWhile { condition: self.lower_expression(condition)?, body })
+            },
+            Statement::For { init, condition, increment, body } => {
+                self.scoped(|s| s.lower_for(*init, condition, *increment, body))
+            },
+            Statement::EnumFor { typ, name, enum_type, body } => {
+                let body = self.lower_loop_body(&typ, &name, body)?;
+                Ok(Statement::EnumFor { typ, name, enum_type, body })
+            },
+            Statement::ForIn { typ, name, iterable, body } => {
+                let iterable = self.lower_iterable(&typ, &name, iterable)?;
+                let body = self.lower_loop_body(&typ, &name, body)?;
+                Ok(Statement::ForIn { typ, name, iterable, body })
+            },
+            Statement::Switch { value, cases, default, no_fallthrough } => {
+                let value = self.lower_expression(value)?;
+                let mut lowered_cases = Vec::new();
+                for case in cases {
+                    lowered_cases.push(CaseStatement { labels: case.labels, body: self.lower_case_body(case.body)? });
+                }
+                Ok(Statement::Switch {
+                    value,
+                    cases: lowered_cases,
+                    default: default.map(|d| self.lower_case_body(d)).transpose()?,
+                    no_fallthrough,
+                })
+            },
+            Statement::Labeled { label, statement } => {
+                Ok(Statement::Labeled { label, statement: Box::new(self.lower_statement(*statement)?) })
+            },
+            other => Ok(other),
+        }
+    }
+}
@@ -0,0 +1,151 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{codegen::simple::SimpleCodeGen, parsing::ast::{identifier::Identifier, program::Program, enumm::Enum, sstruct::{Derive, Struct, StructMember}, toplevel::TopLevel, typedef::TypeDefInner, types::{Type, TypeType}}};
+
+pub struct StructSymbol {
+    pub derives: Vec<Derive>,
+    pub is_union: bool,
+    /// Every field, including those of anonymous inner structs and unions
+    pub fields: Vec<FieldSymbol>,
+    /// The indexes in `fields` that an initializer list sets in order, which skips all but the
+    /// first member of a union. `None` when preprocessor directives decide which fields there are
+    pub positional: Option<Vec<usize>>,
+}
+
+pub struct FieldSymbol {
+    pub name: String,
+    pub typ: Type,
+    pub array_size: Option<usize>,
+}
+
+/// Program-wide information about the named types that have been declared,
+/// keyed by their mangled C name (e.g. `mod__sl__Color`)
+pub struct SymbolTable {
+    pub structs: HashMap<String, StructSymbol>,
+    pub enums: HashSet<String>,
+    /// The type of each enum variant; variants of anonymous enums are `int`s
+    pub enum_constants: HashMap<String, Type>,
+    pub typedefs: HashMap<String, Type>,
+}
+
+impl SymbolTable {
+    pub fn collect(program: &Program) -> Self {
+        let mut table = SymbolTable {
+            structs: HashMap::new(),
+            enums: HashSet::new(),
+            enum_constants: HashMap::new(),
+            typedefs: HashMap::new(),
+        };
+        for t in &program.statements {
+            table.collect_toplevel(t, &String::new());
+        }
+        table
+    }
+
+    fn collect_toplevel(&mut self, t: &TopLevel, name_prefix: &String) {
+        match t {
+            TopLevel::Struct(s) if !s.is_anonymous => self.add_struct(s, format!("{}{}", name_prefix, s.name)),
+            TopLevel::Enum(e) if !e.is_anonymous => self.add_enum(e, Some(format!("{}{}", name_prefix, e.name))),
+            TopLevel::Enum(e) => self.add_enum(e, None),
+            TopLevel::TypeDef(typedef) => {
+                let name = format!("{}{}", name_prefix, typedef.name);
+                match &typedef.typ {
+                    TypeDefInner::Type(typ) => {
+                        self.typedefs.insert(name, typ.clone());
+                    },
+                    TypeDefInner::Struct(s) => self.add_struct(s, name),
+                    TypeDefInner::Enum(e) => self.add_enum(e, Some(name)),
+                    TypeDefInner::FunctionPointer(signature) => {
+                        self.typedefs.insert(name, Type::function_pointer(signature.clone()));
+                    },
+                }
+            },
+            TopLevel::Module { name, body } => {
+                let prefix = format!("mod__{}__", name);
+                for t in body {
+                    self.collect_toplevel(t, &prefix);
+                }
+            },
+            _ => (),
+        }
+    }
+
+    fn add_struct(&mut self, s: &Struct, name: String) {
+        let mut fields = Vec::new();
+        let mut positional = Some(Vec::new());
+        Self::collect_fields(s, &mut fields, &mut positional, true);
+        self.structs.insert(name, StructSymbol {
+            derives: s.derives.clone(),
+            is_union: s.is_union,
+            fields,
+            positional,
+        });
+    }
+
+    /// Adds the fields of `s`, and those that an initializer list sets if `in_order` is true
+    fn collect_fields(s: &Struct, fields: &mut Vec<FieldSymbol>, positional: &mut Option<Vec<usize>>, in_order: bool) {
+        for (i, member) in s.members.iter().enumerate() {
+            // Only a union's first member is initialized
+            let in_order = in_order && (!s.is_union || i == 0);
+            match member {
+                StructMember::Field(field) => {
+                    if let Some(positional) = positional.as_mut().filter(|_| in_order) {
+                        positional.push(fields.len());
+                    }
+                    fields.push(FieldSymbol {
+                        name: field.name.clone(),
+                        typ: field.typ.clone(),
+                        array_size: field.array_size,
+                    });
+                },
+                StructMember::AnonStruct(inner) => Self::collect_fields(inner, fields, positional, in_order),
+                // Fields on either side of an `#if` are all known, since only one of them is compiled
+                StructMember::Directive(_) => *positional = None,
+            }
+        }
+    }
+
+    /// Registers a named enum (by its mangled name) and the types of its variants
+    fn add_enum(&mut self, e: &Enum, name: Option<String>) {
+        let typ = match &name {
+            Some(name) => Type::simple(Identifier::Plain(name.clone()), 0),
+            None => Type::simple(Identifier::Plain(String::from("int")), 0),
+        };
+        for entry in &e.entries {
+            self.enum_constants.insert(entry.name.clone(), typ.clone());
+        }
+        if let Some(name) = name {
+            self.enums.insert(name);
+        }
+    }
+
+    /// Follows typedefs until reaching a type that isn't an alias, adding up pointer layers along the way.
+    /// Qualifiers on an alias apply to its outermost layer, so `const IntPtr` is `int* const`.
+    pub fn resolve_typedefs(&self, typ: &Type) -> Type {
+        let mut resolved = typ.clone();
+        let mut seen = HashSet::new();
+        while let (TypeType::Simple, Some(inner)) = (&resolved.typetype, self.typedefs.get(&resolved.name.generate())) {
+            // Guard against self-referential typedefs
+            if !seen.insert(resolved.name.generate()) {
+                break;
+            }
+            let mut expanded = inner.clone();
+            match expanded.pointers.last_mut() {
+                Some(outer) => outer.extend(resolved.qualifiers.clone()),
+                None => expanded.qualifiers.extend(resolved.qualifiers.clone()),
+            }
+            expanded.pointers.extend(resolved.pointers.clone());
+            expanded.storage = resolved.storage.clone();
+            resolved = expanded;
+        }
+        resolved
+    }
+
+    pub fn get_struct(&self, ident: &Identifier) -> Option<&StructSymbol> {
+        self.structs.get(&ident.generate())
+    }
+
+    pub fn is_enum(&self, ident: &Identifier) -> bool {
+        self.enums.contains(&ident.generate())
+    }
+}
@@ -0,0 +1,637 @@
+use std::collections::HashMap;
+
+use crate::{codegen::simple::SimpleCodeGen, parsing::ast::{expression::{Atom, BinOp, ExprTail, Expression, UnaryOp}, function::{Function, Parameter}, identifier::Identifier, program::Program, sstruct::Derive, toplevel::TopLevel, typedef::TypeDefInner, types::{Qualifier, Type, TypeType}}};
+use anyhow::{bail, Result};
+
+use super::{chain::Chain, symbols::SymbolTable};
+
+#[derive(Clone)]
+pub struct Variable {
+    pub typ: Type,
+    pub array_size: Option<usize>,
+}
+
+/// What an expression refers to, before arrays decay to pointers
+#[derive(Clone)]
+enum Value {
+    Plain(Type),
+    /// An array's element type and length, if it's known
+    Array(Type, Option<usize>),
+    /// A named function, with its return type
+    Function(Type),
+}
+
+/// Works out the C types of expressions, following the usual arithmetic conversions.
+/// Callers track local variables with `push_scope`, `declare` and `pop_scope` as
+/// they walk function bodies.
+pub struct ExpressionTyper {
+    symbols: SymbolTable,
+    /// Return types of every function, keyed by mangled name
+    functions: HashMap<String, Type>,
+    globals: HashMap<String, Variable>,
+    scopes: Vec<HashMap<String, Variable>>,
+}
+
+impl ExpressionTyper {
+    pub fn new(program: &Program) -> Self {
+        let mut typer = ExpressionTyper {
+            symbols: SymbolTable::collect(program),
+            functions: HashMap::new(),
+            globals: HashMap::new(),
+            scopes: Vec::new(),
+        };
+        typer.collect_names(&program.statements, &String::new());
+        typer
+    }
+
+    fn collect_names(&mut self, toplevels: &Vec<TopLevel>, name_prefix: &String) {
+        for t in toplevels {
+            match t {
+                TopLevel::Function(Function { header, .. }) | TopLevel::FunctionHeader(header) => {
+                    self.functions.insert(format!("{}{}", name_prefix, header.name), header.return_type.clone());
+                },
+                TopLevel::VarDeclaration { typ, name, array_size, .. } => {
+                    self.globals.insert(format!("{}{}", name_prefix, name), Variable { typ: typ.clone(), array_size: *array_size });
+                },
+                TopLevel::Enum(e) if !e.is_anonymous => self.add_enum_reflection(format!("{}{}", name_prefix, e.name), e.entries.len()),
+                TopLevel::Struct(s) if !s.is_anonymous => self.add_derived_functions(format!("{}{}", name_prefix, s.name), &s.derives),
+                TopLevel::TypeDef(typedef) => {
+                    let name = format!("{}{}", name_prefix, typedef.name);
+                    match &typedef.typ {
+                        TypeDefInner::Enum(e) => self.add_enum_reflection(name, e.entries.len()),
+                        TypeDefInner::Struct(s) => self.add_derived_functions(name, &s.derives),
+                        TypeDefInner::Type(_) | TypeDefInner::FunctionPointer(_) => (),
+                    }
+                },
+                TopLevel::Module { name, body } => self.collect_names(body, &format!("mod__{}__", name)),
+                _ => (),
+            }
+        }
+    }
+
+    /// The helpers generated alongside every named enum
+    fn add_enum_reflection(&mut self, name: String, count: usize) {
+        let enum_type = Type::simple(Identifier::Plain(name.clone()), 0);
+        self.globals.insert(format!("{}__COUNT", name), Variable { typ: simple("int", 0), array_size: None });
+        self.globals.insert(format!("{}__values", name), Variable { typ: enum_type, array_size: Some(count) });
+        let mut name_type = simple("char", 1);
+        name_type.qualifiers.push(Qualifier::Const);
+        self.globals.insert(format!("{}__names", name), Variable { typ: name_type.clone(), array_size: Some(count) });
+        self.functions.insert(format!("{}__name", name), name_type);
+        self.functions.insert(format!("{}__is_valid", name), simple("_Bool", 0));
+    }
+
+    /// The functions generated by `@derive`, which don't exist yet when types are inferred
+    fn add_derived_functions(&mut self, name: String, derives: &Vec<Derive>) {
+        for derive in derives {
+            let functions = match derive {
+                Derive::Eq => vec![("eq", simple("_Bool", 0))],
+                Derive::Hash => vec![("hash", simple("size_t", 0))],
+                Derive::Debug => vec![("debug", simple("void", 0))],
+                Derive::Clone => vec![("clone", Type::simple(Identifier::Plain(name.clone()), 0))],
+                Derive::Json => vec![("to_json", simple("void", 0)), ("read_json", simple("void", 0)), ("from_json", simple("_Bool", 0))],
+            };
+            for (suffix, return_type) in functions {
+                self.functions.insert(format!("{}__{}", name, suffix), return_type);
+            }
+        }
+    }
+
+    // == Scopes ==
+
+    pub fn push_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    pub fn pop_scope(&mut self) {
+        self.scopes.pop();
+    }
+
+    pub fn declare(&mut self, name: &str, typ: &Type, array_size: Option<usize>) {
+        self.scopes.last_mut().unwrap().insert(String::from(name), Variable { typ: typ.clone(), array_size });
+    }
+
+    pub fn declare_params(&mut self, params: &Vec<Parameter>) {
+        for p in params {
+            self.declare(&p.name, &p.typ, None);
+        }
+    }
+
+    // == Expressions ==
+
+    /// The type of an expression's value, with arrays decayed to pointers
+    pub fn type_of(&self, expression: &Expression) -> Result<Type> {
+        Self::decay(self.value_of(expression)?)
+    }
+
+    fn value_of(&self, expression: &Expression) -> Result<Value> {
+        let chain = Chain::split(expression);
+        if let Some((second, third)) = chain.ternary {
+            for operand in &chain.operands {
+                self.operand_value(operand)?;
+            }
+            let (second, third) = self.branch_types(second, third)?;
+            return Ok(Value::Plain(self.merge_branches(second, third)?));
+        }
+        chain.regroup(|operand| self.operand_value(&operand), |left, op, right| Ok(Value::Plain(self.binary(left?, op, right?)?)))
+    }
+
+    /// The value of an operand of a chain of binary operators
+    fn operand_value(&self, operand: &Expression) -> Result<Value> {
+        let mut current = self.atom_value(&operand.atom)?;
+        let mut tail = &operand.tail;
+        // Whether a `?->` may skip the rest of the chain
+        let mut may_skip = false;
+        loop {
+            match tail {
+                ExprTail::None => return self.skippable(current, may_skip),
+                ExprTail::Call { next, .. } => {
+                    current = self.call_result(current)?;
+                    tail = next;
+                },
+                ExprTail::MemberAccess { member, next } => {
+                    current = self.member(current, member, false)?;
+                    tail = next;
+                },
+                ExprTail::PointerAccess { member, next } => {
+                    current = self.member(current, member, true)?;
+                    tail = next;
+                },
+                ExprTail::SafeAccess { member, next } => {
+                    current = self.member(current, member, true)?;
+                    may_skip = true;
+                    tail = next;
+                },
+                ExprTail::Coalesce { fallback, next } => {
+                    let left = Self::decay(self.skippable(current, may_skip)?)?;
+                    let fallback = match is_null_constant(fallback) {
+                        // `NULL` is a macro, so it takes the type of the other side
+                        true => self.with_nullability(left.clone(), true),
+                        false => self.type_of(fallback)?,
+                    };
+                    current = Value::Plain(self.coalesce(left, fallback, may_skip)?);
+                    may_skip = false;
+                    tail = next;
+                },
+                ExprTail::Index { next, .. } => {
+                    current = Value::Plain(match &current {
+                        Value::Plain(typ) if self.slice_element(typ).is_some() => self.slice_element(typ).unwrap(),
+                        _ => self.element(current)?,
+                    });
+                    tail = next;
+                },
+                ExprTail::Slice { next, .. } => {
+                    current = Value::Plain(Type::slice(match current {
+                        Value::Array(element, _) => element,
+                        Value::Plain(typ) if self.slice_element(&typ).is_some() => self.slice_element(&typ).unwrap(),
+                        other => bail!("'{}' is not an array or slice, so it can't be sliced", Self::decay(other)?.describe()),
+                    }));
+                    tail = next;
+                },
+                // Operators were split off by `Chain::split`
+                ExprTail::BinaryOp { .. } | ExprTail::TernaryConditional { .. } => unreachable!(),
+            }
+        }
+    }
+
+    fn atom_value(&self, atom: &Atom) -> Result<Value> {
+        Ok(Value::Plain(match atom {
+            Atom::Char(_) => simple("char", 0),
+            Atom::Short(_) => simple("short", 0),
+            Atom::Int(_) | Atom::Octal(_) => simple("int", 0),
+            Atom::TrueLong(_) => simple("long", 0),
+            Atom::Float(_) => simple("float", 0),
+            Atom::Double(_) => simple("double", 0),
+            Atom::Boolean(_) => simple("_Bool", 0),
+            Atom::String(_) => simple("char", 1),
+            Atom::Identifier(ident) => return self.identifier_value(ident),
+            Atom::TypeCast { typ, .. } | Atom::VaArg { typ, .. } => typ.clone(),
+            Atom::UnaryOperation { op, value } => self.unary(op, value)?,
+            Atom::SizeOf(_) => simple("size_t", 0),
+            Atom::Wrapped(inner) => return self.value_of(inner),
+            Atom::InitList(_) => bail!("an initializer list has no type on its own"),
+            Atom::Lambda(lambda) => Type::closure(lambda.signature()),
+            Atom::Interpolated(_) => simple("char", 1),
+            Atom::Assign { value, .. } => self.type_of(value)?,
+            Atom::Embed(_) | Atom::Bytes(_) => bail!("an embedded file has no type on its own"),
+            Atom::MacroCall { .. } => unreachable!("macros are expanded before expressions are typed"),
+        }))
+    }
+
+    fn identifier_value(&self, ident: &Identifier) -> Result<Value> {
+        let name = ident.generate();
+        let variable = match ident {
+            Identifier::Plain(plain_name) => self.scopes.iter().rev().find_map(|s| s.get(plain_name)).or_else(|| self.globals.get(&name)),
+            Identifier::Module(_, _) => self.globals.get(&name),
+        };
+        if let Some(variable) = variable {
+            return Ok(match variable.array_size {
+                Some(_) => Value::Array(variable.typ.clone(), variable.array_size),
+                None => Value::Plain(variable.typ.clone()),
+            });
+        }
+        if let Some(return_type) = self.functions.get(&name) {
+            return Ok(Value::Function(return_type.clone()));
+        }
+        if let Some(typ) = self.symbols.enum_constants.get(&name) {
+            return Ok(Value::Plain(typ.clone()));
+        }
+        bail!("'{}' is not declared in this program, so the type must be written out", name)
+    }
+
+    fn unary(&self, op: &UnaryOp, value: &Expression) -> Result<Type> {
+        let operand = self.value_of(value)?;
+        match op {
+            UnaryOp::AddressOf => match operand {
+                Value::Plain(mut typ) => {
+                    typ.pointers.push(Vec::new());
+                    Ok(typ)
+                },
+                Value::Array(..) => bail!("taking the address of an array isn't supported"),
+                Value::Function(_) => bail!("taking the address of a function isn't supported"),
+            },
+            UnaryOp::Dereference => self.element(operand),
+            UnaryOp::LogicNot => Ok(simple("int", 0)),
+            UnaryOp::Increment | UnaryOp::Decrement => Self::decay(operand),
+            UnaryOp::Plus | UnaryOp::Minus | UnaryOp::BitNot => {
+                let typ = Self::decay(operand)?;
+                match self.arithmetic(&typ) {
+                    Some(Arithmetic::Integer(rank, _)) if rank < INT_RANK => Ok(simple("int", 0)),
+                    Some(_) => Ok(typ),
+                    None => bail!("'{}' is not a number", typ.describe()),
+                }
+            },
+        }
+    }
+
+    fn call_result(&self, callee: Value) -> Result<Value> {
+        match callee {
+            Value::Function(return_type) => Ok(Value::Plain(return_type)),
+            Value::Plain(typ) => match self.symbols.resolve_typedefs(&typ).typetype {
+                TypeType::Closure(signature) | TypeType::FunctionPointer(signature) => Ok(Value::Plain(signature.return_type)),
+                _ => bail!("a value of type '{}' can't be called", typ.describe()),
+            },
+            Value::Array(..) => bail!("an array can't be called"),
+        }
+    }
+
+    fn member(&self, value: Value, member: &String, through_pointer: bool) -> Result<Value> {
+        let typ = match (value, through_pointer) {
+            (Value::Array(element, _), true) => element,
+            (value, _) => Self::decay(value)?,
+        };
+        let resolved = self.symbols.resolve_typedefs(&typ);
+        let expected_layers = if through_pointer { 1 } else { 0 };
+        if let TypeType::Slice(element) = &resolved.typetype {
+            let mut pointer = element.without_storage();
+            pointer.pointers.push(Vec::new());
+            return match member.as_str() {
+                "ptr" if resolved.pointer_layers() == expected_layers => Ok(Value::Plain(pointer)),
+                "len" if resolved.pointer_layers() == expected_layers => Ok(Value::Plain(simple("size_t", 0))),
+                _ => bail!("'{}' has no field '{}'", typ.describe(), member),
+            };
+        }
+        if let TypeType::Tuple(elements) = &resolved.typetype {
+            let element = member.strip_prefix('_').and_then(|i| i.parse::<usize>().ok()).and_then(|i| elements.get(i));
+            return match element {
+                Some(element) if resolved.pointer_layers() == expected_layers => Ok(Value::Plain(element.clone())),
+                _ => bail!("'{}' has no field '{}'", typ.describe(), member),
+            };
+        }
+        let fields = match self.symbols.structs.get(&resolved.name.generate()) {
+            Some(s) if resolved.pointer_layers() == expected_layers => &s.fields,
+            _ => bail!("'{}' has no field '{}'", typ.describe(), member),
+        };
+        match fields.iter().find(|f| &f.name == member) {
+            Some(field) if field.array_size.is_some() => Ok(Value::Array(field.typ.clone(), field.array_size)),
+            Some(field) => Ok(Value::Plain(field.typ.clone())),
+            None => bail!("'{}' has no field '{}'", typ.describe(), member),
+        }
+    }
+
+    /// The element types of a tuple, or `None` if `typ` isn't one
+    pub fn tuple_elements(&self, typ: &Type) -> Option<Vec<Type>> {
+        match self.symbols.resolve_typedefs(typ) {
+            Type { typetype: TypeType::Tuple(elements), pointers, .. } if pointers.is_empty() => Some(elements),
+            _ => None,
+        }
+    }
+
+    /// The element type of a slice, or `None` if `typ` isn't one
+    pub fn slice_element(&self, typ: &Type) -> Option<Type> {
+        match self.symbols.resolve_typedefs(typ) {
+            Type { typetype: TypeType::Slice(element), pointers, .. } if pointers.is_empty() => Some(*element),
+            _ => None,
+        }
+    }
+
+    /// The types and array lengths of the fields that an initializer list of a struct sets in
+    /// order, or `None` if `typ` isn't a struct whose fields are all known
+    pub fn initialized_fields(&self, typ: &Type) -> Option<Vec<(Type, Option<usize>)>> {
+        let resolved = self.symbols.resolve_typedefs(typ);
+        if resolved.pointer_layers() > 0 || !matches!(resolved.typetype, TypeType::Simple | TypeType::Struct) {
+            return None;
+        }
+        let symbol = self.symbols.structs.get(&resolved.name.generate())?;
+        let positional = symbol.positional.as_ref()?;
+        Some(positional.iter().map(|&i| (symbol.fields[i].typ.clone(), symbol.fields[i].array_size)).collect())
+    }
+
+    /// Whether `typ` is a pointer that may be null, written `T?*` directly or through a typedef
+    pub fn is_nullable(&self, typ: &Type) -> bool {
+        self.symbols.resolve_typedefs(typ).is_nullable()
+    }
+
+    pub fn is_pointer(&self, typ: &Type) -> bool {
+        self.symbols.resolve_typedefs(typ).pointer_layers() > 0
+    }
+
+    /// The element type and length (if known) of an array, or `None` if the expression isn't an array
+    pub fn array_of(&self, expression: &Expression) -> Result<Option<(Type, Option<usize>)>> {
+        Ok(match self.value_of(expression)? {
+            Value::Array(element, length) => Some((element, length)),
+            _ => None,
+        })
+    }
+
+    /// The type of `*value` or `value[i]`
+    fn element(&self, value: Value) -> Result<Type> {
+        match value {
+            Value::Array(element, _) => Ok(element),
+            other => {
+                let typ = Self::decay(other)?;
+                let resolved = self.symbols.resolve_typedefs(&typ);
+                if resolved.pointer_layers() == 0 {
+                    bail!("'{}' is not a pointer or array", typ.describe());
+                }
+                let mut element = resolved;
+                element.pointers.pop();
+                Ok(element)
+            },
+        }
+    }
+
+    fn binary(&self, left: Value, op: &BinOp, right: Value) -> Result<Type> {
+        let left = Self::decay(left)?;
+        let right = Self::decay(right)?;
+        let is_pointer = |t: &Type| self.symbols.resolve_typedefs(t).pointer_layers() > 0;
+        match op {
+            BinOp::IsEqual | BinOp::IsNotEqual | BinOp::IsLT | BinOp::IsLTE | BinOp::IsGT | BinOp::IsGTE
+                | BinOp::LogicAnd | BinOp::LogicOr => Ok(simple("int", 0)),
+            BinOp::Add if is_pointer(&left) => Ok(left),
+            BinOp::Add if is_pointer(&right) => Ok(right),
+            BinOp::Sub if is_pointer(&left) && is_pointer(&right) => Ok(simple("ptrdiff_t", 0)),
+            BinOp::Sub if is_pointer(&left) => Ok(left),
+            BinOp::LeftShift | BinOp::RightShift => match self.arithmetic(&left) {
+                Some(Arithmetic::Integer(rank, _)) if rank < INT_RANK => Ok(simple("int", 0)),
+                Some(Arithmetic::Integer(_, _)) => Ok(left),
+                _ => bail!("'{}' can't be shifted", left.describe()),
+            },
+            _ => match self.usual_conversion(&left, &right) {
+                Some(result) => Ok(result),
+                None => bail!("'{}' can't be applied to '{}' and '{}'", op.generate(), left.describe(), right.describe()),
+            },
+        }
+    }
+
+    /// The types of the branches of a `?:`. A null constant takes the type of a pointer in the
+    /// other branch, which may then be null.
+    fn branch_types(&self, second: &Expression, third: &Expression) -> Result<(Type, Type)> {
+        let other = match (is_null_constant(second), is_null_constant(third)) {
+            (false, true) => Some(second),
+            (true, false) => Some(third),
+            _ => None,
+        };
+        if let Some(Ok(typ)) = other.map(|other| self.type_of(other)) {
+            if self.is_pointer(&typ) {
+                let typ = self.with_nullability(typ, true);
+                return Ok((typ.clone(), typ));
+            }
+        }
+        Ok((self.type_of(second)?, self.type_of(third)?))
+    }
+
+    fn merge_branches(&self, second: Type, third: Type) -> Result<Type> {
+        if let Some(result) = self.usual_conversion(&second, &third) {
+            return Ok(result);
+        }
+        if second.describe() == third.describe() {
+            return Ok(second);
+        }
+        // Allow `ptr` and `0` in the two branches
+        match (self.arithmetic(&second), self.arithmetic(&third)) {
+            (None, Some(Arithmetic::Integer(_, _))) => Ok(second),
+            (Some(Arithmetic::Integer(_, _)), None) => Ok(third),
+            _ => bail!("the branches have different types, '{}' and '{}'", second.describe(), third.describe()),
+        }
+    }
+
+    /// The value of a chain that a `?->` may have cut short, giving null or zero instead
+    fn skippable(&self, value: Value, may_skip: bool) -> Result<Value> {
+        if !may_skip {
+            return Ok(value);
+        }
+        let typ = Self::decay(value)?;
+        if self.is_pointer(&typ) {
+            return Ok(Value::Plain(self.with_nullability(typ, true)));
+        }
+        match self.arithmetic(&typ) {
+            Some(_) => Ok(Value::Plain(typ)),
+            None => bail!("'?->' gives null or zero when it skips the rest of the chain, but '{}' is neither a pointer nor a number", typ.describe()),
+        }
+    }
+
+    /// The type of `left ?? fallback`, which can only be null if the fallback can
+    fn coalesce(&self, left: Type, fallback: Type, may_skip: bool) -> Result<Type> {
+        if !may_skip && !self.is_pointer(&left) {
+            bail!("'{}' can't be null, so '??' would never use its fallback", left.describe());
+        }
+        let fallback_nullable = !self.is_pointer(&fallback) || self.is_nullable(&fallback);
+        let left = self.with_nullability(left, false);
+        let fallback = self.with_nullability(fallback, false);
+        let merged = self.merge_branches(left, fallback)?;
+        Ok(match self.is_pointer(&merged) {
+            true => self.with_nullability(merged, fallback_nullable),
+            false => merged,
+        })
+    }
+
+    /// A pointer type with its outermost layer marked `?*` or not
+    fn with_nullability(&self, typ: Type, nullable: bool) -> Type {
+        if !self.is_pointer(&typ) || self.is_nullable(&typ) == nullable {
+            return typ;
+        }
+        let mut resolved = self.symbols.resolve_typedefs(&typ);
+        let outer = resolved.pointers.last_mut().unwrap();
+        match nullable {
+            true => outer.push(Qualifier::Nullable),
+            false => outer.retain(|q| *q != Qualifier::Nullable),
+        }
+        resolved
+    }
+
+    fn decay(value: Value) -> Result<Type> {
+        match value {
+            Value::Plain(typ) => Ok(typ),
+            Value::Array(mut element, _) => {
+                element.pointers.push(Vec::new());
+                Ok(element)
+            },
+            Value::Function(_) => bail!("a function can't be used as a value"),
+        }
+    }
+
+    // == Conversions ==
+
+    /// How a value of type `from` is implicitly converted to `to` when passed as an argument,
+    /// or `None` if C++ wouldn't allow it either. Used to choose between overloads.
+    pub fn conversion(&self, from: &Type, to: &Type) -> Option<Conversion> {
+        let from = self.symbols.resolve_typedefs(from).unqualified();
+        let to = self.symbols.resolve_typedefs(to).unqualified();
+        if let (TypeType::Closure(from), TypeType::Closure(to)) = (&from.typetype, &to.typetype) {
+            return (from.describe() == to.describe()).then_some(Conversion::Exact);
+        }
+        if from.generate() == to.generate() {
+            return Some(Conversion::Exact);
+        }
+        if from.pointer_layers() > 0 || to.pointer_layers() > 0 {
+            return Self::pointer_conversion(&from, &to);
+        }
+        let to_enum = self.is_enum(&to);
+        match (self.arithmetic(&from)?, self.arithmetic(&to)?) {
+            (Arithmetic::Integer(rank, _), Arithmetic::Integer(INT_RANK, false)) if !to_enum && (rank < INT_RANK || self.is_enum(&from)) => {
+                Some(Conversion::Promotion)
+            },
+            (Arithmetic::Floating(1), Arithmetic::Floating(2)) => Some(Conversion::Promotion),
+            _ => Some(Conversion::Standard),
+        }
+    }
+
+    /// Pointers convert to pointers to the same type with more qualifiers, and to and from `void*`
+    fn pointer_conversion(from: &Type, to: &Type) -> Option<Conversion> {
+        if from.pointer_layers() == 0 || to.pointer_layers() == 0 {
+            return None;
+        }
+        // Nullability isn't a C qualifier, and is checked by `NullChecker` instead
+        let adds_qualifiers = |from: &Vec<Qualifier>, to: &Vec<Qualifier>| from.iter().all(|q| *q == Qualifier::Nullable || to.contains(q));
+        let is_void = |t: &Type| t.pointer_layers() == 1 && t.name.generate() == "void";
+        if is_void(to) && (from.pointer_layers() > 1 || adds_qualifiers(&from.qualifiers, &to.qualifiers)) {
+            return Some(Conversion::Standard);
+        }
+        if is_void(from) {
+            return Some(Conversion::Standard);
+        }
+        let same_shape = from.name == to.name && from.pointer_layers() == to.pointer_layers()
+            && std::mem::discriminant(&from.typetype) == std::mem::discriminant(&to.typetype);
+        let qualified = adds_qualifiers(&from.qualifiers, &to.qualifiers)
+            && from.pointers.iter().zip(&to.pointers).all(|(from, to)| adds_qualifiers(from, to));
+        (same_shape && qualified).then_some(Conversion::Qualification)
+    }
+
+    fn is_enum(&self, typ: &Type) -> bool {
+        matches!(typ.typetype, TypeType::Enum) || (matches!(typ.typetype, TypeType::Simple) && self.symbols.enums.contains(&typ.name.generate()))
+    }
+
+    // == Arithmetic ==
+
+    fn arithmetic(&self, typ: &Type) -> Option<Arithmetic> {
+        let resolved = self.symbols.resolve_typedefs(typ);
+        if resolved.pointer_layers() > 0 {
+            return None;
+        }
+        let name = resolved.name.generate();
+        match resolved.typetype {
+            TypeType::Enum => return Some(Arithmetic::Integer(INT_RANK, false)),
+            TypeType::Simple if self.symbols.enums.contains(&name) => return Some(Arithmetic::Integer(INT_RANK, false)),
+            TypeType::Simple => (),
+            _ => return None,
+        }
+        if name.contains(' ') {
+            return Some(Self::builtin_arithmetic(&name));
+        }
+        let unsigned = name.starts_with('u') || name == "size_t";
+        match name.as_str() {
+            "float" => Some(Arithmetic::Floating(1)),
+            "double" => Some(Arithmetic::Floating(2)),
+            "_Bool" | "bool" => Some(Arithmetic::Integer(0, true)),
+            "char" | "int8_t" | "uint8_t" => Some(Arithmetic::Integer(1, unsigned)),
+            "short" | "int16_t" | "uint16_t" => Some(Arithmetic::Integer(2, unsigned)),
+            "int" | "signed" | "int32_t" | "uint32_t" => Some(Arithmetic::Integer(INT_RANK, unsigned)),
+            "unsigned" => Some(Arithmetic::Integer(INT_RANK, true)),
+            "long" | "size_t" | "ssize_t" | "usize" | "ptrdiff_t" | "intptr_t" | "uintptr_t" | "int64_t" | "uint64_t" => {
+                Some(Arithmetic::Integer(4, unsigned))
+            },
+            _ => None,
+        }
+    }
+
+    /// Classifies a builtin type spelled with several words, such as `unsigned long long int`
+    fn builtin_arithmetic(name: &str) -> Arithmetic {
+        let words = name.split(' ').collect::<Vec<&str>>();
+        let unsigned = words.contains(&"unsigned");
+        let longs = words.iter().filter(|w| **w == "long").count() as u8;
+        if words.contains(&"double") {
+            Arithmetic::Floating(2 + longs)
+        } else if words.contains(&"char") {
+            Arithmetic::Integer(1, unsigned)
+        } else if words.contains(&"short") {
+            Arithmetic::Integer(2, unsigned)
+        } else {
+            Arithmetic::Integer(INT_RANK + longs, unsigned)
+        }
+    }
+
+    /// C's usual arithmetic conversions, or `None` if either side isn't a number
+    fn usual_conversion(&self, left: &Type, right: &Type) -> Option<Type> {
+        match (self.arithmetic(left)?, self.arithmetic(right)?) {
+            (Arithmetic::Floating(l), Arithmetic::Floating(r)) => Some(if l >= r { left.clone() } else { right.clone() }),
+            (Arithmetic::Floating(_), _) => Some(left.clone()),
+            (_, Arithmetic::Floating(_)) => Some(right.clone()),
+            (Arithmetic::Integer(l, l_unsigned), Arithmetic::Integer(r, r_unsigned)) => {
+                if l < INT_RANK && r < INT_RANK {
+                    Some(simple("int", 0))
+                } else if l > r || (l == r && (l_unsigned || !r_unsigned)) {
+                    Some(left.clone())
+                } else {
+                    Some(right.clone())
+                }
+            },
+        }
+    }
+}
+
+/// The implicit conversions of an argument, from best to worst
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Conversion {
+    Exact,
+    /// Adding `const` or `volatile` to what a pointer points to
+    Qualification,
+    /// Integer promotion, including from an enum, or `float` to `double`
+    Promotion,
+    /// Any other conversion between numbers, or to and from `void*`
+    Standard,
+}
+
+enum Arithmetic {
+    /// Conversion rank and whether the type is unsigned
+    Integer(u8, bool),
+    Floating(u8),
+}
+
+/// The rank of `int`; smaller integer types are promoted to it
+const INT_RANK: u8 = 3;
+
+fn simple(name: &str, pointer_layers: usize) -> Type {
+    Type::simple(Identifier::Plain(String::from(name)), pointer_layers)
+}
+
+/// `NULL`, `0` or either of them cast to a pointer
+pub(crate) fn is_null_constant(value: &Expression) -> bool {
+    match (&value.atom, &value.tail) {
+        (Atom::Identifier(Identifier::Plain(name)), ExprTail::None) => name == "NULL",
+        (Atom::Int(0), ExprTail::None) => true,
+        (Atom::Wrapped(inner), ExprTail::None) | (Atom::TypeCast { value: inner, .. }, ExprTail::None) => is_null_constant(inner),
+        _ => false,
+    }
+}
+
@@ -1,15 +1,27 @@
-use crate::{mutating::mutator::Mutator, parsing::ast::{expression::Expression, program::Program, statement::Statement}};
+use crate::{analysis::{consteval::DataModel, nullability::NullChecker}, mutating::{arguments::ArgumentResolver, attributes::AttributeLowering, closure::ClosureLowering, const_fold::ConstFolding, derive::DeriveExpander, embed::EmbedResolver, enum_reflection::{EnumReflectionResolver, EnumReflectionUsage}, for_in::ForInResolver, inference::LetInference, interpolation::StringInterpolation, labels::LabelLowering, macros::MacroExpander, mutator::Mutator, overload::Overloads, safe_navigation::SafeNavigation, slice::SliceLowering, tests::TestLowering, type_definitions::TypeDefinitions}, parsing::ast::{expression::Expression, function::Function, program::Program, sstruct::Struct, statement::Statement, toplevel::TopLevel}};
 use anyhow::Result;
 use super::simple::ModuleMemberCodeGen;
 
 pub struct CodeGenerator {
     mutator: Mutator,
+    data_model: DataModel,
+    overload_macros: bool,
+    bounds_checks: bool,
+    non_null_pointers: bool,
+    test_mode: bool,
+    dependencies: Vec<String>,
 }
 
 impl CodeGenerator {
     pub fn new() -> Self {
         CodeGenerator {
             mutator: Mutator::new(),
+            data_model: DataModel::default(),
+            overload_macros: false,
+            bounds_checks: false,
+            non_null_pointers: false,
+            test_mode: false,
+            dependencies: Vec::new(),
         }
     }
 
@@ -19,12 +31,99 @@ impl CodeGenerator {
     pub fn add_statement_mutator(&mut self, m: Box<fn(Statement) -> Result<Statement>>) {
         self.mutator.add_statement_mutator(m);
     }
+    pub fn add_function_mutator(&mut self, m: Box<fn(Function) -> Result<Function>>) {
+        self.mutator.add_function_mutator(m);
+    }
+    pub fn add_struct_mutator(&mut self, m: Box<fn(Struct) -> Result<Struct>>) {
+        self.mutator.add_struct_mutator(m);
+    }
+
+    /// Sets the sizes of builtin types that `sizeof` is evaluated with in constant expressions
+    pub fn set_data_model(&mut self, data_model: DataModel) {
+        self.data_model = data_model;
+    }
+
+    /// Also generates a macro for each overloaded function, so that C code including the output
+    /// as a header can call the function by its miniC name
+    pub fn set_overload_macros(&mut self, enabled: bool) {
+        self.overload_macros = enabled;
+    }
+
+    /// Makes indexing a slice abort with the file and line when the index is out of range
+    pub fn set_bounds_checks(&mut self, enabled: bool) {
+        self.bounds_checks = enabled;
+    }
+
+    /// Treats plain `T*` pointers as non-null, so that only `T?*` pointers can hold null
+    pub fn set_non_null_pointers(&mut self, enabled: bool) {
+        self.non_null_pointers = enabled;
+    }
+
+    /// Compiles the `test` blocks, and replaces `main` with one that runs them, instead of
+    /// leaving them out
+    pub fn set_test_mode(&mut self, enabled: bool) {
+        self.test_mode = enabled;
+    }
+
+    /// The files that the code from the last `code_gen` was generated from, besides the source
+    /// file itself, e.g. those read by `embed`
+    pub fn dependencies(&self) -> &Vec<String> {
+        &self.dependencies
+    }
+
+    /// A Makefile rule saying that `target` depends on `dependencies`, so that the generated code
+    /// is rebuilt when an embedded file changes
+    pub fn depfile(&self, target: &str) -> String {
+        let escape = |path: &str| path.replace(' ', "\\ ");
+        let mut s = escape(target);
+        s.push(':');
+        for dependency in &self.dependencies {
+            s.push(' ');
+            s.push_str(escape(dependency).as_str());
+        }
+        s.push('\n');
+        s
+    }
 
     pub fn code_gen(&mut self, program: Program) -> Result<String> {
         let mut s = String::new();
 
-        let mutated_program = self.mutator.mutate_program(program)?;
-        for top in mutated_program.statements {
+        // Macros are expanded first, so that every other pass sees the code they expand to
+        let expanded_program = MacroExpander::new().expand_program(program)?;
+        let tested_program = TestLowering::new(self.test_mode).lower_program(expanded_program)?;
+        let mut embeds = EmbedResolver::new();
+        let embedded_program = embeds.resolve_program(tested_program)?;
+        self.dependencies = embeds.dependencies().clone();
+        let mutated_program = self.mutator.mutate_program(embedded_program)?;
+        // Default values are copied into calls first, so that every later pass sees them
+        let resolved_program = ArgumentResolver::new(&mutated_program)?
+            .into_mutator()
+            .mutate_program(mutated_program)?;
+        let resolved_program = EnumReflectionResolver::new(&resolved_program)
+            .into_mutator()
+            .mutate_program(resolved_program)?;
+        let folded_program = ConstFolding::new(&resolved_program, self.data_model).fold_program(resolved_program)?;
+        let overloads = Overloads::new(&folded_program)?;
+        let mangled_program = overloads.mangle_program(folded_program);
+        let macros = if self.overload_macros { overloads.wrapper_macros() } else { Vec::new() };
+        let inferred_program = LetInference::new(&mangled_program, overloads).infer_program(mangled_program)?;
+        NullChecker::new(&inferred_program, self.non_null_pointers).check_program(&inferred_program)?;
+        let interpolated_program = StringInterpolation::new(&inferred_program).lower_program(inferred_program)?;
+        let navigated_program = SafeNavigation::new(&interpolated_program).lower_program(interpolated_program)?;
+        let sliced_program = SliceLowering::new(&navigated_program, self.bounds_checks).lower_program(navigated_program)?;
+        let lowered_program = ClosureLowering::new(&sliced_program).lower_program(sliced_program)?;
+        let resolved_program = ForInResolver::new(&lowered_program).resolve_program(lowered_program)?;
+        let resolved_program = LabelLowering::new().lower_program(resolved_program)?;
+        let derived_program = DeriveExpander::new(&resolved_program).expand_program(resolved_program)?;
+        let lowered_program = AttributeLowering::new().lower_program(derived_program)?;
+        // Tuple and slice types are only known once everything else is generated, e.g. in hoisted lambdas
+        let lowered_program = TypeDefinitions::new().define_program(lowered_program)?;
+        let mut lowered_program = EnumReflectionUsage::new().mark_program(lowered_program)?;
+        if !macros.is_empty() {
+            lowered_program.statements.insert(0, TopLevel::Import { name: String::from("mc/overload.h"), is_lib: false });
+            lowered_program.statements.extend(macros);
+        }
+        for top in lowered_program.statements {
             s.push_str(top.generate(&String::new()).as_str());
             s.push_str("\n");
         }
@@ -1,3 +1,4 @@
 pub mod parsing;
 pub mod codegen;
 pub mod mutating;
+pub mod analysis;
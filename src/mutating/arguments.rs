@@ -0,0 +1,220 @@
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use crate::{codegen::simple::SimpleCodeGen, parsing::ast::{expression::{Atom, ExprTail, Expression, NamedArgument}, function::{FunctionHeader, Parameter}, identifier::Identifier, program::Program, toplevel::TopLevel}};
+use anyhow::{bail, Result};
+
+use super::{mutator::Mutator, overload::mangle_params};
+
+/// Fills in default parameter values and moves named arguments to their positions, so that
+/// `open("x", mode: 0600)` becomes `open("x", 0, 0600)` for `void open(char* path, int flags = 0, int mode = 0644)`.
+///
+/// Callees are looked up by their mangled name, so `io::open(...)` finds the `open` declared in
+/// module `io`. Defaults are copied into every call that leaves them out and evaluated there, so
+/// names in them that refer to members of the callee's module are qualified with it first.
+pub struct ArgumentResolver {
+    functions: HashMap<String, Signature>,
+}
+
+struct Signature {
+    /// The name the function is called by in miniC, for error messages
+    name: String,
+    params: Vec<Parameter>,
+    is_variadic: bool,
+    /// Whether other declarations have different parameters, which `Overloads` resolves later
+    is_overloaded: bool,
+}
+
+impl Signature {
+    fn has_defaults(&self) -> bool {
+        self.params.iter().any(|p| p.default.is_some())
+    }
+
+    /// The C arguments for a call, in parameter order
+    fn arrange(&self, positional: Vec<Expression>, named: Vec<NamedArgument>) -> Result<Vec<Expression>> {
+        if positional.len() > self.params.len() && !self.is_variadic {
+            bail!("'{}' takes at most {} arguments, but {} were given", self.name, self.params.len(), positional.len());
+        }
+        let mut positional = positional.into_iter();
+        let mut slots: Vec<Option<Expression>> = self.params.iter().map(|_| positional.next()).collect();
+        for argument in named {
+            let Some(i) = self.params.iter().position(|p| p.name == argument.name) else {
+                bail!("'{}' has no parameter named '{}'", self.name, argument.name);
+            };
+            if slots[i].is_some() {
+                bail!("Parameter '{}' of '{}' is given more than once", argument.name, self.name);
+            }
+            slots[i] = Some(argument.value);
+        }
+        let mut arguments = Vec::new();
+        for (slot, param) in slots.into_iter().zip(&self.params) {
+            match slot.or_else(|| param.default.clone()) {
+                Some(argument) => arguments.push(argument),
+                None => bail!("Call to '{}' is missing a value for parameter '{}'", self.name, param.name),
+            }
+        }
+        // Anything left over is passed on to `...`
+        arguments.extend(positional);
+        Ok(arguments)
+    }
+}
+
+impl ArgumentResolver {
+    pub fn new(program: &Program) -> Result<Self> {
+        let mut resolver = ArgumentResolver { functions: HashMap::new() };
+        resolver.declare_toplevels(&program.statements, None)?;
+        Ok(resolver)
+    }
+
+    pub fn into_mutator(self) -> Mutator {
+        let mut mutator = Mutator::new();
+        mutator.add_expression_closure(Box::new(move |e| self.resolve(e)));
+        mutator
+    }
+
+    fn declare_toplevels(&mut self, toplevels: &[TopLevel], module: Option<&String>) -> Result<()> {
+        let members = match module {
+            Some(_) => Self::member_names(toplevels),
+            None => Vec::new(),
+        };
+        for t in toplevels {
+            match t {
+                TopLevel::Function(func) => self.declare(&func.header, module, &members)?,
+                TopLevel::FunctionHeader(header) => self.declare(header, module, &members)?,
+                TopLevel::Module { name, body } => self.declare_toplevels(body, Some(name))?,
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+
+    /// Records a function's parameters. When a function is declared more than once, the
+    /// declaration that gives defaults is used, and all that do must agree on them.
+    fn declare(&mut self, header: &FunctionHeader, module: Option<&String>, members: &[String]) -> Result<()> {
+        let (key, name) = match module {
+            Some(m) => (Identifier::Module(m.clone(), header.name.clone()).generate(), format!("{}::{}", m, header.name)),
+            None => (header.name.clone(), header.name.clone()),
+        };
+        Self::check_defaults(header, &name)?;
+        let mut params = header.params.clone();
+        if let Some(m) = module {
+            for param in &mut params {
+                if let Some(default) = param.default.take() {
+                    param.default = Some(Self::qualify_members(default, m, members)?);
+                }
+            }
+        }
+        let signature = Signature { name, params, is_variadic: header.is_variadic, is_overloaded: false };
+        if let Some(existing) = self.functions.get_mut(&key) {
+            if mangle_params(&existing.params, existing.is_variadic) != mangle_params(&signature.params, signature.is_variadic) {
+                if existing.has_defaults() || signature.has_defaults() {
+                    bail!("Overloaded function '{}' can't have default values", signature.name);
+                }
+                existing.is_overloaded = true;
+                return Ok(());
+            }
+        }
+        match self.functions.get(&key) {
+            Some(existing) if existing.is_overloaded && signature.has_defaults() => {
+                bail!("Overloaded function '{}' can't have default values", signature.name);
+            },
+            Some(existing) if existing.has_defaults() && signature.has_defaults() => {
+                if Self::describe_defaults(existing) != Self::describe_defaults(&signature) {
+                    bail!("'{}' is declared more than once with different default values", signature.name);
+                }
+            },
+            Some(existing) if existing.has_defaults() || !signature.has_defaults() => (),
+            _ => {
+                self.functions.insert(key, signature);
+            },
+        }
+        Ok(())
+    }
+
+    /// The functions and variables declared in a module's body
+    fn member_names(body: &[TopLevel]) -> Vec<String> {
+        body.iter().filter_map(|t| match t {
+            TopLevel::Function(func) => Some(func.header.name.clone()),
+            TopLevel::FunctionHeader(header) => Some(header.name.clone()),
+            TopLevel::VarDeclaration { name, .. } => Some(name.clone()),
+            _ => None,
+        }).collect()
+    }
+
+    /// `value` with the names of `members` qualified with `module`, so that a default value
+    /// means the same in a caller outside the module
+    fn qualify_members(value: Expression, module: &str, members: &[String]) -> Result<Expression> {
+        let mut mutator = Mutator::new();
+        let (module, members) = (module.to_string(), members.to_vec());
+        mutator.add_expression_closure(Box::new(move |e| match e.atom {
+            Atom::Identifier(Identifier::Plain(name)) if members.contains(&name) => {
+                Ok(Expression::new(Atom::Identifier(Identifier::Module(module.clone(), name)), e.tail))
+            },
+            atom => Ok(Expression::new(atom, e.tail)),
+        }));
+        mutator.mutate_expression(value)
+    }
+
+    fn describe_defaults(signature: &Signature) -> Vec<Option<String>> {
+        signature.params.iter().map(|p| p.default.as_ref().map(|d| d.generate())).collect()
+    }
+
+    /// Defaults are evaluated by the caller, where the other parameters don't exist
+    fn check_defaults(header: &FunctionHeader, name: &String) -> Result<()> {
+        let params: Vec<String> = header.params.iter().map(|p| p.name.clone()).collect();
+        for param in &header.params {
+            let Some(default) = &param.default else { continue };
+            let found = Rc::new(RefCell::new(None));
+            let mut mutator = Mutator::new();
+            let (params, found_in_closure) = (params.clone(), found.clone());
+            mutator.add_expression_closure(Box::new(move |e| {
+                if let Atom::Identifier(Identifier::Plain(ident)) = &e.atom {
+                    if params.contains(ident) {
+                        *found_in_closure.borrow_mut() = Some(ident.clone());
+                    }
+                }
+                Ok(e)
+            }));
+            mutator.mutate_expression(default.clone())?;
+            if let Some(other) = found.take() {
+                bail!("The default value of '{}' in '{}' can't use parameter '{}'", param.name, name, other);
+            }
+        }
+        Ok(())
+    }
+
+    fn resolve(&self, expression: Expression) -> Result<Expression> {
+        let Expression { atom, tail } = expression;
+        let tail = match (&atom, tail) {
+            (Atom::Identifier(callee), ExprTail::Call { body, named, next }) => match self.functions.get(&callee.generate()) {
+                Some(signature) if signature.is_overloaded && !named.is_empty() => {
+                    bail!("Named arguments can't be used with overloaded function '{}'", signature.name);
+                },
+                Some(signature) if signature.has_defaults() || !named.is_empty() => {
+                    ExprTail::Call { body: signature.arrange(body, named)?, named: Vec::new(), next }
+                },
+                None if !named.is_empty() => {
+                    bail!("Named arguments can only be used with functions declared in this program, which '{}' isn't", callee.generate());
+                },
+                _ => ExprTail::Call { body, named, next },
+            },
+            (_, tail) => tail,
+        };
+        Self::check_unnamed(&tail)?;
+        Ok(Expression { atom, tail })
+    }
+
+    /// Anything called other than by name, like a closure or a function pointer in a struct,
+    /// has no parameter names to match against
+    fn check_unnamed(tail: &ExprTail) -> Result<()> {
+        match tail {
+            ExprTail::None => Ok(()),
+            ExprTail::Call { named, next, .. } => match named.first() {
+                Some(argument) => bail!("Named arguments can only be used when calling a function by name, not for '{}:'", argument.name),
+                None => Self::check_unnamed(next),
+            },
+            ExprTail::BinaryOp { next, .. } | ExprTail::MemberAccess { next, .. } | ExprTail::PointerAccess { next, .. } |
+            ExprTail::Index { next, .. } | ExprTail::Slice { next, .. } | ExprTail::TernaryConditional { next, .. } |
+            ExprTail::SafeAccess { next, .. } | ExprTail::Coalesce { next, .. } => Self::check_unnamed(next),
+        }
+    }
+}
@@ -0,0 +1,88 @@
+use crate::parsing::ast::{attribute::Attribute, function::{Function, FunctionHeader}, program::Program, sstruct::{Struct, StructMember}, toplevel::TopLevel, typedef::{TypeDef, TypeDefInner}, types::StorageClass};
+use anyhow::{bail, Result};
+
+/// Checks that each `@attribute` is used on something it applies to, and includes the
+/// `mc/attributes.h` macros that they are generated as. `@inline` functions are also
+/// declared `inline`, which GCC expects of functions that are always inlined.
+///
+/// This runs after user mutators, so attributes they add are checked too.
+#[derive(Default)]
+pub struct AttributeLowering {
+    uses_attributes: bool,
+}
+
+impl AttributeLowering {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn lower_program(mut self, program: Program) -> Result<Program> {
+        let mut statements = self.lower_toplevels(program.statements)?;
+        if self.uses_attributes {
+            statements.insert(0, TopLevel::Import { name: String::from("mc/attributes.h"), is_lib: false });
+        }
+        Ok(Program {
+            statements,
+        })
+    }
+
+    fn lower_toplevels(&mut self, toplevels: Vec<TopLevel>) -> Result<Vec<TopLevel>> {
+        let mut result = Vec::new();
+        for t in toplevels {
+            result.push(match t {
+                TopLevel::Function(func) => TopLevel::Function(Function { header: self.lower_header(func.header)?, body: func.body }),
+                TopLevel::FunctionHeader(header) => TopLevel::FunctionHeader(self.lower_header(header)?),
+                TopLevel::Struct(s) => {
+                    self.check_struct(&s, &s.name)?;
+                    TopLevel::Struct(s)
+                },
+                TopLevel::TypeDef(TypeDef { name, typ: TypeDefInner::Struct(s) }) => {
+                    self.check_struct(&s, &name)?;
+                    TopLevel::TypeDef(TypeDef { name, typ: TypeDefInner::Struct(s) })
+                },
+                TopLevel::Module { name, body } => TopLevel::Module { name, body: self.lower_toplevels(body)? },
+                other => other,
+            });
+        }
+        Ok(result)
+    }
+
+    fn lower_header(&mut self, mut header: FunctionHeader) -> Result<FunctionHeader> {
+        for attribute in &header.attributes {
+            if !attribute.applies_to_functions() {
+                bail!("'@{}' can't be used on function '{}', only on structs, unions and their fields", attribute.name(), header.name);
+            }
+        }
+        self.uses_attributes |= !header.attributes.is_empty();
+        if header.attributes.contains(&Attribute::Inline) && !header.return_type.storage.contains(&StorageClass::Inline) {
+            header.return_type.storage.push(StorageClass::Inline);
+        }
+        Ok(header)
+    }
+
+    /// Checks a struct or union, which is named `owner` or is anonymous inside `owner`
+    fn check_struct(&mut self, s: &Struct, owner: &String) -> Result<()> {
+        let kind = if s.is_union { "union" } else { "struct" };
+        for attribute in &s.attributes {
+            if !attribute.applies_to_structs() {
+                bail!("'@{}' can't be used on {} '{}', only on functions", attribute.name(), kind, owner);
+            }
+        }
+        self.uses_attributes |= !s.attributes.is_empty();
+        for member in &s.members {
+            match member {
+                StructMember::Field(field) => {
+                    for attribute in &field.attributes {
+                        if !attribute.applies_to_structs() {
+                            bail!("'@{}' can't be used on field '{}' of '{}', only on functions", attribute.name(), field.name, owner);
+                        }
+                    }
+                    self.uses_attributes |= !field.attributes.is_empty();
+                },
+                StructMember::AnonStruct(inner) => self.check_struct(inner, owner)?,
+                StructMember::Directive(_) => (),
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,137 @@
+//! Shorthands for building AST nodes in passes that generate code
+
+use crate::parsing::ast::{expression::{Atom, BinOp, ExprTail, Expression, UnaryOp}, identifier::Identifier, statement::{ConditionBody, IdentifierExpression, Statement}, types::Type};
+
+pub fn plain(name: &str) -> Identifier {
+    Identifier::Plain(String::from(name))
+}
+
+/// An identifier inside the `mc` standard module
+pub fn mc(name: &str) -> Identifier {
+    Identifier::Module(String::from("mc"), String::from(name))
+}
+
+pub fn ident(name: &str) -> Expression {
+    Expression::identifier(plain(name))
+}
+
+pub fn int(value: i32) -> Expression {
+    Expression::new(Atom::Int(value), ExprTail::None)
+}
+
+pub fn string(value: &str) -> Expression {
+    Expression::new(Atom::String(String::from(value)), ExprTail::None)
+}
+
+pub fn size_of(typ: Type) -> Expression {
+    Expression::new(Atom::SizeOf(typ), ExprTail::None)
+}
+
+/// `base->member`
+pub fn access(base: &str, member: &str) -> Expression {
+    Expression::new(
+        Atom::Identifier(plain(base)),
+        ExprTail::PointerAccess { member: String::from(member), next: Box::new(ExprTail::None) },
+    )
+}
+
+/// `base.member`
+pub fn member(base: &str, member: &str) -> Expression {
+    Expression::new(
+        Atom::Identifier(plain(base)),
+        ExprTail::MemberAccess { member: String::from(member), next: Box::new(ExprTail::None) },
+    )
+}
+
+/// `{a, b, ...}`
+pub fn init_list(values: Vec<Expression>) -> Expression {
+    Expression::new(Atom::InitList(values), ExprTail::None)
+}
+
+/// `value[index]`
+pub fn index(value: Expression, index: Expression) -> Expression {
+    Expression::new(
+        value.atom,
+        value.tail.append(ExprTail::Index { inner: Box::new(index), next: Box::new(ExprTail::None) }),
+    )
+}
+
+pub fn unary(op: UnaryOp, value: Expression) -> Expression {
+    Expression::new(Atom::UnaryOperation { op, value: Box::new(value) }, ExprTail::None)
+}
+
+pub fn address_of(value: Expression) -> Expression {
+    unary(UnaryOp::AddressOf, value)
+}
+
+pub fn wrapped(value: Expression) -> Expression {
+    Expression::new(Atom::Wrapped(Box::new(value)), ExprTail::None)
+}
+
+/// A value that can be cast or tested as a whole, wrapped in parentheses unless it's a single term
+pub fn operand(value: Expression) -> Expression {
+    match &value.atom {
+        Atom::UnaryOperation { .. } | Atom::TypeCast { .. } => wrapped(value),
+        _ if is_postfix(&value.tail) => value,
+        _ => wrapped(value),
+    }
+}
+
+/// Whether `tail` only calls, indexes or accesses members, which bind tighter than a cast
+fn is_postfix(tail: &ExprTail) -> bool {
+    match tail {
+        ExprTail::None => true,
+        ExprTail::Call { next, .. } | ExprTail::Index { next, .. } | ExprTail::MemberAccess { next, .. } | ExprTail::PointerAccess { next, .. } => is_postfix(next),
+        _ => false,
+    }
+}
+
+pub fn cast(typ: Type, value: Expression) -> Expression {
+    Expression::new(Atom::TypeCast { typ, value: Box::new(value) }, ExprTail::None)
+}
+
+pub fn call(func: Identifier, args: Vec<Expression>) -> Expression {
+    Expression::new(Atom::Identifier(func), ExprTail::Call { body: args, named: Vec::new(), next: Box::new(ExprTail::None) })
+}
+
+pub fn binary(left: Expression, op: BinOp, right: Expression) -> Expression {
+    Expression::new(
+        left.atom,
+        left.tail.append(ExprTail::BinaryOp { op, right: Box::new(right), next: Box::new(ExprTail::None) }),
+    )
+}
+
+pub fn ternary(condition: Expression, second: Expression, third: Expression) -> Expression {
+    Expression::new(
+        condition.atom,
+        condition.tail.append(ExprTail::TernaryConditional { second: Box::new(second), third: Box::new(third), next: Box::new(ExprTail::None) }),
+    )
+}
+
+pub fn var_dec(typ: Type, name: &str, right: Option<Expression>) -> Statement {
+    Statement::VarDec { typ, name: String::from(name), array_size: None, right }
+}
+
+/// `target = right`, where `target` is any assignable text (e.g. `this->x`)
+pub fn assign(target: String, right: Expression) -> Statement {
+    Statement::VarAssign { identifier: IdentifierExpression::Standard(target), right }
+}
+
+/// `if (condition) { body }`
+pub fn if_then(condition: Expression, body: Vec<Statement>) -> Statement {
+    Statement::If {
+        base: ConditionBody { condition, body },
+        elseifs: Vec::new(),
+        tail: None,
+    }
+}
+
+/// `for (size_t index = 0; index<count; index++) { body }`
+pub fn for_count(index: &str, count: Expression, body: Vec<Statement>) -> Statement {
+    Statement::For {
+        init: Box::new(var_dec(Type::simple(plain("size_t"), 0), index, Some(int(0)))),
+        condition: binary(ident(index), BinOp::IsLT, count),
+        increment: Box::new(Statement::IncDec { identifier: IdentifierExpression::Standard(String::from(index)), is_inc: true }),
+        body,
+    }
+}
@@ -0,0 +1,635 @@
+use std::collections::HashMap;
+
+use crate::{analysis::{scopes::{Scoped, ScopedLowering}, symbols::SymbolTable}, codegen::simple::SimpleCodeGen, parsing::ast::{expression::{Atom, ExprTail, Expression, UnaryOp}, function::{Function, FunctionHeader, Parameter}, identifier::Identifier, lambda::Lambda, program::Program, sstruct::{Struct, StructField, StructMember}, statement::{ConditionBody, ForIterable, IdentifierExpression, Statement}, toplevel::TopLevel, typedef::TypeDefInner, types::{ClosureSignature, StorageClass, Type, TypeType}}};
+use anyhow::{bail, Result};
+
+use super::{builder::{access, address_of, call, cast, ident, int, mc, plain, size_of, unary, var_dec, wrapped}, mutator::Mutator};
+
+/// The hoisted function's untyped environment parameter
+const ENV_PARAM: &str = "__mc_env";
+/// The environment parameter, cast to the lambda's capture struct
+const SELF: &str = "__mc_self";
+
+#[derive(Clone)]
+struct Local {
+    typ: Type,
+    is_array: bool,
+}
+
+/// Lowers lambdas to plain C. Each lambda body is hoisted into a function taking its
+/// captures through a `void*` environment, and the lambda itself becomes an
+/// `mc::Closure` holding that function and a pointer to a struct of the captures.
+///
+/// The capture struct lives on the stack when the closure provably can't outlive it:
+/// when it's passed straight to a non-`escaping` closure parameter, or stored in a
+/// local that is only ever called or passed on like that. Otherwise it's copied to
+/// the heap and must be released with `mc::closure_free`.
+///
+/// Calls through closure-typed variables are lowered to calls through the stored
+/// function pointer, with the environment as the first argument.
+pub struct ClosureLowering {
+    symbols: SymbolTable,
+    /// Parameter types of every named function, keyed by mangled name
+    functions: HashMap<String, Vec<Type>>,
+    globals: HashMap<String, Type>,
+    uses_closures: bool,
+
+    // State for the top-level function currently being lowered
+    function_name: String,
+    module: Option<String>,
+    lambda_count: usize,
+    scopes: Vec<HashMap<String, Local>>,
+    /// Whether the statement being lowered declares a lambda that can stay on the stack
+    on_stack: bool,
+    /// Capture structs and prototypes, emitted before the function
+    before: Vec<TopLevel>,
+    /// Hoisted lambda bodies, emitted after the function
+    after: Vec<TopLevel>,
+}
+
+impl ClosureLowering {
+    pub fn new(program: &Program) -> Self {
+        let mut lowering = ClosureLowering {
+            symbols: SymbolTable::collect(program),
+            functions: HashMap::new(),
+            globals: HashMap::new(),
+            uses_closures: false,
+            function_name: String::new(),
+            module: None,
+            lambda_count: 0,
+            scopes: Vec::new(),
+            on_stack: false,
+            before: Vec::new(),
+            after: Vec::new(),
+        };
+        lowering.collect_names(&program.statements, &String::new());
+        lowering
+    }
+
+    fn collect_names(&mut self, toplevels: &Vec<TopLevel>, name_prefix: &String) {
+        for t in toplevels {
+            match t {
+                TopLevel::Function(Function { header, .. }) | TopLevel::FunctionHeader(header) => {
+                    let params = header.params.iter().map(|p| p.typ.clone()).collect();
+                    self.functions.insert(format!("{}{}", name_prefix, header.name), params);
+                },
+                TopLevel::VarDeclaration { typ, name, .. } => {
+                    self.globals.insert(format!("{}{}", name_prefix, name), typ.clone());
+                },
+                TopLevel::Module { name, body } => self.collect_names(body, &format!("mod__{}__", name)),
+                _ => (),
+            }
+        }
+    }
+
+    pub fn lower_program(mut self, program: Program) -> Result<Program> {
+        let mut statements = self.lower_toplevels(program.statements, None)?;
+        if self.uses_closures {
+            statements.insert(0, TopLevel::Import { name: String::from("mc/closure.h"), is_lib: false });
+        }
+        Ok(Program {
+            statements,
+        })
+    }
+
+    fn lower_toplevels(&mut self, toplevels: Vec<TopLevel>, module: Option<&String>) -> Result<Vec<TopLevel>> {
+        let mut result = Vec::new();
+        for t in toplevels {
+            match t {
+                TopLevel::Function(func) => {
+                    self.note_header(&func.header);
+                    self.check_parameters(&func.header.name, &func.header.params, &func.body)?;
+                    self.function_name = func.header.name.clone();
+                    self.module = module.cloned();
+                    self.lambda_count = 0;
+                    self.scopes = vec![Self::parameter_scope(&func.header.params)];
+                    let body = self.lower_body(func.body)?;
+                    result.append(&mut self.before);
+                    result.push(TopLevel::Function(Function { header: func.header, body }));
+                    result.append(&mut self.after);
+                },
+                TopLevel::Module { name, body } => {
+                    let body = self.lower_toplevels(body, Some(&name))?;
+                    result.push(TopLevel::Module { name, body });
+                },
+                TopLevel::VarDeclaration { typ, name, array_size, right } => {
+                    // Capture structs would need to be constants, which they aren't
+                    if let Some(Expression { atom: Atom::Lambda(_), .. }) = &right {
+                        bail!("Global variable '{}' cannot be initialized with a lambda", name);
+                    }
+                    self.note_type(&typ);
+                    result.push(TopLevel::VarDeclaration { typ, name, array_size, right });
+                },
+                TopLevel::FunctionHeader(header) => {
+                    self.note_header(&header);
+                    result.push(TopLevel::FunctionHeader(header));
+                },
+                TopLevel::Struct(s) => {
+                    self.note_struct(&s);
+                    result.push(TopLevel::Struct(s));
+                },
+                TopLevel::TypeDef(typedef) => {
+                    match &typedef.typ {
+                        TypeDefInner::Type(typ) => self.note_type(typ),
+                        TypeDefInner::Struct(s) => self.note_struct(s),
+                        TypeDefInner::Enum(_) | TypeDefInner::FunctionPointer(_) => (),
+                    }
+                    result.push(TopLevel::TypeDef(typedef));
+                },
+                other => result.push(other),
+            }
+        }
+        Ok(result)
+    }
+
+    // == Closure types ==
+
+    /// Records whether the closure runtime is needed
+    fn note_type(&mut self, typ: &Type) {
+        if let TypeType::Closure(_) = typ.typetype {
+            self.uses_closures = true;
+        }
+    }
+
+    fn note_header(&mut self, header: &FunctionHeader) {
+        self.note_type(&header.return_type);
+        for p in &header.params {
+            self.note_type(&p.typ);
+        }
+    }
+
+    fn note_struct(&mut self, s: &Struct) {
+        for member in &s.members {
+            match member {
+                StructMember::Field(field) => self.note_type(&field.typ),
+                StructMember::AnonStruct(inner) => self.note_struct(inner),
+                StructMember::Directive(_) => (),
+            }
+        }
+    }
+
+    fn closure_signature(&self, typ: &Type) -> Option<ClosureSignature> {
+        let resolved = self.symbols.resolve_typedefs(typ);
+        match resolved.typetype {
+            TypeType::Closure(signature) if resolved.pointer_layers() == 0 => Some(*signature),
+            _ => None,
+        }
+    }
+
+    /// Whether a closure given for a parameter of this type may outlive the call
+    fn may_escape_through(&self, param: Option<&Type>) -> bool {
+        match param.and_then(|p| self.closure_signature(p)) {
+            Some(signature) => signature.is_escaping,
+            None => true,
+        }
+    }
+
+    /// Non-`escaping` closure parameters can only be called or passed on to other
+    /// non-`escaping` parameters, which is what allows callers to keep them on the stack
+    fn check_parameters(&self, owner: &String, params: &Vec<Parameter>, body: &[Statement]) -> Result<()> {
+        for p in params {
+            if let Some(signature) = self.closure_signature(&p.typ) {
+                if !signature.is_escaping && self.escapes_in_body(&p.name, body) {
+                    bail!(
+                        "Closure parameter '{}' of '{}' may escape, so it must be declared as 'escaping {}'",
+                        p.name, owner, signature.describe()
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // == Scopes ==
+
+    fn parameter_scope(params: &[Parameter]) -> HashMap<String, Local> {
+        params.iter().map(|p| (p.name.clone(), Local { typ: p.typ.clone(), is_array: false })).collect()
+    }
+
+    fn lookup_local(&self, name: &String) -> Option<&Local> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name))
+    }
+
+    /// The signature of a closure-typed variable being called
+    fn variable_closure(&self, ident: &Identifier) -> Option<ClosureSignature> {
+        let typ = match ident {
+            Identifier::Plain(name) => match self.lookup_local(name) {
+                Some(local) => Some(&local.typ),
+                None => self.globals.get(name),
+            },
+            Identifier::Module(_, _) => self.globals.get(&ident.generate()),
+        };
+        typ.and_then(|t| self.closure_signature(t))
+    }
+
+    /// The parameter types of a named function being called
+    fn function_params(&self, ident: &Identifier) -> Option<Vec<Type>> {
+        if let Identifier::Plain(name) = ident {
+            if self.lookup_local(name).is_some() {
+                return None;
+            }
+        }
+        self.functions.get(&ident.generate()).cloned()
+    }
+
+    // == Statements ==
+
+    /// Whether a statement declares a local closure from a lambda, and the local
+    /// never escapes in the rest of its block
+    fn is_stack_local(&self, statement: &Statement, rest: &[Statement]) -> bool {
+        match statement {
+            Statement::VarDec { typ, name, array_size: None, right: Some(Expression { atom: Atom::Lambda(_), tail: ExprTail::None }) } => {
+                !typ.storage.contains(&StorageClass::Static) && self.closure_signature(typ).is_some() && !self.escapes_in_body(name, rest)
+            },
+            _ => false,
+        }
+    }
+
+    // == Expressions ==
+
+    /// Lowers a value that is stored or passed somewhere expecting `expected`. A lambda
+    /// given directly is checked against that type, and `escaping` decides where its
+    /// captures are kept.
+    fn lower_value(&mut self, value: Expression, expected: Option<&Type>, escaping: bool) -> Result<Expression> {
+        match value {
+            Expression { atom: Atom::Lambda(lambda), tail: ExprTail::None } => {
+                if let Some(expected) = expected {
+                    let matches = match self.closure_signature(expected) {
+                        Some(signature) => signature.describe() == lambda.signature().describe(),
+                        None => false,
+                    };
+                    if !matches {
+                        bail!("A lambda of type '{}' cannot be used as '{}'", lambda.signature().describe(), expected.describe());
+                    }
+                }
+                self.lower_lambda(*lambda, escaping)
+            },
+            other => self.lower_expression(other),
+        }
+    }
+
+    fn lower_atom(&mut self, atom: Atom) -> Result<Atom> {
+        match atom {
+            Atom::Lambda(lambda) => Ok(Atom::Wrapped(Box::new(self.lower_lambda(*lambda, true)?))),
+            Atom::TypeCast { typ, value } => Ok(Atom::TypeCast { typ, value: Box::new(self.lower_expression(*value)?) }),
+            Atom::VaArg { list, typ } => Ok(Atom::VaArg { list: Box::new(self.lower_expression(*list)?), typ }),
+            Atom::UnaryOperation { op, value } => Ok(Atom::UnaryOperation { op, value: Box::new(self.lower_expression(*value)?) }),
+            Atom::Wrapped(value) => Ok(Atom::Wrapped(Box::new(self.lower_expression(*value)?))),
+            Atom::InitList(values) => Ok(Atom::InitList(values.into_iter().map(|v| self.lower_expression(v)).collect::<Result<_, _>>()?)),
+            Atom::Assign { target, value } => Ok(Atom::Assign { target, value: Box::new(self.lower_expression(*value)?) }),
+            other => Ok(other),
+        }
+    }
+
+    /// Lowers call arguments, keeping lambdas on the stack where the parameter allows it
+    fn lower_call_tail(&mut self, tail: ExprTail, params: Option<&Vec<Type>>) -> Result<ExprTail> {
+        match tail {
+            ExprTail::Call { body, next, .. } => {
+                let mut args = Vec::new();
+                for (i, arg) in body.into_iter().enumerate() {
+                    let param = params.and_then(|p| p.get(i));
+                    let escaping = self.may_escape_through(param);
+                    args.push(self.lower_value(arg, param, escaping)?);
+                }
+                Ok(ExprTail::Call { body: args, named: Vec::new(), next: Box::new(self.lower_tail(*next)?) })
+            },
+            other => self.lower_tail(other),
+        }
+    }
+
+    fn lower_tail(&mut self, tail: ExprTail) -> Result<ExprTail> {
+        match tail {
+            ExprTail::None => Ok(ExprTail::None),
+            ExprTail::Call { .. } => self.lower_call_tail(tail, None),
+            ExprTail::BinaryOp { op, right, next } => {
+                Ok(ExprTail::BinaryOp { op, right: Box::new(self.lower_expression(*right)?), next: Box::new(self.lower_tail(*next)?) })
+            },
+            ExprTail::MemberAccess { member, next } => Ok(ExprTail::MemberAccess { member, next: Box::new(self.lower_tail(*next)?) }),
+            ExprTail::PointerAccess { member, next } => Ok(ExprTail::PointerAccess { member, next: Box::new(self.lower_tail(*next)?) }),
+            ExprTail::Index { inner, next } => {
+                Ok(ExprTail::Index { inner: Box::new(self.lower_expression(*inner)?), next: Box::new(self.lower_tail(*next)?) })
+            },
+            ExprTail::Slice { .. } => unreachable!("slicing is lowered before closures"),
+            ExprTail::SafeAccess { .. } | ExprTail::Coalesce { .. } => unreachable!("'?->' and '??' are lowered before closures"),
+            ExprTail::TernaryConditional { second, third, next } => {
+                Ok(ExprTail::TernaryConditional {
+                    second: Box::new(self.lower_expression(*second)?),
+                    third: Box::new(self.lower_expression(*third)?),
+                    next: Box::new(self.lower_tail(*next)?),
+                })
+            },
+        }
+    }
+
+    /// `f(args)` => `((ret (*)(void*, params))f.fn)(f.env, args)`
+    fn lower_closure_call(&mut self, closure: Identifier, signature: ClosureSignature, tail: ExprTail) -> Result<Expression> {
+        let (body, next) = match tail {
+            ExprTail::Call { body, next, .. } => (body, next),
+            _ => unreachable!(),
+        };
+        if signature.is_variadic && body.len() < signature.params.len() {
+            bail!("Closure '{}' takes at least {} arguments, but {} were given", closure.generate(), signature.params.len(), body.len());
+        }
+        if !signature.is_variadic && body.len() != signature.params.len() {
+            bail!("Closure '{}' takes {} arguments, but {} were given", closure.generate(), signature.params.len(), body.len());
+        }
+        let member = |name: &str| Expression::new(
+            Atom::Identifier(closure.clone()),
+            ExprTail::MemberAccess { member: String::from(name), next: Box::new(ExprTail::None) },
+        );
+        let mut args = vec![member("env")];
+        let params: Vec<Type> = signature.params.clone();
+        if let ExprTail::Call { body: lowered, .. } = self.lower_call_tail(ExprTail::Call { body, named: Vec::new(), next: Box::new(ExprTail::None) }, Some(&params))? {
+            args.extend(lowered);
+        }
+        let function = wrapped(cast(Type::simple(plain(signature.function_pointer().as_str()), 0), member("fn")));
+        Ok(Expression::new(function.atom, ExprTail::Call { body: args, named: Vec::new(), next: Box::new(self.lower_tail(*next)?) }))
+    }
+
+    // == Lambdas ==
+
+    fn hoisted_ident(&self, name: &str) -> Identifier {
+        match &self.module {
+            Some(m) => Identifier::Module(m.clone(), String::from(name)),
+            None => Identifier::Plain(String::from(name)),
+        }
+    }
+
+    /// Hoists a lambda's body and returns the `mc::Closure` value that replaces it
+    fn lower_lambda(&mut self, lambda: Lambda, escaping: bool) -> Result<Expression> {
+        self.uses_closures = true;
+        let name = format!("{}__lambda{}", self.function_name, self.lambda_count);
+        self.lambda_count += 1;
+        let env_name = format!("{}__env", name);
+        let env_type = Type::simple(self.hoisted_ident(&env_name), 0);
+
+        let mut fields = Vec::new();
+        let mut captured = HashMap::new();
+        let mut prologue = Vec::new();
+        let mut by_reference = Vec::new();
+        for capture in &lambda.captures {
+            if captured.contains_key(&capture.name) {
+                bail!("'{}' is captured more than once", capture.name);
+            }
+            let local = match self.lookup_local(&capture.name) {
+                Some(local) => local.clone(),
+                None if self.globals.contains_key(&capture.name) => {
+                    bail!("Cannot capture global variable '{}', which can be used directly", capture.name)
+                },
+                None => bail!("Cannot capture '{}': there is no variable with that name", capture.name),
+            };
+            if local.is_array {
+                bail!("Cannot capture array '{}'; capture a pointer to it instead", capture.name);
+            }
+            // Captures are copied into the environment, so a `static` local becomes a plain field
+            let mut field_type = local.typ.without_storage();
+            if capture.by_reference {
+                // Only a static local outlives a closure that escapes the function
+                if escaping && !local.typ.storage.contains(&StorageClass::Static) {
+                    bail!("Cannot capture '{}' by reference in a lambda that escapes '{}', since the closure would outlive it. Capture it by value instead", capture.name, self.function_name);
+                }
+                field_type.pointers.push(Vec::new());
+                by_reference.push(capture.name.clone());
+            } else {
+                prologue.push(var_dec(local.typ.without_storage(), capture.name.as_str(), Some(access(SELF, &capture.name))));
+            }
+            fields.push(StructMember::Field(StructField { typ: field_type, name: capture.name.clone(), array_size: None, attributes: Vec::new() }));
+            captured.insert(capture.name.clone(), local);
+        }
+
+        self.check_parameters(&name, &lambda.params, &lambda.body)?;
+        let saved_scopes = std::mem::replace(&mut self.scopes, vec![captured, Self::parameter_scope(&lambda.params)]);
+        let body = self.lower_body(lambda.body);
+        self.scopes = saved_scopes;
+        let mut body = body?;
+        if !by_reference.is_empty() {
+            body = Self::by_reference_mutator(by_reference).mutate_body(body)?;
+        }
+
+        let mut params = vec![Parameter { name: String::from(ENV_PARAM), typ: Type::simple(plain("void"), 1), default: None }];
+        params.extend(lambda.params);
+        let header = || FunctionHeader { return_type: lambda.return_type.clone(), name: name.clone(), params: params.clone(), is_variadic: lambda.is_variadic, attributes: Vec::new() };
+
+        let env = if fields.is_empty() {
+            int(0)
+        } else {
+            let inits = lambda.captures.iter().map(|c| if c.by_reference { address_of(ident(c.name.as_str())) } else { ident(c.name.as_str()) }).collect();
+            let env = address_of(cast(env_type.clone(), Expression::new(Atom::InitList(inits), ExprTail::None)));
+            prologue.insert(0, var_dec(Type::simple(env_type.name.clone(), 1), SELF, Some(ident(ENV_PARAM))));
+            self.before.push(TopLevel::Struct(Struct { name: env_name, members: fields, is_anonymous: false, is_union: false, derives: Vec::new(), attributes: Vec::new() }));
+            if escaping {
+                call(mc("closure_env"), vec![env, size_of(env_type)])
+            } else {
+                env
+            }
+        };
+        self.before.push(TopLevel::FunctionHeader(header()));
+        prologue.extend(body);
+        self.after.push(TopLevel::Function(Function { header: header(), body: prologue }));
+
+        let function = cast(Type::simple(mc("ClosureFn"), 0), Expression::identifier(self.hoisted_ident(&name)));
+        Ok(cast(Type::simple(mc("Closure"), 0), Expression::new(Atom::InitList(vec![function, env]), ExprTail::None)))
+    }
+
+    /// Rewrites uses of variables captured by reference to go through the capture struct
+    fn by_reference_mutator(names: Vec<String>) -> Mutator {
+        let mut mutator = Mutator::new();
+        let expression_names = names.clone();
+        mutator.add_expression_closure(Box::new(move |e| {
+            if let Atom::Identifier(Identifier::Plain(name)) = &e.atom {
+                if expression_names.contains(name) {
+                    let value = wrapped(unary(UnaryOp::Dereference, access(SELF, name)));
+                    return Ok(Expression::new(value.atom, e.tail));
+                }
+            }
+            Ok(e)
+        }));
+        mutator.add_statement_closure(Box::new(move |s| {
+            let target = |identifier: IdentifierExpression| match identifier {
+                IdentifierExpression::Standard(name) if names.contains(&name) => IdentifierExpression::Standard(format!("(*{}->{})", SELF, name)),
+                other => other,
+            };
+            match s {
+                Statement::VarDec { name, .. } if names.contains(&name) => {
+                    bail!("Cannot declare '{}' in a lambda that captures it by reference", name)
+                },
+                Statement::VarAssign { identifier, right } => Ok(Statement::VarAssign { identifier: target(identifier), right }),
+                Statement::BinOpVarAssign { identifier, op, right } => Ok(Statement::BinOpVarAssign { identifier: target(identifier), op, right }),
+                Statement::IncDec { identifier, is_inc } => Ok(Statement::IncDec { identifier: target(identifier), is_inc }),
+                other => Ok(other),
+            }
+        }));
+        mutator
+    }
+
+    // == Escape analysis ==
+
+    /// Whether a closure variable may outlive its scope: anything other than calling
+    /// it or passing it to a non-`escaping` parameter counts, including capturing it
+    fn escapes_in_body(&self, name: &String, body: &[Statement]) -> bool {
+        body.iter().any(|s| self.statement_escapes(name, s))
+    }
+
+    fn statement_escapes(&self, name: &String, statement: &Statement) -> bool {
+        let expr = |e: &Expression| self.expression_escapes(name, e);
+        let target = |identifier: &IdentifierExpression| match identifier {
+            IdentifierExpression::Standard(target) => target == name,
+            IdentifierExpression::Pointer(e) => expr(e),
+        };
+        let condition_body = |c: &ConditionBody| expr(&c.condition) || self.escapes_in_body(name, &c.body);
+        match statement {
+            Statement::Expression(e) => expr(e),
+            Statement::VarDec { right, .. } => right.as_ref().is_some_and(expr),
+            Statement::Let { right, .. } | Statement::LetTuple { right, .. } => expr(right),
+            Statement::VarAssign { identifier, right } | Statement::BinOpVarAssign { identifier, right, .. } => target(identifier) || expr(right),
+            Statement::IncDec { identifier, .. } => target(identifier),
+            Statement::Return(e) => e.as_ref().is_some_and(expr),
+            Statement::ReturnTuple(values) => values.iter().any(expr),
+            Statement::If { base, elseifs, tail } => {
+                condition_body(base) || elseifs.iter().any(condition_body) || tail.as_ref().is_some_and(|t| self.escapes_in_body(name, t))
+            },
+            Statement::While(c) => condition_body(c),
+            Statement::DoWhile { condition, body } => expr(condition) || self.escapes_in_body(name, body),
+            Statement::For { init, condition, increment, body } => {
+                self.statement_escapes(name, init) || expr(condition) || self.statement_escapes(name, increment) || self.escapes_in_body(name, body)
+            },
+            Statement::EnumFor { body, .. } => self.escapes_in_body(name, body),
+            Statement::ForIn { iterable, body, .. } => {
+                let iterable_escapes = match iterable {
+                    ForIterable::Range { start, end } => expr(start) || expr(end),
+                    ForIterable::Array { array, .. } => expr(array),
+                    ForIterable::Slice { slice, .. } => expr(slice),
+                };
+                iterable_escapes || self.escapes_in_body(name, body)
+            },
+            Statement::Switch { value, cases, default, .. } => {
+                expr(value)
+                    || cases.iter().any(|c| self.escapes_in_body(name, &c.body))
+                    || default.as_ref().is_some_and(|d| self.escapes_in_body(name, d))
+            },
+            Statement::Labeled { statement, .. } => self.statement_escapes(name, statement),
+            Statement::Goto(_) | Statement::Label(_) | Statement::Continue(_) | Statement::Break(_) | Statement::StaticAssert(_) => false,
+            Statement::Directive(_) => false,
+        }
+    }
+
+    fn expression_escapes(&self, name: &String, expression: &Expression) -> bool {
+        match (&expression.atom, &expression.tail) {
+            (Atom::Identifier(Identifier::Plain(n)), ExprTail::Call { body, next, .. }) if n == name => {
+                self.arguments_escape(name, body, None) || self.tail_escapes(name, next)
+            },
+            (Atom::Identifier(Identifier::Plain(n)), _) if n == name => true,
+            (Atom::Identifier(callee), ExprTail::Call { body, next, .. }) => {
+                let params = self.functions.get(&callee.generate());
+                self.arguments_escape(name, body, params) || self.tail_escapes(name, next)
+            },
+            (atom, tail) => self.atom_escapes(name, atom) || self.tail_escapes(name, tail),
+        }
+    }
+
+    fn arguments_escape(&self, name: &String, args: &[Expression], params: Option<&Vec<Type>>) -> bool {
+        args.iter().enumerate().any(|(i, arg)| match (&arg.atom, &arg.tail) {
+            (Atom::Identifier(Identifier::Plain(n)), ExprTail::None) if n == name => {
+                self.may_escape_through(params.and_then(|p| p.get(i)))
+            },
+            _ => self.expression_escapes(name, arg),
+        })
+    }
+
+    fn atom_escapes(&self, name: &String, atom: &Atom) -> bool {
+        match atom {
+            Atom::TypeCast { value, .. } | Atom::UnaryOperation { value, .. } | Atom::Wrapped(value) | Atom::VaArg { list: value, .. }
+                | Atom::Assign { value, .. } => self.expression_escapes(name, value),
+            Atom::InitList(values) => values.iter().any(|v| self.expression_escapes(name, v)),
+            Atom::Lambda(lambda) => lambda.captures.iter().any(|c| &c.name == name),
+            _ => false,
+        }
+    }
+
+    fn tail_escapes(&self, name: &String, tail: &ExprTail) -> bool {
+        match tail {
+            ExprTail::None => false,
+            ExprTail::Call { body, next, .. } => self.arguments_escape(name, body, None) || self.tail_escapes(name, next),
+            ExprTail::BinaryOp { right, next, .. } => self.expression_escapes(name, right) || self.tail_escapes(name, next),
+            ExprTail::MemberAccess { next, .. } | ExprTail::PointerAccess { next, .. } => self.tail_escapes(name, next),
+            ExprTail::Index { inner, next } => self.expression_escapes(name, inner) || self.tail_escapes(name, next),
+            ExprTail::Slice { start, end, next } => {
+                self.expression_escapes(name, start) || self.expression_escapes(name, end) || self.tail_escapes(name, next)
+            },
+            ExprTail::TernaryConditional { second, third, next } => {
+                self.expression_escapes(name, second) || self.expression_escapes(name, third) || self.tail_escapes(name, next)
+            },
+            ExprTail::SafeAccess { .. } | ExprTail::Coalesce { .. } => unreachable!("'?->' and '??' are lowered before closures"),
+        }
+    }
+}
+
+impl Scoped for ClosureLowering {
+    fn push_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    fn pop_scope(&mut self) {
+        self.scopes.pop();
+    }
+
+    fn declare(&mut self, name: &str, typ: &Type, array_size: Option<usize>) {
+        self.note_type(typ);
+        self.scopes.last_mut().unwrap().insert(String::from(name), Local { typ: typ.clone(), is_array: array_size.is_some() });
+    }
+}
+
+impl ScopedLowering for ClosureLowering {
+    fn lower_expression(&mut self, expression: Expression) -> Result<Expression> {
+        if let (Atom::Identifier(ident), ExprTail::Call { .. }) = (&expression.atom, &expression.tail) {
+            if let Some(signature) = self.variable_closure(ident) {
+                return self.lower_closure_call(ident.clone(), signature, expression.tail);
+            }
+            let params = self.function_params(ident);
+            let tail = self.lower_call_tail(expression.tail, params.as_ref())?;
+            return Ok(Expression { atom: expression.atom, tail });
+        }
+        Ok(Expression { atom: self.lower_atom(expression.atom)?, tail: self.lower_tail(expression.tail)? })
+    }
+
+    fn lower_statements(&mut self, body: Vec<Statement>) -> Result<Vec<Statement>> {
+        // Decide which lambdas can stay on the stack before the body is consumed,
+        // since that depends on the statements after each declaration
+        let on_stack: Vec<bool> = (0..body.len()).map(|i| self.is_stack_local(&body[i], &body[i + 1..])).collect();
+        let mut result = Vec::new();
+        for (statement, stack) in body.into_iter().zip(on_stack) {
+            self.on_stack = stack;
+            result.push(self.lower_statement(statement)?);
+        }
+        Ok(result)
+    }
+
+    fn lower_statement(&mut self, statement: Statement) -> Result<Statement> {
+        match statement {
+            Statement::VarDec { typ, name, array_size, right } => {
+                let escaping = !std::mem::take(&mut self.on_stack);
+                let right = match right {
+                    Some(value) => Some(self.lower_value(value, Some(&typ), escaping)?),
+                    None => None,
+                };
+                self.declare(&name, &typ, array_size);
+                Ok(Statement::VarDec { typ, name, array_size, right })
+            },
+            Statement::Let { .. } | Statement::LetTuple { .. } | Statement::ReturnTuple(_) => {
+                unreachable!("`let` and `return (a, b)` are resolved before closures are lowered")
+            },
+            Statement::VarAssign { identifier, right } => {
+                let target = match &identifier {
+                    IdentifierExpression::Standard(name) => self.lookup_local(name).map(|l| l.typ.clone()),
+                    IdentifierExpression::Pointer(_) => None,
+                };
+                let right = self.lower_value(right, target.as_ref(), true)?;
+                Ok(Statement::VarAssign { identifier: self.lower_identifier_expression(identifier)?, right })
+            },
+            Statement::Return(expression) => {
+                Ok(Statement::Return(expression.map(|e| self.lower_value(e, None, true)).transpose()?))
+            },
+            other => self.walk_statement(other),
+        }
+    }
+}
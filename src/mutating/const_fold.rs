@@ -0,0 +1,307 @@
+use std::{cell::RefCell, collections::{HashMap, HashSet}, rc::Rc};
+
+use crate::{analysis::consteval::{ConstError, ConstEvaluator, ConstValue, DataModel}, codegen::simple::SimpleCodeGen, parsing::ast::{enumm::{Enum, EnumEntry}, expression::{Atom, ExprTail, Expression, UnaryOp}, function::Function, identifier::Identifier, program::Program, statement::{CaseLabel, StaticAssert, Statement}, toplevel::TopLevel, typedef::{TypeDef, TypeDefInner}, types::{Qualifier, Type}}};
+use anyhow::{bail, Result};
+
+use super::mutator::Mutator;
+
+/// Evaluates what C needs to be constant while transpiling: enum variant values are folded to
+/// literals, `static_assert`s are checked, and module-level `const` numbers are emitted as the
+/// literal they evaluate to, so that later constants, enums and assertions can use them.
+/// `case` labels are checked too, although ones using names the transpiler doesn't know, like
+/// macros from C headers, are left for the C compiler.
+///
+/// Everything is evaluated in declaration order, so only earlier declarations can be referred to.
+pub struct ConstFolding {
+    evaluator: ConstEvaluator,
+    /// The values of module-level constants. C doesn't count `const` variables as constant
+    /// expressions, so they are replaced with their values in the `static_assert`s that are emitted.
+    constants: HashMap<String, ConstValue>,
+    /// The mangled names of the global variables declared so far, which aren't constant
+    globals: HashSet<String>,
+    /// The parameters and local variables of the function being checked
+    locals: HashSet<String>,
+}
+
+impl ConstFolding {
+    pub fn new(program: &Program, data_model: DataModel) -> Self {
+        ConstFolding {
+            evaluator: ConstEvaluator::new(program, data_model),
+            constants: HashMap::new(),
+            globals: HashSet::new(),
+            locals: HashSet::new(),
+        }
+    }
+
+    pub fn fold_program(mut self, program: Program) -> Result<Program> {
+        Ok(Program {
+            statements: self.fold_toplevels(program.statements, &String::new())?,
+        })
+    }
+
+    fn fold_toplevels(&mut self, toplevels: Vec<TopLevel>, name_prefix: &String) -> Result<Vec<TopLevel>> {
+        let mut result = Vec::new();
+        for t in toplevels {
+            result.push(match t {
+                TopLevel::Module { name, body } => {
+                    let prefix = format!("mod__{}__", name);
+                    TopLevel::Module { body: self.fold_toplevels(body, &prefix)?, name }
+                },
+                TopLevel::Enum(e) => {
+                    let count = (!e.is_anonymous).then(|| format!("{}{}__COUNT", name_prefix, e.name));
+                    let e = self.fold_enum(e)?;
+                    if let Some(count) = count {
+                        self.evaluator.define(count, ConstValue::int(e.entries.len() as i128));
+                    }
+                    TopLevel::Enum(e)
+                },
+                TopLevel::Struct(s) => {
+                    if !s.is_anonymous {
+                        self.evaluator.declare_struct(format!("{}{}", name_prefix, s.name), &s);
+                    }
+                    TopLevel::Struct(s)
+                },
+                TopLevel::TypeDef(TypeDef { name, typ }) => {
+                    let typ = match typ {
+                        TypeDefInner::Struct(s) => {
+                            self.evaluator.declare_struct(format!("{}{}", name_prefix, name), &s);
+                            TypeDefInner::Struct(s)
+                        },
+                        TypeDefInner::Enum(e) => TypeDefInner::Enum(self.fold_enum(e)?),
+                        other => other,
+                    };
+                    TopLevel::TypeDef(TypeDef { name, typ })
+                },
+                TopLevel::VarDeclaration { typ, name, array_size, right: Some(right) }
+                    if typ.qualifiers.contains(&Qualifier::Const) && typ.pointer_layers() == 0 && array_size.is_none() => {
+                    let right = self.fold_constant(&typ, format!("{}{}", name_prefix, name), right)?;
+                    TopLevel::VarDeclaration { typ, name, array_size, right: Some(right) }
+                },
+                TopLevel::VarDeclaration { typ, name, array_size, right } => {
+                    self.globals.insert(format!("{}{}", name_prefix, name));
+                    TopLevel::VarDeclaration { typ, name, array_size, right }
+                },
+                TopLevel::StaticAssert(assertion) => {
+                    self.check(&assertion, None)?;
+                    TopLevel::StaticAssert(inline_constants(&self.constants, assertion)?)
+                },
+                TopLevel::Function(func) => {
+                    self.locals = local_names(&func);
+                    self.check_body(&func.body, &func.header.name)?;
+                    TopLevel::Function(self.inline_in_function(func)?)
+                },
+                other => other,
+            });
+        }
+        Ok(result)
+    }
+
+    /// Replaces explicit variant values with the literals they evaluate to
+    fn fold_enum(&mut self, e: Enum) -> Result<Enum> {
+        let values = self.evaluator.evaluate_enum(&e)?;
+        let entries = e.entries.into_iter().zip(values).map(|(entry, value)| EnumEntry {
+            value: entry.value.map(|v| if is_octal(&v) { v } else { literal(Atom::Int(value)) }),
+            name: entry.name,
+        }).collect();
+        Ok(Enum { name: e.name, entries, is_anonymous: e.is_anonymous, reflected: e.reflected })
+    }
+
+    /// Evaluates a module-level constant of arithmetic type. Initializers the transpiler can't
+    /// evaluate, such as macros from C headers, are left for the C compiler.
+    fn fold_constant(&mut self, typ: &Type, name: String, right: Expression) -> Result<Expression> {
+        let value = match self.evaluator.evaluate(&right).and_then(|v| self.evaluator.convert(v, typ)) {
+            Ok(value) => value,
+            Err(ConstError::NotConstant(_)) => return Ok(right),
+            Err(ConstError::Invalid(reason)) => bail!("Cannot compute the value of constant '{}': {}", name, reason),
+        };
+        self.evaluator.define(name.clone(), value);
+        self.constants.insert(name, value);
+        // Enum constants keep their variant names, which are more readable than numbers, and
+        // octal literals stay in octal
+        if self.evaluator.is_enum(typ) || is_octal(&right) {
+            return Ok(right);
+        }
+        Ok(value.to_atom().map(literal).unwrap_or(right))
+    }
+
+    fn check(&self, assertion: &StaticAssert, function: Option<&String>) -> Result<()> {
+        let place = function.map(|f| format!(" in '{}'", f)).unwrap_or_default();
+        match self.evaluator.evaluate(&assertion.condition) {
+            Ok(value) if value.is_true() => Ok(()),
+            Ok(_) => bail!("Static assertion failed{}: {} ({})", place, assertion.message, assertion.condition.generate()),
+            Err(e) => bail!("Cannot evaluate the condition of static_assert{}: {}", place, e),
+        }
+    }
+
+    fn inline_in_function(&self, func: Function) -> Result<Function> {
+        let constants = self.constants.clone();
+        let mut mutator = Mutator::new();
+        mutator.add_statement_closure(Box::new(move |s| match s {
+            Statement::StaticAssert(assertion) => Ok(Statement::StaticAssert(inline_constants(&constants, assertion)?)),
+            other => Ok(other),
+        }));
+        Ok(Function { header: func.header, body: mutator.mutate_body(func.body)? })
+    }
+
+    /// Checks the `static_assert`s in a function body. Lambda bodies are left to the C compiler.
+    fn check_body(&self, body: &Vec<Statement>, function: &String) -> Result<()> {
+        for statement in body {
+            self.check_statement(statement, function)?;
+        }
+        Ok(())
+    }
+
+    fn check_statement(&self, statement: &Statement, function: &String) -> Result<()> {
+        match statement {
+            Statement::StaticAssert(assertion) => self.check(assertion, Some(function)),
+            Statement::If { base, elseifs, tail } => {
+                self.check_body(&base.body, function)?;
+                for elseif in elseifs {
+                    self.check_body(&elseif.body, function)?;
+                }
+                match tail {
+                    Some(tail) => self.check_body(tail, function),
+                    None => Ok(()),
+                }
+            },
+            Statement::While(body) => self.check_body(&body.body, function),
+            Statement::DoWhile { body, .. } | Statement::EnumFor { body, .. } | Statement::ForIn { body, .. } => self.check_body(body, function),
+            Statement::For { init, increment, body, .. } => {
+                self.check_statement(init, function)?;
+                self.check_statement(increment, function)?;
+                self.check_body(body, function)
+            },
+            Statement::Switch { cases, default, .. } => {
+                for case in cases {
+                    for label in &case.labels {
+                        match label {
+                            CaseLabel::Value(value) => self.check_case_label(value, function)?,
+                            CaseLabel::Range { start, end } => {
+                                self.check_case_label(start, function)?;
+                                self.check_case_label(end, function)?;
+                            },
+                        }
+                    }
+                    self.check_body(&case.body, function)?;
+                }
+                match default {
+                    Some(default) => self.check_body(default, function),
+                    None => Ok(()),
+                }
+            },
+            Statement::Labeled { statement, .. } => self.check_statement(statement, function),
+            _ => Ok(()),
+        }
+    }
+
+    /// Rejects a `case` label that can't be constant. One that can't be evaluated is still fine
+    /// if that's only because it uses names the transpiler doesn't know, e.g. from C headers.
+    fn check_case_label(&self, value: &Expression, function: &String) -> Result<()> {
+        let reason = match self.evaluator.evaluate(value) {
+            Ok(_) => return Ok(()),
+            Err(ConstError::Invalid(reason)) => reason,
+            Err(ConstError::NotConstant(reason)) => match self.variable_in(value) {
+                Some(variable) => format!("'{}' is a variable", variable),
+                None if only_names(value) => return Ok(()),
+                None => reason,
+            },
+        };
+        bail!("The label 'case {}' in '{}' isn't constant: {}", value.generate(), function, reason)
+    }
+
+    /// The first local or global variable that `value` refers to, if any
+    fn variable_in(&self, value: &Expression) -> Option<String> {
+        let found = Rc::new(RefCell::new(None));
+        let (locals, globals, found_by_closure) = (self.locals.clone(), self.globals.clone(), found.clone());
+        let mut finder = Mutator::new();
+        finder.add_expression_closure(Box::new(move |e| {
+            if let Atom::Identifier(ident) = &e.atom {
+                let is_variable = matches!(ident, Identifier::Plain(name) if locals.contains(name)) || globals.contains(&ident.generate());
+                if is_variable && found_by_closure.borrow().is_none() {
+                    *found_by_closure.borrow_mut() = Some(ident.generate());
+                }
+            }
+            Ok(e)
+        }));
+        // Finding can't fail, since the closure always succeeds
+        let _ = finder.mutate_expression(value.clone());
+        let result = found.borrow().clone();
+        result
+    }
+}
+
+/// The names of a function's parameters and of every variable declared in its body
+fn local_names(func: &Function) -> HashSet<String> {
+    let names = Rc::new(RefCell::new(func.header.params.iter().map(|p| p.name.clone()).collect::<HashSet<String>>()));
+    let declared = names.clone();
+    let mut collector = Mutator::new();
+    collector.add_statement_closure(Box::new(move |s| {
+        match &s {
+            Statement::VarDec { name, .. } | Statement::Let { name, .. } | Statement::ForIn { name, .. } | Statement::EnumFor { name, .. } => {
+                declared.borrow_mut().insert(name.clone());
+            },
+            Statement::LetTuple { names, .. } => declared.borrow_mut().extend(names.iter().cloned()),
+            _ => (),
+        }
+        Ok(s)
+    }));
+    // Collecting can't fail, since the closure always succeeds
+    let _ = collector.mutate_body(func.body.clone());
+    let result = names.borrow().clone();
+    result
+}
+
+/// Whether `value` only combines names and literals with operators, so that it's constant if
+/// the names are, e.g. `SIGINT + 1` but not `values[0]` or `next()`
+fn only_names(value: &Expression) -> bool {
+    let atom = match &value.atom {
+        Atom::Identifier(_) | Atom::Char(_) | Atom::Short(_) | Atom::Int(_) | Atom::Octal(_) | Atom::TrueLong(_) | Atom::Float(_) | Atom::Double(_)
+            | Atom::Boolean(_) | Atom::SizeOf(_) => true,
+        Atom::UnaryOperation { op: UnaryOp::Plus | UnaryOp::Minus | UnaryOp::BitNot | UnaryOp::LogicNot, value } | Atom::TypeCast { value, .. }
+            | Atom::Wrapped(value) => only_names(value),
+        _ => false,
+    };
+    atom && only_names_tail(&value.tail)
+}
+
+fn only_names_tail(tail: &ExprTail) -> bool {
+    match tail {
+        ExprTail::None => true,
+        ExprTail::BinaryOp { right, next, .. } => only_names(right) && only_names_tail(next),
+        ExprTail::TernaryConditional { second, third, next } => only_names(second) && only_names(third) && only_names_tail(next),
+        _ => false,
+    }
+}
+
+fn literal(atom: Atom) -> Expression {
+    Expression { atom, tail: ExprTail::None }
+}
+
+fn is_octal(value: &Expression) -> bool {
+    matches!(value, Expression { atom: Atom::Octal(_), tail: ExprTail::None })
+}
+
+/// A literal spelling out a value, in parentheses if it's negative so that it can follow
+/// any operator, or `None` if no literal can spell it
+fn literal_of(value: ConstValue) -> Option<Expression> {
+    let atom = value.to_atom()?;
+    let negative = match value {
+        ConstValue::Int { value, .. } => value < 0,
+        ConstValue::Float(value) => value < 0.0,
+    };
+    Some(if negative { literal(Atom::Wrapped(Box::new(literal(atom)))) } else { literal(atom) })
+}
+
+fn inline_constants(constants: &HashMap<String, ConstValue>, assertion: StaticAssert) -> Result<StaticAssert> {
+    let constants = constants.clone();
+    let mut mutator = Mutator::new();
+    mutator.add_expression_closure(Box::new(move |e| {
+        if let Atom::Identifier(ident) = &e.atom {
+            if let Some(Expression { atom, .. }) = constants.get(&ident.generate()).and_then(|v| literal_of(*v)) {
+                return Ok(Expression { atom, tail: e.tail });
+            }
+        }
+        Ok(e)
+    }));
+    Ok(StaticAssert { condition: mutator.mutate_expression(assertion.condition)?, message: assertion.message })
+}
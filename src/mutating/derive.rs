@@ -0,0 +1,489 @@
+use crate::{analysis::symbols::SymbolTable, codegen::simple::SimpleCodeGen, parsing::ast::{expression::{BinOp, Expression, UnaryOp}, function::{Function, FunctionHeader, Parameter}, identifier::Identifier, program::Program, sstruct::{Derive, Struct, StructMember}, statement::{IdentifierExpression, Statement}, toplevel::TopLevel, types::{Qualifier, Type, TypeType}}};
+use anyhow::{bail, Result};
+
+use super::builder::{access, address_of, assign, binary, call, cast, for_count, ident, if_then, index, int, plain, size_of, string, ternary, unary, var_dec, wrapped};
+
+/// Expands `@derive(...)` on structs into the generated helper functions, which are
+/// placed directly after the struct (and inside the same module, so they get the same prefix)
+pub struct DeriveExpander {
+    pub(super) symbols: SymbolTable,
+}
+
+/// A field reachable directly from the struct, including those of anonymous inner structs/unions
+pub(super) struct FlatField<'a> {
+    pub name: &'a String,
+    pub typ: &'a Type,
+    pub array_size: Option<usize>,
+    pub in_union: bool,
+}
+
+/// How a field's value (or each element's value, for arrays) is handled by the generated helpers
+pub(super) enum FieldKind {
+    Integer,
+    // Holds the identifier of the enum's type, for looking up its reflection helpers
+    Enum(Identifier),
+    Float,
+    Char,
+    Bool,
+    String,
+    Pointer,
+    // A named struct, which must derive the same trait; holds the identifier of its type
+    Nested(Identifier),
+    // Compared/hashed byte-by-byte, since we can't know which member is active
+    Union,
+    // A plain C `struct x`, which we know nothing about
+    ForeignStruct,
+    Unknown,
+}
+
+type Generated = (Type, Vec<Parameter>, Vec<Statement>);
+
+/// Name of the hidden index used when looping over array fields
+pub(super) const INDEX: &str = "__mc_i";
+
+const INTEGER_TYPES: [&str; 18] = [
+    "int", "short", "long", "signed", "unsigned", "size_t", "ssize_t", "usize", "intptr_t", "uintptr_t",
+    "int8_t", "int16_t", "int32_t", "int64_t", "uint8_t", "uint16_t", "uint32_t", "uint64_t",
+];
+
+/// The standard headers that the generated helpers need, and the derives that need them
+const DERIVE_HEADERS: [(&str, &[Derive]); 4] = [
+    ("stdint.h", &[Derive::Hash]),
+    ("stdio.h", &[Derive::Debug]),
+    ("stdlib.h", &[Derive::Clone]),
+    ("string.h", &[Derive::Eq, Derive::Hash, Derive::Clone]),
+];
+
+impl DeriveExpander {
+    pub fn new(program: &Program) -> Self {
+        DeriveExpander {
+            symbols: SymbolTable::collect(program),
+        }
+    }
+
+    pub fn expand_program(&self, program: Program) -> Result<Program> {
+        let mut statements = self.expand_toplevels(program.statements, None)?;
+        let derives: Vec<&Derive> = self.symbols.structs.values().flat_map(|s| &s.derives).collect();
+        if derives.contains(&&Derive::Json) {
+            // Pull in the JSON runtime that the generated code relies on
+            statements.insert(0, TopLevel::Import { name: String::from("mc/json.h"), is_lib: false });
+        }
+        // And the standard headers declaring the functions that the other helpers call
+        for (header, users) in DERIVE_HEADERS.iter().rev() {
+            let included = statements.iter().any(|t| matches!(t, TopLevel::Import { name, is_lib: true } if name == header));
+            if !included && derives.iter().any(|d| users.contains(d)) {
+                statements.insert(0, TopLevel::Import { name: String::from(*header), is_lib: true });
+            }
+        }
+        Ok(Program {
+            statements,
+        })
+    }
+
+    fn expand_toplevels(&self, toplevels: Vec<TopLevel>, module: Option<&String>) -> Result<Vec<TopLevel>> {
+        let mut result = Vec::new();
+        for t in toplevels {
+            match t {
+                TopLevel::Struct(s) if !s.derives.is_empty() => {
+                    let functions = self.generate_derives(&s, module)?;
+                    result.push(TopLevel::Struct(s));
+                    for f in functions {
+                        result.push(TopLevel::Function(f));
+                    }
+                },
+                TopLevel::Module { name, body } => {
+                    let body = self.expand_toplevels(body, Some(&name))?;
+                    result.push(TopLevel::Module { name, body });
+                },
+                other => result.push(other),
+            }
+        }
+        Ok(result)
+    }
+
+    fn generate_derives(&self, s: &Struct, module: Option<&String>) -> Result<Vec<Function>> {
+        let self_ident = match module {
+            Some(m) => Identifier::Module(m.clone(), s.name.clone()),
+            None => Identifier::Plain(s.name.clone()),
+        };
+        if let Some(derive) = s.derives.first().filter(|_| s.has_directives()) {
+            bail!("Cannot derive {} for '{}', since preprocessor directives decide which fields it has", derive.name(), s.name);
+        }
+        let mut fields = Vec::new();
+        Self::flatten_fields(&s.members, false, &mut fields);
+
+        let mut functions = Vec::new();
+        for derive in &s.derives {
+            let generated = match derive {
+                Derive::Eq => self.generate_eq(s, &self_ident, &fields)?,
+                Derive::Hash => self.generate_hash(s, &fields)?,
+                Derive::Debug => self.generate_debug(s, &fields)?,
+                Derive::Clone => self.generate_clone(s, &self_ident, &fields)?,
+                Derive::Json => {
+                    functions.extend(self.generate_json(s, &self_ident, &fields)?);
+                    continue;
+                },
+            };
+            functions.push(Self::method(s, &self_ident, derive.name(), generated));
+        }
+        Ok(functions)
+    }
+
+    /// Builds `T__name(T* this, ...)`
+    pub(super) fn method(s: &Struct, self_ident: &Identifier, name: &str, generated: Generated) -> Function {
+        let (return_type, mut params, body) = generated;
+        params.insert(0, Parameter { name: String::from("this"), typ: Type::simple(self_ident.clone(), 1), default: None });
+        Function {
+            header: FunctionHeader {
+                return_type,
+                name: format!("{}__{}", s.name, name),
+                params,
+                is_variadic: false,
+                attributes: Vec::new(),
+            },
+            body,
+        }
+    }
+
+    fn flatten_fields<'a>(members: &'a Vec<StructMember>, in_union: bool, fields: &mut Vec<FlatField<'a>>) {
+        for member in members {
+            match member {
+                StructMember::Field(f) => fields.push(FlatField { name: &f.name, typ: &f.typ, array_size: f.array_size, in_union }),
+                // Members of anonymous structs/unions are accessed as if they were our own
+                StructMember::AnonStruct(inner) => Self::flatten_fields(&inner.members, in_union || inner.is_union, fields),
+                StructMember::Directive(_) => (),
+            }
+        }
+    }
+
+    pub(super) fn classify(&self, typ: &Type) -> FieldKind {
+        classify(&self.symbols, typ)
+    }
+
+    /// Gets the helper generated for a nested struct, ensuring that it derives the trait too
+    pub(super) fn nested_helper(&self, s: &Struct, field: &FlatField, nested: &Identifier, derive: Derive, helper: &str) -> Result<Identifier> {
+        let derives_it = self.symbols.get_struct(nested).map(|n| n.derives.contains(&derive)).unwrap_or(false);
+        if !derives_it {
+            bail!(
+                "Cannot derive {} for '{}': field '{}' has type '{}', which does not derive {}",
+                derive.name(), s.name, field.name, field.typ.generate(), derive.name(),
+            );
+        }
+        Ok(suffixed(nested, helper))
+    }
+
+    pub(super) fn unsupported<T>(s: &Struct, field: &FlatField, derive: Derive) -> Result<T> {
+        bail!(
+            "Cannot derive {} for '{}': field '{}' has unsupported type '{}'",
+            derive.name(), s.name, field.name, field.typ.generate(),
+        )
+    }
+
+    /// `_Bool T__eq(T* this, T* other)`
+    fn generate_eq(&self, s: &Struct, self_ident: &Identifier, fields: &Vec<FlatField>) -> Result<Generated> {
+        let mut body = Vec::new();
+        for field in fields {
+            let kind = self.classify(field.typ);
+            let differs = match kind {
+                _ if field.in_union => None,
+                FieldKind::Union => None,
+                FieldKind::Nested(nested) => {
+                    let helper = self.nested_helper(s, field, &nested, Derive::Eq, "eq")?;
+                    let args = vec![address_of(element("this", field)), address_of(element("other", field))];
+                    Some(unary(UnaryOp::LogicNot, call(helper, args)))
+                },
+                FieldKind::String => {
+                    // Equal pointers, including two nulls, are equal strings without comparing them
+                    let (this, other) = (element("this", field), element("other", field));
+                    let either_null = binary(
+                        binary(this.clone(), BinOp::IsEqual, ident("NULL")),
+                        BinOp::LogicOr,
+                        binary(other.clone(), BinOp::IsEqual, ident("NULL")),
+                    );
+                    let contents_differ = binary(call(plain("strcmp"), vec![this.clone(), other.clone()]), BinOp::IsNotEqual, int(0));
+                    let differ = wrapped(binary(either_null, BinOp::LogicOr, contents_differ));
+                    Some(binary(binary(this, BinOp::IsNotEqual, other), BinOp::LogicAnd, differ))
+                },
+                FieldKind::ForeignStruct | FieldKind::Unknown => Self::unsupported(s, field, Derive::Eq)?,
+                _ => Some(binary(element("this", field), BinOp::IsNotEqual, element("other", field))),
+            };
+            match differs {
+                Some(condition) => body.extend(per_element(field, vec![if_then(condition, vec![Statement::Return(Some(int(0)))])])),
+                None => {
+                    let args = vec![address_of(access("this", field.name)), address_of(access("other", field.name)), field_size(field)];
+                    let condition = binary(call(plain("memcmp"), args), BinOp::IsNotEqual, int(0));
+                    body.push(if_then(condition, vec![Statement::Return(Some(int(0)))]));
+                },
+            }
+        }
+        body.push(Statement::Return(Some(int(1))));
+
+        let params = vec![Parameter { name: String::from("other"), typ: Type::simple(self_ident.clone(), 1), default: None }];
+        Ok((Type::simple(plain("_Bool"), 0), params, body))
+    }
+
+    /// `size_t T__hash(T* this)`
+    fn generate_hash(&self, s: &Struct, fields: &Vec<FlatField>) -> Result<Generated> {
+        let size_t = Type::simple(plain("size_t"), 0);
+        let mut body = vec![var_dec(size_t.clone(), "hash", Some(int(17)))];
+        // Floating-point fields are copied into these to hash their bits, declared once up front
+        let mut scratch = Vec::new();
+        let mut declared = Vec::new();
+        for field in fields {
+            let kind = self.classify(field.typ);
+            let value = match kind {
+                _ if field.in_union => None,
+                FieldKind::Union => None,
+                FieldKind::Nested(nested) => {
+                    let helper = self.nested_helper(s, field, &nested, Derive::Hash, "hash")?;
+                    Some(call(helper, vec![address_of(element("this", field))]))
+                },
+                FieldKind::String => {
+                    body.extend(per_element(field, vec![hash_string(element("this", field))]));
+                    continue;
+                },
+                FieldKind::Float => {
+                    // `long double` goes through `double`, since its padding bytes are unspecified
+                    let (float, bits) = match self.symbols.resolve_typedefs(field.typ).name.generate().as_str() {
+                        "float" => ("float", "uint32_t"),
+                        _ => ("double", "uint64_t"),
+                    };
+                    if !declared.contains(&float) {
+                        declared.push(float);
+                        scratch.push(var_dec(Type::simple(plain(float), 0), &format!("__mc_{}", float), None));
+                        scratch.push(var_dec(Type::simple(plain(bits), 0), &format!("__mc_{}_bits", float), None));
+                    }
+                    body.extend(per_element(field, hash_float(element("this", field), float, bits)));
+                    continue;
+                },
+                FieldKind::ForeignStruct | FieldKind::Unknown => Self::unsupported(s, field, Derive::Hash)?,
+                _ => Some(cast(size_t.clone(), element("this", field))),
+            };
+            match value {
+                Some(v) => body.extend(per_element(field, vec![mix_hash(v)])),
+                None => {
+                    // Hash unions byte-by-byte, to match how they're compared
+                    let bytes = wrapped(cast(Type::simple(plain("char"), 1), address_of(access("this", field.name))));
+                    let byte = cast(size_t.clone(), index(bytes, ident(INDEX)));
+                    body.push(for_count(INDEX, field_size(field), vec![mix_hash(byte)]));
+                },
+            }
+        }
+        body.splice(1..1, scratch);
+        body.push(Statement::Return(Some(ident("hash"))));
+        Ok((size_t, Vec::new(), body))
+    }
+
+    /// `void T__debug(T* this, FILE* out)`, printing e.g. `Point { x: 1, y: [2, 3] }`
+    fn generate_debug(&self, s: &Struct, fields: &Vec<FlatField>) -> Result<Generated> {
+        let mut body = vec![fprintf(format!("{} {{", s.name), Vec::new())];
+        for (i, field) in fields.iter().enumerate() {
+            let prefix = format!("{}{}: ", if i == 0 { " " } else { ", " }, field.name);
+            let kind = self.classify(field.typ);
+            match field.array_size {
+                // Char arrays are printed as (possibly unterminated) strings
+                Some(size) if matches!(kind, FieldKind::Char) => {
+                    let args = vec![int(size as i32), access("this", field.name)];
+                    body.push(fprintf(format!("{}\\\"%.*s\\\"", prefix), args));
+                },
+                Some(_) => {
+                    body.push(fprintf(format!("{}[", prefix), Vec::new()));
+                    let mut each = vec![if_then(binary(ident(INDEX), BinOp::IsGT, int(0)), vec![fprintf(String::from(", "), Vec::new())])];
+                    each.extend(self.debug_value(s, field, kind, String::new())?);
+                    body.extend(per_element(field, each));
+                    body.push(fprintf(String::from("]"), Vec::new()));
+                },
+                None => body.extend(self.debug_value(s, field, kind, prefix)?),
+            }
+        }
+        body.push(fprintf(String::from(" }"), Vec::new()));
+
+        let params = vec![Parameter { name: String::from("out"), typ: Type::simple(plain("FILE"), 1), default: None }];
+        Ok((Type::simple(plain("void"), 0), params, body))
+    }
+
+    fn debug_value(&self, s: &Struct, field: &FlatField, kind: FieldKind, prefix: String) -> Result<Vec<Statement>> {
+        let value = element("this", field);
+        let statement = match kind {
+            FieldKind::Integer | FieldKind::Enum(_) => fprintf(format!("{}%ld", prefix), vec![cast(Type::simple(plain("long"), 0), value)]),
+            FieldKind::Float => fprintf(format!("{}%f", prefix), vec![cast(Type::simple(plain("double"), 0), value)]),
+            FieldKind::Char => fprintf(format!("{}'%c'", prefix), vec![value]),
+            FieldKind::Bool => fprintf(format!("{}%s", prefix), vec![ternary(value, string("true"), string("false"))]),
+            FieldKind::String => {
+                let text = ternary(value, element("this", field), string("(null)"));
+                fprintf(format!("{}\\\"%s\\\"", prefix), vec![text])
+            },
+            FieldKind::Pointer => fprintf(format!("{}%p", prefix), vec![cast(Type::simple(plain("void"), 1), value)]),
+            FieldKind::Nested(nested) => {
+                let helper = self.nested_helper(s, field, &nested, Derive::Debug, "debug")?;
+                let call_helper = Statement::Expression(call(helper, vec![address_of(value), ident("out")]));
+                if prefix.is_empty() {
+                    return Ok(vec![call_helper]);
+                }
+                return Ok(vec![fprintf(prefix, Vec::new()), call_helper]);
+            },
+            FieldKind::Union | FieldKind::ForeignStruct | FieldKind::Unknown => Self::unsupported(s, field, Derive::Debug)?,
+        };
+        Ok(vec![statement])
+    }
+
+    /// `T T__clone(T* this)`; a shallow copy, except that nested structs use their own clone and
+    /// `char*` strings are copied into new allocations
+    fn generate_clone(&self, s: &Struct, self_ident: &Identifier, fields: &Vec<FlatField>) -> Result<Generated> {
+        let self_type = Type::simple(self_ident.clone(), 0);
+        let mut body = vec![var_dec(self_type.clone(), "copy", Some(unary(UnaryOp::Dereference, ident("this"))))];
+        for field in fields {
+            // Union members were already copied byte-for-byte along with everything else
+            if field.in_union {
+                continue;
+            }
+            match self.classify(field.typ) {
+                FieldKind::Nested(nested) => {
+                    let helper = self.nested_helper(s, field, &nested, Derive::Clone, "clone")?;
+                    let copy = assign(element_target("copy.", field), call(helper, vec![address_of(element("this", field))]));
+                    body.extend(per_element(field, vec![copy]));
+                },
+                FieldKind::String => body.extend(per_element(field, copy_string(element("this", field), element_target("copy.", field)))),
+                FieldKind::ForeignStruct => Self::unsupported(s, field, Derive::Clone)?,
+                _ => (),
+            }
+        }
+        body.push(Statement::Return(Some(ident("copy"))));
+        Ok((self_type, Vec::new(), body))
+    }
+}
+
+/// How a value of type `typ` is compared, hashed or printed
+pub(super) fn classify(symbols: &SymbolTable, typ: &Type) -> FieldKind {
+    let resolved = symbols.resolve_typedefs(typ);
+    let name = resolved.name.generate();
+    if resolved.pointer_layers() > 0 {
+        return if name == "char" && resolved.pointer_layers() == 1 { FieldKind::String } else { FieldKind::Pointer };
+    }
+    match resolved.typetype {
+        TypeType::FunctionPointer(_) => FieldKind::Pointer,
+        TypeType::Enum => FieldKind::Integer,
+        TypeType::Union => FieldKind::Union,
+        TypeType::Struct => FieldKind::ForeignStruct,
+        TypeType::Closure(_) | TypeType::Tuple(_) | TypeType::Slice(_) => FieldKind::Unknown,
+        TypeType::Simple => {
+            if let Some(symbol) = symbols.get_struct(&resolved.name) {
+                if symbol.is_union { FieldKind::Union } else { FieldKind::Nested(resolved.name) }
+            } else if symbols.is_enum(&resolved.name) {
+                FieldKind::Enum(resolved.name)
+            } else if name == "float" || name.ends_with("double") {
+                FieldKind::Float
+            } else if INTEGER_TYPES.contains(&name.as_str()) || name.contains(' ') {
+                // The other builtin types spelled with several words, like `unsigned char`, are all integers
+                FieldKind::Integer
+            } else if name == "char" {
+                FieldKind::Char
+            } else if name == "bool" || name == "_Bool" {
+                FieldKind::Bool
+            } else {
+                FieldKind::Unknown
+            }
+        },
+    }
+}
+
+/// Appends `__suffix` to the name of a (possibly module-qualified) identifier
+pub(super) fn suffixed(ident: &Identifier, suffix: &str) -> Identifier {
+    match ident {
+        Identifier::Plain(name) => Identifier::Plain(format!("{}__{}", name, suffix)),
+        Identifier::Module(module, name) => Identifier::Module(module.clone(), format!("{}__{}", name, suffix)),
+    }
+}
+
+/// `base->field`, or `base->field[__mc_i]` for arrays
+pub(super) fn element(base: &str, field: &FlatField) -> Expression {
+    match field.array_size {
+        Some(_) => index(access(base, field.name), ident(INDEX)),
+        None => access(base, field.name),
+    }
+}
+
+/// The text of `prefix` + `field` (+ `[__mc_i]` for arrays), for use as an assignment target
+pub(super) fn element_target(prefix: &str, field: &FlatField) -> String {
+    match field.array_size {
+        Some(_) => format!("{}{}[{}]", prefix, field.name, INDEX),
+        None => format!("{}{}", prefix, field.name),
+    }
+}
+
+/// Wraps the statements in a loop over each element if the field is an array
+pub(super) fn per_element(field: &FlatField, body: Vec<Statement>) -> Vec<Statement> {
+    match field.array_size {
+        Some(size) => vec![for_count(INDEX, int(size as i32), body)],
+        None => body,
+    }
+}
+
+/// `sizeof(T)`, or `sizeof(T)*N` for arrays
+fn field_size(field: &FlatField) -> Expression {
+    match field.array_size {
+        Some(size) => binary(size_of(field.typ.clone()), BinOp::Mul, int(size as i32)),
+        None => size_of(field.typ.clone()),
+    }
+}
+
+/// `hash = hash*31+value`
+fn mix_hash(value: Expression) -> Statement {
+    assign(String::from("hash"), binary(ident("hash"), BinOp::Mul, binary(int(31), BinOp::Add, value)))
+}
+
+/// Mixes the bits of a `float` or `double` into `hash`, through the `__mc_float`/`__mc_double`
+/// variables. `-0.0` is hashed as `0.0`, since the two are equal; NaN is never equal to anything,
+/// so its hash doesn't matter
+fn hash_float(value: Expression, float: &str, bits: &str) -> Vec<Statement> {
+    let (copy, copy_bits) = (format!("__mc_{}", float), format!("__mc_{}_bits", float));
+    let normalized = ternary(binary(value.clone(), BinOp::IsEqual, int(0)), int(0), value);
+    let mut mixed = ident(&copy_bits);
+    if bits == "uint64_t" {
+        // Fold the upper half in, which a 32-bit `size_t` would otherwise drop
+        mixed = wrapped(binary(ident(&copy_bits), BinOp::BitXor, wrapped(binary(ident(&copy_bits), BinOp::RightShift, int(32)))));
+    }
+    vec![
+        assign(copy.clone(), normalized),
+        Statement::Expression(call(plain("memcpy"), vec![address_of(ident(&copy_bits)), address_of(ident(&copy)), size_of(Type::simple(plain(bits), 0))])),
+        mix_hash(cast(Type::simple(plain("size_t"), 0), mixed)),
+    ]
+}
+
+/// Mixes each character of a string into `hash`, which a null string leaves unchanged:
+/// `for (const char* __mc_c = value; __mc_c != NULL && *__mc_c != 0; __mc_c++) { ... }`
+fn hash_string(value: Expression) -> Statement {
+    let c = "__mc_c";
+    let typ = Type { qualifiers: vec![Qualifier::Const], ..Type::simple(plain("char"), 1) };
+    let character = cast(Type::simple(plain("size_t"), 0), cast(Type::simple(plain("unsigned char"), 0), unary(UnaryOp::Dereference, ident(c))));
+    Statement::For {
+        init: Box::new(var_dec(typ, c, Some(value))),
+        condition: binary(
+            binary(ident(c), BinOp::IsNotEqual, ident("NULL")),
+            BinOp::LogicAnd,
+            binary(unary(UnaryOp::Dereference, ident(c)), BinOp::IsNotEqual, int(0)),
+        ),
+        increment: Box::new(Statement::IncDec { identifier: IdentifierExpression::Standard(String::from(c)), is_inc: true }),
+        body: vec![mix_hash(character)],
+    }
+}
+
+/// Copies the string `value` into a new allocation stored in `target`, unless it's null
+fn copy_string(value: Expression, target: String) -> Vec<Statement> {
+    let copy = "__mc_copy";
+    let length = binary(call(plain("strlen"), vec![value.clone()]), BinOp::Add, int(1));
+    vec![if_then(binary(value.clone(), BinOp::IsNotEqual, ident("NULL")), vec![
+        var_dec(Type::simple(plain("char"), 1), copy, Some(call(plain("malloc"), vec![length]))),
+        if_then(binary(ident(copy), BinOp::IsNotEqual, ident("NULL")), vec![
+            Statement::Expression(call(plain("strcpy"), vec![ident(copy), value])),
+        ]),
+        assign(target, ident(copy)),
+    ])]
+}
+
+/// `fprintf(out, "format", args...)`
+fn fprintf(format: String, args: Vec<Expression>) -> Statement {
+    let mut all_args = vec![ident("out"), string(format.as_str())];
+    all_args.extend(args);
+    Statement::Expression(call(plain("fprintf"), all_args))
+}
@@ -0,0 +1,125 @@
+use std::fs;
+
+use crate::{analysis::symbols::SymbolTable, codegen::simple::SimpleCodeGen, parsing::ast::{expression::{Atom, ExprTail, Expression}, program::Program, statement::Statement, toplevel::TopLevel, types::{Qualifier, Type, TypeType}}};
+use anyhow::{anyhow, bail, Result};
+
+use super::{builder::plain, mutator::Mutator};
+
+/// The element types that hold exactly one byte of an embedded file
+const BYTE_TYPES: [&str; 5] = ["char", "signed char", "unsigned char", "int8_t", "uint8_t"];
+
+/// Reads the files of `embed` initializers. `const u8 data[] = embed("font.bin");` becomes
+/// `const u8 data[3] = {0x89, 0x50, 0x4e};` with the file's bytes, followed by
+/// `const size_t data__len = 3;`, which has the same storage classes as the array.
+///
+/// An array of `char` also gets a terminating zero, which the length leaves out, so that an
+/// embedded text file can be used as a string. Only global arrays can be embedded, since a local
+/// one would be copied onto the stack at every call.
+///
+/// The element type must be one of `BYTE_TYPES`, possibly through a typedef, since each byte of the
+/// file initializes one element.
+pub struct EmbedResolver {
+    /// The files that were read, in the order they're first embedded
+    dependencies: Vec<String>,
+}
+
+impl Default for EmbedResolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EmbedResolver {
+    pub fn new() -> Self {
+        EmbedResolver { dependencies: Vec::new() }
+    }
+
+    /// The files that the generated code depends on
+    pub fn dependencies(&self) -> &Vec<String> {
+        &self.dependencies
+    }
+
+    pub fn resolve_program(&mut self, program: Program) -> Result<Program> {
+        let symbols = SymbolTable::collect(&program);
+        let mut statements = self.resolve_toplevels(&symbols, program.statements, "")?;
+        if !self.dependencies.is_empty() {
+            statements.insert(0, TopLevel::Import { name: String::from("stddef.h"), is_lib: true });
+        }
+        Ok(Program {
+            statements,
+        })
+    }
+
+    fn resolve_toplevels(&mut self, symbols: &SymbolTable, toplevels: Vec<TopLevel>, prefix: &str) -> Result<Vec<TopLevel>> {
+        let mut result = Vec::new();
+        for t in toplevels {
+            match t {
+                TopLevel::VarDeclaration { typ, name, array_size: None, right: Some(Expression { atom: Atom::Embed(path), tail: ExprTail::None }) } => {
+                    result.extend(self.embed(symbols, prefix, typ, name, path)?);
+                },
+                TopLevel::Module { name, body } => {
+                    let body = self.resolve_toplevels(symbols, body, &format!("mod__{}__", name))?;
+                    result.push(TopLevel::Module { name, body });
+                },
+                TopLevel::Function(mut func) => {
+                    func.body = Self::local_checker().mutate_body(func.body)?;
+                    result.push(TopLevel::Function(func));
+                },
+                other => result.push(other),
+            }
+        }
+        Ok(result)
+    }
+
+    /// The array `name` with the bytes of the file at `path`, and its length
+    fn embed(&mut self, symbols: &SymbolTable, prefix: &str, typ: Type, name: String, path: String) -> Result<Vec<TopLevel>> {
+        // A typedef declared in the same module is keyed by its mangled name
+        let local = format!("{}{}", prefix, typ.name.generate());
+        let element = match symbols.typedefs.contains_key(&local) {
+            true => symbols.resolve_typedefs(&Type { name: plain(&local), ..typ.clone() }),
+            false => symbols.resolve_typedefs(&typ),
+        };
+        if element.pointer_layers() > 0 || !matches!(element.typetype, TypeType::Simple) || !BYTE_TYPES.contains(&element.name.generate().as_str()) {
+            bail!("Cannot embed '{}' in '{}', since it must be an array of bytes ({}), not of '{}'", path, name, BYTE_TYPES.join(", "), typ.describe());
+        }
+        let mut bytes = fs::read(&path).map_err(|e| anyhow!("Cannot embed '{}' in '{}': {}", path, name, e))?;
+        if bytes.is_empty() {
+            bail!("Cannot embed the empty file '{}' in '{}', since C has no empty arrays", path, name);
+        }
+        let length = i32::try_from(bytes.len()).map_err(|_| anyhow!("Cannot embed '{}' in '{}', since it's larger than 2 GiB", path, name))?;
+        if !self.dependencies.contains(&path) {
+            self.dependencies.push(path);
+        }
+        if typ.name == plain("char") {
+            bytes.push(0);
+        }
+
+        let length_type = Type { storage: typ.storage.clone(), qualifiers: vec![Qualifier::Const], ..Type::simple(plain("size_t"), 0) };
+        Ok(vec![
+            TopLevel::VarDeclaration {
+                typ,
+                name: name.clone(),
+                array_size: Some(bytes.len()),
+                right: Some(Expression::new(Atom::Bytes(bytes), ExprTail::None)),
+            },
+            TopLevel::VarDeclaration {
+                typ: length_type,
+                name: format!("{}__len", name),
+                array_size: None,
+                right: Some(Expression::new(Atom::Int(length), ExprTail::None)),
+            },
+        ])
+    }
+
+    /// Rejects `embed` in the local variables of a function
+    fn local_checker() -> Mutator {
+        let mut checker = Mutator::new();
+        checker.add_statement_closure(Box::new(|s| match &s {
+            Statement::VarDec { name, right: Some(Expression { atom: Atom::Embed(_), .. }), .. } => {
+                bail!("Cannot embed a file in the local '{}', since it would be copied onto the stack at every call. Declare it globally instead", name)
+            },
+            _ => Ok(s),
+        }));
+        checker
+    }
+}
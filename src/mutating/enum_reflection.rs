@@ -0,0 +1,112 @@
+use std::{cell::RefCell, collections::HashSet, rc::Rc};
+
+use crate::{codegen::simple::SimpleCodeGen, parsing::ast::{enumm::{Enum, REFLECTION_MEMBERS}, expression::{Atom, Expression}, identifier::Identifier, program::Program, statement::Statement, toplevel::TopLevel}};
+use anyhow::Result;
+
+use super::mutator::Mutator;
+
+/// Resolves `Color::COUNT`, `Color::values`, `Color::name` etc. to the helpers
+/// generated alongside each top-level enum. Enums inside modules don't need this,
+/// since the parser already turns `sl::Color::COUNT` into `sl::Color__COUNT`.
+pub struct EnumReflectionResolver {
+    enums: HashSet<String>,
+    modules: HashSet<String>,
+}
+
+impl EnumReflectionResolver {
+    pub fn new(program: &Program) -> Self {
+        let mut enums = HashSet::new();
+        let mut modules = HashSet::new();
+        for t in &program.statements {
+            match t {
+                TopLevel::Enum(e) if !e.is_anonymous => {
+                    enums.insert(e.name.clone());
+                },
+                TopLevel::Module { name, .. } => {
+                    modules.insert(name.clone());
+                },
+                _ => (),
+            }
+        }
+        EnumReflectionResolver {
+            enums,
+            modules,
+        }
+    }
+
+    pub fn into_mutator(self) -> Mutator {
+        let mut mutator = Mutator::new();
+        mutator.add_expression_closure(Box::new(move |e| self.resolve(e)));
+        mutator
+    }
+
+    fn resolve(&self, expression: Expression) -> Result<Expression> {
+        if let Atom::Identifier(Identifier::Module(parent, child)) = &expression.atom {
+            // Modules take priority, so an enum can't shadow a module of the same name
+            if self.enums.contains(parent) && !self.modules.contains(parent) && REFLECTION_MEMBERS.contains(&child.as_str()) {
+                let name = format!("{}__{}", parent, child);
+                return Ok(Expression { atom: Atom::Identifier(Identifier::Plain(name)), tail: expression.tail });
+            }
+        }
+        Ok(expression)
+    }
+}
+
+/// Marks the enums whose reflection helpers are used by the finished program, e.g. by
+/// `Color::name`, a `for (Color c : Color)` loop or derived JSON code, so that only those
+/// enums get them. Runs last, once every helper that later passes refer to exists.
+pub struct EnumReflectionUsage {
+    /// The C names of the used enums, e.g. `mod__sl__Color`
+    used: Rc<RefCell<HashSet<String>>>,
+}
+
+impl Default for EnumReflectionUsage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EnumReflectionUsage {
+    pub fn new() -> Self {
+        EnumReflectionUsage { used: Rc::new(RefCell::new(HashSet::new())) }
+    }
+
+    pub fn mark_program(self, program: Program) -> Result<Program> {
+        let mut collector = Mutator::new();
+        let used_by_expressions = self.used.clone();
+        collector.add_expression_closure(Box::new(move |e| {
+            if let Atom::Identifier(ident) = &e.atom {
+                let name = ident.generate();
+                for member in REFLECTION_MEMBERS {
+                    if let Some(enum_name) = name.strip_suffix(member).and_then(|n| n.strip_suffix("__")) {
+                        used_by_expressions.borrow_mut().insert(String::from(enum_name));
+                    }
+                }
+            }
+            Ok(e)
+        }));
+        let used_by_statements = self.used.clone();
+        collector.add_statement_closure(Box::new(move |s| {
+            if let Statement::EnumFor { enum_type, .. } = &s {
+                used_by_statements.borrow_mut().insert(enum_type.name.generate());
+            }
+            Ok(s)
+        }));
+        let program = collector.mutate_program(program)?;
+        Ok(Program { statements: self.mark_toplevels(program.statements, "") })
+    }
+
+    fn mark_toplevels(&self, toplevels: Vec<TopLevel>, name_prefix: &str) -> Vec<TopLevel> {
+        toplevels.into_iter().map(|t| match t {
+            TopLevel::Enum(e) if !e.is_anonymous => {
+                let reflected = self.used.borrow().contains(&format!("{}{}", name_prefix, e.name));
+                TopLevel::Enum(Enum { reflected, ..e })
+            },
+            TopLevel::Module { name, body } => {
+                let body = self.mark_toplevels(body, &format!("mod__{}__", name));
+                TopLevel::Module { name, body }
+            },
+            other => other,
+        }).collect()
+    }
+}
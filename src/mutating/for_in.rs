@@ -0,0 +1,153 @@
+use std::collections::HashMap;
+
+use crate::{analysis::scopes::{Scoped, ScopedLowering}, codegen::simple::SimpleCodeGen, parsing::ast::{expression::{Atom, ExprTail, Expression}, function::Function, program::Program, statement::ForIterable, toplevel::TopLevel, types::Type}};
+use anyhow::{bail, Result};
+
+/// A name in scope, along with its element type and length if it is a fixed-size array
+type ScopeEntry = Option<(Type, usize)>;
+
+/// Checks `for (T x in ...)` loops and fills in what codegen needs to know about the
+/// array being iterated over: its length and whether `x` points at each element or
+/// holds a copy of it. Arrays are looked up by name through the enclosing scopes,
+/// so only variables declared with a size (e.g. `int values[4]`) can be iterated.
+pub struct ForInResolver {
+    globals: HashMap<String, (Type, usize)>,
+    scopes: Vec<HashMap<String, ScopeEntry>>,
+}
+
+impl ForInResolver {
+    pub fn new(program: &Program) -> Self {
+        let mut globals = HashMap::new();
+        Self::collect_globals(&program.statements, &String::new(), &mut globals);
+        ForInResolver {
+            globals,
+            scopes: Vec::new(),
+        }
+    }
+
+    fn collect_globals(toplevels: &Vec<TopLevel>, name_prefix: &String, globals: &mut HashMap<String, (Type, usize)>) {
+        for t in toplevels {
+            match t {
+                TopLevel::VarDeclaration { typ, name, array_size: Some(size), .. } => {
+                    globals.insert(format!("{}{}", name_prefix, name), (typ.clone(), *size));
+                },
+                TopLevel::Module { name, body } => {
+                    Self::collect_globals(body, &format!("mod__{}__", name), globals);
+                },
+                _ => (),
+            }
+        }
+    }
+
+    pub fn resolve_program(mut self, program: Program) -> Result<Program> {
+        Ok(Program {
+            statements: self.resolve_toplevels(program.statements)?,
+        })
+    }
+
+    fn resolve_toplevels(&mut self, toplevels: Vec<TopLevel>) -> Result<Vec<TopLevel>> {
+        let mut result = Vec::new();
+        for t in toplevels {
+            result.push(match t {
+                TopLevel::Function(func) => {
+                    // Parameters can shadow global arrays, and are never arrays themselves
+                    let params = func.header.params.iter().map(|p| (p.name.clone(), None)).collect();
+                    self.scopes.push(params);
+                    let body = self.lower_body(func.body);
+                    self.scopes.pop();
+                    TopLevel::Function(Function { header: func.header, body: body? })
+                },
+                TopLevel::Module { name, body } => TopLevel::Module { name, body: self.resolve_toplevels(body)? },
+                other => other,
+            });
+        }
+        Ok(result)
+    }
+
+    /// Finds the declaration of an array named by a plain or module identifier
+    fn lookup_array(&self, array: &Expression) -> Option<(Type, usize)> {
+        let name = match (&array.atom, &array.tail) {
+            (Atom::Identifier(ident), ExprTail::None) => ident.generate(),
+            _ => return None,
+        };
+        for scope in self.scopes.iter().rev() {
+            if let Some(entry) = scope.get(&name) {
+                return entry.clone();
+            }
+        }
+        self.globals.get(&name).cloned()
+    }
+}
+
+impl Scoped for ForInResolver {
+    fn push_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    fn pop_scope(&mut self) {
+        self.scopes.pop();
+    }
+
+    fn declare(&mut self, name: &str, typ: &Type, array_size: Option<usize>) {
+        self.scopes.last_mut().unwrap().insert(String::from(name), array_size.map(|size| (typ.clone(), size)));
+    }
+}
+
+/// Only loops are resolved, so expressions are left as they are
+impl ScopedLowering for ForInResolver {
+    fn lower_expression(&mut self, expression: Expression) -> Result<Expression> {
+        Ok(expression)
+    }
+
+    fn lower_iterable(&mut self, typ: &Type, name: &String, iterable: ForIterable) -> Result<ForIterable> {
+        match iterable {
+            ForIterable::Range { start, end } => {
+                if typ.pointer_layers() > 0 {
+                    bail!("Cannot loop '{}' over a range: '{}' is not an integer type", name, typ.generate());
+                }
+                Ok(ForIterable::Range { start, end })
+            },
+            ForIterable::Array { array, .. } => {
+                let (element_type, length) = match self.lookup_array(&array) {
+                    Some(found) => found,
+                    None => bail!("Cannot iterate over '{}': it is not an array with a known length", array.generate()),
+                };
+                let by_reference = element_binding(typ, name, &element_type, &array)?;
+                Ok(ForIterable::Array { array, length: Some(length), by_reference })
+            },
+            ForIterable::Slice { slice, by_reference } => Ok(ForIterable::Slice { slice, by_reference }),
+        }
+    }
+}
+
+/// Checks that a loop variable of type `typ` can take the elements of `iterable`, returning
+/// whether it takes them by pointer
+pub(super) fn element_binding(typ: &Type, name: &String, element_type: &Type, iterable: &Expression) -> Result<bool> {
+    // Qualifiers can be added but not dropped, e.g. `const int* x` can point into an `int` array
+    let element = element_type.unqualified();
+    let by_reference = if typ.unqualified().generate() == element.generate() {
+        false
+    } else if typ.pointer_layers() > 0 && same_or_more_qualified(&pointee(typ), element_type) {
+        true
+    } else {
+        let shown = element_type.without_storage().generate();
+        bail!(
+            "Cannot iterate over '{}' with '{} {}': elements have type '{}' (use '{}' or '{}*')",
+            iterable.generate(), typ.generate(), name, shown, shown, shown
+        );
+    };
+    Ok(by_reference)
+}
+
+fn pointee(typ: &Type) -> Type {
+    let mut inner = typ.without_storage();
+    inner.pointers.pop();
+    inner
+}
+
+pub(super) fn same_or_more_qualified(pointee: &Type, element: &Type) -> bool {
+    let outer_qualifiers = |t: &Type| t.pointers.last().unwrap_or(&t.qualifiers).clone();
+    let element_qualifiers = outer_qualifiers(element);
+    element_qualifiers.iter().all(|q| outer_qualifiers(pointee).contains(q))
+        && pointee.unqualified().generate() == element.unqualified().generate()
+}
@@ -0,0 +1,231 @@
+use crate::{analysis::{scopes::{Scoped, ScopedLowering}, typing::ExpressionTyper}, parsing::ast::{expression::{Atom, ExprTail, Expression, StringPart}, function::Function, lambda::Lambda, program::Program, statement::Statement, toplevel::TopLevel, types::Type}};
+use anyhow::{anyhow, bail, Result};
+
+use super::{builder::{cast, init_list, member, var_dec}, overload::Overloads};
+
+/// Replaces each `let name = value` with a declaration of the value's inferred type, and calls
+/// to overloaded functions with calls to the overload chosen by the types of the arguments.
+///
+/// Tuples are built and taken apart here too, since that needs the types of the function and
+/// the value: `return (a, b)` becomes `return (T){a, b}` for the function's tuple type `T`, and
+/// `let (a, b) = value` copies the value into a hidden variable and declares `a` and `b` from its fields.
+pub struct LetInference {
+    typer: ExpressionTyper,
+    overloads: Overloads,
+    /// The return types of the function and lambdas being inferred, innermost last
+    return_types: Vec<Type>,
+    /// The number of tuples destructured in the current function, for naming their hidden variables
+    tuple_count: usize,
+}
+
+impl LetInference {
+    /// `program` must already have its overloads renamed by `Overloads::mangle_program`
+    pub fn new(program: &Program, overloads: Overloads) -> Self {
+        LetInference {
+            typer: ExpressionTyper::new(program),
+            overloads,
+            return_types: Vec::new(),
+            tuple_count: 0,
+        }
+    }
+
+    pub fn infer_program(mut self, program: Program) -> Result<Program> {
+        Ok(Program {
+            statements: self.infer_toplevels(program.statements)?,
+        })
+    }
+
+    fn infer_toplevels(&mut self, toplevels: Vec<TopLevel>) -> Result<Vec<TopLevel>> {
+        let mut result = Vec::new();
+        for t in toplevels {
+            result.push(match t {
+                TopLevel::Function(func) => {
+                    self.typer.push_scope();
+                    self.typer.declare_params(&func.header.params);
+                    self.return_types.push(func.header.return_type.clone());
+                    self.tuple_count = 0;
+                    let body = self.lower_body(func.body);
+                    self.return_types.pop();
+                    self.typer.pop_scope();
+                    TopLevel::Function(Function { header: func.header, body: body? })
+                },
+                TopLevel::Module { name, body } => TopLevel::Module { name, body: self.infer_toplevels(body)? },
+                other => other,
+            });
+        }
+        Ok(result)
+    }
+
+    /// `let (a, b) = right` => `T __mc_tuple0 = right; A a = __mc_tuple0._0; B b = __mc_tuple0._1;`
+    fn destructure(&mut self, names: Vec<String>, right: Expression) -> Result<Vec<Statement>> {
+        let pattern = format!("({})", names.join(", "));
+        let right = self.infer_expression(right)?;
+        let typ = self.typer.type_of(&right).map_err(|e| anyhow!("Cannot infer the type of '{}': {}", pattern, e))?.unqualified();
+        let Some(elements) = self.typer.tuple_elements(&typ) else {
+            bail!("Cannot destructure '{}' into '{}', since it isn't a tuple", typ.describe(), pattern);
+        };
+        if elements.len() != names.len() {
+            bail!("Cannot destructure '{}' into '{}', since it has {} elements", typ.describe(), pattern, elements.len());
+        }
+        let tuple = format!("__mc_tuple{}", self.tuple_count);
+        self.tuple_count += 1;
+        self.typer.declare(&tuple, &typ, None);
+        let mut result = vec![var_dec(typ, &tuple, Some(right))];
+        for (i, (name, element)) in names.iter().zip(elements).enumerate() {
+            if name == "_" {
+                continue;
+            }
+            let element = element.unqualified();
+            self.typer.declare(name, &element, None);
+            result.push(var_dec(element, name, Some(member(&tuple, &format!("_{}", i)))));
+        }
+        Ok(result)
+    }
+
+    /// `return (a, b)` => `return (T){a, b}`
+    fn build_tuple(&mut self, values: Vec<Expression>) -> Result<Statement> {
+        let typ = self.return_types.last().unwrap().unqualified();
+        match self.typer.tuple_elements(&typ) {
+            Some(elements) if elements.len() == values.len() => (),
+            _ => bail!("Cannot return {} values from a function returning '{}'", values.len(), typ.describe()),
+        }
+        let values = values.into_iter().map(|v| self.infer_expression(v)).collect::<Result<_, _>>()?;
+        Ok(Statement::Return(Some(cast(typ, init_list(values)))))
+    }
+
+    /// Expressions are visited for the bodies of lambdas inside them, and for calls to overloaded functions
+    fn infer_expression(&mut self, expression: Expression) -> Result<Expression> {
+        let expression = Expression { atom: self.infer_atom(expression.atom)?, tail: self.infer_tail(expression.tail)? };
+        self.resolve_overload(expression)
+    }
+
+    /// Arguments are resolved first, so that their types are known
+    fn resolve_overload(&self, expression: Expression) -> Result<Expression> {
+        let Atom::Identifier(callee) = &expression.atom else { return Ok(expression) };
+        if !self.overloads.is_overloaded(callee) {
+            return Ok(expression);
+        }
+        let name = self.overloads.describe(callee);
+        let ExprTail::Call { body, .. } = &expression.tail else {
+            bail!("Overloaded function '{}' can only be called, since its C name depends on the arguments", name);
+        };
+        let args = body.iter().map(|a| self.typer.type_of(a)).collect::<Result<Vec<_>>>()
+            .map_err(|e| anyhow!("Cannot choose an overload of '{}': {}", name, e))?;
+        let resolved = self.overloads.resolve(callee, &args, &self.typer)?;
+        Ok(Expression { atom: Atom::Identifier(resolved), tail: expression.tail })
+    }
+
+    fn infer_atom(&mut self, atom: Atom) -> Result<Atom> {
+        match atom {
+            Atom::Lambda(lambda) => {
+                let Lambda { captures, params, is_variadic, return_type, body } = *lambda;
+                self.typer.push_scope();
+                self.typer.declare_params(&params);
+                self.return_types.push(return_type.clone());
+                let body = self.lower_body(body);
+                self.return_types.pop();
+                self.typer.pop_scope();
+                Ok(Atom::Lambda(Box::new(Lambda { captures, params, is_variadic, return_type, body: body? })))
+            },
+            Atom::TypeCast { typ, value } => Ok(Atom::TypeCast { typ, value: Box::new(self.infer_expression(*value)?) }),
+            Atom::VaArg { list, typ } => Ok(Atom::VaArg { list: Box::new(self.infer_expression(*list)?), typ }),
+            Atom::UnaryOperation { op, value } => Ok(Atom::UnaryOperation { op, value: Box::new(self.infer_expression(*value)?) }),
+            Atom::Wrapped(value) => Ok(Atom::Wrapped(Box::new(self.infer_expression(*value)?))),
+            Atom::InitList(values) => Ok(Atom::InitList(values.into_iter().map(|v| self.infer_expression(v)).collect::<Result<_, _>>()?)),
+            Atom::Interpolated(parts) => {
+                let mut inferred = Vec::new();
+                for part in parts {
+                    inferred.push(match part {
+                        StringPart::Value { value, format } => StringPart::Value { value: Box::new(self.infer_expression(*value)?), format },
+                        text => text,
+                    });
+                }
+                Ok(Atom::Interpolated(inferred))
+            },
+            other => Ok(other),
+        }
+    }
+
+    fn infer_tail(&mut self, tail: ExprTail) -> Result<ExprTail> {
+        match tail {
+            ExprTail::None => Ok(ExprTail::None),
+            ExprTail::Call { body, named, next } => {
+                let body = body.into_iter().map(|a| self.infer_expression(a)).collect::<Result<_, _>>()?;
+                Ok(ExprTail::Call { body, named, next: Box::new(self.infer_tail(*next)?) })
+            },
+            ExprTail::BinaryOp { op, right, next } => {
+                Ok(ExprTail::BinaryOp { op, right: Box::new(self.infer_expression(*right)?), next: Box::new(self.infer_tail(*next)?) })
+            },
+            ExprTail::MemberAccess { member, next } => Ok(ExprTail::MemberAccess { member, next: Box::new(self.infer_tail(*next)?) }),
+            ExprTail::PointerAccess { member, next } => Ok(ExprTail::PointerAccess { member, next: Box::new(self.infer_tail(*next)?) }),
+            ExprTail::Index { inner, next } => {
+                Ok(ExprTail::Index { inner: Box::new(self.infer_expression(*inner)?), next: Box::new(self.infer_tail(*next)?) })
+            },
+            ExprTail::Slice { start, end, next } => {
+                Ok(ExprTail::Slice {
+                    start: Box::new(self.infer_expression(*start)?),
+                    end: Box::new(self.infer_expression(*end)?),
+                    next: Box::new(self.infer_tail(*next)?),
+                })
+            },
+            ExprTail::TernaryConditional { second, third, next } => {
+                Ok(ExprTail::TernaryConditional {
+                    second: Box::new(self.infer_expression(*second)?),
+                    third: Box::new(self.infer_expression(*third)?),
+                    next: Box::new(self.infer_tail(*next)?),
+                })
+            },
+            ExprTail::SafeAccess { member, next } => Ok(ExprTail::SafeAccess { member, next: Box::new(self.infer_tail(*next)?) }),
+            ExprTail::Coalesce { fallback, next } => {
+                Ok(ExprTail::Coalesce { fallback: Box::new(self.infer_expression(*fallback)?), next: Box::new(self.infer_tail(*next)?) })
+            },
+        }
+    }
+}
+
+impl Scoped for LetInference {
+    fn push_scope(&mut self) {
+        self.typer.push_scope();
+    }
+
+    fn pop_scope(&mut self) {
+        self.typer.pop_scope();
+    }
+
+    fn declare(&mut self, name: &str, typ: &Type, array_size: Option<usize>) {
+        self.typer.declare(name, typ, array_size);
+    }
+}
+
+impl ScopedLowering for LetInference {
+    fn lower_expression(&mut self, expression: Expression) -> Result<Expression> {
+        self.infer_expression(expression)
+    }
+
+    fn lower_statements(&mut self, body: Vec<Statement>) -> Result<Vec<Statement>> {
+        let mut result = Vec::new();
+        for statement in body {
+            match statement {
+                Statement::LetTuple { names, right } => result.extend(self.destructure(names, right)?),
+                other => result.push(self.lower_statement(other)?),
+            }
+        }
+        Ok(result)
+    }
+
+    fn lower_statement(&mut self, statement: Statement) -> Result<Statement> {
+        match statement {
+            Statement::Let { name, right } => {
+                let right = self.infer_expression(right)?;
+                // Like C23's `auto`, the variable doesn't inherit qualifiers from the value it is copied from
+                let typ = self.typer.type_of(&right).map_err(|e| anyhow!("Cannot infer the type of '{}': {}", name, e))?.unqualified();
+                self.typer.declare(&name, &typ, None);
+                Ok(Statement::VarDec { typ, name, array_size: None, right: Some(right) })
+            },
+            // Destructuring declares several variables, so it has to be a statement of its own
+            Statement::LetTuple { names, .. } => bail!("'let ({})' can only be used directly inside a block", names.join(", ")),
+            Statement::ReturnTuple(values) => self.build_tuple(values),
+            other => self.walk_statement(other),
+        }
+    }
+}
@@ -0,0 +1,359 @@
+use crate::{analysis::{scopes::{Scoped, ScopedLowering}, symbols::SymbolTable, typing::ExpressionTyper}, codegen::simple::SimpleCodeGen, parsing::ast::{expression::{Atom, BinOp, ExprTail, Expression, StringPart}, function::Function, lambda::Lambda, program::Program, statement::{CaseStatement, ConditionBody, IdentifierExpression, Statement}, toplevel::TopLevel, types::Type}};
+use anyhow::{anyhow, bail, Result};
+
+use super::{builder::{call, cast, ident, int, mc, operand, plain, string, ternary, var_dec}, derive::{classify, FieldKind}};
+
+/// Lowers interpolated strings `$"text {value}"` to `printf`-style formatting:
+/// - `char buffer[N] = $"..."`, and assigning one to a `char` array, becomes
+///   `snprintf(buffer, N, "...", values...)`
+/// - initializing, assigning or returning a pointer becomes `mc::string("...", values...)`,
+///   which allocates the result for the code that receives it
+/// - any other use in a statement, like an argument, is allocated into a temporary before the
+///   statement and freed after it. Where that isn't possible, such as in a loop condition or an
+///   operand that's only evaluated sometimes, the string is rejected.
+///
+/// A value written without a conversion gets one from its type: integers are printed as
+/// `long long` or `unsigned long long`, floating-point numbers with `%g` (`%Lg` for
+/// `long double`), `char` with `%c`, `bool` as `true` or `false`, strings with `%s` and other
+/// pointers with `%p`.
+pub struct StringInterpolation {
+    typer: ExpressionTyper,
+    symbols: SymbolTable,
+    /// Whether `mc::string` is used, so that its header has to be included
+    uses_runtime: bool,
+    /// Whether an interpolated string in the expression being lowered can become a temporary
+    hoisting: bool,
+    /// The temporaries of the statement being lowered, and the strings they hold
+    temporaries: Vec<(String, Expression)>,
+    temporary_count: usize,
+}
+
+impl StringInterpolation {
+    pub fn new(program: &Program) -> Self {
+        StringInterpolation {
+            typer: ExpressionTyper::new(program),
+            symbols: SymbolTable::collect(program),
+            uses_runtime: false,
+            hoisting: false,
+            temporaries: Vec::new(),
+            temporary_count: 0,
+        }
+    }
+
+    pub fn lower_program(mut self, program: Program) -> Result<Program> {
+        let mut statements = self.lower_toplevels(program.statements)?;
+        if self.uses_runtime {
+            statements.insert(0, TopLevel::Import { name: String::from("mc/string.h"), is_lib: false });
+        }
+        Ok(Program {
+            statements,
+        })
+    }
+
+    fn lower_toplevels(&mut self, toplevels: Vec<TopLevel>) -> Result<Vec<TopLevel>> {
+        let mut result = Vec::new();
+        for t in toplevels {
+            result.push(match t {
+                TopLevel::Function(func) => {
+                    self.temporary_count = 0;
+                    self.typer.push_scope();
+                    self.typer.declare_params(&func.header.params);
+                    let body = self.lower_body(func.body);
+                    self.typer.pop_scope();
+                    TopLevel::Function(Function { header: func.header, body: body? })
+                },
+                TopLevel::VarDeclaration { name, right: Some(Expression { atom: Atom::Interpolated(_), .. }), .. } => {
+                    bail!("Cannot initialize the global '{}' with an interpolated string, since it's built at run time", name);
+                },
+                TopLevel::Module { name, body } => TopLevel::Module { name, body: self.lower_toplevels(body)? },
+                other => other,
+            });
+        }
+        Ok(result)
+    }
+
+    /// Lowers an expression whose value is stored or returned, so that an interpolated string
+    /// making up all of it is allocated for whoever receives it
+    fn lower_owned(&mut self, expression: Expression) -> Result<Expression> {
+        match expression {
+            Expression { atom: Atom::Interpolated(parts), tail: ExprTail::None } => self.allocate(parts),
+            other => self.lower_hoisting(other),
+        }
+    }
+
+    /// Lowers an expression that's evaluated once per statement, whose interpolated strings can
+    /// be temporaries
+    fn lower_hoisting(&mut self, expression: Expression) -> Result<Expression> {
+        let outer = std::mem::replace(&mut self.hoisting, true);
+        let result = self.lower_expression(expression);
+        self.hoisting = outer;
+        result
+    }
+
+    /// Lowers an operand that's only evaluated sometimes, where a temporary would change when
+    /// the string is built
+    fn lower_conditional(&mut self, expression: Expression) -> Result<Expression> {
+        let outer = std::mem::replace(&mut self.hoisting, false);
+        let result = self.lower_expression(expression);
+        self.hoisting = outer;
+        result
+    }
+
+    fn unowned_error() -> &'static str {
+        "Cannot use an interpolated string here, since nothing could free it. Assign it to a variable and free that instead"
+    }
+
+    fn lower_atom(&mut self, atom: Atom) -> Result<Atom> {
+        match atom {
+            Atom::Lambda(lambda) => {
+                let Lambda { captures, params, is_variadic, return_type, body } = *lambda;
+                self.typer.push_scope();
+                self.typer.declare_params(&params);
+                let body = self.lower_body(body);
+                self.typer.pop_scope();
+                Ok(Atom::Lambda(Box::new(Lambda { captures, params, is_variadic, return_type, body: body? })))
+            },
+            Atom::TypeCast { typ, value } => Ok(Atom::TypeCast { typ, value: Box::new(self.lower_expression(*value)?) }),
+            Atom::VaArg { list, typ } => Ok(Atom::VaArg { list: Box::new(self.lower_expression(*list)?), typ }),
+            Atom::UnaryOperation { op, value } => Ok(Atom::UnaryOperation { op, value: Box::new(self.lower_expression(*value)?) }),
+            Atom::Wrapped(value) => Ok(Atom::Wrapped(Box::new(self.lower_expression(*value)?))),
+            Atom::InitList(values) => Ok(Atom::InitList(values.into_iter().map(|v| self.lower_expression(v)).collect::<Result<_, _>>()?)),
+            other => Ok(other),
+        }
+    }
+
+    fn lower_tail(&mut self, tail: ExprTail) -> Result<ExprTail> {
+        match tail {
+            ExprTail::None => Ok(ExprTail::None),
+            ExprTail::Call { body, named, next } => {
+                let body = body.into_iter().map(|a| self.lower_expression(a)).collect::<Result<_, _>>()?;
+                Ok(ExprTail::Call { body, named, next: Box::new(self.lower_tail(*next)?) })
+            },
+            ExprTail::BinaryOp { op, right, next } => {
+                let right = match op {
+                    BinOp::LogicAnd | BinOp::LogicOr => self.lower_conditional(*right)?,
+                    _ => self.lower_expression(*right)?,
+                };
+                Ok(ExprTail::BinaryOp { op, right: Box::new(right), next: Box::new(self.lower_tail(*next)?) })
+            },
+            ExprTail::MemberAccess { member, next } => Ok(ExprTail::MemberAccess { member, next: Box::new(self.lower_tail(*next)?) }),
+            ExprTail::PointerAccess { member, next } => Ok(ExprTail::PointerAccess { member, next: Box::new(self.lower_tail(*next)?) }),
+            ExprTail::SafeAccess { member, next } => Ok(ExprTail::SafeAccess { member, next: Box::new(self.lower_tail(*next)?) }),
+            ExprTail::Index { inner, next } => {
+                Ok(ExprTail::Index { inner: Box::new(self.lower_expression(*inner)?), next: Box::new(self.lower_tail(*next)?) })
+            },
+            ExprTail::Slice { start, end, next } => {
+                Ok(ExprTail::Slice {
+                    start: Box::new(self.lower_expression(*start)?),
+                    end: Box::new(self.lower_expression(*end)?),
+                    next: Box::new(self.lower_tail(*next)?),
+                })
+            },
+            ExprTail::TernaryConditional { second, third, next } => {
+                Ok(ExprTail::TernaryConditional {
+                    second: Box::new(self.lower_conditional(*second)?),
+                    third: Box::new(self.lower_conditional(*third)?),
+                    next: Box::new(self.lower_tail(*next)?),
+                })
+            },
+            ExprTail::Coalesce { fallback, next } => {
+                Ok(ExprTail::Coalesce { fallback: Box::new(self.lower_conditional(*fallback)?), next: Box::new(self.lower_tail(*next)?) })
+            },
+        }
+    }
+
+    /// `snprintf(buffer, size, "...", values...)`
+    fn format_into(&mut self, buffer: &str, size: usize, parts: Vec<StringPart>) -> Result<Expression> {
+        let mut args = vec![ident(buffer), int(size as i32)];
+        args.extend(self.format(parts)?);
+        Ok(call(plain("snprintf"), args))
+    }
+
+    /// `mc::string("...", values...)`
+    fn allocate(&mut self, parts: Vec<StringPart>) -> Result<Expression> {
+        self.uses_runtime = true;
+        Ok(call(mc("string"), self.format(parts)?))
+    }
+
+    /// The format string and the values it prints
+    fn format(&mut self, parts: Vec<StringPart>) -> Result<Vec<Expression>> {
+        let mut format = String::new();
+        let mut args = Vec::new();
+        for part in parts {
+            match part {
+                StringPart::Text(text) => format.push_str(text.replace('%', "%%").as_str()),
+                StringPart::Value { value, format: Some(conversion) } => {
+                    format.push_str(conversion.as_str());
+                    args.push(self.lower_expression(*value)?);
+                },
+                StringPart::Value { value, format: None } => {
+                    let typ = self.typer.type_of(&value).map_err(|e| anyhow!("Cannot interpolate '{}': {}", value.generate(), e))?;
+                    let written = value.generate();
+                    let lowered = self.lower_expression(*value)?;
+                    let (conversion, arg) = self.conversion(&typ, &written, lowered)?;
+                    format.push_str(conversion);
+                    args.push(arg);
+                },
+            }
+        }
+        args.insert(0, string(format.as_str()));
+        Ok(args)
+    }
+
+    /// The conversion that prints a value of type `typ`, and the value as it's passed to it
+    fn conversion(&self, typ: &Type, written: &String, value: Expression) -> Result<(&'static str, Expression)> {
+        Ok(match classify(&self.symbols, typ) {
+            FieldKind::Integer | FieldKind::Enum(_) if self.is_unsigned(typ) => ("%llu", cast(Type::simple(plain("unsigned long long"), 0), operand(value))),
+            FieldKind::Integer | FieldKind::Enum(_) => ("%lld", cast(Type::simple(plain("long long"), 0), operand(value))),
+            FieldKind::Float if self.symbols.resolve_typedefs(typ).name.generate() == "long double" => ("%Lg", value),
+            FieldKind::Float => ("%g", value),
+            FieldKind::Char => ("%c", value),
+            FieldKind::Bool => ("%s", ternary(operand(value), string("true"), string("false"))),
+            FieldKind::String => ("%s", value),
+            FieldKind::Pointer => ("%p", cast(Type::simple(plain("void"), 1), operand(value))),
+            _ => bail!("Cannot interpolate '{}' of type '{}' without a conversion, e.g. '{{{}:%d}}'", written, typ.describe(), written),
+        })
+    }
+
+    fn is_char(&self, typ: &Type) -> bool {
+        matches!(classify(&self.symbols, typ), FieldKind::Char)
+    }
+
+    fn is_unsigned(&self, typ: &Type) -> bool {
+        let name = self.symbols.resolve_typedefs(typ).name.generate();
+        name.contains("unsigned") || name.starts_with('u') || name == "size_t"
+    }
+}
+
+impl Scoped for StringInterpolation {
+    fn push_scope(&mut self) {
+        self.typer.push_scope();
+    }
+
+    fn pop_scope(&mut self) {
+        self.typer.pop_scope();
+    }
+
+    fn declare(&mut self, name: &str, typ: &Type, array_size: Option<usize>) {
+        self.typer.declare(name, typ, array_size);
+    }
+}
+
+impl ScopedLowering for StringInterpolation {
+    fn lower_expression(&mut self, expression: Expression) -> Result<Expression> {
+        let atom = match expression.atom {
+            Atom::Interpolated(parts) => {
+                if !self.hoisting {
+                    bail!("{}", Self::unowned_error());
+                }
+                let allocated = self.allocate(parts)?;
+                let name = format!("__mc_string{}", self.temporary_count);
+                self.temporary_count += 1;
+                self.temporaries.push((name.clone(), allocated));
+                return Ok(Expression::new(Atom::Identifier(plain(name.as_str())), self.lower_tail(expression.tail)?));
+            },
+            other => self.lower_atom(other)?,
+        };
+        Ok(Expression { atom, tail: self.lower_tail(expression.tail)? })
+    }
+
+    fn lower_statements(&mut self, body: Vec<Statement>) -> Result<Vec<Statement>> {
+        let mut result = Vec::new();
+        for statement in body {
+            match statement {
+                // `char buffer[N] = $"..."` is formatted into the buffer once it's declared
+                Statement::VarDec { typ, name, array_size: Some(size), right: Some(Expression { atom: Atom::Interpolated(parts), tail: ExprTail::None }) }
+                    if self.is_char(&typ) => {
+                    self.typer.declare(&name, &typ, Some(size));
+                    result.push(Statement::VarDec { typ, name: name.clone(), array_size: Some(size), right: None });
+                    result.push(Statement::Expression(self.format_into(&name, size, parts)?));
+                },
+                other => {
+                    // Statements in a lambda get temporaries of their own
+                    let outer = std::mem::take(&mut self.temporaries);
+                    let outer_hoisting = std::mem::replace(&mut self.hoisting, false);
+                    let lowered = self.lower_statement(other);
+                    self.hoisting = outer_hoisting;
+                    let temporaries = std::mem::replace(&mut self.temporaries, outer);
+                    let lowered = lowered?;
+                    let names: Vec<String> = temporaries.iter().map(|(name, _)| name.clone()).collect();
+                    for (name, value) in temporaries {
+                        result.push(var_dec(Type::simple(plain("char"), 1), name.as_str(), Some(value)));
+                    }
+                    result.push(lowered);
+                    for name in names {
+                        result.push(Statement::Expression(call(plain("free"), vec![ident(name.as_str())])));
+                    }
+                },
+            }
+        }
+        Ok(result)
+    }
+
+    fn lower_statement(&mut self, statement: Statement) -> Result<Statement> {
+        match statement {
+            Statement::VarDec { typ, name, array_size, right } => {
+                let right = right.map(|r| self.lower_owned(r)).transpose()?;
+                self.typer.declare(&name, &typ, array_size);
+                Ok(Statement::VarDec { typ, name, array_size, right })
+            },
+            Statement::Expression(expression) => Ok(Statement::Expression(self.lower_hoisting(expression)?)),
+            Statement::VarAssign { identifier: IdentifierExpression::Standard(name), right: Expression { atom: Atom::Interpolated(parts), tail: ExprTail::None } } => {
+                match self.typer.array_of(&ident(&name)) {
+                    Ok(Some((element, Some(size)))) if self.is_char(&element) => Ok(Statement::Expression(self.format_into(&name, size, parts)?)),
+                    _ => {
+                        let right = self.allocate(parts)?;
+                        Ok(Statement::VarAssign { identifier: IdentifierExpression::Standard(name), right })
+                    },
+                }
+            },
+            Statement::VarAssign { identifier, right } => {
+                Ok(Statement::VarAssign { identifier: self.lower_identifier_expression(identifier)?, right: self.lower_owned(right)? })
+            },
+            Statement::BinOpVarAssign { identifier, op, right } => {
+                Ok(Statement::BinOpVarAssign { identifier: self.lower_identifier_expression(identifier)?, op, right: self.lower_hoisting(right)? })
+            },
+            // The caller receives a returned string, but nothing could free a temporary after `return`
+            Statement::Return(Some(Expression { atom: Atom::Interpolated(parts), tail: ExprTail::None })) => Ok(Statement::Return(Some(self.allocate(parts)?))),
+            Statement::If { base, elseifs, tail } => {
+                // Only the first condition is always evaluated before the statement
+                let condition = self.lower_hoisting(base.condition)?;
+                Ok(Statement::If {
+                    base: ConditionBody { condition, body: self.lower_body(base.body)? },
+                    elseifs: elseifs.into_iter().map(|c| self.lower_condition_body(c)).collect::<Result<_, _>>()?,
+                    tail: tail.map(|t| self.lower_body(t)).transpose()?,
+                })
+            },
+            Statement::Switch { value, cases, default, no_fallthrough } => {
+                let mut lowered_cases = Vec::new();
+                for case in cases {
+                    lowered_cases.push(CaseStatement { labels: case.labels, body: self.lower_case_body(case.body)? });
+                }
+                Ok(Statement::Switch {
+                    value: self.lower_hoisting(value)?,
+                    cases: lowered_cases,
+                    default: default.map(|d| self.lower_case_body(d)).transpose()?,
+                    no_fallthrough,
+                })
+            },
+            other => self.walk_statement(other),
+        }
+    }
+
+    fn lower_for(&mut self, init: Statement, condition: Expression, increment: Statement, body: Vec<Statement>) -> Result<Statement> {
+        // Temporaries of the initializer live until the end of the loop, but the increment runs
+        // at every iteration
+        let init = self.lower_statement(init)?;
+        let temporaries = self.temporaries.len();
+        let increment = self.lower_statement(increment)?;
+        if self.temporaries.len() > temporaries {
+            bail!("{}", Self::unowned_error());
+        }
+        Ok(Statement::For {
+            init: Box::new(init),
+            condition: self.lower_expression(condition)?,
+            increment: Box::new(increment),
+            body: self.lower_body(body)?,
+        })
+    }
+}
@@ -0,0 +1,195 @@
+use crate::{codegen::simple::SimpleCodeGen, parsing::ast::{expression::{BinOp, Expression}, function::{Function, FunctionHeader, Parameter}, identifier::Identifier, sstruct::{Derive, Struct}, statement::{ConditionBody, IdentifierExpression, Statement}, types::{Qualifier, Type}}};
+use anyhow::{bail, Result};
+
+use super::{builder::{access, address_of, assign, binary, call, cast, ident, if_then, index, int, mc, plain, string, var_dec}, derive::{element, element_target, per_element, suffixed, DeriveExpander, FieldKind, FlatField, INDEX}};
+
+/// Generation for `@derive(json)`, backed by the runtime in `std/mc/json.h`
+impl DeriveExpander {
+    /// Generates `T__to_json`, `T__read_json` and `T__from_json`
+    pub(super) fn generate_json(&self, s: &Struct, self_ident: &Identifier, fields: &Vec<FlatField>) -> Result<Vec<Function>> {
+        for field in fields {
+            if field.in_union {
+                bail!("Cannot derive json for '{}': field '{}' is part of a union", s.name, field.name);
+            }
+        }
+        Ok(vec![
+            Self::const_this(Self::method(s, self_ident, "to_json", self.generate_to_json(s, fields)?)),
+            Self::method(s, self_ident, "read_json", self.generate_read_json(s, fields)?),
+            Self::generate_from_json(s, self_ident),
+        ])
+    }
+
+    /// Makes `this` a `const T*`, for helpers that only read the struct
+    fn const_this(mut function: Function) -> Function {
+        function.header.params[0].typ.qualifiers.push(Qualifier::Const);
+        function
+    }
+
+    /// `void T__to_json(const T* this, mc::Buffer* out)`, appending the struct as a JSON object
+    fn generate_to_json(&self, s: &Struct, fields: &Vec<FlatField>) -> Result<(Type, Vec<Parameter>, Vec<Statement>)> {
+        let mut body = Vec::new();
+        if fields.is_empty() {
+            body.push(write_raw(String::from("{")));
+        }
+        for (i, field) in fields.iter().enumerate() {
+            body.push(write_raw(format!("{}\\\"{}\\\":", if i == 0 { "{" } else { "," }, field.name)));
+            let kind = self.classify(field.typ);
+            match field.array_size {
+                Some(size) if matches!(kind, FieldKind::Char) => {
+                    body.push(write("json_write_string_n", vec![access("this", field.name), int(size as i32)]));
+                },
+                Some(_) => {
+                    body.push(write_raw(String::from("[")));
+                    let separator = if_then(binary(ident(INDEX), BinOp::IsGT, int(0)), vec![write_raw(String::from(","))]);
+                    let each = vec![separator, self.write_value(s, field, kind)?];
+                    body.extend(per_element(field, each));
+                    body.push(write_raw(String::from("]")));
+                },
+                None => body.push(self.write_value(s, field, kind)?),
+            }
+        }
+        body.push(write_raw(String::from("}")));
+
+        let params = vec![Parameter { name: String::from("out"), typ: Type::simple(mc("Buffer"), 1), default: None }];
+        Ok((Type::simple(plain("void"), 0), params, body))
+    }
+
+    fn write_value(&self, s: &Struct, field: &FlatField, kind: FieldKind) -> Result<Statement> {
+        let value = element("this", field);
+        Ok(match kind {
+            FieldKind::Integer => {
+                let (suffix, c_type) = self.json_integer(field.typ);
+                write(&format!("json_write_{}", suffix), vec![cast(Type::simple(plain(c_type), 0), value)])
+            },
+            FieldKind::Enum(e) => write("json_write_string", vec![call(suffixed(&e, "name"), vec![value])]),
+            FieldKind::Float => write("json_write_double", vec![cast(Type::simple(plain("double"), 0), value)]),
+            FieldKind::Char => write("json_write_char", vec![value]),
+            FieldKind::Bool => write("json_write_bool", vec![value]),
+            FieldKind::String => write("json_write_string", vec![value]),
+            FieldKind::Nested(nested) => {
+                let helper = self.nested_helper(s, field, &nested, Derive::Json, "to_json")?;
+                Statement::Expression(call(helper, vec![address_of(value), ident("out")]))
+            },
+            FieldKind::Pointer | FieldKind::Union | FieldKind::ForeignStruct | FieldKind::Unknown => Self::unsupported(s, field, Derive::Json)?,
+        })
+    }
+
+    /// `void T__read_json(T* this, mc::JsonReader* reader)`, filling in each field found in the
+    /// object and skipping unknown ones; errors are recorded in the reader
+    fn generate_read_json(&self, s: &Struct, fields: &Vec<FlatField>) -> Result<(Type, Vec<Parameter>, Vec<Statement>)> {
+        let mut branches = Vec::new();
+        for field in fields {
+            let kind = self.classify(field.typ);
+            let read = match field.array_size {
+                Some(size) if matches!(kind, FieldKind::Char) => {
+                    vec![read_statement("json_read_string_into", vec![access("this", field.name), int(size as i32)])]
+                },
+                Some(size) => {
+                    // Keep reading elements until the array ends, failing if there are too many
+                    vec![Statement::For {
+                        init: Box::new(var_dec(Type::simple(plain("size_t"), 0), INDEX, Some(int(0)))),
+                        condition: call(mc("json_next_element"), vec![ident("reader"), ident(INDEX), int(size as i32)]),
+                        increment: Box::new(Statement::IncDec { identifier: IdentifierExpression::Standard(String::from(INDEX)), is_inc: true }),
+                        body: self.read_value(s, field, kind)?,
+                    }]
+                },
+                None => self.read_value(s, field, kind)?,
+            };
+            let is_field = call(mc("json_field_is"), vec![ident("reader"), string(field.name.as_str())]);
+            branches.push(ConditionBody { condition: is_field, body: read });
+        }
+
+        let skip = vec![read_statement("json_skip_value", Vec::new())];
+        let loop_body = if branches.is_empty() {
+            skip
+        } else {
+            let base = branches.remove(0);
+            vec![Statement::If { base, elseifs: branches, tail: Some(skip) }]
+        };
+        let body = vec![
+            read_statement("json_begin_object", Vec::new()),
+            Statement::While(ConditionBody { condition: call(mc("json_next_field"), vec![ident("reader")]), body: loop_body }),
+        ];
+
+        let params = vec![Parameter { name: String::from("reader"), typ: Type::simple(mc("JsonReader"), 1), default: None }];
+        Ok((Type::simple(plain("void"), 0), params, body))
+    }
+
+    fn read_value(&self, s: &Struct, field: &FlatField, kind: FieldKind) -> Result<Vec<Statement>> {
+        let target = element_target("this->", field);
+        let read = |func: &str| call(mc(func), vec![ident("reader")]);
+        Ok(vec![match kind {
+            FieldKind::Integer => assign(target, cast(field.typ.clone(), read(&format!("json_read_{}", self.json_integer(field.typ).0)))),
+            FieldKind::Enum(e) => {
+                let variant = call(mc("json_read_enum"), vec![ident("reader"), Expression::identifier(suffixed(&e, "names")), Expression::identifier(suffixed(&e, "COUNT"))]);
+                assign(target, index(Expression::identifier(suffixed(&e, "values")), variant))
+            },
+            FieldKind::Float => assign(target, cast(field.typ.clone(), read("json_read_double"))),
+            FieldKind::Char => assign(target, read("json_read_char")),
+            FieldKind::Bool => assign(target, read("json_read_bool")),
+            FieldKind::String => assign(target, read("json_read_string")),
+            FieldKind::Nested(nested) => {
+                let helper = self.nested_helper(s, field, &nested, Derive::Json, "read_json")?;
+                Statement::Expression(call(helper, vec![address_of(element("this", field)), ident("reader")]))
+            },
+            FieldKind::Pointer | FieldKind::Union | FieldKind::ForeignStruct | FieldKind::Unknown => Self::unsupported(s, field, Derive::Json)?,
+        }])
+    }
+
+    /// The suffix of the `mc` helpers that write and read an integer of type `typ`, and the C
+    /// type they take. `long` holds any type up to `int`, but only 32 bits on LLP64 targets, so
+    /// wider types and `unsigned int` go through `long long` or `unsigned long long`.
+    fn json_integer(&self, typ: &Type) -> (&'static str, &'static str) {
+        let name = self.symbols.resolve_typedefs(typ).name.generate();
+        let is_unsigned = name.contains("unsigned") || name.starts_with('u') || name == "size_t";
+        let is_small = name.contains("char") || name.contains("short") || matches!(name.as_str(), "int8_t" | "int16_t" | "uint8_t" | "uint16_t");
+        let is_wide = name.contains("long") || matches!(name.as_str(), "size_t" | "ssize_t" | "usize" | "intptr_t" | "uintptr_t" | "int64_t" | "uint64_t");
+        match (is_unsigned, is_small, is_wide) {
+            (true, false, _) => ("unsigned_long_long", "unsigned long long"),
+            (false, _, true) => ("long_long", "long long"),
+            _ => ("long", "long"),
+        }
+    }
+
+    /// `_Bool T__from_json(const char* json, T* this)`, returning whether the whole text was read successfully
+    fn generate_from_json(s: &Struct, self_ident: &Identifier) -> Function {
+        let reader = address_of(ident("reader"));
+        Function {
+            header: FunctionHeader {
+                return_type: Type::simple(plain("_Bool"), 0),
+                name: format!("{}__from_json", s.name),
+                params: vec![
+                    Parameter { name: String::from("json"), typ: Type { qualifiers: vec![Qualifier::Const], ..Type::simple(plain("char"), 1) }, default: None },
+                    Parameter { name: String::from("this"), typ: Type::simple(self_ident.clone(), 1), default: None },
+                ],
+                is_variadic: false,
+                attributes: Vec::new(),
+            },
+            body: vec![
+                var_dec(Type::simple(mc("JsonReader"), 0), "reader", None),
+                Statement::Expression(call(mc("json_reader_init"), vec![address_of(ident("reader")), ident("json")])),
+                Statement::Expression(call(suffixed(self_ident, "read_json"), vec![ident("this"), address_of(ident("reader"))])),
+                Statement::Return(Some(call(mc("json_reader_finish"), vec![reader]))),
+            ],
+        }
+    }
+}
+
+/// `mc::json_write_raw(out, "text")`
+fn write_raw(text: String) -> Statement {
+    write("json_write_raw", vec![string(text.as_str())])
+}
+
+/// `mc::func(out, args...)`
+fn write(func: &str, args: Vec<Expression>) -> Statement {
+    let mut all_args = vec![ident("out")];
+    all_args.extend(args);
+    Statement::Expression(call(mc(func), all_args))
+}
+
+/// `mc::func(reader, args...)`
+fn read_statement(func: &str, args: Vec<Expression>) -> Statement {
+    let mut all_args = vec![ident("reader")];
+    all_args.extend(args);
+    Statement::Expression(call(mc(func), all_args))
+}
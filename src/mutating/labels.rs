@@ -0,0 +1,335 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::parsing::ast::{function::Function, program::Program, statement::{CaseStatement, ConditionBody, Statement}, toplevel::TopLevel};
+use anyhow::{bail, Result};
+
+/// A loop or switch enclosing the statement being lowered
+enum Frame {
+    Loop(LoopFrame),
+    Switch,
+}
+
+struct LoopFrame {
+    label: Option<String>,
+    break_label: String,
+    continue_label: String,
+    break_used: bool,
+    continue_used: bool,
+}
+
+/// The labels defined and referred to in one function
+#[derive(Default)]
+struct FunctionLabels {
+    /// In order of definition, with loop labels that are reused appearing more than once
+    defined: Vec<String>,
+    /// The labels on anything but a loop, which are always kept in the output
+    kept: HashSet<String>,
+    /// Labels referred to by `goto`, `break` or `continue`
+    used: HashSet<String>,
+    gotos: Vec<String>,
+}
+
+/// Checks the labels in each function and lowers labeled loops to plain ones. `break label`
+/// becomes a `goto` to a label placed just after the loop, and `continue label` a `goto` to one
+/// at the end of the loop's body, so that the loop's increment and condition still run. When the
+/// label names the innermost loop, plain `break` and `continue` are used instead.
+///
+/// This runs after closures are lowered, so that lambda bodies are already separate functions
+/// and can't refer to the labels of the code around them.
+#[derive(Default)]
+pub struct LabelLowering {
+    frames: Vec<Frame>,
+    /// How many times each loop label has been used in the current function, to keep generated labels unique
+    label_counts: HashMap<String, usize>,
+    /// The labels targeted by a `goto` in the current function, which are kept in the output
+    goto_targets: HashSet<String>,
+}
+
+impl LabelLowering {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn lower_program(mut self, program: Program) -> Result<Program> {
+        Ok(Program {
+            statements: self.lower_toplevels(program.statements)?,
+        })
+    }
+
+    fn lower_toplevels(&mut self, toplevels: Vec<TopLevel>) -> Result<Vec<TopLevel>> {
+        let mut result = Vec::new();
+        for t in toplevels {
+            result.push(match t {
+                TopLevel::Function(func) => {
+                    self.check_function(&func)?;
+                    self.label_counts.clear();
+                    TopLevel::Function(Function { header: func.header, body: self.lower_body(func.body)? })
+                },
+                TopLevel::Module { name, body } => TopLevel::Module { name, body: self.lower_toplevels(body)? },
+                other => other,
+            });
+        }
+        Ok(result)
+    }
+
+    // == Checks ==
+
+    /// Reports gotos to labels that don't exist, labels defined more than once, and labels that
+    /// nothing refers to. Loop labels may be reused by later loops, since they are renamed when
+    /// lowered, but can't be the target of a `goto` then.
+    fn check_function(&mut self, func: &Function) -> Result<()> {
+        let mut labels = FunctionLabels::default();
+        Self::collect_labels(&func.body, &mut labels);
+        if let Some(duplicate) = labels.kept.iter().find(|l| labels.defined.iter().filter(|d| d == l).count() > 1) {
+            bail!("Label '{}' in '{}' is defined more than once", duplicate, func.header.name);
+        }
+        for target in &labels.gotos {
+            match labels.defined.iter().filter(|l| *l == target).count() {
+                0 => bail!("'goto {}' in '{}' refers to a label that doesn't exist", target, func.header.name),
+                1 => (),
+                _ => bail!("'goto {}' in '{}' is ambiguous: the label is defined more than once", target, func.header.name),
+            }
+        }
+        if let Some(unused) = labels.defined.iter().find(|l| !labels.used.contains(*l)) {
+            bail!("Label '{}' in '{}' is never used", unused, func.header.name);
+        }
+        self.goto_targets = labels.gotos.into_iter().collect();
+        Ok(())
+    }
+
+    fn collect_labels(body: &Vec<Statement>, labels: &mut FunctionLabels) {
+        for statement in body {
+            Self::collect_statement_labels(statement, labels);
+        }
+    }
+
+    fn collect_statement_labels(statement: &Statement, labels: &mut FunctionLabels) {
+        match statement {
+            Statement::Labeled { label, statement } => {
+                labels.defined.push(label.clone());
+                if Self::loop_body(statement).is_none() {
+                    labels.kept.insert(label.clone());
+                }
+                Self::collect_statement_labels(statement, labels);
+            },
+            Statement::Goto(label) => {
+                labels.used.insert(label.clone());
+                labels.gotos.push(label.clone());
+            },
+            Statement::Break(Some(label)) | Statement::Continue(Some(label)) => {
+                labels.used.insert(label.clone());
+            },
+            Statement::If { base, elseifs, tail } => {
+                Self::collect_labels(&base.body, labels);
+                for c in elseifs {
+                    Self::collect_labels(&c.body, labels);
+                }
+                if let Some(t) = tail {
+                    Self::collect_labels(t, labels);
+                }
+            },
+            Statement::Switch { cases, default, .. } => {
+                for c in cases {
+                    Self::collect_labels(&c.body, labels);
+                }
+                if let Some(d) = default {
+                    Self::collect_labels(d, labels);
+                }
+            },
+            other => {
+                if let Some(body) = Self::loop_body(other) {
+                    Self::collect_labels(body, labels);
+                }
+            },
+        }
+    }
+
+    // == Lowering ==
+
+    fn lower_body(&mut self, body: Vec<Statement>) -> Result<Vec<Statement>> {
+        let mut result = Vec::new();
+        for statement in body {
+            match statement {
+                Statement::Labeled { label, statement } => result.extend(self.lower_labeled(label, *statement)?),
+                other => result.push(self.lower_statement(other)?),
+            }
+        }
+        Ok(result)
+    }
+
+    /// Lowers a labeled statement, which can turn into several statements if it is a loop
+    fn lower_labeled(&mut self, label: String, statement: Statement) -> Result<Vec<Statement>> {
+        match statement {
+            Statement::VarDec { .. } | Statement::Let { .. } => {
+                bail!("Label '{}' must be followed by a statement, not a declaration", label)
+            },
+            Statement::Labeled { label: inner_label, statement: inner } => {
+                let mut lowered = self.lower_labeled(inner_label, *inner)?;
+                let first = lowered.remove(0);
+                lowered.insert(0, Statement::Labeled { label, statement: Box::new(first) });
+                Ok(lowered)
+            },
+            statement if Self::loop_body(&statement).is_some() => {
+                let (lowered, break_label) = self.lower_labeled_loop(&label, statement)?;
+                // The label itself is only needed in C if something jumps to it directly
+                let mut result = vec![if self.goto_targets.contains(&label) {
+                    Statement::Labeled { label, statement: Box::new(lowered) }
+                } else {
+                    lowered
+                }];
+                if let Some(break_label) = break_label {
+                    result.push(Statement::Label(break_label));
+                }
+                Ok(result)
+            },
+            other => Ok(vec![Statement::Labeled { label, statement: Box::new(self.lower_statement(other)?) }]),
+        }
+    }
+
+    /// Lowers a labeled loop, also returning the label to place after it if it is broken out of
+    fn lower_labeled_loop(&mut self, label: &String, statement: Statement) -> Result<(Statement, Option<String>)> {
+        if self.find_loop(label).is_some() {
+            bail!("Loop label '{}' is already used by an enclosing loop", label);
+        }
+        let count = self.label_counts.entry(label.clone()).or_insert(0);
+        *count += 1;
+        let suffix = if *count > 1 { format!("_{}", count) } else { String::new() };
+        self.frames.push(Frame::Loop(LoopFrame {
+            label: Some(label.clone()),
+            break_label: format!("__mc_break_{}{}", label, suffix),
+            continue_label: format!("__mc_continue_{}{}", label, suffix),
+            break_used: false,
+            continue_used: false,
+        }));
+        let lowered = self.lower_loop(statement);
+        let frame = match self.frames.pop() {
+            Some(Frame::Loop(frame)) => frame,
+            _ => unreachable!(),
+        };
+        let mut lowered = lowered?;
+        if frame.continue_used {
+            if let Some(body) = Self::loop_body_mut(&mut lowered) {
+                body.push(Statement::Label(frame.continue_label));
+            }
+        }
+        Ok((lowered, if frame.break_used { Some(frame.break_label) } else { None }))
+    }
+
+    /// Lowers the body of a loop, which must already have its frame pushed
+    fn lower_loop(&mut self, statement: Statement) -> Result<Statement> {
+        match statement {
+            Statement::While(condition_body) => Ok(Statement::While(self.lower_condition_body(condition_body)?)),
+            Statement::DoWhile { condition, body } => Ok(Statement::DoWhile { condition, body: self.lower_body(body)? }),
+            Statement::For { init, condition, increment, body } => {
+                Ok(Statement::For { init, condition, increment, body: self.lower_body(body)? })
+            },
+            Statement::EnumFor { typ, name, enum_type, body } => {
+                Ok(Statement::EnumFor { typ, name, enum_type, body: self.lower_body(body)? })
+            },
+            Statement::ForIn { typ, name, iterable, body } => {
+                Ok(Statement::ForIn { typ, name, iterable, body: self.lower_body(body)? })
+            },
+            other => Ok(other),
+        }
+    }
+
+    fn loop_body(statement: &Statement) -> Option<&Vec<Statement>> {
+        match statement {
+            Statement::While(ConditionBody { body, .. }) | Statement::DoWhile { body, .. } | Statement::For { body, .. }
+                | Statement::EnumFor { body, .. } | Statement::ForIn { body, .. } => Some(body),
+            _ => None,
+        }
+    }
+
+    fn loop_body_mut(statement: &mut Statement) -> Option<&mut Vec<Statement>> {
+        match statement {
+            Statement::While(ConditionBody { body, .. }) | Statement::DoWhile { body, .. } | Statement::For { body, .. }
+                | Statement::EnumFor { body, .. } | Statement::ForIn { body, .. } => Some(body),
+            _ => None,
+        }
+    }
+
+    fn lower_statement(&mut self, statement: Statement) -> Result<Statement> {
+        match statement {
+            Statement::If { base, elseifs, tail } => {
+                Ok(Statement::If {
+                    base: self.lower_condition_body(base)?,
+                    elseifs: elseifs.into_iter().map(|c| self.lower_condition_body(c)).collect::<Result<_, _>>()?,
+                    tail: tail.map(|t| self.lower_body(t)).transpose()?,
+                })
+            },
+            Statement::While(_) | Statement::DoWhile { .. } | Statement::For { .. } | Statement::EnumFor { .. } | Statement::ForIn { .. } => {
+                self.frames.push(Frame::Loop(LoopFrame {
+                    label: None,
+                    break_label: String::new(),
+                    continue_label: String::new(),
+                    break_used: false,
+                    continue_used: false,
+                }));
+                let lowered = self.lower_loop(statement);
+                self.frames.pop();
+                lowered
+            },
+            Statement::Switch { value, cases, default, no_fallthrough } => {
+                self.frames.push(Frame::Switch);
+                let lowered = self.lower_switch(cases, default);
+                self.frames.pop();
+                let (cases, default) = lowered?;
+                Ok(Statement::Switch { value, cases, default, no_fallthrough })
+            },
+            Statement::Break(Some(label)) => {
+                let index = match self.find_loop(&label) {
+                    Some(index) => index,
+                    None => bail!("Cannot break out of '{}': there is no enclosing loop with that label", label),
+                };
+                // A plain `break` inside a switch would only leave the switch
+                if index == self.frames.len() - 1 {
+                    return Ok(Statement::Break(None));
+                }
+                let frame = self.loop_frame(index);
+                frame.break_used = true;
+                Ok(Statement::Goto(frame.break_label.clone()))
+            },
+            Statement::Continue(Some(label)) => {
+                let index = match self.find_loop(&label) {
+                    Some(index) => index,
+                    None => bail!("Cannot continue '{}': there is no enclosing loop with that label", label),
+                };
+                // Switches don't affect `continue`, so only loops in between need a `goto`
+                let innermost = self.frames.iter().rposition(|f| matches!(f, Frame::Loop(_)));
+                if innermost == Some(index) {
+                    return Ok(Statement::Continue(None));
+                }
+                let frame = self.loop_frame(index);
+                frame.continue_used = true;
+                Ok(Statement::Goto(frame.continue_label.clone()))
+            },
+            Statement::Labeled { label, .. } => bail!("Label '{}' must be on a statement directly inside a block", label),
+            other => Ok(other),
+        }
+    }
+
+    fn lower_switch(&mut self, cases: Vec<CaseStatement>, default: Option<Vec<Statement>>) -> Result<(Vec<CaseStatement>, Option<Vec<Statement>>)> {
+        let mut lowered_cases = Vec::new();
+        for case in cases {
+            lowered_cases.push(CaseStatement { labels: case.labels, body: self.lower_body(case.body)? });
+        }
+        Ok((lowered_cases, default.map(|d| self.lower_body(d)).transpose()?))
+    }
+
+    fn lower_condition_body(&mut self, condition_body: ConditionBody) -> Result<ConditionBody> {
+        Ok(ConditionBody { condition: condition_body.condition, body: self.lower_body(condition_body.body)? })
+    }
+
+    /// The index of the frame of the enclosing loop with a label
+    fn find_loop(&self, label: &String) -> Option<usize> {
+        self.frames.iter().rposition(|f| matches!(f, Frame::Loop(LoopFrame { label: Some(l), .. }) if l == label))
+    }
+
+    fn loop_frame(&mut self, index: usize) -> &mut LoopFrame {
+        match &mut self.frames[index] {
+            Frame::Loop(frame) => frame,
+            Frame::Switch => unreachable!(),
+        }
+    }
+}
@@ -0,0 +1,783 @@
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use crate::{codegen::simple::SimpleCodeGen, parsing::ast::{enumm::{Enum, EnumEntry}, expression::{Atom, BinOp, ExprTail, Expression, NamedArgument, StringPart, UnaryOp}, identifier::Identifier, lambda::{Capture, Lambda}, macros::{Macro, MacroArgument, MacroParamKind}, program::Program, statement::{CaseLabel, CaseStatement, ConditionBody, ForIterable, IdentifierExpression, StaticAssert, Statement}, toplevel::TopLevel, types::{Type, TypeType}}};
+use anyhow::{anyhow, bail, Result};
+
+use super::{builder::{address_of, binary, ident, operand, unary}, mutator::Mutator};
+
+/// Expands the calls of `macro` definitions, before any other pass sees the program:
+/// - `swap!(a, b);` as a statement is replaced with the statements of the macro's body
+/// - `max!(a, b)` as a value is replaced with the single expression statement of its body
+///
+/// Every parameter of the macro is replaced with its argument: an expression in parentheses
+/// unless it's a single term, a name, a type, or the statements of a block. An expression that
+/// the body uses more than once is evaluated only once instead, into a hidden `let` variable
+/// before the statement with the call, e.g. `__mc_max1_a`, unless reading it twice does no harm
+/// like for names and constants. The variables and
+/// labels that the body declares get a hidden name for each expansion, e.g. `__mc_swap1_tmp`,
+/// so they can't clash with the names at the call or with another expansion. Other names in the
+/// body refer to whatever they name where it's expanded.
+///
+/// Arguments are expanded where they are written, before the body they're put into. Errors in
+/// a body list the macro calls that it was expanded from.
+pub struct MacroExpander {
+    macros: HashMap<String, Macro>,
+    /// The call being expanded, if any
+    expansion: Option<Expansion>,
+    /// The names of the macros being expanded, outermost first
+    stack: Vec<String>,
+    /// Where the outermost call is written, e.g. "function 'main'"
+    location: String,
+    /// The number of expansions so far, which keeps their hidden names apart
+    expansions: usize,
+    /// The variables holding arguments, which go before the statement being expanded, if any
+    hoisted: Option<Vec<Statement>>,
+    /// Whether the expression being expanded isn't always evaluated when the statement runs,
+    /// or runs more than once, e.g. after `&&` or in a loop's condition
+    conditional: bool,
+}
+
+/// What the names in a macro's body stand for while one of its calls is expanded
+struct Expansion {
+    arguments: HashMap<String, Bound>,
+    renamed: HashMap<String, String>,
+}
+
+/// The argument of a parameter, already expanded where it was written
+#[derive(Clone)]
+enum Bound {
+    Expression(Expression),
+    Identifier(String),
+    Type(Type),
+    Block(Vec<Statement>),
+}
+
+impl Default for MacroExpander {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MacroExpander {
+    pub fn new() -> Self {
+        MacroExpander {
+            macros: HashMap::new(),
+            expansion: None,
+            stack: Vec::new(),
+            location: String::new(),
+            expansions: 0,
+            hoisted: None,
+            conditional: false,
+        }
+    }
+
+    pub fn expand_program(mut self, program: Program) -> Result<Program> {
+        let mut toplevels = Vec::new();
+        for t in program.statements {
+            match t {
+                TopLevel::Macro(m) => self.define(m)?,
+                other => toplevels.push(other),
+            }
+        }
+        Ok(Program {
+            statements: self.expand_toplevels(toplevels, "")?,
+        })
+    }
+
+    fn define(&mut self, mut m: Macro) -> Result<()> {
+        if self.macros.contains_key(&m.name) {
+            bail!("The macro '{}' is defined twice", m.name);
+        }
+        // `a * b;` is parsed as declaring `b` as an `a*`, but a parameter that isn't a type can't
+        // name one, so it's the product the macro means
+        for statement in &mut m.body {
+            if let Statement::VarDec { typ, name, array_size: None, right: None } = statement {
+                let is_value = |n: &String| m.params.iter().any(|p| p.name == *n && p.kind != MacroParamKind::Type);
+                if let (Identifier::Plain(left), TypeType::Simple, 1) = (&typ.name, &typ.typetype, typ.pointer_layers()) {
+                    if is_value(left) && typ.storage.is_empty() && typ.qualifiers.is_empty() && typ.pointers[0].is_empty() {
+                        *statement = Statement::Expression(binary(ident(left), BinOp::Mul, ident(name)));
+                    }
+                }
+            }
+        }
+        let mut declared = Vec::new();
+        declared_names(&m.body, &mut declared);
+        for name in &declared {
+            if let Some(param) = m.params.iter().find(|p| p.name == *name && p.kind != MacroParamKind::Identifier) {
+                bail!("The macro '{}' declares '{}', which is already its {} parameter", m.name, name, param.kind.keyword());
+            }
+        }
+        self.macros.insert(m.name.clone(), m);
+        Ok(())
+    }
+
+    fn expand_toplevels(&mut self, toplevels: Vec<TopLevel>, module: &str) -> Result<Vec<TopLevel>> {
+        let mut result = Vec::new();
+        for t in toplevels {
+            result.push(match t {
+                TopLevel::Function(mut func) => {
+                    self.location = format!("function '{}{}'", module, func.header.name);
+                    func.body = self.expand_body(func.body)?;
+                    TopLevel::Function(func)
+                },
+                TopLevel::VarDeclaration { typ, name, array_size, right } => {
+                    self.location = format!("the global '{}{}'", module, name);
+                    let right = right.map(|r| self.expand_expression(r)).transpose()?;
+                    TopLevel::VarDeclaration { typ, name, array_size, right }
+                },
+                TopLevel::Enum(e) => {
+                    self.location = format!("the enum '{}{}'", module, e.name);
+                    let mut entries = Vec::new();
+                    for entry in e.entries {
+                        entries.push(EnumEntry { name: entry.name, value: entry.value.map(|v| self.expand_expression(v)).transpose()? });
+                    }
+                    TopLevel::Enum(Enum { name: e.name, entries, is_anonymous: e.is_anonymous, reflected: e.reflected })
+                },
+                TopLevel::StaticAssert(assertion) => {
+                    self.location = String::from("a static_assert");
+                    TopLevel::StaticAssert(self.expand_static_assert(assertion)?)
+                },
+                TopLevel::Test(mut test) => {
+                    self.location = format!("the test \"{}{}\"", module, test.name);
+                    test.body = self.expand_body(test.body)?;
+                    TopLevel::Test(test)
+                },
+                TopLevel::Module { name, body } => {
+                    let prefix = format!("{}::", name);
+                    TopLevel::Module { name, body: self.expand_toplevels(body, &prefix)? }
+                },
+                other => other,
+            });
+        }
+        Ok(result)
+    }
+
+    /// An error in the expansion that is going on, listing the calls it was expanded from
+    fn error(&self, message: String) -> anyhow::Error {
+        let mut s = message;
+        for name in self.stack.iter().rev() {
+            s.push_str(format!("\n    in the expansion of '{}!'", name).as_str());
+        }
+        s.push_str(format!("\n    in {}", self.location).as_str());
+        anyhow!(s)
+    }
+
+    // == Calls ==
+
+    /// The statements that the call `name!(args)` expands to
+    fn expand_call(&mut self, name: String, args: Vec<MacroArgument>) -> Result<Vec<Statement>> {
+        let Some(m) = self.macros.get(&name).cloned() else {
+            return Err(self.error(format!("There is no macro named '{}'", name)));
+        };
+        if self.stack.contains(&name) {
+            return Err(self.error(format!("The macro '{}' expands to itself, which would never end", name)));
+        }
+        if args.len() != m.params.len() {
+            let plural = if m.params.len() == 1 { "" } else { "s" };
+            return Err(self.error(format!("'{}!' takes {} argument{}, but {} were given", name, m.params.len(), plural, args.len())));
+        }
+        let mut arguments = HashMap::new();
+        for (param, arg) in m.params.iter().zip(args) {
+            arguments.insert(param.name.clone(), self.bind(&name, &param.name, param.kind, arg)?);
+        }
+        self.expansions += 1;
+        for param in &m.params {
+            if let Some(Bound::Expression(argument)) = arguments.get(&param.name) {
+                if !is_pure(argument) {
+                    let uses = count_uses(m.body.clone(), &param.name);
+                    if uses.count > 1 {
+                        let evaluated = self.evaluate_once(&name, &param.name, argument.clone(), uses.address_taken)?;
+                        arguments.insert(param.name.clone(), Bound::Expression(evaluated));
+                    }
+                }
+            }
+        }
+        let mut declared = Vec::new();
+        declared_names(&m.body, &mut declared);
+        let renamed = declared.into_iter()
+            .filter(|d| !arguments.contains_key(d))
+            .map(|d| (d.clone(), format!("__mc_{}{}_{}", name, self.expansions, d)))
+            .collect();
+
+        let outer = self.expansion.replace(Expansion { arguments, renamed });
+        self.stack.push(name);
+        let result = self.expand_body(m.body);
+        self.stack.pop();
+        self.expansion = outer;
+        result
+    }
+
+    /// The value that the call `name!(args)` expands to, from a body that is a single expression
+    fn expand_value(&mut self, name: String, args: Vec<MacroArgument>) -> Result<Expression> {
+        let mut statements = self.expand_call(name.clone(), args)?;
+        match (statements.pop(), statements.is_empty()) {
+            (Some(Statement::Expression(value)), true) => Ok(value),
+            _ => Err(self.error(format!("The macro '{}' can't be used as a value, since its body isn't a single expression", name))),
+        }
+    }
+
+    /// Checks that `arg` fits the kind of the parameter `param`, and expands it
+    fn bind(&mut self, name: &String, param: &String, kind: MacroParamKind, arg: MacroArgument) -> Result<Bound> {
+        match (kind, arg) {
+            (MacroParamKind::Expression, MacroArgument::Expression(value)) => Ok(Bound::Expression(self.expand_expression(value)?)),
+            (MacroParamKind::Identifier, MacroArgument::Expression(value)) => match self.expand_expression(value)? {
+                Expression { atom: Atom::Identifier(Identifier::Plain(n)), tail: ExprTail::None } => Ok(Bound::Identifier(n)),
+                _ => Err(self.error(format!("'{}!' takes a plain name for its parameter '{}'", name, param))),
+            },
+            (MacroParamKind::Type, MacroArgument::Type(typ)) => Ok(Bound::Type(self.expand_type(typ)?)),
+            // A type that is a single name, like `Point` or `int`, is parsed as an expression
+            (MacroParamKind::Type, MacroArgument::Expression(Expression { atom: Atom::Identifier(type_name), tail: ExprTail::None })) => {
+                Ok(Bound::Type(self.expand_type(Type::simple(type_name, 0))?))
+            },
+            (MacroParamKind::Block, MacroArgument::Block(body)) => Ok(Bound::Block(self.expand_body(body)?)),
+            (kind, _) => {
+                let expected = match kind {
+                    MacroParamKind::Expression => "an expression",
+                    MacroParamKind::Type => "a type",
+                    MacroParamKind::Identifier => "a plain name",
+                    MacroParamKind::Block => "a block in braces",
+                };
+                Err(self.error(format!("'{}!' takes {} for its parameter '{}'", name, expected, param)))
+            },
+        }
+    }
+
+    /// Stores the argument of `param` in a hidden variable before the statement being expanded,
+    /// returning what the body reads instead. A dereferenced pointer keeps the pointer, and an
+    /// argument whose address the body takes is stored as a pointer to it, so that the body
+    /// still refers to the same object.
+    fn evaluate_once(&mut self, name: &String, param: &String, argument: Expression, address_taken: bool) -> Result<Expression> {
+        if self.conditional || self.hoisted.is_none() {
+            return Err(self.error(format!(
+                "The argument '{}' of '{}!' is used more than once, so it has to be evaluated before the statement, which isn't possible here. Store it in a variable first",
+                argument.generate(), name,
+            )));
+        }
+        let hidden = format!("__mc_{}{}_{}", name, self.expansions, param);
+        let (value, read) = match argument {
+            Expression { atom: Atom::UnaryOperation { op: UnaryOp::Dereference, value }, tail: ExprTail::None } => {
+                (*value, unary(UnaryOp::Dereference, ident(&hidden)))
+            },
+            argument if address_taken => (address_of(operand(argument)), unary(UnaryOp::Dereference, ident(&hidden))),
+            argument => (argument, ident(&hidden)),
+        };
+        self.hoisted.as_mut().unwrap().push(Statement::Let { name: hidden, right: value });
+        Ok(read)
+    }
+
+    fn bound(&self, name: &String) -> Option<&Bound> {
+        self.expansion.as_ref().and_then(|e| e.arguments.get(name))
+    }
+
+    /// What a name that the body declares or labels stands for in this expansion
+    fn declared(&self, name: String) -> String {
+        match &self.expansion {
+            Some(expansion) => match (expansion.renamed.get(&name), expansion.arguments.get(&name)) {
+                (Some(renamed), _) => renamed.clone(),
+                (None, Some(Bound::Identifier(argument))) => argument.clone(),
+                _ => name,
+            },
+            None => name,
+        }
+    }
+
+    /// The name that a parameter stands for where the body needs a variable, e.g. in a capture
+    fn variable(&self, name: String, usage: &str) -> Result<String> {
+        match self.bound(&name) {
+            Some(Bound::Expression(Expression { atom: Atom::Identifier(Identifier::Plain(argument)), tail: ExprTail::None })) => Ok(argument.clone()),
+            Some(Bound::Identifier(argument)) => Ok(argument.clone()),
+            Some(_) => Err(self.error(format!("'{}' is {}, so its argument must be a variable", name, usage))),
+            None => Ok(self.declared(name)),
+        }
+    }
+
+    // == Statements ==
+
+    fn expand_body(&mut self, body: Vec<Statement>) -> Result<Vec<Statement>> {
+        let conditional = std::mem::replace(&mut self.conditional, false);
+        let mut result = Vec::new();
+        for statement in body {
+            let outer = self.hoisted.replace(Vec::new());
+            let expanded = self.expand_statement(statement);
+            let hoisted = std::mem::replace(&mut self.hoisted, outer).unwrap();
+            result.extend(hoisted);
+            result.append(&mut expanded?);
+        }
+        self.conditional = conditional;
+        Ok(result)
+    }
+
+    /// Expands a part of a statement that isn't always evaluated, or is evaluated more than once
+    fn expand_conditional(&mut self, expression: Expression) -> Result<Expression> {
+        let outer = std::mem::replace(&mut self.conditional, true);
+        let result = self.expand_expression(expression);
+        self.conditional = outer;
+        result
+    }
+
+    /// A statement that has to stay a single statement, e.g. the parts of a `for` loop
+    fn expand_single(&mut self, statement: Statement, usage: &str) -> Result<Statement> {
+        let mut statements = self.expand_statement(statement)?;
+        if statements.len() != 1 {
+            return Err(self.error(format!("{} must be a single statement, but its macro expands to {}", usage, statements.len())));
+        }
+        Ok(statements.pop().unwrap())
+    }
+
+    /// The first or last part of a `for` loop, which can't be a block statement
+    fn expand_for_part(&mut self, statement: Statement, usage: &str) -> Result<Statement> {
+        match self.expand_single(statement, usage)? {
+            part @ (Statement::Expression(_) | Statement::VarDec { .. } | Statement::Let { .. } | Statement::LetTuple { .. }
+                | Statement::VarAssign { .. } | Statement::BinOpVarAssign { .. } | Statement::IncDec { .. }) => Ok(part),
+            _ => Err(self.error(format!("{} must be a declaration, an assignment or an expression, but its macro expands to another statement", usage))),
+        }
+    }
+
+    fn expand_statement(&mut self, statement: Statement) -> Result<Vec<Statement>> {
+        let statement = match statement {
+            Statement::Expression(Expression { atom: Atom::MacroCall { name, args }, tail: ExprTail::None }) => {
+                return self.expand_call(name, args);
+            },
+            Statement::Expression(Expression { atom: Atom::Identifier(Identifier::Plain(name)), tail: ExprTail::None })
+                if matches!(self.bound(&name), Some(Bound::Block(_))) => {
+                let Some(Bound::Block(body)) = self.bound(&name) else { unreachable!() };
+                return Ok(body.clone());
+            },
+            Statement::Expression(expression) => Statement::Expression(self.expand_expression(expression)?),
+            Statement::VarDec { typ, name, array_size, right } => {
+                Statement::VarDec {
+                    typ: self.expand_type(typ)?,
+                    name: self.declared(name),
+                    array_size,
+                    right: right.map(|r| self.expand_expression(r)).transpose()?,
+                }
+            },
+            Statement::Let { name, right } => Statement::Let { name: self.declared(name), right: self.expand_expression(right)? },
+            Statement::LetTuple { names, right } => {
+                Statement::LetTuple { names: names.into_iter().map(|n| self.declared(n)).collect(), right: self.expand_expression(right)? }
+            },
+            Statement::VarAssign { identifier, right } => {
+                Statement::VarAssign { identifier: self.expand_target(identifier)?, right: self.expand_expression(right)? }
+            },
+            Statement::BinOpVarAssign { identifier, op, right } => {
+                Statement::BinOpVarAssign { identifier: self.expand_target(identifier)?, op, right: self.expand_expression(right)? }
+            },
+            Statement::IncDec { identifier, is_inc } => Statement::IncDec { identifier: self.expand_target(identifier)?, is_inc },
+            Statement::Return(value) => Statement::Return(value.map(|v| self.expand_expression(v)).transpose()?),
+            Statement::ReturnTuple(values) => {
+                Statement::ReturnTuple(values.into_iter().map(|v| self.expand_expression(v)).collect::<Result<_>>()?)
+            },
+            Statement::If { base, elseifs, tail } => {
+                let base = ConditionBody { condition: self.expand_expression(base.condition)?, body: self.expand_body(base.body)? };
+                let elseifs = elseifs.into_iter().map(|c| {
+                    Ok(ConditionBody { condition: self.expand_conditional(c.condition)?, body: self.expand_body(c.body)? })
+                }).collect::<Result<_>>()?;
+                Statement::If { base, elseifs, tail: tail.map(|t| self.expand_body(t)).transpose()? }
+            },
+            Statement::While(condition_body) => {
+                Statement::While(ConditionBody { condition: self.expand_conditional(condition_body.condition)?, body: self.expand_body(condition_body.body)? })
+            },
+            Statement::DoWhile { condition, body } => {
+                Statement::DoWhile { condition: self.expand_conditional(condition)?, body: self.expand_body(body)? }
+            },
+            Statement::For { init, condition, increment, body } => {
+                let init = self.expand_for_part(*init, "The first part of a 'for' loop")?;
+                let condition = self.expand_conditional(condition)?;
+                let outer = std::mem::replace(&mut self.conditional, true);
+                let increment = self.expand_for_part(*increment, "The last part of a 'for' loop");
+                self.conditional = outer;
+                Statement::For { init: Box::new(init), condition, increment: Box::new(increment?), body: self.expand_body(body)? }
+            },
+            Statement::EnumFor { typ, name, enum_type, body } => {
+                Statement::EnumFor {
+                    typ: self.expand_type(typ)?,
+                    name: self.declared(name),
+                    enum_type: self.expand_type(enum_type)?,
+                    body: self.expand_body(body)?,
+                }
+            },
+            Statement::ForIn { typ, name, iterable, body } => {
+                let iterable = match iterable {
+                    ForIterable::Range { start, end } => {
+                        ForIterable::Range { start: Box::new(self.expand_expression(*start)?), end: Box::new(self.expand_expression(*end)?) }
+                    },
+                    ForIterable::Array { array, length, by_reference } => {
+                        ForIterable::Array { array: self.expand_expression(array)?, length, by_reference }
+                    },
+                    ForIterable::Slice { slice, by_reference } => {
+                        ForIterable::Slice { slice: self.expand_expression(slice)?, by_reference }
+                    },
+                };
+                Statement::ForIn { typ: self.expand_type(typ)?, name: self.declared(name), iterable, body: self.expand_body(body)? }
+            },
+            Statement::Switch { value, cases, default, no_fallthrough } => {
+                let value = self.expand_expression(value)?;
+                let mut expanded_cases = Vec::new();
+                for case in cases {
+                    let labels = case.labels.into_iter().map(|l| match l {
+                        CaseLabel::Value(value) => Ok(CaseLabel::Value(Box::new(self.expand_expression(*value)?))),
+                        CaseLabel::Range { start, end } => {
+                            Ok(CaseLabel::Range { start: Box::new(self.expand_expression(*start)?), end: Box::new(self.expand_expression(*end)?) })
+                        },
+                    }).collect::<Result<_>>()?;
+                    expanded_cases.push(CaseStatement { labels, body: self.expand_body(case.body)? });
+                }
+                Statement::Switch {
+                    value,
+                    cases: expanded_cases,
+                    default: default.map(|d| self.expand_body(d)).transpose()?,
+                    no_fallthrough,
+                }
+            },
+            // A `goto` to the label would skip the variables holding arguments
+            Statement::Labeled { label, statement } => {
+                let usage = format!("The statement after the label '{}'", label);
+                let outer = std::mem::replace(&mut self.conditional, true);
+                let statement = self.expand_single(*statement, &usage);
+                self.conditional = outer;
+                Statement::Labeled { label: self.declared(label), statement: Box::new(statement?) }
+            },
+            Statement::Goto(label) => Statement::Goto(self.declared(label)),
+            Statement::Label(label) => Statement::Label(self.declared(label)),
+            Statement::Continue(label) => Statement::Continue(label.map(|l| self.declared(l))),
+            Statement::Break(label) => Statement::Break(label.map(|l| self.declared(l))),
+            Statement::StaticAssert(assertion) => Statement::StaticAssert(self.expand_static_assert(assertion)?),
+            Statement::Directive(directive) => Statement::Directive(directive),
+        };
+        Ok(vec![statement])
+    }
+
+    fn expand_static_assert(&mut self, assertion: StaticAssert) -> Result<StaticAssert> {
+        Ok(StaticAssert { condition: self.expand_expression(assertion.condition)?, message: assertion.message })
+    }
+
+    /// The target of an assignment, where an expression parameter's argument has to be a
+    /// variable or a dereferenced pointer
+    fn expand_target(&mut self, identifier: IdentifierExpression) -> Result<IdentifierExpression> {
+        match identifier {
+            IdentifierExpression::Pointer(pointer) => Ok(IdentifierExpression::Pointer(self.expand_expression(pointer)?)),
+            IdentifierExpression::Standard(name) => match self.bound(&name) {
+                Some(Bound::Expression(Expression { atom: Atom::UnaryOperation { op: UnaryOp::Dereference, value }, tail: ExprTail::None })) => {
+                    Ok(IdentifierExpression::Pointer(*value.clone()))
+                },
+                _ => Ok(IdentifierExpression::Standard(self.variable(name, "assigned to")?)),
+            },
+        }
+    }
+
+    // == Expressions ==
+
+    fn expand_expression(&mut self, expression: Expression) -> Result<Expression> {
+        let tail = self.expand_tail(expression.tail)?;
+        let value = match expression.atom {
+            Atom::MacroCall { name, args } => operand(self.expand_value(name, args)?),
+            Atom::Identifier(Identifier::Plain(name)) => match self.bound(&name) {
+                Some(Bound::Expression(argument)) => operand(argument.clone()),
+                Some(Bound::Identifier(argument)) => ident(argument),
+                Some(Bound::Type(_)) => return Err(self.error(format!("The type parameter '{}' can't be used as a value", name))),
+                Some(Bound::Block(_)) => return Err(self.error(format!("The block parameter '{}' can only be used as the statement '{};'", name, name))),
+                None => ident(&self.declared(name)),
+            },
+            atom => return Ok(Expression { atom: self.expand_atom(atom)?, tail }),
+        };
+        Ok(match (value, tail) {
+            (value, ExprTail::None) => value,
+            (Expression { atom, tail: ExprTail::None }, tail) => Expression { atom, tail },
+            (value, tail) => Expression { atom: Atom::Wrapped(Box::new(value)), tail },
+        })
+    }
+
+    fn expand_atom(&mut self, atom: Atom) -> Result<Atom> {
+        Ok(match atom {
+            Atom::Char(_) | Atom::Short(_) | Atom::Int(_) | Atom::Octal(_) | Atom::TrueLong(_) | Atom::Float(_) | Atom::Double(_) | Atom::Boolean(_) | Atom::String(_)
+                | Atom::Embed(_) | Atom::Bytes(_) => atom,
+            Atom::Identifier(identifier) => Atom::Identifier(identifier),
+            Atom::TypeCast { typ, value } => Atom::TypeCast { typ: self.expand_type(typ)?, value: Box::new(self.expand_expression(*value)?) },
+            Atom::UnaryOperation { op, value } => Atom::UnaryOperation { op, value: Box::new(self.expand_expression(*value)?) },
+            Atom::SizeOf(typ) => Atom::SizeOf(self.expand_type(typ)?),
+            Atom::VaArg { list, typ } => Atom::VaArg { list: Box::new(self.expand_expression(*list)?), typ: self.expand_type(typ)? },
+            Atom::Wrapped(value) => Atom::Wrapped(Box::new(self.expand_expression(*value)?)),
+            Atom::InitList(values) => Atom::InitList(values.into_iter().map(|v| self.expand_expression(v)).collect::<Result<_>>()?),
+            Atom::Lambda(lambda) => Atom::Lambda(Box::new(self.expand_lambda(*lambda)?)),
+            Atom::Interpolated(parts) => {
+                Atom::Interpolated(parts.into_iter().map(|p| match p {
+                    StringPart::Value { value, format } => Ok(StringPart::Value { value: Box::new(self.expand_expression(*value)?), format }),
+                    text => Ok(text),
+                }).collect::<Result<_>>()?)
+            },
+            Atom::Assign { target, value } => Atom::Assign { target, value: Box::new(self.expand_expression(*value)?) },
+            Atom::MacroCall { .. } => unreachable!("macro calls are expanded by `expand_expression`"),
+        })
+    }
+
+    /// A lambda in a macro's body gets hidden names for its parameters, like the body's variables
+    fn expand_lambda(&mut self, lambda: Lambda) -> Result<Lambda> {
+        let Lambda { captures, params, is_variadic, return_type, body } = lambda;
+        let mut expanded_params = Vec::new();
+        for mut param in params {
+            param.typ = self.expand_type(param.typ)?;
+            if let Some(expansion) = &mut self.expansion {
+                let hidden = format!("__mc_{}{}_{}", self.stack.last().unwrap(), self.expansions, param.name);
+                expansion.arguments.remove(&param.name);
+                expansion.renamed.insert(param.name.clone(), hidden.clone());
+                param.name = hidden;
+            }
+            expanded_params.push(param);
+        }
+        let captures = captures.into_iter().map(|c| {
+            Ok(Capture { name: self.variable(c.name, "captured by a lambda")?, by_reference: c.by_reference })
+        }).collect::<Result<_>>()?;
+        Ok(Lambda {
+            captures,
+            params: expanded_params,
+            is_variadic,
+            return_type: self.expand_type(return_type)?,
+            body: self.expand_body(body)?,
+        })
+    }
+
+    fn expand_tail(&mut self, tail: ExprTail) -> Result<ExprTail> {
+        Ok(match tail {
+            ExprTail::None => ExprTail::None,
+            ExprTail::Call { body, named, next } => {
+                ExprTail::Call {
+                    body: body.into_iter().map(|a| self.expand_expression(a)).collect::<Result<_>>()?,
+                    named: named.into_iter().map(|a| Ok(NamedArgument { name: a.name, value: self.expand_expression(a.value)? })).collect::<Result<_>>()?,
+                    next: Box::new(self.expand_tail(*next)?),
+                }
+            },
+            // The right side of `&&` and `||` and what follows it may not be evaluated
+            ExprTail::BinaryOp { op: op @ (BinOp::LogicAnd | BinOp::LogicOr), right, next } => {
+                let outer = std::mem::replace(&mut self.conditional, true);
+                let expanded = self.expand_expression(*right).and_then(|right| Ok((right, self.expand_tail(*next)?)));
+                self.conditional = outer;
+                let (right, next) = expanded?;
+                ExprTail::BinaryOp { op, right: Box::new(right), next: Box::new(next) }
+            },
+            ExprTail::BinaryOp { op, right, next } => {
+                ExprTail::BinaryOp { op, right: Box::new(self.expand_expression(*right)?), next: Box::new(self.expand_tail(*next)?) }
+            },
+            ExprTail::MemberAccess { member, next } => ExprTail::MemberAccess { member, next: Box::new(self.expand_tail(*next)?) },
+            ExprTail::PointerAccess { member, next } => ExprTail::PointerAccess { member, next: Box::new(self.expand_tail(*next)?) },
+            ExprTail::Index { inner, next } => {
+                ExprTail::Index { inner: Box::new(self.expand_expression(*inner)?), next: Box::new(self.expand_tail(*next)?) }
+            },
+            ExprTail::Slice { start, end, next } => {
+                ExprTail::Slice {
+                    start: Box::new(self.expand_expression(*start)?),
+                    end: Box::new(self.expand_expression(*end)?),
+                    next: Box::new(self.expand_tail(*next)?),
+                }
+            },
+            ExprTail::TernaryConditional { second, third, next } => {
+                let outer = std::mem::replace(&mut self.conditional, true);
+                let expanded = self.expand_expression(*second)
+                    .and_then(|second| Ok((second, self.expand_expression(*third)?)))
+                    .and_then(|(second, third)| Ok((second, third, self.expand_tail(*next)?)));
+                self.conditional = outer;
+                let (second, third, next) = expanded?;
+                ExprTail::TernaryConditional { second: Box::new(second), third: Box::new(third), next: Box::new(next) }
+            },
+            ExprTail::SafeAccess { member, next } => ExprTail::SafeAccess { member, next: Box::new(self.expand_tail(*next)?) },
+            ExprTail::Coalesce { fallback, next } => {
+                let outer = std::mem::replace(&mut self.conditional, true);
+                let expanded = self.expand_expression(*fallback).and_then(|fallback| Ok((fallback, self.expand_tail(*next)?)));
+                self.conditional = outer;
+                let (fallback, next) = expanded?;
+                ExprTail::Coalesce { fallback: Box::new(fallback), next: Box::new(next) }
+            },
+        })
+    }
+
+    // == Types ==
+
+    /// Replaces a type parameter with its argument. Qualifiers and pointers written around the
+    /// parameter apply to the whole argument, like with a `typedef`, so `const T*` with `T` being
+    /// `char*` is `char* const*`.
+    fn expand_type(&mut self, mut typ: Type) -> Result<Type> {
+        typ.typetype = match typ.typetype {
+            TypeType::Closure(mut signature) => {
+                signature.params = signature.params.into_iter().map(|p| self.expand_type(p)).collect::<Result<_>>()?;
+                signature.return_type = self.expand_type(signature.return_type)?;
+                TypeType::Closure(signature)
+            },
+            TypeType::Tuple(elements) => TypeType::Tuple(elements.into_iter().map(|e| self.expand_type(e)).collect::<Result<_>>()?),
+            TypeType::Slice(element) => TypeType::Slice(Box::new(self.expand_type(*element)?)),
+            // Named after its parameter and return types, which may have changed
+            TypeType::FunctionPointer(mut signature) => {
+                signature.params = signature.params.into_iter().map(|p| self.expand_type(p)).collect::<Result<_>>()?;
+                signature.return_type = self.expand_type(signature.return_type)?;
+                let expanded = Type::function_pointer(*signature);
+                typ.name = expanded.name;
+                expanded.typetype
+            },
+            other => other,
+        };
+        let Identifier::Plain(name) = &typ.name else {
+            return Ok(typ);
+        };
+        match self.bound(name) {
+            Some(Bound::Type(argument)) => {
+                let mut result = argument.clone();
+                result.storage.extend(typ.storage);
+                match result.pointers.last_mut() {
+                    Some(outer) => outer.extend(typ.qualifiers),
+                    None => result.qualifiers.extend(typ.qualifiers),
+                }
+                result.pointers.extend(typ.pointers);
+                Ok(result)
+            },
+            Some(Bound::Identifier(argument)) => {
+                typ.name = Identifier::Plain(argument.clone());
+                Ok(typ)
+            },
+            Some(_) => Err(self.error(format!("'{}' is used as a type, but it isn't a type parameter", name))),
+            None => Ok(typ),
+        }
+    }
+}
+
+/// The variables and labels that `body` declares, outside of lambdas
+fn declared_names(body: &Vec<Statement>, names: &mut Vec<String>) {
+    for statement in body {
+        declared_in(statement, names);
+    }
+}
+
+fn declared_in(statement: &Statement, names: &mut Vec<String>) {
+    match statement {
+        Statement::VarDec { name, .. } | Statement::Let { name, .. } | Statement::Label(name) => names.push(name.clone()),
+        Statement::LetTuple { names: declared, .. } => names.extend(declared.iter().filter(|n| *n != "_").cloned()),
+        Statement::If { base, elseifs, tail } => {
+            declared_names(&base.body, names);
+            elseifs.iter().for_each(|c| declared_names(&c.body, names));
+            if let Some(tail) = tail {
+                declared_names(tail, names);
+            }
+        },
+        Statement::While(condition_body) => declared_names(&condition_body.body, names),
+        Statement::DoWhile { body, .. } => declared_names(body, names),
+        Statement::For { init, body, .. } => {
+            declared_in(init, names);
+            declared_names(body, names);
+        },
+        Statement::EnumFor { name, body, .. } | Statement::ForIn { name, body, .. } => {
+            names.push(name.clone());
+            declared_names(body, names);
+        },
+        Statement::Switch { cases, default, .. } => {
+            cases.iter().for_each(|c| declared_names(&c.body, names));
+            if let Some(default) = default {
+                declared_names(default, names);
+            }
+        },
+        Statement::Labeled { label, statement } => {
+            names.push(label.clone());
+            declared_in(statement, names);
+        },
+        _ => (),
+    }
+}
+
+/// Whether evaluating `expression` twice gives the same value without doing anything else, so
+/// that it can be used in several places, e.g. `p->x + 1` but not `next(p)` or `i++`
+fn is_pure(expression: &Expression) -> bool {
+    let atom = match &expression.atom {
+        Atom::Char(_) | Atom::Short(_) | Atom::Int(_) | Atom::Octal(_) | Atom::TrueLong(_) | Atom::Float(_) | Atom::Double(_) | Atom::Boolean(_)
+            | Atom::String(_) | Atom::Embed(_) | Atom::Bytes(_) | Atom::Identifier(_) | Atom::SizeOf(_) => true,
+        Atom::UnaryOperation { op: UnaryOp::Increment | UnaryOp::Decrement, .. } => false,
+        Atom::UnaryOperation { value, .. } | Atom::TypeCast { value, .. } | Atom::Wrapped(value) => is_pure(value),
+        Atom::InitList(values) => values.iter().all(is_pure),
+        Atom::VaArg { .. } | Atom::Lambda(_) | Atom::Interpolated(_) | Atom::Assign { .. } | Atom::MacroCall { .. } => false,
+    };
+    atom && is_pure_tail(&expression.tail)
+}
+
+fn is_pure_tail(tail: &ExprTail) -> bool {
+    match tail {
+        ExprTail::None => true,
+        ExprTail::Call { .. } => false,
+        ExprTail::BinaryOp { right, next, .. } | ExprTail::Index { inner: right, next } | ExprTail::Coalesce { fallback: right, next } => {
+            is_pure(right) && is_pure_tail(next)
+        },
+        ExprTail::MemberAccess { next, .. } | ExprTail::PointerAccess { next, .. } | ExprTail::SafeAccess { next, .. } => is_pure_tail(next),
+        ExprTail::Slice { start, end, next } | ExprTail::TernaryConditional { second: start, third: end, next } => {
+            is_pure(start) && is_pure(end) && is_pure_tail(next)
+        },
+    }
+}
+
+/// How a macro's body uses one of its parameters
+#[derive(Default, Clone, Copy)]
+struct Uses {
+    /// How often the parameter is read, where uses in a loop count twice since they may run again
+    count: usize,
+    /// Whether the body takes the parameter's address, e.g. `&a`
+    address_taken: bool,
+}
+
+impl Uses {
+    fn add(&mut self, other: Uses) {
+        self.count += other.count;
+        self.address_taken |= other.address_taken;
+    }
+}
+
+/// How `body` uses the parameter `param`, including in the arguments of the macros it calls
+fn count_uses(body: Vec<Statement>, param: &str) -> Uses {
+    let uses = Rc::new(RefCell::new(Uses::default()));
+    let mut counter = Mutator::new();
+    let (in_expressions, name) = (uses.clone(), String::from(param));
+    counter.add_expression_closure(Box::new(move |e| {
+        let mut uses = in_expressions.borrow_mut();
+        match &e.atom {
+            Atom::Identifier(Identifier::Plain(n)) if *n == name => uses.count += 1,
+            Atom::UnaryOperation { op: UnaryOp::AddressOf, value } if matches!(&value.atom, Atom::Identifier(Identifier::Plain(n)) if *n == name) => {
+                uses.address_taken = true;
+            },
+            Atom::MacroCall { args, .. } => {
+                for arg in args {
+                    match arg {
+                        MacroArgument::Expression(value) => uses.add(count_uses(vec![Statement::Expression(value.clone())], &name)),
+                        MacroArgument::Block(body) => uses.add(count_uses(body.clone(), &name)),
+                        MacroArgument::Type(_) => (),
+                    }
+                }
+            },
+            _ => (),
+        }
+        Ok(e)
+    }));
+    let (in_statements, name) = (uses.clone(), String::from(param));
+    counter.add_statement_closure(Box::new(move |s| {
+        let mut repeated = match &s {
+            Statement::While(ConditionBody { condition, body }) | Statement::DoWhile { condition, body } => {
+                let mut parts = body.clone();
+                parts.push(Statement::Expression(condition.clone()));
+                parts
+            },
+            Statement::For { condition, increment, body, .. } => {
+                let mut parts = body.clone();
+                parts.extend([Statement::Expression(condition.clone()), (**increment).clone()]);
+                parts
+            },
+            Statement::ForIn { body, .. } | Statement::EnumFor { body, .. } => body.clone(),
+            _ => Vec::new(),
+        };
+        if let Statement::VarAssign { identifier: IdentifierExpression::Standard(n), .. } | Statement::BinOpVarAssign { identifier: IdentifierExpression::Standard(n), .. }
+            | Statement::IncDec { identifier: IdentifierExpression::Standard(n), .. } = &s {
+            if *n == name {
+                in_statements.borrow_mut().count += 1;
+            }
+        }
+        if !repeated.is_empty() {
+            let again = count_uses(std::mem::take(&mut repeated), &name);
+            in_statements.borrow_mut().add(again);
+        }
+        Ok(s)
+    }));
+    // Counting can't fail, since the closures always succeed
+    let _ = counter.mutate_body(body);
+    let result = *uses.borrow();
+    result
+}
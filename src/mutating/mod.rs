@@ -1 +1,20 @@
 pub mod mutator;
+pub mod builder;
+pub mod enum_reflection;
+pub mod derive;
+pub mod json;
+pub mod for_in;
+pub mod closure;
+pub mod inference;
+pub mod labels;
+pub mod const_fold;
+pub mod attributes;
+pub mod arguments;
+pub mod overload;
+pub mod type_definitions;
+pub mod slice;
+pub mod safe_navigation;
+pub mod interpolation;
+pub mod macros;
+pub mod embed;
+pub mod tests;
@@ -1,9 +1,16 @@
-use crate::parsing::ast::{expression::{Atom, ExprTail, Expression}, function::Function, program::Program, sstruct::Struct, statement::{CaseStatement, ConditionBody, Statement}, toplevel::TopLevel};
+use crate::parsing::ast::{enumm::{Enum, EnumEntry}, expression::{Atom, ExprTail, Expression, NamedArgument, StringPart}, function::Function, lambda::Lambda, program::Program, sstruct::Struct, statement::{CaseLabel, CaseStatement, ConditionBody, ForIterable, StaticAssert, Statement}, toplevel::TopLevel};
 use anyhow::Result;
 
+pub type ExpressionMutator = Box<dyn Fn(Expression) -> Result<Expression>>;
+pub type StatementMutator = Box<dyn Fn(Statement) -> Result<Statement>>;
+pub type FunctionMutator = Box<dyn Fn(Function) -> Result<Function>>;
+pub type StructMutator = Box<dyn Fn(Struct) -> Result<Struct>>;
+
 pub struct Mutator {
-    expr_mutators: Vec<Box<fn(Expression) -> Result<Expression>>>,
-    stmt_mutators: Vec<Box<fn(Statement) -> Result<Statement>>>,
+    expr_mutators: Vec<ExpressionMutator>,
+    stmt_mutators: Vec<StatementMutator>,
+    func_mutators: Vec<FunctionMutator>,
+    struct_mutators: Vec<StructMutator>,
 }
 
 impl Mutator {
@@ -11,6 +18,8 @@ impl Mutator {
         Mutator {
             expr_mutators: Vec::new(),
             stmt_mutators: Vec::new(),
+            func_mutators: Vec::new(),
+            struct_mutators: Vec::new(),
         }
     }
 
@@ -22,6 +31,23 @@ impl Mutator {
         self.stmt_mutators.push(m);
     }
 
+    // Functions and structs are mutated before their contents, e.g. to add or inspect `@attributes`
+    pub fn add_function_mutator(&mut self, m: Box<fn(Function) -> Result<Function>>) {
+        self.func_mutators.push(m);
+    }
+
+    pub fn add_struct_mutator(&mut self, m: Box<fn(Struct) -> Result<Struct>>) {
+        self.struct_mutators.push(m);
+    }
+
+    // Closure-based variants, for mutators that need to carry state (e.g. built-in passes)
+    pub fn add_expression_closure(&mut self, m: ExpressionMutator) {
+        self.expr_mutators.push(m);
+    }
+    pub fn add_statement_closure(&mut self, m: StatementMutator) {
+        self.stmt_mutators.push(m);
+    }
+
     pub fn mutate_program(&self, p: Program) -> Result<Program> {
         let mut toplevels = Vec::new();
         for t in p.statements {
@@ -41,34 +67,56 @@ impl Mutator {
                 Ok(TopLevel::Module { name: name, body: toplevels })
             },
             TopLevel::Function(func) => Ok(TopLevel::Function(self.mutate_function(func)?)),
-            TopLevel::VarDeclaration { typ, name, right, modifier } => {
+            TopLevel::VarDeclaration { typ, name, array_size, right } => {
                 let resolved_right = if let Some(exp) = right {
                     Some(self.mutate_expression(exp)?)
                 } else {
                     None
                 };
-                Ok(TopLevel::VarDeclaration { typ: typ, name: name, right: resolved_right, modifier: modifier })
+                Ok(TopLevel::VarDeclaration { typ, name, array_size, right: resolved_right })
             },
             TopLevel::Import { name, is_lib } => Ok(TopLevel::Import { name: name, is_lib: is_lib }),
             TopLevel::Struct(s) => Ok(TopLevel::Struct(self.mutate_struct(s)?)),
-            TopLevel::Enum(e) => Ok(TopLevel::Enum(e)),
+            TopLevel::Enum(e) => Ok(TopLevel::Enum(self.mutate_enum(e)?)),
             TopLevel::FunctionHeader(function_header) => Ok(TopLevel::FunctionHeader(function_header)),
             TopLevel::TypeDef(type_def) => Ok(TopLevel::TypeDef(type_def)),
-            TopLevel::PreprocessorDirective(str) => Ok(TopLevel::PreprocessorDirective(str)),
+            TopLevel::StaticAssert(assertion) => Ok(TopLevel::StaticAssert(self.mutate_static_assert(assertion)?)),
+            TopLevel::Directive(directive) => Ok(TopLevel::Directive(directive)),
+            TopLevel::Macro(m) => Ok(TopLevel::Macro(m)),
+            TopLevel::Test(test) => Ok(TopLevel::Test(test)),
         }
     }
-    fn mutate_function(&self, func: Function) -> Result<Function> {
+    fn mutate_function(&self, mut func: Function) -> Result<Function> {
+        for m in &self.func_mutators {
+            func = m(func)?;
+        }
         Ok(Function { header: func.header, body: self.mutate_body(func.body)? })
     }
-    fn mutate_struct(&self, struc: Struct) -> Result<Struct> {
+    fn mutate_struct(&self, mut struc: Struct) -> Result<Struct> {
+        for m in &self.struct_mutators {
+            struc = m(struc)?;
+        }
         Ok(Struct {
             name: struc.name,
             members: struc.members,
             is_anonymous: struc.is_anonymous,
             is_union: struc.is_union,
+            derives: struc.derives,
+            attributes: struc.attributes,
         })
     }
 
+    fn mutate_enum(&self, e: Enum) -> Result<Enum> {
+        let mut entries = Vec::new();
+        for entry in e.entries {
+            entries.push(EnumEntry { name: entry.name, value: entry.value.map(|v| self.mutate_expression(v)).transpose()? });
+        }
+        Ok(Enum { name: e.name, entries, is_anonymous: e.is_anonymous, reflected: e.reflected })
+    }
+    fn mutate_static_assert(&self, assertion: StaticAssert) -> Result<StaticAssert> {
+        Ok(StaticAssert { condition: self.mutate_expression(assertion.condition)?, message: assertion.message })
+    }
+
     pub fn mutate_expression(&self, mut expression: Expression) -> Result<Expression> {
         for m in &self.expr_mutators {
             expression = m(expression)?;
@@ -86,6 +134,7 @@ impl Mutator {
             Atom::Char(v) => Ok(Atom::Char(v)),
             Atom::Short(v) => Ok(Atom::Short(v)),
             Atom::Int(v) => Ok(Atom::Int(v)),
+            Atom::Octal(v) => Ok(Atom::Octal(v)),
             Atom::TrueLong(v) => Ok(Atom::TrueLong(v)),
             Atom::Float(v) => Ok(Atom::Float(v)),
             Atom::Double(v) => Ok(Atom::Double(v)),
@@ -99,18 +148,42 @@ impl Mutator {
                 Ok(Atom::UnaryOperation { op: op, value: Box::new(self.mutate_expression(*value)?) })
             },
             Atom::SizeOf(v) => Ok(Atom::SizeOf(v)),
+            Atom::VaArg { list, typ } => {
+                Ok(Atom::VaArg { list: Box::new(self.mutate_expression(*list)?), typ })
+            },
             Atom::Wrapped(expression) => {
                 Ok(Atom::Wrapped(Box::new(self.mutate_expression(*expression)?)))
             },
+            Atom::Lambda(lambda) => {
+                let Lambda { captures, params, is_variadic, return_type, body } = *lambda;
+                Ok(Atom::Lambda(Box::new(Lambda { captures, params, is_variadic, return_type, body: self.mutate_body(body)? })))
+            },
+            Atom::InitList(values) => {
+                Ok(Atom::InitList(values.into_iter().map(|v| self.mutate_expression(v)).collect::<Result<_, _>>()?))
+            },
+            Atom::Interpolated(parts) => Ok(Atom::Interpolated(parts.into_iter().map(|p| self.mutate_string_part(p)).collect::<Result<_>>()?)),
+            Atom::Assign { target, value } => {
+                Ok(Atom::Assign { target, value: Box::new(self.mutate_expression(*value)?) })
+            },
+            Atom::MacroCall { name, args } => Ok(Atom::MacroCall { name, args }),
+            Atom::Embed(path) => Ok(Atom::Embed(path)),
+            Atom::Bytes(bytes) => Ok(Atom::Bytes(bytes)),
+        }
+    }
+    fn mutate_string_part(&self, part: StringPart) -> Result<StringPart> {
+        match part {
+            StringPart::Value { value, format } => Ok(StringPart::Value { value: Box::new(self.mutate_expression(*value)?), format }),
+            text => Ok(text),
         }
     }
     fn mutate_tail(&self, tail: ExprTail) -> Result<ExprTail> {
         match tail {
             ExprTail::None => Ok(ExprTail::None),
-            ExprTail::Call { body, next } => {
+            ExprTail::Call { body, named, next } => {
                 Ok(
                     ExprTail::Call {
                         body: body.into_iter().map(|c| self.mutate_expression(c)).collect::<Result<_, _>>()?,
+                        named: named.into_iter().map(|a| Ok(NamedArgument { name: a.name, value: self.mutate_expression(a.value)? })).collect::<Result<_>>()?,
                         next: Box::new(self.mutate_tail(*next)?)
                     }
                 )
@@ -148,6 +221,15 @@ impl Mutator {
                     }
                 )
             },
+            ExprTail::Slice { start, end, next } => {
+                Ok(
+                    ExprTail::Slice {
+                        start: Box::new(self.mutate_expression(*start)?),
+                        end: Box::new(self.mutate_expression(*end)?),
+                        next: Box::new(self.mutate_tail(*next)?)
+                    }
+                )
+            },
             ExprTail::TernaryConditional { second, third, next } => {
                 Ok(
                     ExprTail::TernaryConditional { 
@@ -157,6 +239,22 @@ impl Mutator {
                     }
                 )
             },
+            ExprTail::SafeAccess { member, next } => {
+                Ok(
+                    ExprTail::SafeAccess {
+                        member,
+                        next: Box::new(self.mutate_tail(*next)?)
+                    }
+                )
+            },
+            ExprTail::Coalesce { fallback, next } => {
+                Ok(
+                    ExprTail::Coalesce {
+                        fallback: Box::new(self.mutate_expression(*fallback)?),
+                        next: Box::new(self.mutate_tail(*next)?)
+                    }
+                )
+            },
         }
     }
 
@@ -167,14 +265,16 @@ impl Mutator {
 
         match statement {
             Statement::Expression(expression) => Ok(Statement::Expression(self.mutate_expression(expression)?)),
-            Statement::VarDec { typ, name, right, modifier } => {
+            Statement::VarDec { typ, name, array_size, right } => {
                 let resolved_right = if let Some(exp) = right {
                     Some(self.mutate_expression(exp)?)
                 } else {
                     None
                 };
-                Ok(Statement::VarDec { typ: typ, name: name, right: resolved_right, modifier: modifier })
+                Ok(Statement::VarDec { typ, name, array_size, right: resolved_right })
             },
+            Statement::Let { name, right } => Ok(Statement::Let { name, right: self.mutate_expression(right)? }),
+            Statement::LetTuple { names, right } => Ok(Statement::LetTuple { names, right: self.mutate_expression(right)? }),
             Statement::VarAssign { identifier, right } => {
                 Ok(Statement::VarAssign { identifier: identifier, right: self.mutate_expression(right)? })
             },
@@ -191,6 +291,9 @@ impl Mutator {
                     }
                 ))
             },
+            Statement::ReturnTuple(values) => {
+                Ok(Statement::ReturnTuple(values.into_iter().map(|v| self.mutate_expression(v)).collect::<Result<_, _>>()?))
+            },
             Statement::If { base, elseifs, tail } => {
                 Ok(Statement::If {
                     base: self.mutate_condition_body(base)?,
@@ -214,17 +317,56 @@ impl Mutator {
                     }
                 )
             },
-            Statement::Switch { atom, cases, default } => {
+            Statement::EnumFor { typ, name, enum_type, body } => {
+                Ok(
+                    Statement::EnumFor {
+                        typ,
+                        name,
+                        enum_type,
+                        body: self.mutate_body(body)?,
+                    }
+                )
+            },
+            Statement::ForIn { typ, name, iterable, body } => {
+                let mutated_iterable = match iterable {
+                    ForIterable::Range { start, end } => {
+                        ForIterable::Range { start: Box::new(self.mutate_expression(*start)?), end: Box::new(self.mutate_expression(*end)?) }
+                    },
+                    ForIterable::Array { array, length, by_reference } => {
+                        ForIterable::Array { array: self.mutate_expression(array)?, length, by_reference }
+                    },
+                    ForIterable::Slice { slice, by_reference } => {
+                        ForIterable::Slice { slice: self.mutate_expression(slice)?, by_reference }
+                    },
+                };
+                Ok(
+                    Statement::ForIn {
+                        typ,
+                        name,
+                        iterable: mutated_iterable,
+                        body: self.mutate_body(body)?,
+                    }
+                )
+            },
+            Statement::Switch { value, cases, default, no_fallthrough } => {
                 Ok(
                     Statement::Switch {
-                        atom: self.mutate_atom(atom)?,
+                        value: self.mutate_expression(value)?,
                         cases: cases.into_iter().map(|c| self.mutate_case_body(c)).collect::<Result<_, _>>()?,
                         default: default.map(|d| self.mutate_body(d)).transpose()?,
+                        no_fallthrough,
                     }
                 )
             },
-            Statement::Continue => Ok(Statement::Continue),
-            Statement::Break => Ok(Statement::Break),
+            Statement::Labeled { label, statement } => {
+                Ok(Statement::Labeled { label, statement: Box::new(self.mutate_statement(*statement)?) })
+            },
+            Statement::Goto(label) => Ok(Statement::Goto(label)),
+            Statement::Label(label) => Ok(Statement::Label(label)),
+            Statement::Continue(label) => Ok(Statement::Continue(label)),
+            Statement::Break(label) => Ok(Statement::Break(label)),
+            Statement::StaticAssert(assertion) => Ok(Statement::StaticAssert(self.mutate_static_assert(assertion)?)),
+            Statement::Directive(directive) => Ok(Statement::Directive(directive)),
         }
     }
 
@@ -244,8 +386,11 @@ impl Mutator {
         })
     }
     fn mutate_case_body(&self, case: CaseStatement) -> Result<CaseStatement> {
-        let atom = self.mutate_atom(case.atom)?;
+        let labels = case.labels.into_iter().map(|l| match l {
+            CaseLabel::Value(value) => Ok(CaseLabel::Value(Box::new(self.mutate_expression(*value)?))),
+            CaseLabel::Range { start, end } => Ok(CaseLabel::Range { start: Box::new(self.mutate_expression(*start)?), end: Box::new(self.mutate_expression(*end)?) }),
+        }).collect::<Result<_>>()?;
         let body = self.mutate_body(case.body)?;
-        Ok(CaseStatement { atom: atom, body: body })
+        Ok(CaseStatement { labels, body })
     }
 }
@@ -0,0 +1,308 @@
+use std::collections::HashMap;
+
+use crate::{analysis::typing::ExpressionTyper, codegen::simple::SimpleCodeGen, parsing::ast::{directive::Directive, function::{Function, FunctionHeader, Parameter}, identifier::Identifier, program::Program, toplevel::TopLevel, types::{Qualifier, Type}}};
+use anyhow::{bail, Result};
+
+/// The most parameters a wrapper macro can dispatch on, which `MC_OVERLOAD` in `mc/overload.h` counts up to
+const MAX_WRAPPED_PARAMS: usize = 8;
+
+/// Functions declared more than once in the same scope with different parameter types. Each
+/// overload is renamed to `name__` followed by its mangled parameter types (see `mangle_params`),
+/// and calls are resolved with the types of their arguments by `LetInference`.
+///
+/// Functions that aren't overloaded keep their names.
+pub struct Overloads {
+    /// Overload sets by the C name of the function they overload, e.g. `mod__io__print`
+    sets: HashMap<String, OverloadSet>,
+    /// The keys of `sets` in declaration order
+    order: Vec<String>,
+}
+
+struct OverloadSet {
+    /// The miniC name, e.g. `io::print`, for error messages
+    name: String,
+    module: Option<String>,
+    overloads: Vec<Overload>,
+}
+
+struct Overload {
+    /// The C name without the module prefix, e.g. `print__int`
+    name: String,
+    params: Vec<Type>,
+    is_variadic: bool,
+}
+
+impl Overload {
+    fn describe(&self, name: &String) -> String {
+        let mut params = self.params.iter().map(|p| p.describe()).collect::<Vec<String>>();
+        if self.is_variadic {
+            params.push(String::from("..."));
+        }
+        format!("{}({})", name, params.join(", "))
+    }
+}
+
+impl Overloads {
+    pub fn new(program: &Program) -> Result<Self> {
+        let mut declarations = Vec::new();
+        Self::collect(&program.statements, None, &mut declarations);
+        let mut by_name: HashMap<String, Vec<(Option<&String>, &FunctionHeader)>> = HashMap::new();
+        let mut names = Vec::new();
+        for (module, header) in declarations {
+            let key = c_name(module, &header.name);
+            if !by_name.contains_key(&key) {
+                names.push(key.clone());
+            }
+            by_name.entry(key).or_default().push((module, header));
+        }
+
+        let mut overloads = Overloads { sets: HashMap::new(), order: Vec::new() };
+        for key in names {
+            let headers = &by_name[&key];
+            let (module, first) = headers[0];
+            let name = match module {
+                Some(m) => format!("{}::{}", m, first.name),
+                None => first.name.clone(),
+            };
+            let mut set = OverloadSet { name, module: module.cloned(), overloads: Vec::new() };
+            for (_, header) in headers {
+                let mangled = format!("{}__{}", header.name, mangle_params(&header.params, header.is_variadic));
+                let params: Vec<Type> = header.params.iter().map(|p| p.typ.clone()).collect();
+                let overload = Overload { name: mangled, params, is_variadic: header.is_variadic };
+                match set.overloads.iter().find(|o| o.name == overload.name) {
+                    // Another declaration of the same overload
+                    Some(existing) if existing.describe(&set.name) == overload.describe(&set.name) => (),
+                    Some(existing) => bail!(
+                        "Overloads '{}' and '{}' would both be named '{}' in C",
+                        existing.describe(&set.name), overload.describe(&set.name), overload.name
+                    ),
+                    None => set.overloads.push(overload),
+                }
+            }
+            if set.overloads.len() < 2 {
+                continue;
+            }
+            if key == "main" {
+                bail!("'main' can't be overloaded");
+            }
+            overloads.order.push(key.clone());
+            overloads.sets.insert(key, set);
+        }
+        Ok(overloads)
+    }
+
+    fn collect<'a>(toplevels: &'a Vec<TopLevel>, module: Option<&'a String>, declarations: &mut Vec<(Option<&'a String>, &'a FunctionHeader)>) {
+        for t in toplevels {
+            match t {
+                TopLevel::Function(Function { header, .. }) | TopLevel::FunctionHeader(header) => declarations.push((module, header)),
+                TopLevel::Module { name, body } => Self::collect(body, Some(name), declarations),
+                _ => (),
+            }
+        }
+    }
+
+    /// Renames every declaration of an overloaded function to the name of its overload
+    pub fn mangle_program(&self, program: Program) -> Program {
+        Program {
+            statements: self.mangle_toplevels(program.statements, None),
+        }
+    }
+
+    fn mangle_toplevels(&self, toplevels: Vec<TopLevel>, module: Option<&String>) -> Vec<TopLevel> {
+        toplevels.into_iter().map(|t| match t {
+            TopLevel::Function(func) => TopLevel::Function(Function { header: self.mangle_header(func.header, module), body: func.body }),
+            TopLevel::FunctionHeader(header) => TopLevel::FunctionHeader(self.mangle_header(header, module)),
+            TopLevel::Module { name, body } => {
+                let body = self.mangle_toplevels(body, Some(&name));
+                TopLevel::Module { name, body }
+            },
+            other => other,
+        }).collect()
+    }
+
+    fn mangle_header(&self, mut header: FunctionHeader, module: Option<&String>) -> FunctionHeader {
+        if self.sets.contains_key(&c_name(module, &header.name)) {
+            header.name = format!("{}__{}", header.name, mangle_params(&header.params, header.is_variadic));
+        }
+        header
+    }
+
+    pub fn is_overloaded(&self, callee: &Identifier) -> bool {
+        self.sets.contains_key(&callee.generate())
+    }
+
+    /// The miniC name of an overloaded function, for error messages
+    pub fn describe(&self, callee: &Identifier) -> String {
+        self.sets[&callee.generate()].name.clone()
+    }
+
+    /// Chooses the overload to call with arguments of the given types. Like in C++, the chosen
+    /// overload must need conversions that are at least as good for every argument as any other
+    /// overload, and a better one for at least one argument.
+    pub fn resolve(&self, callee: &Identifier, args: &[Type], typer: &ExpressionTyper) -> Result<Identifier> {
+        let set = &self.sets[&callee.generate()];
+        let mut viable = Vec::new();
+        for overload in &set.overloads {
+            if args.len() < overload.params.len() || (args.len() > overload.params.len() && !overload.is_variadic) {
+                continue;
+            }
+            let ranks: Option<Vec<u8>> = args.iter().enumerate().map(|(i, arg)| match overload.params.get(i) {
+                Some(param) => typer.conversion(arg, param).map(|c| c as u8),
+                // Arguments passed to `...` rank below any conversion
+                None => Some(u8::MAX),
+            }).collect();
+            if let Some(ranks) = ranks {
+                viable.push((overload, ranks));
+            }
+        }
+        let described_args = args.iter().map(|a| a.describe()).collect::<Vec<String>>().join(", ");
+        let better = |a: &Vec<u8>, b: &Vec<u8>| a.iter().zip(b).all(|(a, b)| a <= b) && a.iter().zip(b).any(|(a, b)| a < b);
+        let best = viable.iter().find(|(_, ranks)| viable.iter().all(|(_, other)| std::ptr::eq(ranks, other) || better(ranks, other)));
+        match best {
+            Some((overload, _)) => Ok(match &set.module {
+                Some(m) => Identifier::Module(m.clone(), overload.name.clone()),
+                None => Identifier::Plain(overload.name.clone()),
+            }),
+            None if viable.is_empty() => {
+                let candidates = set.overloads.iter().map(|o| o.describe(&set.name)).collect::<Vec<String>>().join(", ");
+                bail!("No overload of '{}' can be called with ({}); the overloads are {}", set.name, described_args, candidates)
+            },
+            None => {
+                let mut candidates = viable.iter().map(|(o, _)| o.describe(&set.name)).collect::<Vec<String>>();
+                let last = candidates.pop().unwrap();
+                bail!("The call to '{}' with ({}) is ambiguous between {} and {}", set.name, described_args, candidates.join(", "), last)
+            },
+        }
+    }
+
+    /// `#define`s that let C code call each overloaded function by its miniC name, choosing the
+    /// overload with `_Generic`. `_Generic` picks a type exactly as it is, with no conversions,
+    /// so C callers need to pass arguments of exactly the parameter types, except that a `float`
+    /// goes to a `double` overload when no overload takes a `float` there.
+    ///
+    /// Functions with a variadic overload, an overload without parameters, or more than
+    /// `MAX_WRAPPED_PARAMS` parameters can't be told apart by a macro, so they get none.
+    pub fn wrapper_macros(&self) -> Vec<TopLevel> {
+        let mut macros = Vec::new();
+        for key in &self.order {
+            let set = &self.sets[key];
+            if set.overloads.iter().any(|o| o.is_variadic || o.params.is_empty() || o.params.len() > MAX_WRAPPED_PARAMS) {
+                continue;
+            }
+            let prefix = c_name(set.module.as_ref(), &String::new());
+            macros.push(TopLevel::Directive(Directive::Define {
+                name: key.clone(),
+                params: Some(vec![String::from("...")]),
+                value: format!("MC_OVERLOAD({}, __VA_ARGS__)", key),
+            }));
+            let mut arities = set.overloads.iter().map(|o| o.params.len()).collect::<Vec<usize>>();
+            arities.sort();
+            arities.dedup();
+            for arity in arities {
+                let overloads = set.overloads.iter().filter(|o| o.params.len() == arity).collect();
+                let args = (1..=arity).map(|i| format!("a{}", i)).collect::<Vec<String>>();
+                let value = format!("{}({})", Self::dispatch(&overloads, &prefix, 0), args.join(", "));
+                macros.push(TopLevel::Directive(Directive::Define { name: format!("{}__mc_{}", key, arity), params: Some(args), value }));
+            }
+        }
+        macros
+    }
+
+    /// A `_Generic` on the argument at `index`, nested for each later argument until one overload is left
+    fn dispatch(overloads: &Vec<&Overload>, prefix: &String, index: usize) -> String {
+        if index == overloads[0].params.len() {
+            return format!("{}{}", prefix, overloads[0].name);
+        }
+        let mut groups: Vec<(Type, Vec<&Overload>)> = Vec::new();
+        for overload in overloads {
+            let typ = overload.params[index].unqualified();
+            match groups.iter_mut().find(|(t, _)| t.generate() == typ.generate()) {
+                Some((_, group)) => group.push(overload),
+                None => groups.push((typ, vec![overload])),
+            }
+        }
+        let mut associations = groups.iter().map(|(typ, group)| (typ.generate(), group)).collect::<Vec<_>>();
+        associations.extend(Self::implicit_associations(&groups));
+        let mut branches = associations.iter().map(|(typ, group)| format!("{}: {}", typ, Self::dispatch(group, prefix, index + 1))).collect::<Vec<String>>();
+        // Branches that aren't chosen still need to compile (see `mc/overload.h`)
+        if index > 0 {
+            branches.push(String::from("default: mc_overload_no_match"));
+        }
+        format!("_Generic((a{}), {})", index + 1, branches.join(", "))
+    }
+
+    /// Associations for the argument types that `resolve` converts implicitly: types smaller than
+    /// `int` are promoted to it, `float` to `double`, and pointers may gain `const` or `volatile`.
+    /// A type that several groups would take gets none, since `resolve` finds such calls ambiguous,
+    /// and neither does one that is compatible with a type a group takes exactly.
+    fn implicit_associations<'a, 'b>(groups: &'b [(Type, Vec<&'a Overload>)]) -> Vec<(String, &'b Vec<&'a Overload>)> {
+        let mut claims: Vec<(String, Vec<usize>)> = Vec::new();
+        for (i, (typ, _)) in groups.iter().enumerate() {
+            let mut claimed = Vec::new();
+            if typ.pointer_layers() > 0 {
+                // Every combination of the pointee's `const` and `volatile` but all of them
+                let removable = typ.qualifiers.iter().filter(|q| matches!(q, Qualifier::Const | Qualifier::Volatile)).cloned().collect::<Vec<_>>();
+                for kept in 0..(1usize << removable.len()) - 1 {
+                    let mut variant = typ.clone();
+                    variant.qualifiers.retain(|q| removable.iter().enumerate().all(|(bit, r)| r != q || kept & (1 << bit) != 0));
+                    claimed.push(variant.generate());
+                }
+            } else {
+                match canonical(&typ.generate()).as_str() {
+                    "int" => claimed.extend(PROMOTED_TO_INT.iter().map(|t| String::from(*t))),
+                    "double" => claimed.push(String::from("float")),
+                    _ => (),
+                }
+            }
+            for typ in claimed {
+                match claims.iter_mut().find(|(t, _)| *t == typ) {
+                    Some((_, claimants)) => claimants.push(i),
+                    None => claims.push((typ, vec![i])),
+                }
+            }
+        }
+        let exact = groups.iter().map(|(typ, _)| canonical(&typ.generate())).collect::<Vec<String>>();
+        claims.into_iter()
+            .filter(|(typ, claimants)| claimants.len() == 1 && !exact.contains(&canonical(typ)))
+            .map(|(typ, claimants)| (typ, &groups[claimants[0]].1))
+            .collect()
+    }
+}
+
+/// The builtin types that are promoted to `int`
+const PROMOTED_TO_INT: [&str; 6] = ["_Bool", "char", "signed char", "unsigned char", "short", "unsigned short"];
+
+/// The builtin spelling of a type that `_Generic` can't tell apart from it, e.g. `short` for
+/// `uint16_t`, so that a type isn't associated twice
+fn canonical(typ: &str) -> String {
+    String::from(match typ {
+        "bool" => "_Bool",
+        "int8_t" => "signed char",
+        "uint8_t" => "unsigned char",
+        "int16_t" | "short int" | "signed short" | "signed short int" => "short",
+        "uint16_t" | "unsigned short int" => "unsigned short",
+        "int32_t" | "signed" | "signed int" => "int",
+        other => other,
+    })
+}
+
+fn c_name(module: Option<&String>, name: &String) -> String {
+    match module {
+        Some(m) => format!("mod__{}__{}", m, name),
+        None => name.clone(),
+    }
+}
+
+/// The part of an overload's C name that comes from its parameters: each parameter's type is
+/// mangled by `Type::mangle` and joined with `_`, `void` stands for no parameters, and `va` is
+/// added for `...`. For example `print(const char* s, int n)` becomes `print__constcharp_int`.
+pub(crate) fn mangle_params(params: &[Parameter], is_variadic: bool) -> String {
+    let mut parts = params.iter().map(|p| p.typ.mangle()).collect::<Vec<String>>();
+    if parts.is_empty() {
+        parts.push(String::from("void"));
+    }
+    if is_variadic {
+        parts.push(String::from("va"));
+    }
+    parts.join("_")
+}
@@ -0,0 +1,223 @@
+use crate::{analysis::{scopes::{Scoped, ScopedLowering}, typing::ExpressionTyper}, codegen::simple::SimpleCodeGen, parsing::ast::{expression::{Atom, BinOp, ExprTail, Expression, UnaryOp}, function::Function, lambda::Lambda, program::Program, statement::Statement, toplevel::TopLevel, types::Type}};
+use anyhow::{anyhow, bail, Result};
+
+use super::builder::{binary, ident, int, ternary, unary, var_dec, wrapped};
+
+/// Lowers `?->` and `??` to conditional expressions:
+/// - `a?->b?->c` becomes `(!a || !(__mc_nav0 = a->b) ? 0 : __mc_nav0->c)`, so a null pointer
+///   skips the rest of the chain and gives null (or zero, for a number)
+/// - `p ?? fallback` becomes `(!p ? fallback : p)`, and a `?->` chain before `??` gives the
+///   fallback instead of null when it is skipped
+///
+/// Every pointer that is tested is evaluated once. Unless it is a plain variable, it is stored
+/// in a hidden variable declared just before the statement that uses it.
+pub struct SafeNavigation {
+    typer: ExpressionTyper,
+    /// Hidden variables needed by the statement being lowered
+    temporaries: Vec<Statement>,
+    /// The number of hidden variables in the current function, for naming them
+    temporary_count: usize,
+}
+
+impl SafeNavigation {
+    pub fn new(program: &Program) -> Self {
+        SafeNavigation {
+            typer: ExpressionTyper::new(program),
+            temporaries: Vec::new(),
+            temporary_count: 0,
+        }
+    }
+
+    pub fn lower_program(mut self, program: Program) -> Result<Program> {
+        Ok(Program {
+            statements: self.lower_toplevels(program.statements)?,
+        })
+    }
+
+    fn lower_toplevels(&mut self, toplevels: Vec<TopLevel>) -> Result<Vec<TopLevel>> {
+        let mut result = Vec::new();
+        for t in toplevels {
+            result.push(match t {
+                TopLevel::Function(func) => {
+                    self.typer.push_scope();
+                    self.typer.declare_params(&func.header.params);
+                    self.temporary_count = 0;
+                    let body = self.lower_body(func.body);
+                    self.typer.pop_scope();
+                    TopLevel::Function(Function { header: func.header, body: body? })
+                },
+                TopLevel::VarDeclaration { typ, name, array_size, right } => {
+                    let right = right.map(|r| self.lower_expression(r)).transpose()?;
+                    if !self.temporaries.is_empty() {
+                        bail!("Cannot initialize the global '{}' with a '?->' or '??' that needs a hidden variable", name);
+                    }
+                    TopLevel::VarDeclaration { typ, name, array_size, right }
+                },
+                TopLevel::Module { name, body } => TopLevel::Module { name, body: self.lower_toplevels(body)? },
+                other => other,
+            });
+        }
+        Ok(result)
+    }
+
+    /// A test that is true when a pointer is null, and the pointer to use after it: the
+    /// variable itself, or a hidden variable that the test stores the value in
+    fn test_null(&mut self, original: &Expression, lowered: Expression) -> Result<(Expression, Expression)> {
+        if let (Atom::Identifier(_), ExprTail::None) = (&lowered.atom, &lowered.tail) {
+            return Ok((unary(UnaryOp::LogicNot, lowered.clone()), lowered));
+        }
+        let typ = self.typer.type_of(original)?.unqualified();
+        let hidden = format!("__mc_nav{}", self.temporary_count);
+        self.temporary_count += 1;
+        self.typer.declare(&hidden, &typ, None);
+        self.temporaries.push(var_dec(typ, &hidden, None));
+        let stored = Expression::new(Atom::Assign { target: hidden.clone(), value: Box::new(lowered) }, ExprTail::None);
+        Ok((unary(UnaryOp::LogicNot, stored), ident(&hidden)))
+    }
+
+    /// `(skip || ... ? instead : value)`, or just `value` if nothing can skip it
+    fn skip_with(skips: Vec<Expression>, instead: Expression, value: Expression) -> Expression {
+        let Some(condition) = skips.into_iter().reduce(|left, right| binary(left, BinOp::LogicOr, right)) else {
+            return value;
+        };
+        wrapped(ternary(condition, instead, value))
+    }
+
+    fn lower_atom(&mut self, atom: Atom) -> Result<Atom> {
+        match atom {
+            Atom::Lambda(lambda) => {
+                let Lambda { captures, params, is_variadic, return_type, body } = *lambda;
+                self.typer.push_scope();
+                self.typer.declare_params(&params);
+                let body = self.lower_body(body);
+                self.typer.pop_scope();
+                Ok(Atom::Lambda(Box::new(Lambda { captures, params, is_variadic, return_type, body: body? })))
+            },
+            Atom::TypeCast { typ, value } => Ok(Atom::TypeCast { typ, value: Box::new(self.lower_expression(*value)?) }),
+            Atom::VaArg { list, typ } => Ok(Atom::VaArg { list: Box::new(self.lower_expression(*list)?), typ }),
+            Atom::UnaryOperation { op, value } => Ok(Atom::UnaryOperation { op, value: Box::new(self.lower_expression(*value)?) }),
+            Atom::Wrapped(value) => Ok(Atom::Wrapped(Box::new(self.lower_expression(*value)?))),
+            Atom::InitList(values) => Ok(Atom::InitList(values.into_iter().map(|v| self.lower_expression(v)).collect::<Result<_, _>>()?)),
+            other => Ok(other),
+        }
+    }
+}
+
+impl Scoped for SafeNavigation {
+    fn push_scope(&mut self) {
+        self.typer.push_scope();
+    }
+
+    fn pop_scope(&mut self) {
+        self.typer.pop_scope();
+    }
+
+    fn declare(&mut self, name: &str, typ: &Type, array_size: Option<usize>) {
+        self.typer.declare(name, typ, array_size);
+    }
+}
+
+impl ScopedLowering for SafeNavigation {
+    fn lower_expression(&mut self, expression: Expression) -> Result<Expression> {
+        let atom = self.lower_atom(expression.atom.clone())?;
+        // `original` is the expression so far as it was written, which is what the typer understands,
+        // and `lowered` is what it becomes
+        let mut original = Expression::new(expression.atom, ExprTail::None);
+        let mut lowered = Expression::new(atom, ExprTail::None);
+        // Tests that skip the rest of the chain when they are true, one for each `?->` so far
+        let mut skips = Vec::<Expression>::new();
+        let mut tail = expression.tail;
+        loop {
+            let (current, next) = match tail {
+                ExprTail::None => return Ok(Self::skip_with(skips, int(0), lowered)),
+                ExprTail::SafeAccess { member, next } => {
+                    let current = ExprTail::SafeAccess { member: member.clone(), next: Box::new(ExprTail::None) };
+                    let navigated = Expression::new(original.atom.clone(), original.tail.clone().append(current.clone()));
+                    self.typer.type_of(&navigated).map_err(|e| anyhow!("Cannot use '?->' on '{}': {}", original.generate(), e))?;
+                    let (test, pointer) = self.test_null(&original, lowered)?;
+                    skips.push(test);
+                    original = navigated;
+                    let access = ExprTail::PointerAccess { member, next: Box::new(ExprTail::None) };
+                    lowered = Expression::new(pointer.atom, pointer.tail.append(access));
+                    tail = *next;
+                    continue;
+                },
+                ExprTail::Coalesce { fallback, next } => {
+                    let fallback = self.lower_expression(*fallback)?;
+                    let current = ExprTail::Coalesce { fallback: Box::new(fallback.clone()), next: Box::new(ExprTail::None) };
+                    let coalesced = Expression::new(original.atom.clone(), original.tail.clone().append(current));
+                    self.typer.type_of(&coalesced).map_err(|e| anyhow!("Cannot use '??' on '{}': {}", original.generate(), e))?;
+                    let is_pointer = self.typer.type_of(&original).is_ok_and(|t| self.typer.is_pointer(&t));
+                    let value = match is_pointer {
+                        true => {
+                            let (test, pointer) = self.test_null(&original, lowered)?;
+                            skips.push(test);
+                            pointer
+                        },
+                        // A number is only replaced when a `?->` skipped it
+                        false => lowered,
+                    };
+                    original = coalesced;
+                    lowered = Self::skip_with(std::mem::take(&mut skips), fallback, value);
+                    tail = *next;
+                    continue;
+                },
+                ExprTail::Call { body, named, next } => {
+                    let body = body.into_iter().map(|a| self.lower_expression(a)).collect::<Result<_, _>>()?;
+                    (ExprTail::Call { body, named, next: Box::new(ExprTail::None) }, *next)
+                },
+                ExprTail::Index { inner, next } => {
+                    (ExprTail::Index { inner: Box::new(self.lower_expression(*inner)?), next: Box::new(ExprTail::None) }, *next)
+                },
+                ExprTail::Slice { start, end, next } => {
+                    let (start, end) = (self.lower_expression(*start)?, self.lower_expression(*end)?);
+                    (ExprTail::Slice { start: Box::new(start), end: Box::new(end), next: Box::new(ExprTail::None) }, *next)
+                },
+                ExprTail::MemberAccess { member, next } => (ExprTail::MemberAccess { member, next: Box::new(ExprTail::None) }, *next),
+                ExprTail::PointerAccess { member, next } => (ExprTail::PointerAccess { member, next: Box::new(ExprTail::None) }, *next),
+                // Operators end the chain that a `?->` skips
+                ExprTail::BinaryOp { op, right, next } => {
+                    lowered = Self::skip_with(std::mem::take(&mut skips), int(0), lowered);
+                    (ExprTail::BinaryOp { op, right: Box::new(self.lower_expression(*right)?), next: Box::new(ExprTail::None) }, *next)
+                },
+                ExprTail::TernaryConditional { second, third, next } => {
+                    lowered = Self::skip_with(std::mem::take(&mut skips), int(0), lowered);
+                    let current = ExprTail::TernaryConditional {
+                        second: Box::new(self.lower_expression(*second)?),
+                        third: Box::new(self.lower_expression(*third)?),
+                        next: Box::new(ExprTail::None),
+                    };
+                    (current, *next)
+                },
+            };
+            original = Expression::new(original.atom, original.tail.append(current.clone()));
+            lowered = Expression::new(lowered.atom, lowered.tail.append(current));
+            tail = next;
+        }
+    }
+
+    /// Lowers each statement, declaring the hidden variables it needs just before it
+    fn lower_statements(&mut self, body: Vec<Statement>) -> Result<Vec<Statement>> {
+        let outer = std::mem::take(&mut self.temporaries);
+        let mut result = Vec::new();
+        for statement in body {
+            let lowered = self.lower_statement(statement);
+            result.append(&mut self.temporaries);
+            match lowered {
+                Ok(lowered) => result.push(lowered),
+                Err(e) => {
+                    self.temporaries = outer;
+                    return Err(e);
+                },
+            }
+        }
+        self.temporaries = outer;
+        Ok(result)
+    }
+
+    /// C doesn't allow a declaration right after a `case` label, so the hidden variables that a
+    /// case's statements need are declared before the `switch` instead
+    fn lower_case_body(&mut self, body: Vec<Statement>) -> Result<Vec<Statement>> {
+        self.scoped(|s| body.into_iter().map(|statement| s.lower_statement(statement)).collect())
+    }
+}
@@ -0,0 +1,343 @@
+use std::collections::HashMap;
+
+use crate::{analysis::{scopes::{Scoped, ScopedLowering}, typing::ExpressionTyper}, codegen::simple::SimpleCodeGen, parsing::ast::{expression::{Atom, ExprTail, Expression, UnaryOp}, function::Function, lambda::Lambda, program::Program, statement::{ForIterable, IdentifierExpression, Statement}, toplevel::TopLevel, types::{Type, TypeType}}};
+use anyhow::{bail, Result};
+
+use super::{builder::{call, cast, ident, init_list, int, plain, string, unary, var_dec, wrapped}, for_in::{element_binding, same_or_more_qualified}};
+
+/// Lowers slices to the structs and helpers defined by `TypeDefinitions`:
+/// - `s[i]` becomes `(*S__at(s, i, "s[i] in f", __FILE__, __LINE__))` when bounds checks are enabled, or `s.ptr[i]` otherwise
+/// - `v[a..b]` becomes `S__slice(v, a, b, "v[a..b] in f", __FILE__, __LINE__)`, which is always checked. Arrays of a
+///   known length are converted to a slice first.
+///
+/// The AST doesn't know where anything was written, so `__FILE__` and `__LINE__` point at the generated
+/// C, and the checks also take the expression as it was written and the function it's in.
+/// - `for (T x in s)` loops over the slice's elements, through a hidden variable unless `s` is one
+///
+/// Fixed-size arrays are converted to slices implicitly when they are passed to a slice parameter
+/// or used to initialize or assign a slice variable.
+pub struct SliceLowering {
+    typer: ExpressionTyper,
+    /// Parameter types of every function, keyed by mangled name
+    functions: HashMap<String, Vec<Type>>,
+    bounds_checks: bool,
+    /// The number of slices iterated over in the current function, for naming their hidden variables
+    slice_count: usize,
+    /// The current function as it was written, e.g. `m::f`, for the messages of failed checks
+    function: String,
+}
+
+impl SliceLowering {
+    pub fn new(program: &Program, bounds_checks: bool) -> Self {
+        let mut functions = HashMap::new();
+        Self::collect_functions(&program.statements, &String::new(), &mut functions);
+        SliceLowering {
+            typer: ExpressionTyper::new(program),
+            functions,
+            bounds_checks,
+            slice_count: 0,
+            function: String::new(),
+        }
+    }
+
+    fn collect_functions(toplevels: &Vec<TopLevel>, name_prefix: &String, functions: &mut HashMap<String, Vec<Type>>) {
+        for t in toplevels {
+            match t {
+                TopLevel::Function(Function { header, .. }) | TopLevel::FunctionHeader(header) => {
+                    let params = header.params.iter().map(|p| p.typ.clone()).collect();
+                    functions.insert(format!("{}{}", name_prefix, header.name), params);
+                },
+                TopLevel::Module { name, body } => Self::collect_functions(body, &format!("mod__{}__", name), functions),
+                _ => (),
+            }
+        }
+    }
+
+    pub fn lower_program(mut self, program: Program) -> Result<Program> {
+        Ok(Program {
+            statements: self.lower_toplevels(program.statements, None)?,
+        })
+    }
+
+    fn lower_toplevels(&mut self, toplevels: Vec<TopLevel>, module: Option<&String>) -> Result<Vec<TopLevel>> {
+        let mut result = Vec::new();
+        for t in toplevels {
+            result.push(match t {
+                TopLevel::Function(func) => {
+                    self.typer.push_scope();
+                    self.typer.declare_params(&func.header.params);
+                    self.slice_count = 0;
+                    self.function = match module {
+                        Some(module) => format!("{}::{}", module, func.header.name),
+                        None => func.header.name.clone(),
+                    };
+                    let body = self.lower_body(func.body);
+                    self.typer.pop_scope();
+                    TopLevel::Function(Function { header: func.header, body: body? })
+                },
+                TopLevel::VarDeclaration { typ, name, array_size, right } => {
+                    let right = right.map(|r| self.lower_value(r, &typ)).transpose()?;
+                    TopLevel::VarDeclaration { typ, name, array_size, right }
+                },
+                TopLevel::Module { name, body } => TopLevel::Module { body: self.lower_toplevels(body, Some(&name))?, name },
+                other => other,
+            });
+        }
+        Ok(result)
+    }
+
+    /// The slice type of an expression, with typedefs resolved, or `None` if it isn't a slice
+    fn slice_type(&self, expression: &Expression) -> Option<Type> {
+        let typ = self.typer.type_of(expression).ok()?;
+        self.typer.slice_element(&typ).map(Type::slice)
+    }
+
+    /// Lowers a value that is converted to `target`, which turns arrays into slices. Elements can
+    /// gain qualifiers but not lose them. Since each slice type is a struct of its own, a slice
+    /// only converts to another one of `const T` or `volatile T` from one of plain `T`, through
+    /// the helper `from` of the target.
+    fn lower_value(&mut self, value: Expression, target: &Type) -> Result<Expression> {
+        let Some(element) = self.typer.slice_element(target) else {
+            return self.lower_expression(value);
+        };
+        let slice_type = Type::slice(element.clone());
+        if let Some(value_type) = self.slice_type(&value) {
+            if value_type.name.generate() == slice_type.name.generate() {
+                return self.lower_expression(value);
+            }
+            if value_type.name.generate() == Type::slice(element.unqualified()).name.generate() {
+                let from = plain(&format!("{}__from", slice_type.name.generate()));
+                return Ok(call(from, vec![self.lower_expression(value)?]));
+            }
+            let value_element = self.typer.slice_element(&value_type).unwrap();
+            bail!("Cannot convert '{}' to '{}', since its elements have type '{}'", value.generate(), slice_type.describe(), value_element.describe());
+        }
+        let Ok(Some((array_element, length))) = self.typer.array_of(&value) else {
+            return self.lower_expression(value);
+        };
+        if !same_or_more_qualified(&element, &array_element) {
+            bail!("Cannot convert '{}' to '{}', since its elements have type '{}'", value.generate(), slice_type.describe(), array_element.describe());
+        }
+        let Some(length) = length else {
+            bail!("Cannot convert '{}' to '{}', since its length isn't known", value.generate(), slice_type.describe());
+        };
+        Ok(Self::from_array(slice_type, self.lower_expression(value)?, length))
+    }
+
+    /// `(S){array, length}`
+    fn from_array(slice_type: Type, array: Expression, length: usize) -> Expression {
+        cast(slice_type, init_list(vec![array, int(length as i32)]))
+    }
+
+    /// `S__name(slice, args..., "written in f", __FILE__, __LINE__)`, a helper from `TypeDefinitions`
+    fn helper(&self, slice_type: &Type, name: &str, slice: Expression, mut args: Vec<Expression>, written: &Expression) -> Expression {
+        args.insert(0, slice);
+        let source = format!("{} in {}", written.generate(), self.function);
+        args.push(string(&source.replace('\\', "\\\\").replace('"', "\\\"")));
+        args.push(ident("__FILE__"));
+        args.push(ident("__LINE__"));
+        call(plain(&format!("{}__{}", slice_type.name.generate(), name)), args)
+    }
+
+    fn lower_atom(&mut self, atom: Atom) -> Result<Atom> {
+        match atom {
+            Atom::Lambda(lambda) => {
+                let Lambda { captures, params, is_variadic, return_type, body } = *lambda;
+                self.typer.push_scope();
+                self.typer.declare_params(&params);
+                let body = self.lower_body(body);
+                self.typer.pop_scope();
+                Ok(Atom::Lambda(Box::new(Lambda { captures, params, is_variadic, return_type, body: body? })))
+            },
+            Atom::TypeCast { typ, value } => {
+                // `(S) array` is already the compound literal `(S){array, length}`, and `(S) slice` the
+                // slice itself or a call to `S__from`, since C can't cast to a struct
+                let is_conversion = self.typer.slice_element(&typ).is_some()
+                    && (matches!(self.typer.array_of(&value), Ok(Some(_))) || self.slice_type(&value).is_some());
+                let value = self.lower_value(*value, &typ)?;
+                if is_conversion {
+                    return Ok(match value {
+                        Expression { atom, tail: ExprTail::None } => atom,
+                        call => Atom::Wrapped(Box::new(call)),
+                    });
+                }
+                Ok(Atom::TypeCast { typ, value: Box::new(value) })
+            },
+            Atom::VaArg { list, typ } => Ok(Atom::VaArg { list: Box::new(self.lower_expression(*list)?), typ }),
+            Atom::UnaryOperation { op, value } => Ok(Atom::UnaryOperation { op, value: Box::new(self.lower_expression(*value)?) }),
+            Atom::Wrapped(value) => Ok(Atom::Wrapped(Box::new(self.lower_expression(*value)?))),
+            Atom::InitList(values) => Ok(Atom::InitList(values.into_iter().map(|v| self.lower_expression(v)).collect::<Result<_, _>>()?)),
+            Atom::Assign { target, value } => Ok(Atom::Assign { target, value: Box::new(self.lower_expression(*value)?) }),
+            other => Ok(other),
+        }
+    }
+}
+
+impl Scoped for SliceLowering {
+    fn push_scope(&mut self) {
+        self.typer.push_scope();
+    }
+
+    fn pop_scope(&mut self) {
+        self.typer.pop_scope();
+    }
+
+    fn declare(&mut self, name: &str, typ: &Type, array_size: Option<usize>) {
+        self.typer.declare(name, typ, array_size);
+    }
+}
+
+impl ScopedLowering for SliceLowering {
+    fn lower_expression(&mut self, expression: Expression) -> Result<Expression> {
+        let atom = self.lower_atom(expression.atom.clone())?;
+        // `original` is the expression so far as it was written, which is what the typer understands,
+        // and `lowered` is what it becomes
+        let mut original = Expression::new(expression.atom, ExprTail::None);
+        let mut lowered = Expression::new(atom, ExprTail::None);
+        let mut tail = expression.tail;
+        loop {
+            let (current, next) = match tail {
+                ExprTail::None => return Ok(lowered),
+                ExprTail::Call { body, named, next } => {
+                    let params = match (&original.atom, &original.tail) {
+                        (Atom::Identifier(name), ExprTail::None) if self.functions.contains_key(&name.generate()) => {
+                            self.functions[&name.generate()].clone()
+                        },
+                        _ => match self.typer.type_of(&original).ok().map(|t| t.typetype) {
+                            Some(TypeType::Closure(signature) | TypeType::FunctionPointer(signature)) => signature.params,
+                            _ => Vec::new(),
+                        },
+                    };
+                    let mut args = Vec::new();
+                    for (i, arg) in body.into_iter().enumerate() {
+                        args.push(match params.get(i) {
+                            Some(param) => self.lower_value(arg, param)?,
+                            None => self.lower_expression(arg)?,
+                        });
+                    }
+                    (ExprTail::Call { body: args, named, next: Box::new(ExprTail::None) }, *next)
+                },
+                ExprTail::Index { inner, next } => {
+                    let written = ExprTail::Index { inner: inner.clone(), next: Box::new(ExprTail::None) };
+                    let inner = self.lower_expression(*inner)?;
+                    if let Some(slice_type) = self.slice_type(&original) {
+                        let written = Expression::new(original.atom.clone(), original.tail.clone().append(written));
+                        let current = ExprTail::Index { inner: Box::new(inner.clone()), next: Box::new(ExprTail::None) };
+                        original = Expression::new(original.atom, original.tail.append(current));
+                        lowered = if self.bounds_checks {
+                            wrapped(unary(UnaryOp::Dereference, self.helper(&slice_type, "at", lowered, vec![inner], &written)))
+                        } else {
+                            let ptr = ExprTail::MemberAccess { member: String::from("ptr"), next: Box::new(ExprTail::None) };
+                            let index = ExprTail::Index { inner: Box::new(inner), next: Box::new(ExprTail::None) };
+                            Expression::new(lowered.atom, lowered.tail.append(ptr).append(index))
+                        };
+                        tail = *next;
+                        continue;
+                    }
+                    (ExprTail::Index { inner: Box::new(inner), next: Box::new(ExprTail::None) }, *next)
+                },
+                ExprTail::Slice { start, end, next } => {
+                    let written = ExprTail::Slice { start: start.clone(), end: end.clone(), next: Box::new(ExprTail::None) };
+                    let written = Expression::new(original.atom.clone(), original.tail.clone().append(written));
+                    let (start, end) = (self.lower_expression(*start)?, self.lower_expression(*end)?);
+                    let base = match self.typer.array_of(&original)? {
+                        Some((element, Some(length))) => {
+                            let slice_type = Type::slice(element);
+                            (Self::from_array(slice_type.clone(), lowered, length), slice_type)
+                        },
+                        Some((_, None)) => bail!("Cannot slice '{}', since its length isn't known", original.generate()),
+                        None => match self.slice_type(&original) {
+                            Some(slice_type) => (lowered, slice_type),
+                            None => {
+                                let typ = self.typer.type_of(&original)?;
+                                bail!("'{}' is not an array or slice, so it can't be sliced", typ.describe());
+                            },
+                        },
+                    };
+                    let current = ExprTail::Slice { start: Box::new(start.clone()), end: Box::new(end.clone()), next: Box::new(ExprTail::None) };
+                    original = Expression::new(original.atom, original.tail.append(current));
+                    lowered = self.helper(&base.1, "slice", base.0, vec![start, end], &written);
+                    tail = *next;
+                    continue;
+                },
+                ExprTail::BinaryOp { op, right, next } => {
+                    (ExprTail::BinaryOp { op, right: Box::new(self.lower_expression(*right)?), next: Box::new(ExprTail::None) }, *next)
+                },
+                ExprTail::TernaryConditional { second, third, next } => {
+                    let current = ExprTail::TernaryConditional {
+                        second: Box::new(self.lower_expression(*second)?),
+                        third: Box::new(self.lower_expression(*third)?),
+                        next: Box::new(ExprTail::None),
+                    };
+                    (current, *next)
+                },
+                ExprTail::MemberAccess { member, next } => (ExprTail::MemberAccess { member, next: Box::new(ExprTail::None) }, *next),
+                ExprTail::PointerAccess { member, next } => (ExprTail::PointerAccess { member, next: Box::new(ExprTail::None) }, *next),
+                ExprTail::SafeAccess { .. } | ExprTail::Coalesce { .. } => unreachable!("'?->' and '??' are lowered before slices"),
+            };
+            original = Expression::new(original.atom, original.tail.append(current.clone()));
+            lowered = Expression::new(lowered.atom, lowered.tail.append(current));
+            tail = next;
+        }
+    }
+
+    fn lower_statements(&mut self, body: Vec<Statement>) -> Result<Vec<Statement>> {
+        let mut result = Vec::new();
+        for statement in body {
+            match statement {
+                // A slice that isn't a variable is stored in one first, so that it is only evaluated once
+                Statement::ForIn { typ, name, iterable: ForIterable::Array { array, length, by_reference }, body } => {
+                    let iterable = match self.slice_type(&array) {
+                        Some(slice_type) if !matches!((&array.atom, &array.tail), (Atom::Identifier(_), ExprTail::None)) => {
+                            let hidden = format!("__mc_slice{}", self.slice_count);
+                            self.slice_count += 1;
+                            result.push(var_dec(slice_type.clone(), &hidden, Some(self.lower_expression(array)?)));
+                            self.typer.declare(&hidden, &slice_type, None);
+                            ForIterable::Array { array: ident(&hidden), length, by_reference }
+                        },
+                        _ => ForIterable::Array { array, length, by_reference },
+                    };
+                    result.push(self.lower_statement(Statement::ForIn { typ, name, iterable, body })?);
+                },
+                other => result.push(self.lower_statement(other)?),
+            }
+        }
+        Ok(result)
+    }
+
+    fn lower_statement(&mut self, statement: Statement) -> Result<Statement> {
+        match statement {
+            Statement::VarDec { typ, name, array_size, right } => {
+                let right = right.map(|r| self.lower_value(r, &typ)).transpose()?;
+                self.typer.declare(&name, &typ, array_size);
+                Ok(Statement::VarDec { typ, name, array_size, right })
+            },
+            Statement::VarAssign { identifier, right } => {
+                let target = self.typer.type_of(&match &identifier {
+                    IdentifierExpression::Standard(name) => ident(name),
+                    IdentifierExpression::Pointer(expression) => expression.clone(),
+                }).ok();
+                let right = match target {
+                    Some(target) => self.lower_value(right, &target)?,
+                    None => self.lower_expression(right)?,
+                };
+                Ok(Statement::VarAssign { identifier: self.lower_identifier_expression(identifier)?, right })
+            },
+            other => self.walk_statement(other),
+        }
+    }
+
+    fn lower_iterable(&mut self, typ: &Type, name: &String, iterable: ForIterable) -> Result<ForIterable> {
+        match iterable {
+            ForIterable::Array { array, length, by_reference } => match self.slice_type(&array) {
+                Some(slice_type) => {
+                    let element = self.typer.slice_element(&slice_type).unwrap();
+                    let by_reference = element_binding(typ, name, &element, &array)?;
+                    Ok(ForIterable::Slice { slice: self.lower_expression(array)?, by_reference })
+                },
+                None => Ok(ForIterable::Array { array: self.lower_expression(array)?, length, by_reference }),
+            },
+            other => self.walk_iterable(other),
+        }
+    }
+}
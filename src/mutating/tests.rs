@@ -0,0 +1,139 @@
+use crate::parsing::ast::{expression::{Atom, BinOp, ExprTail, Expression}, function::{Function, FunctionHeader, Parameter}, identifier::Identifier, program::Program, statement::Statement, test::TestBlock, toplevel::TopLevel, types::{Qualifier, StorageClass, Type}};
+use anyhow::{bail, Result};
+
+use super::{builder::{binary, call, ident, index, init_list, int, mc, plain, string, ternary}, mutator::Mutator};
+
+/// Lowers `test "name" { ... }` blocks. Normally they're removed, so that tests cost nothing in
+/// the program. In test mode each becomes a function `__mc_test0`, `__mc_test1`, ..., in the
+/// module the test was written in, and the program's `main` is replaced with one that runs every
+/// test through `mc::run_tests`. Passing a name to the test program only runs the tests whose
+/// names contain it.
+///
+/// A failing `assert` in a test only fails that test, since it's replaced with `mc::test_assert`,
+/// which jumps back to the runner with `longjmp`.
+pub struct TestLowering {
+    enabled: bool,
+    /// The names of the tests that were lowered, prefixed with their module, and their functions
+    tests: Vec<(String, Identifier)>,
+}
+
+impl Default for TestLowering {
+    fn default() -> Self {
+        Self::new(false)
+    }
+}
+
+impl TestLowering {
+    pub fn new(enabled: bool) -> Self {
+        TestLowering { enabled, tests: Vec::new() }
+    }
+
+    pub fn lower_program(mut self, program: Program) -> Result<Program> {
+        let mut statements = self.lower_toplevels(program.statements, None)?;
+        if self.enabled {
+            statements.retain(|t| !Self::is_main(t));
+            statements.insert(0, TopLevel::Import { name: String::from("mc/test.h"), is_lib: false });
+            statements.extend(self.runner());
+        }
+        Ok(Program {
+            statements,
+        })
+    }
+
+    fn lower_toplevels(&mut self, toplevels: Vec<TopLevel>, module: Option<&String>) -> Result<Vec<TopLevel>> {
+        let mut result = Vec::new();
+        for t in toplevels {
+            match t {
+                TopLevel::Test(test) => {
+                    if self.enabled {
+                        result.push(TopLevel::Function(self.lower_test(test, module)?));
+                    }
+                },
+                TopLevel::Module { name, body } => {
+                    let body = self.lower_toplevels(body, Some(&name))?;
+                    result.push(TopLevel::Module { name, body });
+                },
+                other => result.push(other),
+            }
+        }
+        Ok(result)
+    }
+
+    /// `static void __mc_test0(void) { ... }`, with the asserts in it failing only this test
+    fn lower_test(&mut self, test: TestBlock, module: Option<&String>) -> Result<Function> {
+        let name = match module {
+            Some(module) => format!("{}::{}", module, test.name),
+            None => test.name,
+        };
+        if self.tests.iter().any(|(other, _)| *other == name) {
+            bail!("There are two tests named \"{}\"", name);
+        }
+        let function_name = format!("__mc_test{}", self.tests.len());
+        let function = match module {
+            Some(module) => Identifier::Module(module.clone(), function_name.clone()),
+            None => plain(&function_name),
+        };
+        self.tests.push((name, function));
+
+        let mut asserts = Mutator::new();
+        asserts.add_expression_closure(Box::new(|e| match e {
+            Expression { atom: Atom::Identifier(Identifier::Plain(name)), tail: tail @ ExprTail::Call { .. } } if name == "assert" => {
+                Ok(Expression::new(Atom::Identifier(mc("test_assert")), tail))
+            },
+            other => Ok(other),
+        }));
+        Ok(Function {
+            header: FunctionHeader {
+                return_type: Type { storage: vec![StorageClass::Static], ..Type::simple(plain("void"), 0) },
+                name: function_name,
+                params: Vec::new(),
+                is_variadic: false,
+                attributes: Vec::new(),
+            },
+            body: asserts.mutate_body(test.body)?,
+        })
+    }
+
+    fn is_main(toplevel: &TopLevel) -> bool {
+        match toplevel {
+            TopLevel::Function(f) => f.header.name == "main",
+            TopLevel::FunctionHeader(h) => h.name == "main",
+            _ => false,
+        }
+    }
+
+    /// The table of tests and the `main` running them with the filter from the command line
+    fn runner(&self) -> Vec<TopLevel> {
+        let mut toplevels = Vec::new();
+        let tests = if self.tests.is_empty() {
+            ident("NULL")
+        } else {
+            let entries = self.tests.iter()
+                .map(|(name, function)| init_list(vec![string(name), Expression::identifier(function.clone())]))
+                .collect();
+            toplevels.push(TopLevel::VarDeclaration {
+                typ: Type { storage: vec![StorageClass::Static], qualifiers: vec![Qualifier::Const], ..Type::simple(mc("Test"), 0) },
+                name: String::from("__mc_tests"),
+                array_size: Some(self.tests.len()),
+                right: Some(init_list(entries)),
+            });
+            ident("__mc_tests")
+        };
+        let filter = ternary(binary(ident("argc"), BinOp::IsGT, int(1)), index(ident("argv"), int(1)), ident("NULL"));
+        let count = int(self.tests.len() as i32);
+        toplevels.push(TopLevel::Function(Function {
+            header: FunctionHeader {
+                return_type: Type::simple(plain("int"), 0),
+                name: String::from("main"),
+                params: vec![
+                    Parameter { name: String::from("argc"), typ: Type::simple(plain("int"), 0), default: None },
+                    Parameter { name: String::from("argv"), typ: Type::simple(plain("char"), 2), default: None },
+                ],
+                is_variadic: false,
+                attributes: Vec::new(),
+            },
+            body: vec![Statement::Return(Some(call(mc("run_tests"), vec![tests, count, filter])))],
+        }));
+        toplevels
+    }
+}
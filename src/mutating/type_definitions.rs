@@ -0,0 +1,252 @@
+use std::{cell::RefCell, collections::HashSet, rc::Rc};
+
+use crate::{codegen::simple::SimpleCodeGen, parsing::ast::{directive::Directive, expression::{Atom, BinOp}, function::{Function, FunctionHeader, Parameter}, program::Program, sstruct::{Struct, StructField, StructMember}, statement::Statement, toplevel::TopLevel, typedef::{TypeDef, TypeDefInner}, types::{Qualifier, StorageClass, Type, TypeType}}};
+use anyhow::Result;
+
+use super::{builder::{binary, cast, call, ident, init_list, mc, member, plain}, mutator::Mutator};
+
+/// Defines the struct of each tuple and slice type, and the `typedef` of each function pointer
+/// type, before its first use.
+///
+/// A tuple's struct is named after its element types (see `Type::tuple`) and has a field `_0`,
+/// `_1`, ... for each of them. A slice's struct has the pointer `ptr` and length `len`, and comes
+/// with the helpers `at`, which checks an index and points at that element, and `slice`, which
+/// checks a range and returns that part of the slice. A slice of qualified elements like
+/// `const int[]` also gets `from`, which converts the slice of unqualified ones to it.
+///
+/// The same type can be used in several generated files that are included together, so each
+/// definition is guarded with `#ifndef`.
+pub struct TypeDefinitions {
+    defined: HashSet<String>,
+    uses_slices: bool,
+    /// Types found in function bodies by `collector`
+    found: Rc<RefCell<Vec<Type>>>,
+    collector: Mutator,
+}
+
+impl Default for TypeDefinitions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TypeDefinitions {
+    pub fn new() -> Self {
+        let found = Rc::new(RefCell::new(Vec::new()));
+        let mut collector = Mutator::new();
+        let found_in_statements = found.clone();
+        collector.add_statement_closure(Box::new(move |s| {
+            match &s {
+                Statement::VarDec { typ, .. } | Statement::ForIn { typ, .. } => found_in_statements.borrow_mut().push(typ.clone()),
+                Statement::EnumFor { typ, enum_type, .. } => found_in_statements.borrow_mut().extend([typ.clone(), enum_type.clone()]),
+                _ => (),
+            }
+            Ok(s)
+        }));
+        let found_in_expressions = found.clone();
+        collector.add_expression_closure(Box::new(move |e| {
+            match &e.atom {
+                Atom::TypeCast { typ, .. } | Atom::SizeOf(typ) | Atom::VaArg { typ, .. } => found_in_expressions.borrow_mut().push(typ.clone()),
+                _ => (),
+            }
+            Ok(e)
+        }));
+        TypeDefinitions { defined: HashSet::new(), uses_slices: false, found, collector }
+    }
+
+    pub fn define_program(mut self, program: Program) -> Result<Program> {
+        let mut statements = Vec::new();
+        for t in program.statements {
+            match t {
+                TopLevel::Module { name, body } => {
+                    // Definitions inside a module would get its prefix, so the module is split around them
+                    let mut part = Vec::new();
+                    for member in body {
+                        let definitions = self.definitions(&member)?;
+                        if !definitions.is_empty() && !part.is_empty() {
+                            statements.push(TopLevel::Module { name: name.clone(), body: std::mem::take(&mut part) });
+                        }
+                        statements.extend(definitions);
+                        part.push(member);
+                    }
+                    if !part.is_empty() {
+                        statements.push(TopLevel::Module { name, body: part });
+                    }
+                },
+                other => {
+                    statements.extend(self.definitions(&other)?);
+                    statements.push(other);
+                },
+            }
+        }
+        if self.uses_slices {
+            statements.insert(0, TopLevel::Import { name: String::from("mc/slice.h"), is_lib: false });
+        }
+        Ok(Program {
+            statements,
+        })
+    }
+
+    /// The definitions of the tuples that `toplevel` uses and that aren't defined yet
+    fn definitions(&mut self, toplevel: &TopLevel) -> Result<Vec<TopLevel>> {
+        let mut types = Vec::new();
+        match toplevel {
+            TopLevel::Function(Function { header, body }) => {
+                Self::header_types(header, &mut types);
+                self.collector.mutate_body(body.clone())?;
+            },
+            TopLevel::FunctionHeader(header) => Self::header_types(header, &mut types),
+            TopLevel::VarDeclaration { typ, right, .. } => {
+                types.push(typ.clone());
+                if let Some(right) = right {
+                    self.collector.mutate_expression(right.clone())?;
+                }
+            },
+            TopLevel::Struct(s) => Self::struct_types(s, &mut types),
+            TopLevel::TypeDef(typedef) => match &typedef.typ {
+                TypeDefInner::Type(typ) => types.push(typ.clone()),
+                TypeDefInner::Struct(s) => Self::struct_types(s, &mut types),
+                TypeDefInner::Enum(_) | TypeDefInner::FunctionPointer(_) => (),
+            },
+            _ => (),
+        }
+        types.append(&mut self.found.borrow_mut());
+        let mut definitions = Vec::new();
+        for typ in &types {
+            self.define(typ, &mut definitions);
+        }
+        Ok(definitions)
+    }
+
+    fn header_types(header: &FunctionHeader, types: &mut Vec<Type>) {
+        types.push(header.return_type.clone());
+        types.extend(header.params.iter().map(|p| p.typ.clone()));
+    }
+
+    fn struct_types(s: &Struct, types: &mut Vec<Type>) {
+        for member in &s.members {
+            match member {
+                StructMember::Field(field) => types.push(field.typ.clone()),
+                StructMember::AnonStruct(inner) => Self::struct_types(inner, types),
+                StructMember::Directive(_) => (),
+            }
+        }
+    }
+
+    /// Defines the tuples and slices in `typ`, elements first
+    fn define(&mut self, typ: &Type, definitions: &mut Vec<TopLevel>) {
+        match &typ.typetype {
+            TypeType::Closure(signature) => {
+                signature.params.iter().for_each(|p| self.define(p, definitions));
+                self.define(&signature.return_type, definitions);
+            },
+            TypeType::FunctionPointer(signature) => {
+                let name = typ.name.generate();
+                if !self.defined.insert(name.clone()) {
+                    return;
+                }
+                signature.params.iter().for_each(|p| self.define(p, definitions));
+                self.define(&signature.return_type, definitions);
+                let typedef = TopLevel::TypeDef(TypeDef { name: name.clone(), typ: TypeDefInner::FunctionPointer((**signature).clone()) });
+                Self::guarded(&name, vec![typedef], definitions);
+            },
+            TypeType::Tuple(elements) => {
+                let name = typ.name.generate();
+                if !self.defined.insert(name.clone()) {
+                    return;
+                }
+                elements.iter().for_each(|e| self.define(e, definitions));
+                let fields = elements.iter().enumerate().map(|(i, e)| {
+                    StructMember::Field(StructField { typ: e.unqualified(), name: format!("_{}", i), array_size: None, attributes: Vec::new() })
+                }).collect();
+                let tuple = TopLevel::Struct(Struct { name: name.clone(), members: fields, is_anonymous: false, is_union: false, derives: Vec::new(), attributes: Vec::new() });
+                Self::guarded(&name, vec![tuple], definitions);
+            },
+            TypeType::Slice(element) => {
+                let name = typ.name.generate();
+                if !self.defined.insert(name.clone()) {
+                    return;
+                }
+                self.uses_slices = true;
+                self.define(element, definitions);
+                let mut pointer = element.without_storage();
+                pointer.pointers.push(Vec::new());
+                let fields = vec![
+                    StructMember::Field(StructField { typ: pointer.clone(), name: String::from("ptr"), array_size: None, attributes: Vec::new() }),
+                    StructMember::Field(StructField { typ: size_t(), name: String::from("len"), array_size: None, attributes: Vec::new() }),
+                ];
+                let slice = TopLevel::Struct(Struct { name: name.clone(), members: fields, is_anonymous: false, is_union: false, derives: Vec::new(), attributes: Vec::new() });
+                let slice_type = Type::simple(plain(&name), 0);
+                let (ptr, len) = (member("s", &String::from("ptr")), member("s", &String::from("len")));
+                let location = vec![ident("source"), ident("file"), ident("line")];
+
+                // `T* S__at(S s, size_t index, ...)`: `return s.ptr + mc::slice_check_index(index, s.len, source, file, line);`
+                let mut check = vec![ident("index"), len.clone()];
+                check.extend(location.clone());
+                let at = Self::helper(pointer, format!("{}__at", name), vec![param(&slice_type, "s"), param(&size_t(), "index")], vec![
+                    Statement::Return(Some(binary(ptr.clone(), BinOp::Add, call(mc("slice_check_index"), check)))),
+                ]);
+
+                // `S S__slice(S s, size_t start, size_t end, ...)`: checks the range, then `return (S){s.ptr + start, end - start};`
+                let mut check = vec![ident("start"), ident("end"), len];
+                check.extend(location);
+                let part = init_list(vec![binary(ptr, BinOp::Add, ident("start")), binary(ident("end"), BinOp::Sub, ident("start"))]);
+                let params = vec![param(&slice_type, "s"), param(&size_t(), "start"), param(&size_t(), "end")];
+                let sub = Self::helper(slice_type.clone(), format!("{}__slice", name), params, vec![
+                    Statement::Expression(call(mc("slice_check_range"), check)),
+                    Statement::Return(Some(cast(slice_type.clone(), part))),
+                ]);
+                let mut toplevels = vec![slice, at, sub];
+
+                // `S S__from(U s)`: `return (S){s.ptr, s.len};`, for a slice of `const T` or `volatile T`
+                // to be made from a slice `U` of plain `T`
+                let source = Type::slice(element.unqualified());
+                if source.name.generate() != name {
+                    self.define(&source, definitions);
+                    let source_type = Type::simple(plain(&source.name.generate()), 0);
+                    let (ptr, len) = (member("s", &String::from("ptr")), member("s", &String::from("len")));
+                    toplevels.push(Self::inline(slice_type.clone(), format!("{}__from", name), vec![param(&source_type, "s")], vec![
+                        Statement::Return(Some(cast(slice_type, init_list(vec![ptr, len])))),
+                    ]));
+                }
+                Self::guarded(&name, toplevels, definitions);
+            },
+            _ => (),
+        }
+    }
+
+    /// A `static inline` helper, which also takes the expression it was written as and the file and
+    /// line it is used at for error messages
+    fn helper(return_type: Type, name: String, mut params: Vec<Parameter>, body: Vec<Statement>) -> TopLevel {
+        let text = Type { qualifiers: vec![Qualifier::Const], ..Type::simple(plain("char"), 1) };
+        params.push(param(&text, "source"));
+        params.push(param(&text, "file"));
+        params.push(param(&Type::simple(plain("int"), 0), "line"));
+        Self::inline(return_type, name, params, body)
+    }
+
+    fn inline(mut return_type: Type, name: String, params: Vec<Parameter>, body: Vec<Statement>) -> TopLevel {
+        return_type.storage = vec![StorageClass::Static, StorageClass::Inline];
+        TopLevel::Function(Function {
+            header: FunctionHeader { return_type, name, params, is_variadic: false, attributes: Vec::new() },
+            body,
+        })
+    }
+
+    fn guarded(name: &String, toplevels: Vec<TopLevel>, definitions: &mut Vec<TopLevel>) {
+        let guard = format!("{}__defined", name);
+        definitions.push(TopLevel::Directive(Directive::IfNDef(guard.clone())));
+        definitions.push(TopLevel::Directive(Directive::define(&guard, "")));
+        definitions.extend(toplevels);
+        definitions.push(TopLevel::Directive(Directive::EndIf));
+    }
+}
+
+fn size_t() -> Type {
+    Type::simple(plain("size_t"), 0)
+}
+
+fn param(typ: &Type, name: &str) -> Parameter {
+    Parameter { name: String::from(name), typ: typ.clone(), default: None }
+}
+
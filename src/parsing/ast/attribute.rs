@@ -0,0 +1,64 @@
+use crate::codegen::simple::SimpleCodeGen;
+
+/// An `@attribute` on a function, struct or field. Each is generated as a macro from
+/// `mc/attributes.h`, which expands to the matching `__attribute__((...))` on GCC and Clang.
+#[derive(Clone, PartialEq)]
+pub enum Attribute {
+    /// `@inline`, forcing a function to be inlined
+    Inline,
+    /// `@noreturn`, for functions that never return to their caller
+    NoReturn,
+    /// `@packed`, removing the padding between fields
+    Packed,
+    /// `@aligned(n)`, raising the alignment of a struct or field to `n` bytes
+    Aligned(usize),
+    /// `@weak`, a function definition that another object file can replace
+    Weak,
+    /// `@section("name")`, placing a function in a specific section of the object file
+    Section(String),
+}
+
+impl Attribute {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Attribute::Inline => "inline",
+            Attribute::NoReturn => "noreturn",
+            Attribute::Packed => "packed",
+            Attribute::Aligned(_) => "aligned",
+            Attribute::Weak => "weak",
+            Attribute::Section(_) => "section",
+        }
+    }
+
+    pub fn applies_to_functions(&self) -> bool {
+        matches!(self, Attribute::Inline | Attribute::NoReturn | Attribute::Weak | Attribute::Section(_))
+    }
+
+    /// Whether the attribute can be used on structs, unions and their fields
+    pub fn applies_to_structs(&self) -> bool {
+        matches!(self, Attribute::Packed | Attribute::Aligned(_))
+    }
+}
+
+impl SimpleCodeGen for Attribute {
+    fn generate(&self) -> String {
+        match self {
+            Attribute::Inline => String::from("MC_INLINE"),
+            Attribute::NoReturn => String::from("MC_NORETURN"),
+            Attribute::Packed => String::from("MC_PACKED"),
+            Attribute::Aligned(n) => format!("MC_ALIGNED({})", n),
+            Attribute::Weak => String::from("MC_WEAK"),
+            Attribute::Section(name) => format!("MC_SECTION(\"{}\")", name),
+        }
+    }
+}
+
+/// Generates a list of attributes, each followed by a space
+pub fn generate_attributes(attributes: &Vec<Attribute>) -> String {
+    let mut s = String::new();
+    for attribute in attributes {
+        s.push_str(attribute.generate().as_str());
+        s.push(' ');
+    }
+    s
+}
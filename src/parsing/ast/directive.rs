@@ -0,0 +1,175 @@
+use crate::codegen::simple::ModuleMemberCodeGen;
+
+/// A preprocessor directive. Directives stay where they were written, between top-level items,
+/// statements or struct fields, and `#if` ... `#endif` blocks are checked to be balanced within
+/// the block that holds them.
+#[derive(Clone, PartialEq)]
+pub enum Directive {
+    /// `#define NAME value`, or `#define NAME(a, b) value` for a function-like macro
+    Define {
+        name: String,
+        params: Option<Vec<String>>,
+        value: String,
+    },
+    /// `#undef NAME`
+    Undef(String),
+    /// `#if condition`
+    If(String),
+    /// `#ifdef NAME`
+    IfDef(String),
+    /// `#ifndef NAME`
+    IfNDef(String),
+    /// `#elif condition`
+    Elif(String),
+    Else,
+    EndIf,
+    /// `#pragma text`
+    Pragma(String),
+    /// Any other directive, e.g. `#error`, which contains everything after the `#`
+    Other(String),
+}
+
+impl Directive {
+    /// `#define NAME value`
+    pub fn define(name: &str, value: &str) -> Self {
+        Directive::Define { name: String::from(name), params: None, value: String::from(value) }
+    }
+
+    /// Whether this directive starts a conditional block
+    pub fn opens_block(&self) -> bool {
+        matches!(self, Directive::If(_) | Directive::IfDef(_) | Directive::IfNDef(_))
+    }
+
+    /// Generates a directive in a module, where a name that the module defines refers to its
+    /// prefixed name, in `#ifdef`, `#ifndef` and `#undef` as well as in the text of `#define`,
+    /// `#if` and `#elif`
+    pub fn generate_in_module(&self, name_prefix: &String, defines: &Vec<&String>) -> String {
+        let own = |name: &str| defines.iter().any(|d| *d == name).then(|| format!("{}{}", name_prefix, name));
+        let own_or_same = |name: &String| own(name).unwrap_or_else(|| name.clone());
+        match self {
+            Directive::Define { name, params, value } => {
+                // A macro's parameters shadow the module's names
+                let is_param = |name: &str| params.iter().flatten().any(|p| p == name);
+                let value = mangle_names(value, &|name| if is_param(name) { None } else { own(name) });
+                generate_define(&format!("{}{}", name_prefix, name), params, &value)
+            },
+            Directive::Undef(name) => format!("#undef {}", own_or_same(name)),
+            Directive::IfDef(name) => format!("#ifdef {}", own_or_same(name)),
+            Directive::IfNDef(name) => format!("#ifndef {}", own_or_same(name)),
+            Directive::If(condition) => format!("#if {}", mangle_names(condition, &own)),
+            Directive::Elif(condition) => format!("#elif {}", mangle_names(condition, &own)),
+            other => other.generate(name_prefix),
+        }
+    }
+
+    /// Checks that every `#if`, `#ifdef` and `#ifndef` in `directives` is closed by an `#endif`,
+    /// and that `#elif` and `#else` only appear inside them
+    pub fn check_conditionals<'a>(directives: impl Iterator<Item = &'a Directive>) -> Result<(), String> {
+        // Whether each open block has had its `#else`
+        let mut open = Vec::<bool>::new();
+        for directive in directives {
+            match directive {
+                d if d.opens_block() => open.push(false),
+                Directive::Elif(_) | Directive::Else => {
+                    let name = if *directive == Directive::Else { "#else" } else { "#elif" };
+                    match open.last_mut() {
+                        None => return Result::Err(format!("'{}' without an '#if' before it in the same block", name)),
+                        Some(true) => return Result::Err(format!("'{}' after the '#else' of the same '#if'", name)),
+                        Some(has_else) => *has_else = *directive == Directive::Else,
+                    }
+                },
+                Directive::EndIf => {
+                    open.pop().ok_or_else(|| String::from("'#endif' without an '#if' before it in the same block"))?;
+                },
+                _ => (),
+            }
+        }
+        if !open.is_empty() {
+            return Result::Err(String::from("'#if' without an '#endif' after it in the same block"));
+        }
+        Result::Ok(())
+    }
+}
+
+impl ModuleMemberCodeGen for Directive {
+    fn generate(&self, name_prefix: &String) -> String {
+        match self {
+            // Like the module's other members, names defined in a module get its prefix
+            Directive::Define { name, params, value } => {
+                generate_define(&format!("{}{}", name_prefix, name), params, &mangle_names(value, &|_| None))
+            },
+            Directive::Undef(name) => format!("#undef {}", name),
+            Directive::If(condition) => format!("#if {}", mangle_names(condition, &|_| None)),
+            Directive::IfDef(name) => format!("#ifdef {}", name),
+            Directive::IfNDef(name) => format!("#ifndef {}", name),
+            Directive::Elif(condition) => format!("#elif {}", mangle_names(condition, &|_| None)),
+            Directive::Else => String::from("#else"),
+            Directive::EndIf => String::from("#endif"),
+            Directive::Pragma(text) => format!("#pragma {}", text),
+            Directive::Other(text) => format!("#{}", text),
+        }
+    }
+}
+
+fn generate_define(name: &str, params: &Option<Vec<String>>, value: &str) -> String {
+    let mut s = String::new();
+    s.push_str("#define ");
+    s.push_str(name);
+    if let Some(params) = params {
+        s.push('(');
+        s.push_str(params.join(", ").as_str());
+        s.push(')');
+    }
+    if !value.is_empty() {
+        s.push(' ');
+        s.push_str(value);
+    }
+    s
+}
+
+/// Rewrites the names in the text of a directive: `module::NAME` becomes `mod__module__NAME`,
+/// and other names become what `own` maps them to, if anything. Numbers and string and
+/// character literals are kept as they are.
+fn mangle_names(text: &str, own: &dyn Fn(&str) -> Option<String>) -> String {
+    let chars = text.chars().collect::<Vec<char>>();
+    let is_name_char = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    let mut s = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let start = i;
+        let c = chars[i];
+        if c == '"' || c == '\'' {
+            i += 1;
+            while i < chars.len() && chars[i] != c {
+                i += if chars[i] == '\\' { 2 } else { 1 };
+            }
+            i = (i + 1).min(chars.len());
+            s.extend(&chars[start..i]);
+        } else if c.is_ascii_digit() {
+            while i < chars.len() && (is_name_char(chars[i]) || chars[i] == '.') {
+                i += 1;
+            }
+            s.extend(&chars[start..i]);
+        } else if is_name_char(c) {
+            while i < chars.len() && is_name_char(chars[i]) {
+                i += 1;
+            }
+            let name = chars[start..i].iter().collect::<String>();
+            let member_start = i + 2;
+            if chars.get(i) == Some(&':') && chars.get(i + 1) == Some(&':') && chars.get(member_start).is_some_and(|c| is_name_char(*c) && !c.is_ascii_digit()) {
+                i = member_start;
+                while i < chars.len() && is_name_char(chars[i]) {
+                    i += 1;
+                }
+                let member = chars[member_start..i].iter().collect::<String>();
+                s.push_str(format!("mod__{}__{}", name, member).as_str());
+            } else {
+                s.push_str(own(&name).unwrap_or(name).as_str());
+            }
+        } else {
+            s.push(c);
+            i += 1;
+        }
+    }
+    s
+}
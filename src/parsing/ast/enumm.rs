@@ -1,9 +1,16 @@
 use crate::codegen::simple::{ModuleMemberCodeGen, PureCodeGen, SimpleCodeGen};
 
+use super::expression::Expression;
+
+/// The helpers generated for a named enum, referred to as `Color::COUNT` or `sl::Color::COUNT`
+pub const REFLECTION_MEMBERS: [&str; 5] = ["COUNT", "values", "names", "name", "is_valid"];
+
 pub struct Enum {
     pub name: String,
     pub entries: Vec<EnumEntry>,
     pub is_anonymous: bool,
+    /// Whether the reflection helpers are generated, which is only the case if something uses them
+    pub reflected: bool,
 }
 impl ModuleMemberCodeGen for Enum {
     fn generate(&self, name_prefix: &String) -> String {
@@ -28,7 +35,82 @@ impl ModuleMemberCodeGen for Enum {
         s.push_str("} ");
         s.push_str(name_prefix.as_str());
         s.push_str(self.name.as_str());
-        s.push_str(";");
+        s.push(';');
+        if self.reflected {
+            s.push('\n');
+            s.push_str(self.generate_reflection(name_prefix).as_str());
+        }
+        s
+    }
+}
+
+impl Enum {
+    /// Generates the reflection helpers for a named enum: the `__COUNT` constant,
+    /// the `__values` and `__names` arrays of every variant, the `__name` lookup
+    /// and the `__is_valid` check
+    fn generate_reflection(&self, name_prefix: &String) -> String {
+        let full_name = format!("{}{}", name_prefix, self.name);
+        let mut s = String::new();
+
+        s.push_str("enum { ");
+        s.push_str(full_name.as_str());
+        s.push_str("__COUNT = ");
+        s.push_str(self.entries.len().to_string().as_str());
+        s.push_str(" };\n");
+
+        s.push_str("static const ");
+        s.push_str(full_name.as_str());
+        s.push(' ');
+        s.push_str(full_name.as_str());
+        s.push_str("__values[");
+        s.push_str(full_name.as_str());
+        s.push_str("__COUNT] = { ");
+        s.push_str(self.entries.iter().map(|e| e.name.clone()).collect::<Vec<String>>().join(", ").as_str());
+        s.push_str(" };\n");
+
+        s.push_str("static const char* const ");
+        s.push_str(full_name.as_str());
+        s.push_str("__names[");
+        s.push_str(full_name.as_str());
+        s.push_str("__COUNT] = { ");
+        s.push_str(self.entries.iter().map(|e| format!("\"{}\"", e.name)).collect::<Vec<String>>().join(", ").as_str());
+        s.push_str(" };\n");
+
+        s.push_str("static inline const char* ");
+        s.push_str(full_name.as_str());
+        s.push_str("__name(");
+        s.push_str(full_name.as_str());
+        s.push_str(" value) {\n");
+        s.push_str("    for (int i = 0; i < ");
+        s.push_str(full_name.as_str());
+        s.push_str("__COUNT; i++) {\n");
+        s.push_str("        if (");
+        s.push_str(full_name.as_str());
+        s.push_str("__values[i] == value) {\n");
+        s.push_str("            return ");
+        s.push_str(full_name.as_str());
+        s.push_str("__names[i];\n");
+        s.push_str("        }\n");
+        s.push_str("    }\n");
+        s.push_str("    return 0;\n");
+        s.push_str("}\n");
+
+        // Loop over the variants rather than switching on them, since explicit values
+        // may be sparse or even repeat
+        s.push_str("static inline _Bool ");
+        s.push_str(full_name.as_str());
+        s.push_str("__is_valid(int value) {\n");
+        s.push_str("    for (int i = 0; i < ");
+        s.push_str(full_name.as_str());
+        s.push_str("__COUNT; i++) {\n");
+        s.push_str("        if ((int)");
+        s.push_str(full_name.as_str());
+        s.push_str("__values[i] == value) {\n");
+        s.push_str("            return 1;\n");
+        s.push_str("        }\n");
+        s.push_str("    }\n");
+        s.push_str("    return 0;\n");
+        s.push('}');
         s
     }
 }
@@ -57,15 +139,16 @@ impl PureCodeGen for Enum {
 
 pub struct EnumEntry {
     pub name: String,
-    pub value: Option<i32>,
+    /// A constant expression, which `ConstFolding` replaces with the literal it evaluates to
+    pub value: Option<Expression>,
 }
 impl SimpleCodeGen for EnumEntry {
     fn generate(&self) -> String {
         let mut s = String::new();
         s.push_str(self.name.as_str());
-        if let Some(val) = self.value {
-            let res = format!(" = {}", val);
-            s.push_str(res.as_str());
+        if let Some(val) = &self.value {
+            s.push_str(" = ");
+            s.push_str(val.generate().as_str());
         }
         s
     }
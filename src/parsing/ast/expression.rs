@@ -1,11 +1,14 @@
 use crate::codegen::simple::SimpleCodeGen;
 
-use super::{identifier::Identifier, types::Type};
+use super::{identifier::Identifier, lambda::Lambda, macros::MacroArgument, types::Type};
 
+#[derive(Clone)]
 pub enum Atom {
     Char(u8),
     Short(i16),
     Int(i32),
+    /// An `int` written in octal, like `0644`, which is generated in octal again
+    Octal(i32),
     TrueLong(i64),
     Float(f32),
     Double(f64),
@@ -21,8 +24,71 @@ pub enum Atom {
         value: Box<Expression>,
     },
     SizeOf(Type),
+    /// `va_arg(list, type)`, the next variadic argument of a function as `type`
+    VaArg {
+        list: Box<Expression>,
+        typ: Type,
+    },
     Wrapped(Box<Expression>),
+    InitList(Vec<Expression>),
+    Lambda(Box<Lambda>),
+    /// `$"text {value}"`, a string built at run time. Lowered to `snprintf` or `mc::string`
+    /// by `StringInterpolation`.
+    Interpolated(Vec<StringPart>),
+    /// `(target = value)`, which stores a value in a hidden temporary while evaluating an
+    /// expression. Only created by `SafeNavigation`.
+    Assign {
+        target: String,
+        value: Box<Expression>,
+    },
+    /// `embed("file")`, the contents of a file as the initializer of an array declared with `[]`.
+    /// The path is joined to the source file's directory by the parser, and the file is read by
+    /// `EmbedResolver`.
+    Embed(String),
+    /// An initializer list of bytes, written in hex. Only created by `EmbedResolver`.
+    Bytes(Vec<u8>),
+    /// `name!(args)`, replaced with the macro's body by `MacroExpander`
+    MacroCall {
+        name: String,
+        args: Vec<MacroArgument>,
+    },
 }
+/// The number of bytes on each line of an embedded file
+const BYTES_PER_LINE: usize = 12;
+/// The length above which a string literal is split, after each line break and otherwise at this width
+const STRING_PIECE_WIDTH: usize = 80;
+
+/// Splits the escaped contents of a long string literal into pieces that are written as adjacent
+/// literals. A piece never ends inside an escape, and a piece that is too long ends after a space
+/// when it has one.
+fn string_pieces(body: &str) -> Vec<&str> {
+    if body.len() <= STRING_PIECE_WIDTH {
+        return vec![body];
+    }
+    let mut pieces = Vec::new();
+    let mut start = 0;
+    let mut after_space = None;
+    let mut chars = body.char_indices().peekable();
+    while let Some((_, c)) = chars.next() {
+        let is_line_break = c == '\\' && chars.next().is_some_and(|(_, escaped)| escaped == 'n');
+        let end = chars.peek().map_or(body.len(), |(i, _)| *i);
+        if end == body.len() {
+            continue;
+        }
+        if c == ' ' {
+            after_space = Some(end);
+        }
+        if is_line_break || end - start >= STRING_PIECE_WIDTH {
+            let split = if is_line_break { end } else { after_space.unwrap_or(end) };
+            pieces.push(&body[start..split]);
+            start = split;
+            after_space = None;
+        }
+    }
+    pieces.push(&body[start..]);
+    pieces
+}
+
 impl SimpleCodeGen for Atom {
     fn generate(&self) -> String {
         match self {
@@ -40,6 +106,7 @@ impl SimpleCodeGen for Atom {
                 s
             },
             Atom::Int(v) => v.to_string(),
+            Atom::Octal(v) => format!("{}0{:o}", if *v < 0 { "-" } else { "" }, v.unsigned_abs()),
             Atom::TrueLong(v) => {
                 let mut s = String::new();
                 s.push_str(v.to_string().as_str());
@@ -48,17 +115,24 @@ impl SimpleCodeGen for Atom {
             },
             Atom::Float(v) => {
                 let mut s = String::new();
-                s.push_str(v.to_string().as_str());
+                // Debug formatting keeps the decimal point, so `2.0f` isn't printed as `2f`
+                s.push_str(format!("{:?}", v).as_str());
                 s.push_str("f");
                 s
             },
-            Atom::Double(v) => v.to_string(),
+            Atom::Double(v) => format!("{:?}", v),
             Atom::Boolean(v) => String::from(if *v { "1" } else { "0" }),
             Atom::String(v) => {
+                // Long strings are written as adjacent literals, each on a line of its own
                 let mut s = String::new();
-                s.push_str("\"");
-                s.push_str(v.as_str());
-                s.push_str("\"");
+                for (i, piece) in string_pieces(v).iter().enumerate() {
+                    if i > 0 {
+                        s.push_str("\n    ");
+                    }
+                    s.push('"');
+                    s.push_str(piece);
+                    s.push('"');
+                }
                 s
             },
             Atom::Identifier(ident) => ident.generate(),
@@ -83,6 +157,15 @@ impl SimpleCodeGen for Atom {
                 s.push_str(")");
                 s
             },
+            Atom::VaArg { list, typ } => {
+                let mut s = String::new();
+                s.push_str("va_arg(");
+                s.push_str(list.generate().as_str());
+                s.push_str(", ");
+                s.push_str(typ.generate().as_str());
+                s.push(')');
+                s
+            },
             Atom::Wrapped(expr) => {
                 let mut s = String::new();
                 s.push_str("(");
@@ -90,14 +173,65 @@ impl SimpleCodeGen for Atom {
                 s.push_str(")");
                 s
             },
+            Atom::InitList(values) => {
+                let mut s = String::new();
+                s.push('{');
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        s.push_str(", ");
+                    }
+                    s.push_str(value.generate().as_str());
+                }
+                s.push('}');
+                s
+            },
+            Atom::Lambda(_) => panic!("Lambdas must be lowered by ClosureLowering before generating code"),
+            Atom::Embed(_) => panic!("Embedded files must be read by EmbedResolver before generating code"),
+            Atom::Bytes(bytes) => {
+                let mut s = String::new();
+                s.push('{');
+                for (i, byte) in bytes.iter().enumerate() {
+                    s.push_str(if i % BYTES_PER_LINE == 0 { "\n    " } else { " " });
+                    s.push_str(format!("0x{:02x}", byte).as_str());
+                    if i + 1 < bytes.len() {
+                        s.push(',');
+                    }
+                }
+                s.push_str("\n}");
+                s
+            },
+            Atom::MacroCall { .. } => panic!("Macros must be expanded by MacroExpander before generating code"),
+            // Lowered by StringInterpolation, so this is only spelled out in error messages
+            Atom::Interpolated(parts) => {
+                let mut s = String::new();
+                s.push_str("$\"");
+                for part in parts {
+                    s.push_str(part.generate().as_str());
+                }
+                s.push('"');
+                s
+            },
+            Atom::Assign { target, value } => {
+                let mut s = String::new();
+                s.push('(');
+                s.push_str(target.as_str());
+                s.push_str(" = ");
+                s.push_str(value.generate().as_str());
+                s.push(')');
+                s
+            },
         }
     }
 }
 
+#[derive(Clone)]
 pub enum ExprTail {
     None,
     Call {
         body: Vec<Expression>,
+        /// Arguments given as `name: value` after the positional ones, which `ArgumentResolver`
+        /// moves into `body`
+        named: Vec<NamedArgument>,
         next: Box<ExprTail>,
     },
     BinaryOp {
@@ -117,17 +251,58 @@ pub enum ExprTail {
         inner: Box<Expression>,
         next: Box<ExprTail>,
     },
+    /// `[start..end]`, the elements from `start` up to but not including `end` as a slice.
+    /// Replaced with a call to the slice type's helper by `SliceLowering`.
+    Slice {
+        start: Box<Expression>,
+        end: Box<Expression>,
+        next: Box<ExprTail>,
+    },
     TernaryConditional {
         second: Box<Expression>,
         third: Box<Expression>,
         next: Box<ExprTail>,
     },
+    /// `?->member`, which skips the rest of the chain and gives null (or zero) if the pointer
+    /// is null. Lowered to a conditional expression by `SafeNavigation`.
+    SafeAccess {
+        member: String,
+        next: Box<ExprTail>,
+    },
+    /// `?? fallback`, the value before it unless that is null or a `?->` before it skipped
+    /// the chain. Lowered to a conditional expression by `SafeNavigation`.
+    Coalesce {
+        fallback: Box<Expression>,
+        next: Box<ExprTail>,
+    },
+}
+impl ExprTail {
+    /// Attaches `tail` to the end of this chain of tails
+    pub fn append(self, tail: ExprTail) -> ExprTail {
+        match self {
+            ExprTail::None => tail,
+            ExprTail::Call { body, named, next } => ExprTail::Call { body, named, next: Box::new(next.append(tail)) },
+            ExprTail::BinaryOp { op, right, next } => ExprTail::BinaryOp { op, right, next: Box::new(next.append(tail)) },
+            ExprTail::MemberAccess { member, next } => ExprTail::MemberAccess { member, next: Box::new(next.append(tail)) },
+            ExprTail::PointerAccess { member, next } => ExprTail::PointerAccess { member, next: Box::new(next.append(tail)) },
+            ExprTail::Index { inner, next } => ExprTail::Index { inner, next: Box::new(next.append(tail)) },
+            ExprTail::Slice { start, end, next } => ExprTail::Slice { start, end, next: Box::new(next.append(tail)) },
+            ExprTail::TernaryConditional { second, third, next } => {
+                ExprTail::TernaryConditional { second, third, next: Box::new(next.append(tail)) }
+            },
+            ExprTail::SafeAccess { member, next } => ExprTail::SafeAccess { member, next: Box::new(next.append(tail)) },
+            ExprTail::Coalesce { fallback, next } => ExprTail::Coalesce { fallback, next: Box::new(next.append(tail)) },
+        }
+    }
 }
 impl SimpleCodeGen for ExprTail {
     fn generate(&self) -> String {
         match self {
             ExprTail::None => String::new(),
-            ExprTail::Call { body, next } => {
+            ExprTail::Call { body, named, next } => {
+                if !named.is_empty() {
+                    panic!("Named arguments must be resolved by ArgumentResolver before generating code");
+                }
                 let mut s = String::new();
                 let mut arg_strings = Vec::<String>::new();
                 for arg in body {
@@ -154,6 +329,32 @@ impl SimpleCodeGen for ExprTail {
                 s.push_str(next.generate().as_str());
                 s
             },
+            // Lowered by SliceLowering, which spells it out for the messages of failed checks
+            ExprTail::Slice { start, end, next } => {
+                let mut s = String::new();
+                s.push('[');
+                s.push_str(start.generate().as_str());
+                s.push_str("..");
+                s.push_str(end.generate().as_str());
+                s.push(']');
+                s.push_str(next.generate().as_str());
+                s
+            },
+            // Lowered by SafeNavigation, so these are only spelled out in error messages
+            ExprTail::SafeAccess { member, next } => {
+                let mut s = String::new();
+                s.push_str("?->");
+                s.push_str(member.as_str());
+                s.push_str(next.generate().as_str());
+                s
+            },
+            ExprTail::Coalesce { fallback, next } => {
+                let mut s = String::new();
+                s.push_str(" ?? ");
+                s.push_str(fallback.generate().as_str());
+                s.push_str(next.generate().as_str());
+                s
+            },
             ExprTail::MemberAccess { member, next } => {
                 let mut s = String::new();
                 s.push_str(".");
@@ -181,10 +382,56 @@ impl SimpleCodeGen for ExprTail {
     }
 }
 
+/// A piece of an interpolated string
+#[derive(Clone)]
+pub enum StringPart {
+    /// Text as written, except that `{{` and `}}` are already single braces
+    Text(String),
+    /// `{value}`, or `{value:%spec}` with the `printf` conversion written out
+    Value {
+        value: Box<Expression>,
+        format: Option<String>,
+    },
+}
+impl SimpleCodeGen for StringPart {
+    fn generate(&self) -> String {
+        match self {
+            StringPart::Text(text) => text.replace('{', "{{").replace('}', "}}"),
+            StringPart::Value { value, format } => {
+                let mut s = String::new();
+                s.push('{');
+                s.push_str(value.generate().as_str());
+                if let Some(format) = format {
+                    s.push(':');
+                    s.push_str(format.as_str());
+                }
+                s.push('}');
+                s
+            },
+        }
+    }
+}
+
+/// A `name: value` argument in a call
+#[derive(Clone)]
+pub struct NamedArgument {
+    pub name: String,
+    pub value: Expression,
+}
+
+#[derive(Clone)]
 pub struct Expression {
     pub atom: Atom,
     pub tail: ExprTail,
 }
+impl Expression {
+    pub fn new(atom: Atom, tail: ExprTail) -> Self {
+        Expression { atom, tail }
+    }
+    pub fn identifier(ident: Identifier) -> Self {
+        Expression { atom: Atom::Identifier(ident), tail: ExprTail::None }
+    }
+}
 impl SimpleCodeGen for Expression {
     fn generate(&self) -> String {
         let mut s = String::new();
@@ -194,6 +441,7 @@ impl SimpleCodeGen for Expression {
     }
 }
 
+#[derive(Clone)]
 pub enum BinOp {
     Add,
     Sub,
@@ -239,6 +487,7 @@ impl SimpleCodeGen for BinOp {
     }
 }
 
+#[derive(Clone)]
 pub enum UnaryOp {
     Plus,
     Minus,
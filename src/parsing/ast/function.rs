@@ -1,25 +1,28 @@
 use crate::codegen::simple::{IndentCodeGen, ModuleMemberCodeGen, SimpleCodeGen};
 
-use super::{statement::Statement, types::Type};
+use super::{attribute::{generate_attributes, Attribute}, expression::Expression, statement::Statement, types::Type};
 
 pub struct FunctionHeader {
     pub return_type: Type,
     pub name: String,
     pub params: Vec<Parameter>,
-    pub is_extern: bool,
+    /// Whether the parameters end with `...`
+    pub is_variadic: bool,
+    pub attributes: Vec<Attribute>,
 }
 impl ModuleMemberCodeGen for FunctionHeader {    
     fn generate(&self, name_prefix: &String) -> String {
         let mut s = String::new();
-        if self.is_extern {
-            s.push_str("extern ");
-        }
+        s.push_str(generate_attributes(&self.attributes).as_str());
         s.push_str(self.return_type.generate().as_str());
         s.push_str(" ");
         s.push_str(name_prefix.as_str());
         s.push_str(self.name.as_str());
         s.push_str("(");
         s.push_str(self.params.iter().map(|p| p.generate()).collect::<Vec<String>>().join(", ").as_str());
+        if self.is_variadic {
+            s.push_str(", ...");
+        }
         s.push_str(")");
         s
     }
@@ -52,9 +55,12 @@ impl ModuleMemberCodeGen for Function {
     }
 }
 
+#[derive(Clone)]
 pub struct Parameter {
     pub name: String,
     pub typ: Type,
+    /// The value used when a call leaves this parameter out, which is only known to miniC
+    pub default: Option<Expression>,
 }
 impl SimpleCodeGen for Parameter {
     fn generate(&self) -> String {
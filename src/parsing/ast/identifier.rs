@@ -1,5 +1,6 @@
 use crate::codegen::simple::SimpleCodeGen;
 
+#[derive(Clone, PartialEq)]
 pub enum Identifier {
     Plain(String),
     Module(String, String),
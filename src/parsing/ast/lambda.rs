@@ -0,0 +1,29 @@
+use super::{function::Parameter, statement::Statement, types::{ClosureSignature, Type}};
+
+#[derive(Clone)]
+pub struct Capture {
+    pub name: String,
+    pub by_reference: bool,
+}
+
+/// `[x, &y](int a) => int { ... }`, lowered to a hoisted function and a capture struct
+/// by `ClosureLowering`
+#[derive(Clone)]
+pub struct Lambda {
+    pub captures: Vec<Capture>,
+    pub params: Vec<Parameter>,
+    pub is_variadic: bool,
+    pub return_type: Type,
+    pub body: Vec<Statement>,
+}
+
+impl Lambda {
+    pub fn signature(&self) -> ClosureSignature {
+        ClosureSignature {
+            params: self.params.iter().map(|p| p.typ.clone()).collect(),
+            return_type: self.return_type.clone(),
+            is_escaping: false,
+            is_variadic: self.is_variadic,
+        }
+    }
+}
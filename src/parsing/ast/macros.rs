@@ -0,0 +1,50 @@
+use super::{expression::Expression, statement::Statement, types::Type};
+
+/// `macro swap(a, b) { ... }`, expanded wherever it is called as `swap!(x, y)` by `MacroExpander`.
+/// A macro whose body is a single expression statement can also be used as a value.
+#[derive(Clone)]
+pub struct Macro {
+    pub name: String,
+    pub params: Vec<MacroParam>,
+    pub body: Vec<Statement>,
+}
+
+#[derive(Clone)]
+pub struct MacroParam {
+    pub name: String,
+    pub kind: MacroParamKind,
+}
+
+/// What a macro parameter takes, written before its name. A parameter without a kind takes an expression.
+#[derive(Clone, Copy, PartialEq)]
+pub enum MacroParamKind {
+    /// `expr a`, a value that is put in parentheses wherever the macro uses it
+    Expression,
+    /// `type T`, a type that the macro can declare variables of, cast to, etc.
+    Type,
+    /// `ident name`, a name that the macro can declare, assign or call
+    Identifier,
+    /// `block body`, statements in braces that the macro uses as the statement `body;`
+    Block,
+}
+
+impl MacroParamKind {
+    /// The keyword written before the parameter's name
+    pub fn keyword(&self) -> &'static str {
+        match self {
+            MacroParamKind::Expression => "expr",
+            MacroParamKind::Type => "type",
+            MacroParamKind::Identifier => "ident",
+            MacroParamKind::Block => "block",
+        }
+    }
+}
+
+/// An argument of a macro call. Which parameter kinds accept it is checked when the call is expanded.
+#[derive(Clone)]
+pub enum MacroArgument {
+    Expression(Expression),
+    /// A type that can't be written as an expression, like `unsigned int` or `char*`
+    Type(Type),
+    Block(Vec<Statement>),
+}
@@ -8,3 +8,8 @@ pub mod sstruct;
 pub mod enumm;
 pub mod identifier;
 pub mod typedef;
+pub mod lambda;
+pub mod attribute;
+pub mod directive;
+pub mod macros;
+pub mod test;
@@ -1,12 +1,24 @@
 use crate::codegen::simple::{IndentCodeGen, ModuleMemberCodeGen, PureCodeGen, SimpleCodeGen};
 
-use super::types::Type;
+use super::{attribute::{generate_attributes, Attribute}, directive::Directive, types::Type};
 
 pub struct Struct {
     pub name: String,
     pub members: Vec<StructMember>,
     pub is_anonymous: bool,
     pub is_union: bool,
+    pub derives: Vec<Derive>,
+    pub attributes: Vec<Attribute>,
+}
+impl Struct {
+    /// Whether preprocessor directives appear between the fields, so that they may depend on the build
+    pub fn has_directives(&self) -> bool {
+        self.members.iter().any(|m| match m {
+            StructMember::Directive(_) => true,
+            StructMember::AnonStruct(inner) => inner.has_directives(),
+            StructMember::Field(_) => false,
+        })
+    }
 }
 impl ModuleMemberCodeGen for Struct {
     fn generate(&self, name_prefix: &String) -> String {
@@ -21,6 +33,7 @@ impl ModuleMemberCodeGen for Struct {
         s.push_str("typedef ");
         s.push_str(struct_name);
         s.push_str(" ");
+        s.push_str(generate_attributes(&self.attributes).as_str());
         s.push_str(self.name.as_str());
         s.push_str("__");
         s.push_str(struct_name);
@@ -47,6 +60,7 @@ impl PureCodeGen for Struct {
         s.push_str(prefix.as_str());
         s.push_str(struct_name);
         s.push_str(" ");
+        s.push_str(generate_attributes(&self.attributes).as_str());
         if !self.is_anonymous {
             s.push_str(self.name.as_str());
             s.push_str(" ");
@@ -65,6 +79,8 @@ impl PureCodeGen for Struct {
 pub enum StructMember {
     Field(StructField),
     AnonStruct(Struct),
+    /// A preprocessor directive between fields, e.g. to only have a field in debug builds
+    Directive(Directive),
 }
 impl IndentCodeGen for StructMember {
     fn generate(&self, indent: usize) -> String {
@@ -81,6 +97,27 @@ impl IndentCodeGen for StructMember {
                 str.push_str(";");
                 str
             },
+            Self::Directive(directive) => directive.generate(&String::new()),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Derive {
+    Eq,
+    Hash,
+    Debug,
+    Clone,
+    Json,
+}
+impl Derive {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Derive::Eq => "eq",
+            Derive::Hash => "hash",
+            Derive::Debug => "debug",
+            Derive::Clone => "clone",
+            Derive::Json => "json",
         }
     }
 }
@@ -88,13 +125,19 @@ impl IndentCodeGen for StructMember {
 pub struct StructField {
     pub typ: Type,
     pub name: String,
+    pub array_size: Option<usize>,
+    pub attributes: Vec<Attribute>,
 }
 impl SimpleCodeGen for StructField {
     fn generate(&self) -> String {
         let mut s = String::new();
+        s.push_str(generate_attributes(&self.attributes).as_str());
         s.push_str(self.typ.generate().as_str());
         s.push_str(" ");
         s.push_str(self.name.as_str());
+        if let Some(size) = self.array_size {
+            s.push_str(format!("[{}]", size).as_str());
+        }
         s.push_str(";");
         s
     }
@@ -1,24 +1,100 @@
-use crate::codegen::simple::{SimpleCodeGen, IndentCodeGen};
+use crate::codegen::simple::{ModuleMemberCodeGen, SimpleCodeGen, IndentCodeGen};
 
-use super::{expression::{Atom, BinOp, Expression}, types::Type};
+use super::{directive::Directive, expression::{Atom, BinOp, ExprTail, Expression}, types::Type};
 
+#[derive(Clone)]
 pub struct ConditionBody {
     pub condition: Expression,
     pub body: Vec<Statement>,
 }
 
+#[derive(Clone)]
 pub struct CaseStatement {
-    pub atom: Atom,
+    pub labels: Vec<CaseLabel>,
     pub body: Vec<Statement>,
 }
 
+/// One of the comma-separated labels of a `case`
+#[derive(Clone)]
+pub enum CaseLabel {
+    Value(Box<Expression>),
+    /// `start ... end`, a GCC case range including both ends
+    Range {
+        start: Box<Expression>,
+        end: Box<Expression>,
+    },
+}
+
+impl SimpleCodeGen for CaseLabel {
+    fn generate(&self) -> String {
+        match self {
+            CaseLabel::Value(value) => value.generate(),
+            CaseLabel::Range { start, end } => format!("{} ... {}", start.generate(), end.generate()),
+        }
+    }
+}
+
+/// What a `for (T x in ...)` loop iterates over
+#[derive(Clone)]
+pub enum ForIterable {
+    /// `start..end`, with the end exclusive and evaluated once
+    Range {
+        start: Box<Expression>,
+        end: Box<Expression>,
+    },
+    /// A fixed-size array. The length and whether elements are taken by pointer are
+    /// filled in by `ForInResolver`, which knows the array's declaration.
+    Array {
+        array: Expression,
+        length: Option<usize>,
+        by_reference: bool,
+    },
+    /// A slice, iterated up to its `len`. It is created by `SliceLowering`, which stores any
+    /// slice that isn't a plain variable in a hidden temporary first.
+    Slice {
+        slice: Expression,
+        by_reference: bool,
+    },
+}
+
+/// `static_assert(condition, "message")`, checked by `ConstFolding` and kept for the C compiler to check again
+#[derive(Clone)]
+pub struct StaticAssert {
+    pub condition: Expression,
+    /// The message as written, without quotes
+    pub message: String,
+}
+impl SimpleCodeGen for StaticAssert {
+    fn generate(&self) -> String {
+        let mut s = String::new();
+        s.push_str("_Static_assert(");
+        s.push_str(self.condition.generate().as_str());
+        s.push_str(", \"");
+        s.push_str(self.message.as_str());
+        s.push_str("\")");
+        s
+    }
+}
+
+#[derive(Clone)]
 pub enum Statement {
     Expression(Expression),
     VarDec {
         typ: Type,
         name: String,
+        array_size: Option<usize>,
         right: Option<Expression>,
-        modifier: Vec<String>,
+    },
+    /// `let name = right`, replaced with a `VarDec` by `LetInference`
+    Let {
+        name: String,
+        right: Expression,
+    },
+    /// `let (a, b) = right`, declaring a variable for each element of a tuple, where `_` skips
+    /// an element. Replaced with a `VarDec` of the tuple and one for each name by `LetInference`.
+    LetTuple {
+        names: Vec<String>,
+        right: Expression,
     },
     VarAssign {
         identifier: IdentifierExpression,
@@ -34,6 +110,8 @@ pub enum Statement {
         is_inc: bool,
     },
     Return(Option<Expression>),
+    /// `return (a, b)`, replaced with a `Return` of the function's tuple type by `LetInference`
+    ReturnTuple(Vec<Expression>),
     If {
         base: ConditionBody,
         elseifs: Vec<ConditionBody>,
@@ -50,13 +128,39 @@ pub enum Statement {
         increment: Box<Statement>,
         body: Vec<Statement>,
     },
+    EnumFor {
+        typ: Type,
+        name: String,
+        enum_type: Type,
+        body: Vec<Statement>,
+    },
+    ForIn {
+        typ: Type,
+        name: String,
+        iterable: ForIterable,
+        body: Vec<Statement>,
+    },
     Switch {
-        atom: Atom,
+        value: Expression,
         cases: Vec<CaseStatement>,
         default: Option<Vec<Statement>>,
+        /// Whether this is a `switch!`, where each case ends with an automatic `break`
+        no_fallthrough: bool,
+    },
+    /// `label: statement`. Labels on loops can also be used by `break label` and `continue label`,
+    /// which `LabelLowering` replaces with `goto`s.
+    Labeled {
+        label: String,
+        statement: Box<Statement>,
     },
-    Continue,
-    Break,
+    Goto(String),
+    /// A target for `goto`. It's followed by an empty statement so that it can end a block.
+    Label(String),
+    Continue(Option<String>),
+    Break(Option<String>),
+    StaticAssert(StaticAssert),
+    /// A preprocessor directive between statements, written at the start of its line
+    Directive(Directive),
 }
 impl IndentCodeGen for Statement {
     fn generate(&self, indent_level: usize) -> String {
@@ -68,37 +172,39 @@ impl IndentCodeGen for Statement {
         }
         let mut has_semicolon = true;
         match self {
-            Statement::Expression(expr) => s.push_str(expr.generate().as_str()),
-            Statement::VarDec { typ, name, right, modifier } => {
-                for m in modifier {
-                    s.push_str(m.as_str());
-                    s.push_str(" ");
-                }
-
+            Statement::Expression(expr) => s.push_str(continued(expr, &indent_prefix).as_str()),
+            Statement::VarDec { typ, name, array_size, right } => {
                 s.push_str(typ.generate().as_str());
                 s.push(' ');
                 s.push_str(name.as_str());
+                if let Some(size) = array_size {
+                    s.push('[');
+                    s.push_str(size.to_string().as_str());
+                    s.push(']');
+                }
                 if let Some(body) = right {
                     s.push_str(" = ");
-                    s.push_str(body.generate().as_str());
+                    s.push_str(continued(body, &indent_prefix).as_str());
                 }
             },
+            Statement::Let { .. } | Statement::LetTuple { .. } => panic!("`let` must be resolved by LetInference before generating code"),
+            Statement::ReturnTuple(_) => panic!("`return (a, b)` must be resolved by LetInference before generating code"),
             Statement::VarAssign { identifier, right } => {
                 s.push_str(identifier.generate().as_str());
                 s.push_str(" = ");
-                s.push_str(right.generate().as_str());
+                s.push_str(continued(right, &indent_prefix).as_str());
             },
             Statement::BinOpVarAssign { identifier, op, right } => {
                 s.push_str(identifier.generate().as_str());
                 s.push_str(" ");
                 s.push_str(op.generate().as_str());
                 s.push_str("= ");
-                s.push_str(right.generate().as_str());
+                s.push_str(continued(right, &indent_prefix).as_str());
             },
             Statement::Return(expr) => {
                 if let Some(val) = expr {
                     s.push_str("return ");
-                    s.push_str(val.generate().as_str());
+                    s.push_str(continued(val, &indent_prefix).as_str());
                 } else {
                     s.push_str("return");
                 }
@@ -157,6 +263,114 @@ impl IndentCodeGen for Statement {
                 s.push_str(indent_prefix.as_str());
                 s.push_str("}");
             },
+            Statement::EnumFor { typ, name, enum_type, body } => {
+                has_semicolon = false;
+                // Iterate over the enum's generated `__values` array with a hidden index
+                let enum_name = enum_type.name.generate();
+                let index = format!("__mc_i_{}", name);
+                s.push_str("for (int ");
+                s.push_str(index.as_str());
+                s.push_str(" = 0; ");
+                s.push_str(index.as_str());
+                s.push_str(" < ");
+                s.push_str(enum_name.as_str());
+                s.push_str("__COUNT; ");
+                s.push_str(index.as_str());
+                s.push_str("++) {\n");
+                s.push_str(indent_prefix.as_str());
+                s.push_str("    ");
+                s.push_str(typ.generate().as_str());
+                s.push(' ');
+                s.push_str(name.as_str());
+                s.push_str(" = ");
+                s.push_str(enum_name.as_str());
+                s.push_str("__values[");
+                s.push_str(index.as_str());
+                s.push_str("];\n");
+                s.push_str(self.add_body(body, indent_level + 1).as_str());
+                s.push_str(indent_prefix.as_str());
+                s.push('}');
+            },
+            Statement::ForIn { typ, name, iterable, body } => {
+                has_semicolon = false;
+                match iterable {
+                    ForIterable::Range { start, end } => {
+                        // The end is stored in a hidden temporary so that it is only evaluated once,
+                        // after the start. The variable is incremented, so it can't be `const`.
+                        let end_name = format!("__mc_end_{}", name);
+                        s.push_str("for (");
+                        s.push_str(typ.unqualified().generate().as_str());
+                        s.push(' ');
+                        s.push_str(name.as_str());
+                        s.push_str(" = ");
+                        s.push_str(start.generate().as_str());
+                        s.push_str(", ");
+                        s.push_str(end_name.as_str());
+                        s.push_str(" = ");
+                        s.push_str(end.generate().as_str());
+                        s.push_str("; ");
+                        s.push_str(name.as_str());
+                        s.push_str(" < ");
+                        s.push_str(end_name.as_str());
+                        s.push_str("; ");
+                        s.push_str(name.as_str());
+                        s.push_str("++) {\n");
+                    },
+                    ForIterable::Array { array, length, by_reference } => {
+                        let array_name = array.generate();
+                        let index = format!("__mc_i_{}", name);
+                        let length = match length {
+                            Some(size) => size.to_string(),
+                            None => format!("sizeof({}) / sizeof({}[0])", array_name, array_name),
+                        };
+                        s.push_str("for (size_t ");
+                        s.push_str(index.as_str());
+                        s.push_str(" = 0; ");
+                        s.push_str(index.as_str());
+                        s.push_str(" < ");
+                        s.push_str(length.as_str());
+                        s.push_str("; ");
+                        s.push_str(index.as_str());
+                        s.push_str("++) {\n");
+                        s.push_str(indent_prefix.as_str());
+                        s.push_str("    ");
+                        s.push_str(typ.generate().as_str());
+                        s.push(' ');
+                        s.push_str(name.as_str());
+                        s.push_str(if *by_reference { " = &" } else { " = " });
+                        s.push_str(array_name.as_str());
+                        s.push('[');
+                        s.push_str(index.as_str());
+                        s.push_str("];\n");
+                    },
+                    ForIterable::Slice { slice, by_reference } => {
+                        let slice_name = slice.generate();
+                        let index = format!("__mc_i_{}", name);
+                        s.push_str("for (size_t ");
+                        s.push_str(index.as_str());
+                        s.push_str(" = 0; ");
+                        s.push_str(index.as_str());
+                        s.push_str(" < ");
+                        s.push_str(slice_name.as_str());
+                        s.push_str(".len; ");
+                        s.push_str(index.as_str());
+                        s.push_str("++) {\n");
+                        s.push_str(indent_prefix.as_str());
+                        s.push_str("    ");
+                        s.push_str(typ.generate().as_str());
+                        s.push(' ');
+                        s.push_str(name.as_str());
+                        s.push_str(if *by_reference { " = &" } else { " = " });
+                        s.push_str(slice_name.as_str());
+                        s.push_str(".ptr[");
+                        s.push_str(index.as_str());
+                        s.push_str("];\n");
+                    },
+                }
+                s.push_str(self.add_body(body, indent_level + 1).as_str());
+                s.push_str(indent_prefix.as_str());
+                s.push('}');
+            },
             Statement::IncDec { identifier, is_inc } => {
                 s.push_str(identifier.generate().as_str());
                 if *is_inc {
@@ -165,21 +379,30 @@ impl IndentCodeGen for Statement {
                     s.push_str("--");
                 }
             },
-            Statement::Switch { atom, cases, default } => {
+            Statement::Switch { value, cases, default, no_fallthrough } => {
                 has_semicolon = false;
                 s.push_str("switch (");
-                s.push_str(atom.generate().as_str());
+                s.push_str(value.generate().as_str());
                 s.push_str(") {\n");
                 for case in cases {
-                    s.push_str(&indent_prefix);
-                    s.push_str("    ");
-                    s.push_str("case ");
-                    s.push_str(case.atom.generate().as_str());
-                    s.push_str(":\n");
+                    for label in &case.labels {
+                        s.push_str(&indent_prefix);
+                        s.push_str("    ");
+                        s.push_str("case ");
+                        s.push_str(label.generate().as_str());
+                        s.push_str(":\n");
+                    }
                     for statement in &case.body {
                         s.push_str(statement.generate(indent_level + 2).as_str());
                         s.push_str("\n");
                     }
+                    // The default case is always last, so only the others can fall through. A case
+                    // with no statements is only another label for the code after it.
+                    let falls_through = case.body.last().is_some_and(|last| !last.is_jump());
+                    if *no_fallthrough && falls_through {
+                        s.push_str(Statement::Break(None).generate(indent_level + 2).as_str());
+                        s.push('\n');
+                    }
                 }
                 if let Some(the_default) = default {
                     s.push_str(&indent_prefix);
@@ -193,8 +416,27 @@ impl IndentCodeGen for Statement {
                 s.push_str(&indent_prefix);
                 s.push_str("}\n");
             },
-            Statement::Continue => s.push_str("continue"),
-            Statement::Break => s.push_str("break"),
+            Statement::Labeled { label, statement } => {
+                has_semicolon = false;
+                s.push_str(label.as_str());
+                s.push_str(":\n");
+                s.push_str(statement.generate(indent_level).as_str());
+            },
+            Statement::Goto(label) => {
+                s.push_str("goto ");
+                s.push_str(label.as_str());
+            },
+            Statement::Label(label) => {
+                s.push_str(label.as_str());
+                s.push_str(": ");
+            },
+            Statement::Continue(None) => s.push_str("continue"),
+            Statement::Break(None) => s.push_str("break"),
+            Statement::Continue(Some(_)) | Statement::Break(Some(_)) => {
+                panic!("Loop labels must be lowered by LabelLowering before generating code")
+            },
+            Statement::StaticAssert(assertion) => s.push_str(assertion.generate().as_str()),
+            Statement::Directive(directive) => return directive.generate(&String::new()),
         }
         if has_semicolon {
             s.push(';');
@@ -203,7 +445,18 @@ impl IndentCodeGen for Statement {
     }
 }
 
+/// An expression whose continuation lines, e.g. those of a long string literal, are indented
+/// relative to the statement
+fn continued(expression: &Expression, indent_prefix: &String) -> String {
+    expression.generate().replace('\n', format!("\n{}", indent_prefix).as_str())
+}
+
 impl Statement {
+    /// Whether control never continues past this statement
+    fn is_jump(&self) -> bool {
+        matches!(self, Statement::Return(_) | Statement::Break(_) | Statement::Continue(_) | Statement::Goto(_))
+    }
+
     fn add_body(&self, body: &Vec<Statement>, indent_level: usize) -> String {
         let mut s = String::new();
         for line in body {
@@ -214,6 +467,7 @@ impl Statement {
     }
 }
 
+#[derive(Clone)]
 pub enum IdentifierExpression {
     Standard(String),
     Pointer(Expression),
@@ -222,7 +476,11 @@ impl SimpleCodeGen for IdentifierExpression {
     fn generate(&self) -> String {
         match self {
             Self::Standard(name) => name.to_string(),
-            Self::Pointer(expr) => expr.generate(),
+            // `*p = value`, where the pointer is put in parentheses unless it's a single name
+            Self::Pointer(expr) => match (&expr.atom, &expr.tail) {
+                (Atom::Identifier(_), ExprTail::None) => format!("*{}", expr.generate()),
+                _ => format!("*({})", expr.generate()),
+            },
         }
     }
 }
@@ -0,0 +1,10 @@
+use super::statement::Statement;
+
+/// `test "name" { ... }`, a unit test that is only compiled in test mode. Lowered to a function
+/// and an entry of the generated test runner by `TestLowering`.
+#[derive(Clone)]
+pub struct TestBlock {
+    /// The name as written, without quotes
+    pub name: String,
+    pub body: Vec<Statement>,
+}
@@ -1,13 +1,13 @@
 use crate::codegen::simple::{ModuleMemberCodeGen, SimpleCodeGen};
 
-use super::{enumm::Enum, expression::Expression, function::{Function, FunctionHeader}, sstruct::Struct, typedef::TypeDef, types::Type};
+use super::{directive::Directive, enumm::Enum, expression::Expression, function::{Function, FunctionHeader}, macros::Macro, sstruct::Struct, statement::StaticAssert, test::TestBlock, typedef::TypeDef, types::Type};
 
 pub enum TopLevel {
     VarDeclaration {
         typ: Type,
         name: String,
+        array_size: Option<usize>,
         right: Option<Expression>,
-        modifier: Vec<String>,
     },
     Import {
         name: String,
@@ -22,21 +22,27 @@ pub enum TopLevel {
         body: Vec<TopLevel>,
     },
     TypeDef(TypeDef),
-    PreprocessorDirective(String), // Just contains everything after the '#'
+    StaticAssert(StaticAssert),
+    Directive(Directive),
+    /// Removed by `MacroExpander`, which expands every call of it
+    Macro(Macro),
+    /// Removed, or lowered to a function in test mode, by `TestLowering`
+    Test(TestBlock),
 }
 impl ModuleMemberCodeGen for TopLevel {
     fn generate(&self, name_prefix: &String) -> String {
         match self {
-            TopLevel::VarDeclaration { typ, name, right, modifier} => {
+            TopLevel::VarDeclaration { typ, name, array_size, right } => {
                 let mut s = String::new();
-                for m in modifier {
-                    s.push_str(m.as_str());
-                    s.push_str(" ");
-                }
                 s.push_str(typ.generate().as_str());
                 s.push_str(" ");
                 s.push_str(name_prefix.as_str());
                 s.push_str(name.as_str());
+                if let Some(size) = array_size {
+                    s.push('[');
+                    s.push_str(size.to_string().as_str());
+                    s.push(']');
+                }
                 if let Some(value) = right {
                     s.push_str(" = ");
                     s.push_str(value.generate().as_str());
@@ -70,19 +76,29 @@ impl ModuleMemberCodeGen for TopLevel {
             TopLevel::Module { name, body } => {
                 let mut s = String::new();
                 let prefix = format!("mod__{}__", name);
+                let defines = body.iter().filter_map(|t| match t {
+                    TopLevel::Directive(Directive::Define { name, .. }) => Some(name),
+                    _ => None,
+                }).collect::<Vec<&String>>();
                 for t in body {
-                    s.push_str(t.generate(&prefix).as_str());
+                    match t {
+                        TopLevel::Directive(directive) => s.push_str(directive.generate_in_module(&prefix, &defines).as_str()),
+                        other => s.push_str(other.generate(&prefix).as_str()),
+                    }
                     s.push_str("\n");
                 }
                 s
             },
             TopLevel::TypeDef(t) => t.generate(name_prefix),
-            TopLevel::PreprocessorDirective(str) => {
+            TopLevel::StaticAssert(assertion) => {
                 let mut s = String::new();
-                s.push_str("#");
-                s.push_str(str.as_str());
+                s.push_str(assertion.generate().as_str());
+                s.push(';');
                 s
-            }
+            },
+            TopLevel::Directive(directive) => directive.generate(name_prefix),
+            TopLevel::Macro(_) => panic!("Macros must be expanded by MacroExpander before generating code"),
+            TopLevel::Test(_) => panic!("Test blocks must be lowered by TestLowering before generating code"),
         }
     }
 }
@@ -1,6 +1,6 @@
 use crate::codegen::simple::{ModuleMemberCodeGen, PureCodeGen, SimpleCodeGen};
 
-use super::{enumm::Enum, sstruct::Struct, types::Type};
+use super::{enumm::Enum, sstruct::Struct, types::{ClosureSignature, Type}};
 
 pub struct TypeDef {
     pub name: String,
@@ -8,6 +8,9 @@ pub struct TypeDef {
 }
 impl ModuleMemberCodeGen for TypeDef {
     fn generate(&self, name_prefix: &String) -> String {
+        if let TypeDefInner::FunctionPointer(signature) = &self.typ {
+            return format!("typedef {};", signature.declare_pointer(format!("{}{}", name_prefix, self.name).as_str()));
+        }
         let mut s = String::new();
         s.push_str("typedef ");
         s.push_str(
@@ -15,6 +18,7 @@ impl ModuleMemberCodeGen for TypeDef {
                 TypeDefInner::Type(t) => t.generate(),
                 TypeDefInner::Enum(e) => e.generate_pure(0),
                 TypeDefInner::Struct(s) => s.generate_pure(0),
+                TypeDefInner::FunctionPointer(_) => unreachable!(),
             }.as_str()
         );
         s.push_str(" ");
@@ -29,4 +33,6 @@ pub enum TypeDefInner {
     Type(Type),
     Struct(Struct),
     Enum(Enum),
+    /// The definition of a `(params) => return_type` type, added by `TypeDefinitions`
+    FunctionPointer(ClosureSignature),
 }
@@ -2,31 +2,328 @@ use crate::codegen::simple::SimpleCodeGen;
 
 use super::identifier::Identifier;
 
+#[derive(Clone)]
 pub enum TypeType {
     Simple,
     Struct,
     Enum,
     Union,
+    Closure(Box<ClosureSignature>),
+    /// `(params) => return_type`, a C function pointer, which is never `escaping`
+    FunctionPointer(Box<ClosureSignature>),
+    /// `(int, bool)`, a struct with one field per element named `_0`, `_1`, ...
+    Tuple(Vec<Type>),
+    /// `T[]`, a struct of a pointer `ptr` to the first element and the number of elements `len`
+    Slice(Box<Type>),
 }
 
+/// The signature of a `closure(params) => return_type`. Every closure is represented
+/// by the same `mc::Closure` struct in C, so this is only used by the transpiler.
+#[derive(Clone)]
+pub struct ClosureSignature {
+    pub params: Vec<Type>,
+    pub return_type: Type,
+    /// Whether a closure passed through a parameter of this type may outlive the call
+    pub is_escaping: bool,
+    /// Whether the parameters end with `...`
+    pub is_variadic: bool,
+}
+
+impl ClosureSignature {
+    /// The miniC spelling of this signature, for error messages
+    pub fn describe(&self) -> String {
+        let mut params = self.params.iter().map(|p| p.describe()).collect::<Vec<String>>();
+        if self.is_variadic {
+            params.push(String::from("..."));
+        }
+        let params = params.join(", ");
+        format!("closure({}) => {}", params, self.return_type.describe())
+    }
+
+    /// The C declaration of a function pointer `name` with this signature, e.g. `int (*name)(int)`
+    pub fn declare_pointer(&self, name: &str) -> String {
+        let mut params = self.params.iter().map(|p| p.generate()).collect::<Vec<String>>();
+        if self.is_variadic {
+            params.push(String::from("..."));
+        }
+        if params.is_empty() {
+            params.push(String::from("void"));
+        }
+        format!("{} (*{})({})", self.return_type.generate(), name, params.join(", "))
+    }
+
+    /// The C function pointer type of the lowered function, e.g. `int (*)(void*, int)`
+    pub fn function_pointer(&self) -> String {
+        let mut params = vec![String::from("void*")];
+        params.extend(self.params.iter().map(|p| p.generate()));
+        if self.is_variadic {
+            params.push(String::from("..."));
+        }
+        format!("{} (*)({})", self.return_type.generate(), params.join(", "))
+    }
+}
+
+/// A qualifier on a type or on one of its pointer layers
+#[derive(Clone, PartialEq)]
+pub enum Qualifier {
+    Const,
+    Volatile,
+    Restrict,
+    /// The `?` in `T?*`, marking a pointer that may be null. It is only checked by `NullChecker`,
+    /// and left out of the generated C.
+    Nullable,
+}
+
+impl SimpleCodeGen for Qualifier {
+    fn generate(&self) -> String {
+        String::from(match self {
+            Qualifier::Const => "const",
+            Qualifier::Volatile => "volatile",
+            Qualifier::Restrict => "restrict",
+            Qualifier::Nullable => "?",
+        })
+    }
+}
+
+/// A storage-class or function specifier, which can only be written where something is declared
+#[derive(Clone, PartialEq)]
+pub enum StorageClass {
+    Static,
+    Extern,
+    Register,
+    Inline,
+    ThreadLocal,
+}
+
+impl SimpleCodeGen for StorageClass {
+    fn generate(&self) -> String {
+        String::from(match self {
+            StorageClass::Static => "static",
+            StorageClass::Extern => "extern",
+            StorageClass::Register => "register",
+            StorageClass::Inline => "inline",
+            StorageClass::ThreadLocal => "_Thread_local",
+        })
+    }
+}
+
+#[derive(Clone)]
 pub struct Type {
     pub typetype: TypeType,
+    /// The base type's name. Builtin types spelled with several words, like `unsigned long`, are kept as one name.
     pub name: Identifier,
-    pub pointer_layers: usize,
+    pub storage: Vec<StorageClass>,
+    /// Qualifiers on the base type, e.g. the `const` in `const char*`
+    pub qualifiers: Vec<Qualifier>,
+    /// How many of the qualifiers were written before the storage classes, so that
+    /// `const extern int` keeps its order
+    pub storage_position: usize,
+    /// The qualifiers written after each `*`, innermost first, so `char* const* p` has `[[Const], []]`
+    pub pointers: Vec<Vec<Qualifier>>,
+}
+
+impl Type {
+    pub fn simple(name: Identifier, pointer_layers: usize) -> Self {
+        Type {
+            typetype: TypeType::Simple,
+            name,
+            storage: Vec::new(),
+            qualifiers: Vec::new(),
+            storage_position: 0,
+            pointers: vec![Vec::new(); pointer_layers],
+        }
+    }
+
+    pub fn closure(signature: ClosureSignature) -> Self {
+        Type {
+            typetype: TypeType::Closure(Box::new(signature)),
+            name: Identifier::Module(String::from("mc"), String::from("Closure")),
+            storage: Vec::new(),
+            qualifiers: Vec::new(),
+            storage_position: 0,
+            pointers: Vec::new(),
+        }
+    }
+
+    /// A tuple type, named after its elements so that the same tuple always gets the same C struct,
+    /// e.g. `(int, bool)` is `mc::Tuple__3_int__4_bool`. Each element is prefixed with the length
+    /// of its name, so that names containing `_` can't make two tuples collide.
+    pub fn tuple(elements: Vec<Type>) -> Self {
+        let mut name = String::from("Tuple");
+        for element in &elements {
+            let mangled = element.mangle();
+            name.push_str(format!("__{}_{}", mangled.len(), mangled).as_str());
+        }
+        Type {
+            typetype: TypeType::Tuple(elements),
+            name: Identifier::Module(String::from("mc"), name),
+            storage: Vec::new(),
+            qualifiers: Vec::new(),
+            storage_position: 0,
+            pointers: Vec::new(),
+        }
+    }
+
+    /// A function pointer type, named after its return and parameter types like tuples, e.g.
+    /// `(int) => void` is `mc::Fn__4_void__3_int`. Variadic ones end with `__v`.
+    pub fn function_pointer(signature: ClosureSignature) -> Self {
+        let mut name = String::from("Fn");
+        for typ in std::iter::once(&signature.return_type).chain(&signature.params) {
+            let mangled = typ.mangle();
+            name.push_str(format!("__{}_{}", mangled.len(), mangled).as_str());
+        }
+        if signature.is_variadic {
+            name.push_str("__v");
+        }
+        Type {
+            typetype: TypeType::FunctionPointer(Box::new(signature)),
+            name: Identifier::Module(String::from("mc"), name),
+            storage: Vec::new(),
+            qualifiers: Vec::new(),
+            storage_position: 0,
+            pointers: Vec::new(),
+        }
+    }
+
+    /// A slice type, named after its element type like tuples, e.g. `int[]` is `mc::Slice__int`.
+    /// The element's own qualifiers are part of the name, since they decide what the slice's
+    /// pointer points to, so `const int[]` is `mc::Slice__constint`.
+    pub fn slice(element: Type) -> Self {
+        let own = element.pointers.last().unwrap_or(&element.qualifiers);
+        Type {
+            name: Identifier::Module(String::from("mc"), format!("Slice__{}{}", mangle_qualifiers(own), element.mangle())),
+            typetype: TypeType::Slice(Box::new(element)),
+            storage: Vec::new(),
+            qualifiers: Vec::new(),
+            storage_position: 0,
+            pointers: Vec::new(),
+        }
+    }
+
+    pub fn pointer_layers(&self) -> usize {
+        self.pointers.len()
+    }
+
+    /// The same type without storage classes, for using a declaration's type somewhere else
+    pub fn without_storage(&self) -> Type {
+        let mut result = self.clone();
+        result.storage.clear();
+        result.storage_position = 0;
+        result
+    }
+
+    /// The type of a value read from something of this type: without storage classes or the
+    /// qualifiers on the outermost layer, so `const char* const` becomes `const char*`.
+    /// Whether the pointer may be null is kept, since that is a property of the value.
+    pub fn unqualified(&self) -> Type {
+        let mut result = self.without_storage();
+        match result.pointers.last_mut() {
+            Some(outer) => outer.retain(|q| *q == Qualifier::Nullable),
+            None => result.qualifiers.clear(),
+        }
+        result
+    }
+
+    /// Whether the outermost pointer layer is written `?*`
+    pub fn is_nullable(&self) -> bool {
+        self.pointers.last().is_some_and(|outer| outer.contains(&Qualifier::Nullable))
+    }
+
+    /// The miniC spelling of this type, for error messages
+    pub fn describe(&self) -> String {
+        match &self.typetype {
+            TypeType::Closure(signature) => signature.describe(),
+            TypeType::FunctionPointer(signature) => {
+                let mut params = signature.params.iter().map(|p| p.describe()).collect::<Vec<String>>();
+                if signature.is_variadic {
+                    params.push(String::from("..."));
+                }
+                format!("({}) => {}", params.join(", "), signature.return_type.describe())
+            },
+            TypeType::Tuple(elements) => {
+                let elements = elements.iter().map(|e| e.describe()).collect::<Vec<String>>().join(", ");
+                format!("({}){}", elements, self.spell_pointers(true))
+            },
+            TypeType::Slice(element) => format!("{}[]{}", element.describe(), self.spell_pointers(true)),
+            _ => self.spell(true),
+        }
+    }
+
+    /// The type as part of a C name: its C name without spaces, followed by `p` for each pointer
+    /// layer. Qualifiers that matter to users of the type are written before what they qualify,
+    /// so `char* const*` becomes `charpconstp`, while the type's own qualifiers are left out.
+    /// Every closure type is `closure`.
+    pub fn mangle(&self) -> String {
+        if let TypeType::Closure(_) = self.typetype {
+            return String::from("closure");
+        }
+        let typ = self.unqualified();
+        let mut s = String::new();
+        if typ.pointer_layers() > 0 {
+            s.push_str(mangle_qualifiers(&typ.qualifiers).as_str());
+        }
+        s.push_str(typ.name.generate().replace(' ', "").as_str());
+        for i in 0..typ.pointer_layers() {
+            if i > 0 {
+                s.push_str(mangle_qualifiers(&typ.pointers[i - 1]).as_str());
+            }
+            s.push('p');
+        }
+        s
+    }
+}
+
+/// The qualifiers that change what a type means to its users, as part of a mangled name
+fn mangle_qualifiers(qualifiers: &[Qualifier]) -> String {
+    [Qualifier::Const, Qualifier::Volatile].iter().filter(|q| qualifiers.contains(q)).map(|q| q.generate()).collect()
 }
 
 impl SimpleCodeGen for Type {
     fn generate(&self) -> String {
+        self.spell(false)
+    }
+}
+
+impl Type {
+    /// The C spelling of this type, or the miniC one with `?*` for nullable pointers
+    fn spell(&self, nullability: bool) -> String {
         let mut result: String = String::new();
+        let (before, after) = self.qualifiers.split_at(self.storage_position.min(self.qualifiers.len()));
+        for q in before {
+            result.push_str(q.generate().as_str());
+            result.push(' ');
+        }
+        for s in &self.storage {
+            result.push_str(s.generate().as_str());
+            result.push(' ');
+        }
+        for q in after {
+            result.push_str(q.generate().as_str());
+            result.push(' ');
+        }
         match self.typetype {
             TypeType::Simple => (),
             TypeType::Struct => result.push_str("struct "),
             TypeType::Enum => result.push_str("enum "),
             TypeType::Union => result.push_str("union "),
+            TypeType::Closure(_) | TypeType::FunctionPointer(_) | TypeType::Tuple(_) | TypeType::Slice(_) => (),
         };
         result.push_str(self.name.generate().as_str());
-        for _i in 0..self.pointer_layers {
-            result.push_str("*");
+        result.push_str(self.spell_pointers(nullability).as_str());
+        result
+    }
+
+    fn spell_pointers(&self, nullability: bool) -> String {
+        let mut result = String::new();
+        for layer in &self.pointers {
+            if nullability && layer.contains(&Qualifier::Nullable) {
+                result.push('?');
+            }
+            result.push('*');
+            for q in layer.iter().filter(|q| **q != Qualifier::Nullable) {
+                result.push(' ');
+                result.push_str(q.generate().as_str());
+            }
         }
         result
     }
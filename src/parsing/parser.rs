@@ -1,9 +1,11 @@
 use pest::iterators::{Pair, Pairs};
-use std::fs;
+use std::{fs, path::Path};
 use pest::Parser;
 use pest_derive::Parser;
 
-use super::ast::{enumm::{Enum, EnumEntry}, expression::{Atom, BinOp, ExprTail, Expression, UnaryOp}, function::{Function, FunctionHeader, Parameter}, identifier::Identifier, program::Program, sstruct::{Struct, StructField, StructMember}, statement::{CaseStatement, ConditionBody, IdentifierExpression, Statement}, toplevel::TopLevel, typedef::{TypeDef, TypeDefInner}, types::{Type, TypeType}};
+use crate::mutating::mutator::Mutator;
+
+use super::ast::{attribute::Attribute, directive::Directive, enumm::{Enum, EnumEntry, REFLECTION_MEMBERS}, expression::{Atom, BinOp, ExprTail, Expression, NamedArgument, StringPart, UnaryOp}, function::{Function, FunctionHeader, Parameter}, identifier::Identifier, macros::{Macro, MacroArgument, MacroParam, MacroParamKind}, program::Program, sstruct::{Derive, Struct, StructField, StructMember}, statement::{CaseLabel, CaseStatement, ConditionBody, ForIterable, IdentifierExpression, StaticAssert, Statement}, test::TestBlock, toplevel::TopLevel, typedef::{TypeDef, TypeDefInner}, types::{ClosureSignature, Qualifier, StorageClass, Type, TypeType}, lambda::{Capture, Lambda}};
 
 #[derive(Parser)]
 #[grammar = "grammar.pest"] // relative to src
@@ -13,11 +15,23 @@ pub struct MyMiniCParser {}
 
 impl MyMiniCParser {
     pub fn parse_file(filepath: String) -> Result<Program, String> {
-        let unparsed_file = fs::read_to_string(filepath).expect("cannot read file");
+        let unparsed_file = fs::read_to_string(&filepath).expect("cannot read file");
         let main_pair = MiniCParser::parse(Rule::program, &unparsed_file)
             .expect("unsuccessful parse")
             .next().unwrap();
-        Self::parse_main(main_pair)
+        let program = Self::parse_main(main_pair)?;
+        Self::resolve_embeds(program, Path::new(&filepath).parent().unwrap_or(Path::new("")))
+    }
+
+    /// Joins the path of each `embed` to the directory of the source file, which later passes don't know
+    fn resolve_embeds(program: Program, directory: &Path) -> Result<Program, String> {
+        let directory = directory.to_path_buf();
+        let mut resolver = Mutator::new();
+        resolver.add_expression_closure(Box::new(move |e| match e.atom {
+            Atom::Embed(path) => Ok(Expression::new(Atom::Embed(directory.join(path).to_string_lossy().into_owned()), e.tail)),
+            _ => Ok(e),
+        }));
+        resolver.mutate_program(program).map_err(|e| e.to_string())
     }
 
     fn parse_main(pair: Pair<Rule>) -> Result<Program, String> {
@@ -35,6 +49,7 @@ impl MyMiniCParser {
                         _ => return Result::Err(String::from("Could not parse top-level statement")),
                     }
                 }
+                Self::check_toplevel_conditionals(&statements)?;
                 Result::Ok(
                     Program {
                         statements: statements
@@ -47,31 +62,18 @@ impl MyMiniCParser {
 
     fn parse_top_level(pair: Pair<Rule>) -> Result<TopLevel, String> {
         match pair.as_rule() {
+            Rule::staticAssert => Result::Ok(TopLevel::StaticAssert(Self::parse_static_assert(pair)?)),
             Rule::varDec => {
                 let mut pairs = pair.into_inner();
-                let mut modifiers = Vec::<String>::new();
-                let mut next = pairs.next().unwrap();
-                loop {
-                    match next.as_rule() {
-                        Rule::varModifier => modifiers.push(String::from(next.as_str())),
-                        _ => break,
-                    }
-                    next = pairs.next().unwrap();
-                }
-
-                let typ = Self::parse_type(next)?;
+                let typ = Self::parse_declared_type(&mut pairs)?;
                 let name = pairs.next().unwrap().as_str();
-                let mut init_val: Option<Expression> = None;
-                if let Some(expr_pair) = pairs.next() {
-                    let result = Self::parse_expression(expr_pair)?;
-                    init_val = Some(result);
-                }
+                let (array_size, init_val) = Self::parse_var_dec_rest(pairs)?;
                 Result::Ok(
                     TopLevel::VarDeclaration { 
                         typ: typ,
                         name: String::from(name),
+                        array_size,
                         right: init_val,
-                        modifier: modifiers,
                     }
                 )
             },
@@ -119,6 +121,7 @@ impl MyMiniCParser {
                 for p in pairs {
                     body.push(Self::parse_top_level(p.into_inner().next().unwrap())?);
                 }
+                Self::check_toplevel_conditionals(&body)?;
                 Result::Ok(
                     TopLevel::Module { name: name, body: body }
                 )
@@ -153,13 +156,13 @@ impl MyMiniCParser {
                     )
                 )
             },
-            Rule::preprocessorDirective => {
-                if pair.as_str().len() < 2 {
-                    Result::Err(String::from("Invalid preprocessor directive"))
-                } else {
-                    let content = String::from(&pair.as_str()[1..]);
-                    Result::Ok(TopLevel::PreprocessorDirective(content))
-                }
+            Rule::directive => Result::Ok(TopLevel::Directive(Self::parse_directive(pair)?)),
+            Rule::macroDefinition => Result::Ok(TopLevel::Macro(Self::parse_macro(pair)?)),
+            Rule::testBlock => {
+                let mut pairs = pair.into_inner();
+                pairs.next(); // `test`
+                let name = Self::parse_plain_string(pairs.next().unwrap(), "The name of a test")?;
+                Result::Ok(TopLevel::Test(TestBlock { name, body: Self::parse_body(pairs)? }))
             },
             _ => {
                 Result::Err(String::from("Could not parse top-level"))
@@ -173,37 +176,39 @@ impl MyMiniCParser {
                 let exp = Self::parse_expression(pair)?;
                 Result::Ok(Statement::Expression(exp))
             },
+            Rule::staticAssert => Result::Ok(Statement::StaticAssert(Self::parse_static_assert(pair)?)),
+            Rule::directive => Result::Ok(Statement::Directive(Self::parse_directive(pair)?)),
             Rule::varDec => {
                 let mut pairs = pair.into_inner();
-                let mut modifiers = Vec::<String>::new();
-                let mut next = pairs.next().unwrap();
-                loop {
-                    match next.as_rule() {
-                        Rule::varModifier => modifiers.push(String::from(next.as_str())),
-                        _ => break,
-                    }
-                    next = pairs.next().unwrap();
-                }
-
-                let typ = Self::parse_type(next)?;
+                let typ = Self::parse_declared_type(&mut pairs)?;
                 let name = pairs.next().unwrap().as_str();
-                let mut init_val: Option<Expression> = None;
-                if let Some(expr_pair) = pairs.next() {
-                    let result = Self::parse_expression(expr_pair)?;
-                    init_val = Some(result);
-                }
+                let (array_size, init_val) = Self::parse_var_dec_rest(pairs)?;
                 Result::Ok(
                     Statement::VarDec { 
                         typ: typ,
                         name: String::from(name),
+                        array_size,
                         right: init_val,
-                        modifier: modifiers,
                     }
                 )
             },
+            Rule::letDec => {
+                let mut pairs = pair.into_inner().skip(1);
+                let target = pairs.next().unwrap();
+                let right = Self::parse_expression(pairs.next().unwrap())?;
+                if target.as_rule() == Rule::tuplePattern {
+                    let names = target.into_inner().map(|n| String::from(n.as_str())).collect();
+                    return Result::Ok(Statement::LetTuple { names, right });
+                }
+                Result::Ok(Statement::Let { name: String::from(target.as_str()), right })
+            },
             Rule::r#return => {
-                let mut pairs = pair.into_inner();
-                let expr = Self::parse_expression(pairs.next().unwrap())?;
+                let value = pair.into_inner().next().unwrap();
+                if value.as_rule() == Rule::tupleValue {
+                    let values = value.into_inner().map(Self::parse_expression).collect::<Result<Vec<Expression>, String>>()?;
+                    return Result::Ok(Statement::ReturnTuple(values));
+                }
+                let expr = Self::parse_expression(value)?;
                 Result::Ok(
                     Statement::Return(Some(expr)),
                 )
@@ -251,12 +256,12 @@ impl MyMiniCParser {
             Rule::r#for => {
                 let mut pairs = pair.into_inner();
                 let init_stmt = Self::parse_statement(pairs.next().unwrap().into_inner().next().unwrap())?;
+                if let Statement::Directive(_) = init_stmt {
+                    return Result::Err(String::from("A preprocessor directive can't be the first part of a 'for' loop"));
+                }
                 let cond = Self::parse_expression(pairs.next().unwrap())?;
                 let step_stmt = Self::parse_statement(pairs.next().unwrap())?;
-                let mut body = Vec::<Statement>::new();
-                for p in pairs {
-                    body.push(Self::parse_statement(p.into_inner().next().unwrap())?);
-                }
+                let body = Self::parse_body(pairs)?;
                 Result::Ok(Statement::For { 
                     init: Box::new(init_stmt),
                     condition: cond,
@@ -264,19 +269,52 @@ impl MyMiniCParser {
                     body: body
                 })
             },
+            Rule::enumFor => {
+                let mut pairs = pair.into_inner();
+                let typ = Self::parse_type(pairs.next().unwrap())?;
+                let name = String::from(pairs.next().unwrap().as_str());
+                let enum_type = Self::parse_type(pairs.next().unwrap())?;
+                let body = Self::parse_body(pairs)?;
+                Result::Ok(Statement::EnumFor {
+                    typ,
+                    name,
+                    enum_type,
+                    body,
+                })
+            },
+            Rule::forIn => {
+                let mut pairs = pair.into_inner();
+                let typ = Self::parse_type(pairs.next().unwrap())?;
+                let name = String::from(pairs.next().unwrap().as_str());
+                let iterable_pair = pairs.next().unwrap();
+                let iterable = match iterable_pair.as_rule() {
+                    Rule::range => {
+                        let mut bounds = iterable_pair.into_inner();
+                        ForIterable::Range {
+                            start: Box::new(Self::parse_expression(bounds.next().unwrap())?),
+                            end: Box::new(Self::parse_expression(bounds.next().unwrap())?),
+                        }
+                    },
+                    _ => ForIterable::Array {
+                        array: Self::parse_expression(iterable_pair)?,
+                        length: None,
+                        by_reference: false,
+                    },
+                };
+                let body = Self::parse_body(pairs)?;
+                Result::Ok(Statement::ForIn {
+                    typ,
+                    name,
+                    iterable,
+                    body,
+                })
+            },
             Rule::doWhile => {
-                let mut peekable = pair.into_inner().peekable();
-                let mut body = Vec::<Statement>::new();
-                let mut last = None;
+                let mut items = pair.into_inner().collect::<Vec<_>>();
+                // The condition is the last item
+                let last = items.pop();
+                let body = Self::parse_body(items.into_iter())?;
                 let expr: Expression;
-                while let Some(item) = peekable.next() {
-                    if peekable.peek().is_none() {
-                        // on last item, so handle outside of loop
-                        last = Some(item);
-                        break;
-                    }
-                    body.push(Self::parse_statement(item.into_inner().next().unwrap())?);
-                }
                 if let Some(last_item) = last {
                     expr = Self::parse_expression(last_item)?;
                     Result::Ok(
@@ -287,44 +325,52 @@ impl MyMiniCParser {
                 }
             },
             Rule::r#continue => {
-                Result::Ok(Statement::Continue)
+                let label = pair.into_inner().next().map(|p| String::from(p.as_str()));
+                Result::Ok(Statement::Continue(label))
             },
             Rule::r#break => {
-                Result::Ok(Statement::Break)
+                let label = pair.into_inner().next().map(|p| String::from(p.as_str()));
+                Result::Ok(Statement::Break(label))
             },
-            Rule::switch => {
+            Rule::goto => {
+                let label = pair.into_inner().next().unwrap().as_str();
+                Result::Ok(Statement::Goto(String::from(label)))
+            },
+            Rule::labeled => {
                 let mut pairs = pair.into_inner();
-                let switch_atom = Self::parse_atom(pairs.next().unwrap().into_inner().next().unwrap())?;
+                let label = String::from(pairs.next().unwrap().as_str());
+                let statement = Self::parse_statement(pairs.next().unwrap().into_inner().next().unwrap())?;
+                if let Statement::Directive(_) = statement {
+                    return Result::Err(format!("The label '{}' must be followed by a statement, not a preprocessor directive", label));
+                }
+                Result::Ok(Statement::Labeled { label, statement: Box::new(statement) })
+            },
+            Rule::switch => {
+                let mut pairs = pair.into_inner().peekable();
+                let no_fallthrough = pairs.next_if(|p| p.as_rule() == Rule::noFallthrough).is_some();
+                let value = Self::parse_expression(pairs.next().unwrap())?;
                 let mut cases = Vec::<CaseStatement>::new();
                 let mut default: Option<Vec<Statement>> = None;
                 for p in pairs {
                     match p.as_rule() {
                         Rule::case => {
-                            let mut body = p.into_inner();
-                            let case_atom = Self::parse_atom(body.next().unwrap().into_inner().next().unwrap())?;
-                            let mut body_lines = Vec::<Statement>::new();
-                            for line in body {
-                                let statement = Self::parse_statement(line.into_inner().next().unwrap())?;
-                                body_lines.push(statement);
+                            let mut items = p.into_inner().peekable();
+                            let mut labels = Vec::<CaseLabel>::new();
+                            while let Some(item) = items.next_if(|item| item.as_rule() == Rule::caseLabel) {
+                                labels.push(Self::parse_case_label(item)?);
                             }
                             cases.push(CaseStatement {
-                                atom: case_atom,
-                                body: body_lines,
+                                labels,
+                                body: Self::parse_body(items)?,
                             })
                         },
                         Rule::default => {
-                            let mut body_lines = Vec::<Statement>::new();
-                            let body = p.into_inner();
-                            for line in body {
-                                let statement = Self::parse_statement(line.into_inner().next().unwrap())?;
-                                body_lines.push(statement);
-                            }
-                            default = Some(body_lines);
+                            default = Some(Self::parse_body(p.into_inner())?);
                         },
                         _ => return Result::Err(String::from("Invalid block in switch body")),
                     }
                 }
-                Result::Ok(Statement::Switch { atom: switch_atom, cases: cases, default: default })
+                Result::Ok(Statement::Switch { value, cases, default, no_fallthrough })
             },
             _ => {
                 println!("\n\n{}\n\n", pair);
@@ -360,6 +406,12 @@ impl MyMiniCParser {
                 let value: i32 = pair.as_str().parse().unwrap();
                 Result::Ok(Atom::Int(value))
             },
+            Rule::octal => {
+                let text = pair.as_str();
+                let digits = text.trim_start_matches(['+', '-']);
+                let value = i32::from_str_radix(digits, 8).map_err(|_| format!("The octal literal '{}' doesn't fit in an int", text))?;
+                Result::Ok(Atom::Octal(if text.starts_with('-') { -value } else { value }))
+            },
             Rule::double => {
                 let value: f64 = pair.as_str().parse().unwrap();
                 Result::Ok(Atom::Double(value))
@@ -376,7 +428,24 @@ impl MyMiniCParser {
                 let value: bool = pair.as_str().parse().unwrap();
                 Result::Ok(Atom::Boolean(value))
             },
-            Rule::string => Result::Ok(Atom::String(String::from(pair.into_inner().next().unwrap().as_str()))),
+            Rule::string => {
+                let inner = pair.into_inner().next().unwrap();
+                if inner.as_rule() != Rule::interpolatedString {
+                    return Result::Ok(Atom::String(Self::parse_string_literal(inner)?));
+                }
+                let mut parts = Vec::<StringPart>::new();
+                for part in inner.into_inner() {
+                    if part.as_rule() == Rule::interpolatedText {
+                        parts.push(StringPart::Text(part.as_str().replace("{{", "{").replace("}}", "}")));
+                        continue;
+                    }
+                    let mut pairs = part.into_inner();
+                    let value = Self::parse_expression(pairs.next().unwrap())?;
+                    let format = pairs.next().map(|f| String::from(f.as_str()));
+                    parts.push(StringPart::Value { value: Box::new(value), format });
+                }
+                Result::Ok(Atom::Interpolated(parts))
+            },
             Rule::hybridIdent => Result::Ok(Atom::Identifier(Self::parse_identifier(pair)?)),
             Rule::typeCast => {
                 let mut pairs = pair.into_inner();
@@ -400,6 +469,14 @@ impl MyMiniCParser {
                     Atom::SizeOf(typ)
                 )
             },
+            Rule::vaArg => {
+                let mut pairs = pair.into_inner();
+                let list = Self::parse_expression(pairs.next().unwrap())?;
+                let typ = Self::parse_type(pairs.next().unwrap())?;
+                Result::Ok(
+                    Atom::VaArg { list: Box::new(list), typ }
+                )
+            },
             Rule::expression => {
                 let exp = Self::parse_expression(pair)?;
                 Result::Ok(Atom::Wrapped(Box::new(exp)))
@@ -408,6 +485,28 @@ impl MyMiniCParser {
                 let c = pair.into_inner().next().unwrap().as_str().as_bytes()[0];
                 Result::Ok(Atom::Char(c))
             },
+            Rule::lambda => Result::Ok(Atom::Lambda(Box::new(Self::parse_lambda(pair)?))),
+            Rule::macroCall => {
+                let mut pairs = pair.into_inner();
+                let name = String::from(pairs.next().unwrap().as_str());
+                let mut args = Vec::<MacroArgument>::new();
+                for p in pairs {
+                    let arg = p.into_inner().next().unwrap();
+                    args.push(match arg.as_rule() {
+                        Rule::macroBlock => MacroArgument::Block(Self::parse_body(arg.into_inner())?),
+                        Rule::expression => MacroArgument::Expression(Self::parse_expression(arg)?),
+                        _ => MacroArgument::Type(Self::parse_type(arg)?),
+                    });
+                }
+                Result::Ok(Atom::MacroCall { name, args })
+            },
+            Rule::initList => {
+                let mut values = Vec::<Expression>::new();
+                for p in pair.into_inner() {
+                    values.push(Self::parse_expression(p)?);
+                }
+                Result::Ok(Atom::InitList(values))
+            },
             _ => {
                 println!("\n\n{}\n\n", pair);
                 Result::Err(String::from("Could not parse atom"))
@@ -427,13 +526,25 @@ impl MyMiniCParser {
                             let mut items: Vec<Pair<'_, Rule>> = pairs.collect();
                             let next_tail = Self::parse_expr_tail(items.pop().unwrap())?;
                             let mut args: Vec<Expression> = Vec::new();
+                            let mut named: Vec<NamedArgument> = Vec::new();
                             for arg in items {
+                                if arg.as_rule() == Rule::namedArgument {
+                                    let mut pairs = arg.into_inner();
+                                    let name = String::from(pairs.next().unwrap().as_str());
+                                    let value = Self::parse_expression(pairs.next().unwrap())?;
+                                    named.push(NamedArgument { name, value });
+                                    continue;
+                                }
+                                if let Some(last) = named.last() {
+                                    return Result::Err(format!("Positional arguments must come before named ones, but one follows '{}:'", last.name));
+                                }
                                 let parsed_arg = Self::parse_expression(arg)?;
                                 args.push(parsed_arg);
                             }
                             Result::Ok(
                                 ExprTail::Call {
                                     body: args,
+                                    named,
                                     next: Box::new(next_tail),
                                 }
                             )
@@ -477,7 +588,15 @@ impl MyMiniCParser {
                         },
                         Rule::indexTail => {
                             let mut pairs = tail_pair.into_inner();
-                            let expr = Self::parse_expression(pairs.next().unwrap())?;
+                            let index = pairs.next().unwrap();
+                            if index.as_rule() == Rule::range {
+                                let mut bounds = index.into_inner();
+                                let start = Self::parse_expression(bounds.next().unwrap())?;
+                                let end = Self::parse_expression(bounds.next().unwrap())?;
+                                let next_tail = Self::parse_expr_tail(pairs.next().unwrap())?;
+                                return Result::Ok(ExprTail::Slice { start: Box::new(start), end: Box::new(end), next: Box::new(next_tail) });
+                            }
+                            let expr = Self::parse_expression(index)?;
                             let next_tail = Self::parse_expr_tail(pairs.next().unwrap())?;
                             Result::Ok(
                                 ExprTail::Index { 
@@ -486,6 +605,18 @@ impl MyMiniCParser {
                                 }
                             )
                         },
+                        Rule::safeAccessTail => {
+                            let mut pairs = tail_pair.into_inner();
+                            let member = String::from(pairs.next().unwrap().as_str());
+                            let next_tail = Self::parse_expr_tail(pairs.next().unwrap())?;
+                            Result::Ok(ExprTail::SafeAccess { member, next: Box::new(next_tail) })
+                        },
+                        Rule::coalesceTail => {
+                            let mut pairs = tail_pair.into_inner();
+                            let fallback = Self::parse_expression(pairs.next().unwrap())?;
+                            let next_tail = Self::parse_expr_tail(pairs.next().unwrap())?;
+                            Result::Ok(ExprTail::Coalesce { fallback: Box::new(fallback), next: Box::new(next_tail) })
+                        },
                         Rule::ternaryTail => {
                             let mut pairs = tail_pair.into_inner();
                             let second = Self::parse_expression(pairs.next().unwrap())?;
@@ -511,22 +642,98 @@ impl MyMiniCParser {
 
     fn parse_type(pair: Pair<Rule>) -> Result<Type, String> {
         match pair.as_rule() {
+            Rule::typ if matches!(pair.clone().into_inner().next().unwrap().as_rule(), Rule::closureType | Rule::functionPointerType) => {
+                Self::parse_type(pair.into_inner().next().unwrap())
+            },
+            Rule::functionPointerType => {
+                let mut types = Vec::<Type>::new();
+                let mut is_variadic = false;
+                for p in pair.into_inner() {
+                    match p.as_rule() {
+                        Rule::ellipsis => is_variadic = true,
+                        _ => types.push(Self::parse_type(p)?),
+                    }
+                }
+                // The last type is the return type
+                let return_type = types.pop().unwrap();
+                Result::Ok(Type::function_pointer(ClosureSignature { params: types, return_type, is_escaping: false, is_variadic }))
+            },
+            Rule::closureType => {
+                let mut pairs = pair.into_inner().peekable();
+                let is_escaping = pairs.next_if(|p| p.as_rule() == Rule::escaping).is_some();
+                let mut types = Vec::<Type>::new();
+                let mut is_variadic = false;
+                for p in pairs {
+                    match p.as_rule() {
+                        Rule::ellipsis => is_variadic = true,
+                        _ => types.push(Self::parse_type(p)?),
+                    }
+                }
+                // The last type is the return type
+                let return_type = types.pop().unwrap();
+                Result::Ok(
+                    Type::closure(ClosureSignature {
+                        params: types,
+                        return_type,
+                        is_escaping,
+                        is_variadic,
+                    })
+                )
+            },
             Rule::typ => {
                 let mut typetype: TypeType = TypeType::Simple;
-                if pair.as_str().starts_with("struct") {
-                    typetype = TypeType::Struct;
-                } else if pair.as_str().starts_with("enum") {
-                    typetype = TypeType::Enum;
-                } else if pair.as_str().starts_with("union") {
-                    typetype = TypeType::Union;
+                let mut name = None;
+                let mut qualifiers = Vec::<Qualifier>::new();
+                let mut pointers = Vec::<Vec<Qualifier>>::new();
+                for p in pair.into_inner() {
+                    match p.as_rule() {
+                        // Qualifiers may be written on either side of the base type
+                        Rule::typeQualifier => qualifiers.push(Self::parse_qualifier(&p)),
+                        Rule::typeTag => {
+                            typetype = match p.as_str() {
+                                "struct" => TypeType::Struct,
+                                "enum" => TypeType::Enum,
+                                _ => TypeType::Union,
+                            };
+                        },
+                        Rule::primitiveType => {
+                            let words = p.into_inner().map(|w| w.as_str()).collect::<Vec<&str>>();
+                            name = Some(Identifier::Plain(words.join(" ")));
+                        },
+                        Rule::hybridIdent => name = Some(Self::parse_identifier(p)?),
+                        Rule::tupleType => {
+                            let tuple = Type::tuple(p.into_inner().map(Self::parse_type).collect::<Result<Vec<Type>, String>>()?);
+                            typetype = tuple.typetype;
+                            name = Some(tuple.name);
+                        },
+                        Rule::pointer => {
+                            pointers.push(p.into_inner().map(|q| Self::parse_qualifier(&q)).collect());
+                        },
+                        // Everything so far is the element type, and later pointers point to the slice
+                        Rule::sliceSuffix => {
+                            let element = Type {
+                                typetype,
+                                name: name.unwrap(),
+                                storage: Vec::new(),
+                                qualifiers: std::mem::take(&mut qualifiers),
+                                storage_position: 0,
+                                pointers: std::mem::take(&mut pointers),
+                            };
+                            let slice = Type::slice(element);
+                            typetype = slice.typetype;
+                            name = Some(slice.name);
+                        },
+                        _ => return Result::Err(String::from("Could not parse type")),
+                    }
                 }
-                let pointer_layers = pair.as_str().chars().filter(|c| *c == '*').count();
-                let name = Self::parse_identifier(pair.into_inner().next().unwrap())?;
                 Result::Ok(
                     Type {
                         typetype: typetype,
-                        pointer_layers: pointer_layers,
-                        name: name,
+                        name: name.unwrap(),
+                        storage: Vec::new(),
+                        qualifiers,
+                        storage_position: 0,
+                        pointers,
                     }
                 )
             },
@@ -536,6 +743,235 @@ impl MyMiniCParser {
         }
     }
 
+    /// Parses a type along with the storage classes written before it, e.g. `static const int`.
+    /// Qualifiers written before the first storage class stay in front of it, like in the source.
+    fn parse_declared_type(pairs: &mut Pairs<Rule>) -> Result<Type, String> {
+        let mut storage = Vec::<StorageClass>::new();
+        let mut qualifiers = Vec::<Qualifier>::new();
+        let mut storage_position = 0;
+        let mut next = pairs.next().unwrap();
+        loop {
+            match next.as_rule() {
+                Rule::storageClass => {
+                    if storage.is_empty() {
+                        storage_position = qualifiers.len();
+                    }
+                    storage.push(Self::parse_storage_class(&next));
+                },
+                Rule::typeQualifier => qualifiers.push(Self::parse_qualifier(&next)),
+                _ => break,
+            }
+            next = pairs.next().unwrap();
+        }
+        let mut typ = Self::qualify_base(Self::parse_type(next)?, qualifiers);
+        typ.storage = storage;
+        typ.storage_position = storage_position;
+        Result::Ok(typ)
+    }
+
+    /// Adds qualifiers written before a type to its base type, which for a slice is its element's
+    fn qualify_base(typ: Type, mut qualifiers: Vec<Qualifier>) -> Type {
+        match typ.typetype {
+            TypeType::Slice(element) if !qualifiers.is_empty() => {
+                Type { pointers: typ.pointers, ..Type::slice(Self::qualify_base(*element, qualifiers)) }
+            },
+            typetype => {
+                qualifiers.extend(typ.qualifiers);
+                Type { typetype, qualifiers, ..typ }
+            },
+        }
+    }
+
+    fn parse_case_label(pair: Pair<Rule>) -> Result<CaseLabel, String> {
+        let mut pairs = pair.into_inner();
+        let start = Self::parse_expression(pairs.next().unwrap())?;
+        match pairs.next() {
+            Some(end) => Result::Ok(CaseLabel::Range { start: Box::new(start), end: Box::new(Self::parse_expression(end)?) }),
+            None => Result::Ok(CaseLabel::Value(Box::new(start))),
+        }
+    }
+
+    fn parse_directive(pair: Pair<Rule>) -> Result<Directive, String> {
+        let inner = pair.into_inner().next().unwrap();
+        let rule = inner.as_rule();
+        if rule == Rule::otherDirective {
+            return Result::Ok(Directive::Other(String::from(inner.as_str().trim_end())));
+        }
+        let mut pairs = inner.into_inner();
+        let mut text = || pairs.next().map(|p| String::from(p.as_str().trim_end())).unwrap_or_default();
+        Result::Ok(match rule {
+            Rule::define => {
+                let name = text();
+                let mut params = None;
+                let mut value = String::new();
+                for p in pairs {
+                    match p.as_rule() {
+                        Rule::macroParams => params = Some(p.into_inner().map(|param| String::from(param.as_str())).collect()),
+                        _ => value = String::from(p.as_str().trim_end()),
+                    }
+                }
+                Directive::Define { name, params, value }
+            },
+            Rule::undef => Directive::Undef(text()),
+            Rule::ifdef => Directive::IfDef(text()),
+            Rule::ifndef => Directive::IfNDef(text()),
+            Rule::ifDirective => Directive::If(text()),
+            Rule::elif => Directive::Elif(text()),
+            Rule::elseDirective => Directive::Else,
+            Rule::endif => Directive::EndIf,
+            Rule::pragma => Directive::Pragma(text()),
+            _ => return Result::Err(String::from("Could not parse preprocessor directive")),
+        })
+    }
+
+    fn check_toplevel_conditionals(toplevels: &[TopLevel]) -> Result<(), String> {
+        Directive::check_conditionals(toplevels.iter().filter_map(|t| match t {
+            TopLevel::Directive(d) => Some(d),
+            _ => None,
+        }))
+    }
+
+    /// Parses the statements of a block, whose `#if` directives have to be closed within it
+    fn parse_body<'a>(pairs: impl Iterator<Item = Pair<'a, Rule>>) -> Result<Vec<Statement>, String> {
+        let mut body = Vec::<Statement>::new();
+        for p in pairs {
+            body.push(Self::parse_statement(p.into_inner().next().unwrap())?);
+        }
+        Directive::check_conditionals(body.iter().filter_map(|s| match s {
+            Statement::Directive(d) => Some(d),
+            _ => None,
+        }))?;
+        Result::Ok(body)
+    }
+
+    fn parse_static_assert(pair: Pair<Rule>) -> Result<StaticAssert, String> {
+        let mut pairs = pair.into_inner();
+        let condition = Self::parse_expression(pairs.next().unwrap())?;
+        let message = Self::parse_plain_string(pairs.next().unwrap(), "The message of static_assert")?;
+        Result::Ok(StaticAssert { condition, message })
+    }
+
+    /// The contents of a string literal that is used as it is written, so it can't be interpolated
+    fn parse_plain_string(pair: Pair<Rule>, usage: &str) -> Result<String, String> {
+        let inner = pair.into_inner().next().unwrap();
+        match inner.as_rule() {
+            Rule::interpolatedString => Result::Err(format!("{} can't be an interpolated string", usage)),
+            _ => Self::parse_string_literal(inner),
+        }
+    }
+
+    /// The contents of a plain, raw or multi-line string literal, escaped as in a C string
+    fn parse_string_literal(pair: Pair<Rule>) -> Result<String, String> {
+        match pair.as_rule() {
+            Rule::rawString => {
+                let text = pair.into_inner().next().unwrap().as_str().replace("\r\n", "\n");
+                let mut s = String::new();
+                for c in text.chars() {
+                    Self::push_escaped(&mut s, c);
+                }
+                Result::Ok(s)
+            },
+            Rule::multilineString => {
+                let text = Self::dedent_multiline(pair.into_inner().next().unwrap().as_str())?;
+                let mut s = String::new();
+                let mut chars = text.chars();
+                while let Some(c) = chars.next() {
+                    match c {
+                        // Escapes are kept as written, except that `\` at the end of a line joins it to the next
+                        '\\' => match chars.next() {
+                            Some('\n') => (),
+                            Some(escaped) => {
+                                s.push(c);
+                                s.push(escaped);
+                            },
+                            None => s.push(c),
+                        },
+                        c => Self::push_escaped(&mut s, c),
+                    }
+                }
+                Result::Ok(s)
+            },
+            _ => Result::Ok(String::from(pair.as_str())),
+        }
+    }
+
+    /// Pushes `c` as it's written in a C string. Other control characters become octal escapes,
+    /// which unlike `\x` can't run into a following digit, and a `?` after another `?` becomes
+    /// `\?`, so that the pair can't start a trigraph like `??=`.
+    fn push_escaped(s: &mut String, c: char) {
+        match c {
+            '\\' => s.push_str("\\\\"),
+            '"' => s.push_str("\\\""),
+            '?' if s.ends_with('?') => s.push_str("\\?"),
+            '\n' => s.push_str("\\n"),
+            '\t' => s.push_str("\\t"),
+            '\r' => s.push_str("\\r"),
+            c if c.is_ascii_control() => s.push_str(&format!("\\{:03o}", c as u32)),
+            c => s.push(c),
+        }
+    }
+
+    /// The text that the escaped contents of a string literal stand for, e.g. for reading a file it names
+    fn unescape(text: &str) -> String {
+        let mut s = String::new();
+        let mut chars = text.chars();
+        while let Some(c) = chars.next() {
+            if c != '\\' {
+                s.push(c);
+                continue;
+            }
+            s.push(match chars.next() {
+                Some('n') => '\n',
+                Some('t') => '\t',
+                Some('r') => '\r',
+                Some(escaped) => escaped,
+                None => '\\',
+            });
+        }
+        s
+    }
+
+    /// Removes the line break after the opening `"""` and, when the closing `"""` is on a line of
+    /// its own, the line break before it and its indentation from every line
+    fn dedent_multiline(text: &str) -> Result<String, String> {
+        let text = text.replace("\r\n", "\n");
+        let text = text.strip_prefix('\n').unwrap_or(text.as_str());
+        let (body, indent) = match text.rsplit_once('\n') {
+            Some((body, last)) if last.chars().all(|c| c == ' ' || c == '\t') => (body, last),
+            _ => return Result::Ok(String::from(text)),
+        };
+        let mut lines = Vec::<&str>::new();
+        for line in body.split('\n') {
+            match line.strip_prefix(indent) {
+                Some(rest) => lines.push(rest),
+                None if line.trim().is_empty() => lines.push(""),
+                None => return Result::Err(format!(
+                    "The line '{}' of a multi-line string is indented less than its closing '\"\"\"'", line.trim(),
+                )),
+            }
+        }
+        Result::Ok(lines.join("\n"))
+    }
+
+    fn parse_qualifier(pair: &Pair<Rule>) -> Qualifier {
+        match pair.as_str() {
+            "const" => Qualifier::Const,
+            "volatile" => Qualifier::Volatile,
+            "?" => Qualifier::Nullable,
+            _ => Qualifier::Restrict,
+        }
+    }
+
+    fn parse_storage_class(pair: &Pair<Rule>) -> StorageClass {
+        match pair.as_str() {
+            "static" => StorageClass::Static,
+            "extern" => StorageClass::Extern,
+            "register" => StorageClass::Register,
+            "inline" => StorageClass::Inline,
+            _ => StorageClass::ThreadLocal,
+        }
+    }
+
     fn parse_identifier(mut pair: Pair<Rule>) -> Result<Identifier, String> {
         pair = pair.into_inner().next().unwrap();
         match pair.as_rule() {
@@ -546,6 +982,16 @@ impl MyMiniCParser {
                 let child = String::from(pairs.next().unwrap().as_str());
                 Result::Ok(Identifier::Module(parent, child))
             },
+            Rule::enumMemberIdent => {
+                let mut pairs = pair.into_inner();
+                let module = String::from(pairs.next().unwrap().as_str());
+                let enum_name = pairs.next().unwrap().as_str();
+                let member = pairs.next().unwrap().as_str();
+                if !REFLECTION_MEMBERS.contains(&member) {
+                    return Result::Err(format!("'{}::{}::{}' doesn't exist, since enums only have the members {}", module, enum_name, member, REFLECTION_MEMBERS.join(", ")));
+                }
+                Result::Ok(Identifier::Module(module, format!("{}__{}", enum_name, member)))
+            },
             _ => {
                 println!("\n\n{}\n\n", pair);
                 Result::Err(String::from("Could not parse identifier"))
@@ -553,18 +999,85 @@ impl MyMiniCParser {
         }
     }
 
+    fn parse_lambda(pair: Pair<Rule>) -> Result<Lambda, String> {
+        let mut captures = Vec::<Capture>::new();
+        let mut params = Vec::<Parameter>::new();
+        let mut is_variadic = false;
+        let mut return_type = None;
+        let mut pairs = pair.into_inner();
+        for p in pairs.by_ref() {
+            match p.as_rule() {
+                Rule::capture => {
+                    let by_reference = p.as_str().starts_with('&');
+                    let name = p.into_inner().last().unwrap().as_str();
+                    captures.push(Capture { name: String::from(name), by_reference });
+                },
+                Rule::paramList => {
+                    is_variadic = Self::parse_param_list(p, &mut params)?;
+                    if let Some(param) = params.iter().find(|p| p.default.is_some()) {
+                        return Result::Err(format!("Lambda parameter '{}' can't have a default value, since closures are called without knowing their lambda", param.name));
+                    }
+                },
+                Rule::typ => {
+                    // The body's statements follow the return type
+                    return_type = Some(Self::parse_type(p)?);
+                    break;
+                },
+                _ => return Result::Err(String::from("Could not parse lambda")),
+            }
+        }
+        let body = Self::parse_body(pairs)?;
+        Result::Ok(
+            Lambda {
+                captures,
+                params,
+                is_variadic,
+                return_type: return_type.unwrap(),
+                body,
+            }
+        )
+    }
+
+    fn parse_macro(pair: Pair<Rule>) -> Result<Macro, String> {
+        let mut pairs = pair.into_inner();
+        pairs.next(); // `macro`
+        let name = String::from(pairs.next().unwrap().as_str());
+        let mut params = Vec::<MacroParam>::new();
+        while pairs.peek().is_some_and(|p| p.as_rule() == Rule::macroDefParam) {
+            let mut param = pairs.next().unwrap().into_inner();
+            let mut kind = MacroParamKind::Expression;
+            if param.peek().unwrap().as_rule() == Rule::macroParamKind {
+                kind = match param.next().unwrap().as_str() {
+                    "type" => MacroParamKind::Type,
+                    "ident" => MacroParamKind::Identifier,
+                    "block" => MacroParamKind::Block,
+                    _ => MacroParamKind::Expression,
+                };
+            }
+            let param_name = String::from(param.next().unwrap().as_str());
+            if params.iter().any(|p| p.name == param_name) {
+                return Result::Err(format!("The macro '{}' has two parameters named '{}'", name, param_name));
+            }
+            params.push(MacroParam { name: param_name, kind });
+        }
+        let body = Self::parse_body(pairs)?;
+        Result::Ok(Macro { name, params, body })
+    }
+
     fn parse_function_header(pair: Pair<Rule>) -> Result<FunctionHeader, String> {
         match pair.as_rule() {
             Rule::functionHeader => {
-                let is_extern = pair.as_str().starts_with("extern");
                 let mut pairs = pair.into_inner();
-                let typ = Self::parse_type(pairs.next().unwrap())?;
+                let mut attributes = Vec::<Attribute>::new();
+                while pairs.peek().unwrap().as_rule() == Rule::attribute {
+                    Self::add_attribute(&mut attributes, pairs.next().unwrap())?;
+                }
+                let typ = Self::parse_declared_type(&mut pairs)?;
                 let name = String::from(pairs.next().unwrap().as_str());
                 let mut params = Vec::<Parameter>::new();
+                let mut is_variadic = false;
                 if let Some(param_list_pairs) = pairs.next() {
-                    for p in param_list_pairs.into_inner() {
-                        params.push(Self::parse_parameter(p)?);
-                    }
+                    is_variadic = Self::parse_param_list(param_list_pairs, &mut params)?;
                 }
 
                 Result::Ok(
@@ -572,7 +1085,8 @@ impl MyMiniCParser {
                         return_type: typ,
                         name: name,
                         params: params,
-                        is_extern: is_extern,
+                        is_variadic,
+                        attributes,
                     }
                 )
             },
@@ -585,12 +1099,8 @@ impl MyMiniCParser {
                 let mut pairs = pair.into_inner();
                 let header = Self::parse_function_header(pairs.next().unwrap())?;
 
-                let mut statements = Vec::<Statement>::new();
-                for stmt in pairs {
-                    // Should all be statements
-                    statements.push(Self::parse_statement(stmt.into_inner().next().unwrap())?);
-                }
-                
+                let statements = Self::parse_body(pairs)?;
+
                 Result::Ok(
                     Function {
                         header: header,
@@ -602,16 +1112,41 @@ impl MyMiniCParser {
         }
     }
 
+    /// Parses the parameters into `params`, returning whether the list ends with `...`
+    fn parse_param_list(pair: Pair<Rule>, params: &mut Vec<Parameter>) -> Result<bool, String> {
+        let mut is_variadic = false;
+        for p in pair.into_inner() {
+            match p.as_rule() {
+                Rule::ellipsis if params.is_empty() => return Result::Err(String::from("'...' needs a named parameter before it, which 'va_start' refers to")),
+                Rule::ellipsis => is_variadic = true,
+                _ => {
+                    let param = Self::parse_parameter(p)?;
+                    // Like in C++, defaults can only be left out from the end of a positional call
+                    if param.default.is_none() && params.last().is_some_and(|p: &Parameter| p.default.is_some()) {
+                        return Result::Err(format!("Parameter '{}' needs a default value, since it follows a parameter that has one", param.name));
+                    }
+                    params.push(param);
+                },
+            }
+        }
+        Result::Ok(is_variadic)
+    }
+
     fn parse_parameter(pair: Pair<Rule>) -> Result<Parameter, String> {
         match pair.as_rule() {
             Rule::parameter => {
                 let mut pairs = pair.into_inner();
                 let typ = Self::parse_type(pairs.next().unwrap())?;
                 let name = pairs.next().unwrap().as_str();
+                let default = match pairs.next() {
+                    Some(p) => Some(Self::parse_expression(p)?),
+                    None => None,
+                };
                 Result::Ok(
                     Parameter {
                         typ: typ,
                         name: String::from(name),
+                        default,
                     }
                 )
             },
@@ -622,7 +1157,16 @@ impl MyMiniCParser {
     fn parse_struct(pair: Pair<Rule>) -> Result<Struct, String> {
         match pair.as_rule() {
             Rule::r#struct => {
-                let mut pairs = pair.into_inner();
+                let mut pairs = pair.into_inner().peekable();
+                let mut derives = Vec::<Derive>::new();
+                let mut attributes = Vec::<Attribute>::new();
+                loop {
+                    match pairs.peek().unwrap().as_rule() {
+                        Rule::derive => Self::add_derives(&mut derives, pairs.next().unwrap())?,
+                        Rule::attribute => Self::add_attribute(&mut attributes, pairs.next().unwrap())?,
+                        _ => break,
+                    }
+                }
                 let name = pairs.next().unwrap().as_str();
                 let members = Self::parse_struct_inner(pairs)?;
                 Result::Ok(
@@ -631,6 +1175,8 @@ impl MyMiniCParser {
                         members: members,
                         is_anonymous: false,
                         is_union: false,
+                        derives,
+                        attributes,
                     }
                 )
             },
@@ -643,6 +1189,8 @@ impl MyMiniCParser {
                         members: members,
                         is_anonymous: true,
                         is_union: false,
+                        derives: Vec::new(),
+                        attributes: Vec::new(),
                     }
                 )
             },
@@ -654,6 +1202,10 @@ impl MyMiniCParser {
         match pair.as_rule() {
             Rule::r#union => {
                 let mut pairs = pair.into_inner();
+                let mut attributes = Vec::<Attribute>::new();
+                while pairs.peek().unwrap().as_rule() == Rule::attribute {
+                    Self::add_attribute(&mut attributes, pairs.next().unwrap())?;
+                }
                 let name = pairs.next().unwrap().as_str();
                 let members = Self::parse_struct_inner(pairs)?;
                 Result::Ok(
@@ -662,6 +1214,8 @@ impl MyMiniCParser {
                         members: members,
                         is_anonymous: false,
                         is_union: true,
+                        derives: Vec::new(),
+                        attributes,
                     }
                 )
             },
@@ -674,25 +1228,85 @@ impl MyMiniCParser {
                         members: members,
                         is_anonymous: true,
                         is_union: true,
+                        derives: Vec::new(),
+                        attributes: Vec::new(),
                     }
                 )
             },
             _ => Result::Err(String::from("Could not parse union")),
         }
     }
-    fn parse_struct_inner(pairs: Pairs<'_, Rule>) -> Result<Vec<StructMember>, String> {
+    fn add_derives(derives: &mut Vec<Derive>, pair: Pair<Rule>) -> Result<(), String> {
+        for p in pair.into_inner() {
+            let derive = match p.as_str() {
+                "eq" => Derive::Eq,
+                "hash" => Derive::Hash,
+                "debug" => Derive::Debug,
+                "clone" => Derive::Clone,
+                "json" => Derive::Json,
+                other => return Result::Err(format!("Unknown derive: '{}'", other)),
+            };
+            if derives.contains(&derive) {
+                return Result::Err(format!("Duplicate derive: '{}'", p.as_str()));
+            }
+            derives.push(derive);
+        }
+        Result::Ok(())
+    }
+
+    fn add_attribute(attributes: &mut Vec<Attribute>, pair: Pair<Rule>) -> Result<(), String> {
+        let mut pairs = pair.into_inner();
+        let name = &pairs.next().unwrap().as_str()[1..];
+        let argument = pairs.next();
+        let attribute = match (name, &argument) {
+            ("inline", None) => Attribute::Inline,
+            ("noreturn", None) => Attribute::NoReturn,
+            ("packed", None) => Attribute::Packed,
+            ("weak", None) => Attribute::Weak,
+            ("aligned", Some(arg)) if arg.as_rule() == Rule::int => {
+                match arg.as_str().parse::<usize>() {
+                    Ok(n) if n.is_power_of_two() => Attribute::Aligned(n),
+                    _ => return Result::Err(format!("'@aligned' needs a power of two, not '{}'", arg.as_str())),
+                }
+            },
+            ("section", Some(arg)) if arg.as_rule() == Rule::string => {
+                Attribute::Section(Self::parse_plain_string(arg.clone(), "The section name of '@section'")?)
+            },
+            ("inline" | "noreturn" | "packed" | "weak", Some(_)) => return Result::Err(format!("'@{}' doesn't take an argument", name)),
+            ("aligned", _) => return Result::Err(String::from("'@aligned' needs a number of bytes, e.g. '@aligned(16)'")),
+            ("section", _) => return Result::Err(String::from("'@section' needs a section name, e.g. '@section(\".text.boot\")'")),
+            _ => return Result::Err(format!("Unknown attribute: '@{}'", name)),
+        };
+        if attributes.iter().any(|a| a.name() == attribute.name()) {
+            return Result::Err(format!("Duplicate attribute: '@{}'", name));
+        }
+        attributes.push(attribute);
+        Result::Ok(())
+    }
+
+    fn parse_struct_inner<'a>(pairs: impl Iterator<Item = Pair<'a, Rule>>) -> Result<Vec<StructMember>, String> {
         let mut members = Vec::<StructMember>::new();
         for p in pairs {
             match p.as_rule() {
                 Rule::structVarDec => {
                     let mut ppairs = p.into_inner();
+                    let mut attributes = Vec::<Attribute>::new();
+                    while ppairs.peek().unwrap().as_rule() == Rule::attribute {
+                        Self::add_attribute(&mut attributes, ppairs.next().unwrap())?;
+                    }
                     let ftyp = Self::parse_type(ppairs.next().unwrap())?;
                     let fname = ppairs.next().unwrap().as_str();
+                    let array_size = match ppairs.next() {
+                        Some(size_pair) => Some(Self::parse_array_size(size_pair)?),
+                        None => None,
+                    };
                     members.push(
                         StructMember::Field(
                             StructField {
                                 name: String::from(fname),
                                 typ: ftyp,
+                                array_size,
+                                attributes,
                             }
                         )
                     );
@@ -705,12 +1319,42 @@ impl MyMiniCParser {
                     let union = Self::parse_union(p)?;
                     members.push(StructMember::AnonStruct(union))
                 },
+                Rule::directive => members.push(StructMember::Directive(Self::parse_directive(p)?)),
                 _ => return Result::Err(String::from("Could not parse inner value of struct/union")),
             }
         }
+        Directive::check_conditionals(members.iter().filter_map(|m| match m {
+            StructMember::Directive(d) => Some(d),
+            _ => None,
+        }))?;
         Result::Ok(members)
     }
 
+    /// Parses the optional array size and initial value following a variable's name
+    fn parse_var_dec_rest(pairs: Pairs<Rule>) -> Result<(Option<usize>, Option<Expression>), String> {
+        let mut array_size = None;
+        let mut init_val = None;
+        for p in pairs {
+            match p.as_rule() {
+                Rule::arraySize => array_size = Some(Self::parse_array_size(p)?),
+                Rule::embed => {
+                    let path = Self::parse_plain_string(p.into_inner().next().unwrap(), "The path of an embedded file")?;
+                    init_val = Some(Expression::new(Atom::Embed(Self::unescape(&path)), ExprTail::None));
+                },
+                _ => init_val = Some(Self::parse_expression(p)?),
+            }
+        }
+        Result::Ok((array_size, init_val))
+    }
+
+    fn parse_array_size(pair: Pair<Rule>) -> Result<usize, String> {
+        let size = pair.into_inner().next().unwrap().as_str();
+        match size.parse::<usize>() {
+            Ok(value) if value > 0 => Result::Ok(value),
+            _ => Result::Err(format!("Invalid array size: '{}'", size)),
+        }
+    }
+
     fn parse_enum(pair: Pair<Rule>) -> Result<Enum, String> {
         match pair.as_rule() {
             Rule::r#enum => {
@@ -722,6 +1366,7 @@ impl MyMiniCParser {
                         name: String::from(name),
                         entries: entries,
                         is_anonymous: false,
+                        reflected: false,
                     }
                 )
             },
@@ -733,6 +1378,7 @@ impl MyMiniCParser {
                         name: String::new(),
                         entries: entries,
                         is_anonymous: true,
+                        reflected: false,
                     }
                 )
             },
@@ -746,13 +1392,14 @@ impl MyMiniCParser {
                 Rule::enumEntry => {
                     let mut ppairs = p.into_inner();
                     let vname = ppairs.next().unwrap().as_str();
-                    let value: Option<i32>;
+                    let value: Option<Expression>;
                     if let Some(next) = ppairs.next() {
-                        value = Some(next.as_str().parse::<i32>().unwrap());
+                        value = Some(Self::parse_expression(next)?);
                     } else {
                         value = None;
                     }
-                    
+
+
                     entries.push(EnumEntry {
                         name: String::from(vname),
                         value: value,
@@ -793,10 +1440,7 @@ impl MyMiniCParser {
     fn parse_cond_body(pair: Pair<Rule>) -> Result<ConditionBody, String> {
         let mut pairs = pair.into_inner();
         let cond = Self::parse_expression(pairs.next().unwrap())?;
-        let mut body = Vec::<Statement>::new();
-        for p in pairs {
-            body.push(Self::parse_statement(p.into_inner().next().unwrap())?);
-        }
+        let body = Self::parse_body(pairs)?;
         Result::Ok(
             ConditionBody {
                 condition: cond,
@@ -815,11 +1459,7 @@ impl MyMiniCParser {
         }
         let mut els = None;
         if let Some(els_pair) = pairs.next() {
-            let mut body = Vec::<Statement>::new();
-            for p in els_pair.into_inner() {
-                body.push(Self::parse_statement(p.into_inner().next().unwrap())?);
-            }
-            els = Some(body);
+            els = Some(Self::parse_body(els_pair.into_inner())?);
         }
 
         Result::Ok(Statement::If { base: base, elseifs: elseifs, tail: els })
@@ -0,0 +1,67 @@
+/**
+ * Macros for miniC's `@attributes`, such as `@packed` or `@section("name")`.
+ * GCC and Clang get the matching `__attribute__((...))`. Other compilers get the
+ * standard C equivalent where there is one, and nothing otherwise; any of these can
+ * be defined before this header is included to support a specific compiler.
+ */
+#ifndef MC_ATTRIBUTES_H
+#define MC_ATTRIBUTES_H
+
+#if defined(__GNUC__) || defined(__clang__)
+#define MC_HAS_GNU_ATTRIBUTES 1
+#else
+#define MC_HAS_GNU_ATTRIBUTES 0
+#endif
+
+// `@inline` functions are also declared `inline`, so only the forcing is compiler-specific
+#ifndef MC_INLINE
+#if MC_HAS_GNU_ATTRIBUTES
+#define MC_INLINE __attribute__((always_inline))
+#else
+#define MC_INLINE
+#endif
+#endif
+
+#ifndef MC_NORETURN
+#if MC_HAS_GNU_ATTRIBUTES
+#define MC_NORETURN __attribute__((noreturn))
+#else
+#define MC_NORETURN _Noreturn
+#endif
+#endif
+
+// Layout attributes have no standard equivalent, so structs keep their usual padding
+// and alignment unless these are defined for the compiler in use
+#ifndef MC_PACKED
+#if MC_HAS_GNU_ATTRIBUTES
+#define MC_PACKED __attribute__((packed))
+#else
+#define MC_PACKED
+#endif
+#endif
+
+#ifndef MC_ALIGNED
+#if MC_HAS_GNU_ATTRIBUTES
+#define MC_ALIGNED(n) __attribute__((aligned(n)))
+#else
+#define MC_ALIGNED(n)
+#endif
+#endif
+
+#ifndef MC_WEAK
+#if MC_HAS_GNU_ATTRIBUTES
+#define MC_WEAK __attribute__((weak))
+#else
+#define MC_WEAK
+#endif
+#endif
+
+#ifndef MC_SECTION
+#if MC_HAS_GNU_ATTRIBUTES
+#define MC_SECTION(name) __attribute__((section(name)))
+#else
+#define MC_SECTION(name)
+#endif
+#endif
+
+#endif
@@ -0,0 +1,37 @@
+/**
+ * Closure runtime for the `mc` module, used by code generated for lambdas.
+ * A closure is a function pointer paired with the captured environment it expects
+ * as its first argument; miniC code can refer to these as e.g. `mc::closure_free`.
+ */
+#ifndef MC_CLOSURE_H
+#define MC_CLOSURE_H
+
+#include <stdlib.h>
+#include <string.h>
+
+// Any function pointer type can be converted to another and back, so this is
+// cast to the lambda's real signature before being called
+typedef void (*mod__mc__ClosureFn)(void);
+
+typedef struct Closure__struct {
+    mod__mc__ClosureFn fn;
+    void* env;
+} mod__mc__Closure;
+
+// Copies a captured environment to the heap, for closures that escape their scope
+static inline void* mod__mc__closure_env(const void* env, size_t size) {
+    void* copy = malloc(size);
+    if (copy == NULL) {
+        abort();
+    }
+    memcpy(copy, env, size);
+    return copy;
+}
+
+// Frees an escaping closure's environment. Must not be called on closures whose
+// environment lives on the stack.
+static inline void mod__mc__closure_free(mod__mc__Closure closure) {
+    free(closure.env);
+}
+
+#endif
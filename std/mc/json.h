@@ -0,0 +1,522 @@
+/**
+ * JSON runtime for the `mc` module, used by code generated for `@derive(json)`.
+ * Everything is `static inline` and named with the `mc` module's mangling, so miniC
+ * code can refer to it as e.g. `mc::Buffer` or `mc::buffer_free`.
+ */
+#ifndef MC_JSON_H
+#define MC_JSON_H
+
+#include <errno.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+// == Buffer ==
+
+typedef struct Buffer__struct {
+    char* data;
+    size_t len;
+    size_t cap;
+} mod__mc__Buffer;
+
+static inline void mod__mc__buffer_init(mod__mc__Buffer* this) {
+    this->data = NULL;
+    this->len = 0;
+    this->cap = 0;
+}
+
+static inline void mod__mc__buffer_free(mod__mc__Buffer* this) {
+    free(this->data);
+    mod__mc__buffer_init(this);
+}
+
+static inline void mod__mc__buffer_append(mod__mc__Buffer* this, const char* data, size_t len) {
+    if (this->len + len + 1 > this->cap) {
+        size_t cap = this->cap == 0 ? 64 : this->cap;
+        while (this->len + len + 1 > cap) {
+            cap *= 2;
+        }
+        char* grown = realloc(this->data, cap);
+        if (grown == NULL) {
+            abort();
+        }
+        this->data = grown;
+        this->cap = cap;
+    }
+    memcpy(this->data + this->len, data, len);
+    this->len += len;
+    // Always keep the contents NUL-terminated so they can be used as a C string
+    this->data[this->len] = '\0';
+}
+
+static inline void mod__mc__buffer_append_str(mod__mc__Buffer* this, const char* str) {
+    mod__mc__buffer_append(this, str, strlen(str));
+}
+
+// == Writing ==
+
+static inline void mod__mc__json_write_raw(mod__mc__Buffer* out, const char* text) {
+    mod__mc__buffer_append_str(out, text);
+}
+
+static inline void mod__mc__json_write_long(mod__mc__Buffer* out, long value) {
+    char text[32];
+    snprintf(text, sizeof(text), "%ld", value);
+    mod__mc__buffer_append_str(out, text);
+}
+
+static inline void mod__mc__json_write_long_long(mod__mc__Buffer* out, long long value) {
+    char text[32];
+    snprintf(text, sizeof(text), "%lld", value);
+    mod__mc__buffer_append_str(out, text);
+}
+
+static inline void mod__mc__json_write_unsigned_long_long(mod__mc__Buffer* out, unsigned long long value) {
+    char text[32];
+    snprintf(text, sizeof(text), "%llu", value);
+    mod__mc__buffer_append_str(out, text);
+}
+
+static inline void mod__mc__json_write_double(mod__mc__Buffer* out, double value) {
+    // JSON has no representation for NaN or infinities
+    if (value != value || value - value != 0) {
+        mod__mc__buffer_append_str(out, "null");
+        return;
+    }
+    char text[32];
+    snprintf(text, sizeof(text), "%.17g", value);
+    mod__mc__buffer_append_str(out, text);
+}
+
+static inline void mod__mc__json_write_bool(mod__mc__Buffer* out, _Bool value) {
+    mod__mc__buffer_append_str(out, value ? "true" : "false");
+}
+
+static inline void mod__mc__json_write_string_n(mod__mc__Buffer* out, const char* value, size_t max_len) {
+    if (value == NULL) {
+        mod__mc__buffer_append_str(out, "null");
+        return;
+    }
+    mod__mc__buffer_append(out, "\"", 1);
+    for (size_t i = 0; i < max_len && value[i] != '\0'; i++) {
+        unsigned char c = (unsigned char)value[i];
+        switch (c) {
+            case '"': mod__mc__buffer_append_str(out, "\\\""); break;
+            case '\\': mod__mc__buffer_append_str(out, "\\\\"); break;
+            case '\b': mod__mc__buffer_append_str(out, "\\b"); break;
+            case '\f': mod__mc__buffer_append_str(out, "\\f"); break;
+            case '\n': mod__mc__buffer_append_str(out, "\\n"); break;
+            case '\r': mod__mc__buffer_append_str(out, "\\r"); break;
+            case '\t': mod__mc__buffer_append_str(out, "\\t"); break;
+            default:
+                if (c < 0x20) {
+                    char escaped[8];
+                    snprintf(escaped, sizeof(escaped), "\\u%04x", c);
+                    mod__mc__buffer_append_str(out, escaped);
+                } else {
+                    mod__mc__buffer_append(out, (const char*)&value[i], 1);
+                }
+                break;
+        }
+    }
+    mod__mc__buffer_append(out, "\"", 1);
+}
+
+static inline void mod__mc__json_write_string(mod__mc__Buffer* out, const char* value) {
+    mod__mc__json_write_string_n(out, value, (size_t)-1);
+}
+
+static inline void mod__mc__json_write_char(mod__mc__Buffer* out, char value) {
+    mod__mc__json_write_string_n(out, &value, 1);
+}
+
+// == Reading ==
+
+/**
+ * Errors are sticky: once `failed` is set, every read returns a zero value and
+ * every loop helper returns false, so generated code only checks once at the end.
+ */
+typedef struct JsonReader__struct {
+    const char* cur;
+    char* key;
+    size_t key_cap;
+    _Bool expect_comma;
+    _Bool failed;
+} mod__mc__JsonReader;
+
+static inline void mod__mc__json_reader_init(mod__mc__JsonReader* this, const char* text) {
+    this->cur = text;
+    this->key = NULL;
+    this->key_cap = 0;
+    this->expect_comma = 0;
+    this->failed = text == NULL;
+}
+
+static inline void mod__mc__json_skip_whitespace(mod__mc__JsonReader* this) {
+    while (*this->cur == ' ' || *this->cur == '\n' || *this->cur == '\r' || *this->cur == '\t') {
+        this->cur++;
+    }
+}
+
+static inline _Bool mod__mc__json_fail(mod__mc__JsonReader* this) {
+    this->failed = 1;
+    return 0;
+}
+
+static inline _Bool mod__mc__json_expect(mod__mc__JsonReader* this, char c) {
+    if (this->failed) {
+        return 0;
+    }
+    mod__mc__json_skip_whitespace(this);
+    if (*this->cur != c) {
+        return mod__mc__json_fail(this);
+    }
+    this->cur++;
+    return 1;
+}
+
+static inline _Bool mod__mc__json_match_literal(mod__mc__JsonReader* this, const char* literal) {
+    size_t len = strlen(literal);
+    if (strncmp(this->cur, literal, len) != 0) {
+        return 0;
+    }
+    this->cur += len;
+    return 1;
+}
+
+/** Returns true (and consumes it) if the next value is `null` */
+static inline _Bool mod__mc__json_read_null(mod__mc__JsonReader* this) {
+    mod__mc__json_skip_whitespace(this);
+    if (!this->failed && mod__mc__json_match_literal(this, "null")) {
+        this->expect_comma = 1;
+        return 1;
+    }
+    return 0;
+}
+
+/**
+ * Reads a string into `dst`, growing it with realloc if `growable` is set. Otherwise, it
+ * fails if the string doesn't fit in `cap` bytes, and is only NUL-terminated if there's
+ * room (matching how fixed-size char arrays are written)
+ */
+static inline _Bool mod__mc__json_read_string_raw(mod__mc__JsonReader* this, char** dst, size_t* cap, _Bool growable) {
+    if (!mod__mc__json_expect(this, '"')) {
+        return 0;
+    }
+    size_t len = 0;
+    while (*this->cur != '"') {
+        char c = *this->cur;
+        if (c == '\0') {
+            return mod__mc__json_fail(this);
+        }
+        this->cur++;
+        if (c == '\\') {
+            char escape = *this->cur;
+            this->cur++;
+            switch (escape) {
+                case '"': c = '"'; break;
+                case '\\': c = '\\'; break;
+                case '/': c = '/'; break;
+                case 'b': c = '\b'; break;
+                case 'f': c = '\f'; break;
+                case 'n': c = '\n'; break;
+                case 'r': c = '\r'; break;
+                case 't': c = '\t'; break;
+                case 'u': {
+                    // Only code points that fit in a single byte are supported
+                    char hex[5] = { 0 };
+                    for (int i = 0; i < 4; i++) {
+                        if (this->cur[i] == '\0') {
+                            return mod__mc__json_fail(this);
+                        }
+                        hex[i] = this->cur[i];
+                    }
+                    this->cur += 4;
+                    char* end;
+                    long code = strtol(hex, &end, 16);
+                    if (*end != '\0' || code > 0xFF) {
+                        return mod__mc__json_fail(this);
+                    }
+                    c = (char)code;
+                    break;
+                }
+                default:
+                    return mod__mc__json_fail(this);
+            }
+        }
+        if (growable ? len + 1 >= *cap : len >= *cap) {
+            if (!growable) {
+                return mod__mc__json_fail(this);
+            }
+            size_t grown_cap = *cap == 0 ? 16 : *cap * 2;
+            char* grown = realloc(*dst, grown_cap);
+            if (grown == NULL) {
+                abort();
+            }
+            *dst = grown;
+            *cap = grown_cap;
+        }
+        (*dst)[len] = c;
+        len++;
+    }
+    this->cur++;
+    if (growable && *cap == 0) {
+        *dst = malloc(1);
+        *cap = 1;
+    }
+    if (len < *cap) {
+        (*dst)[len] = '\0';
+    }
+    this->expect_comma = 1;
+    return 1;
+}
+
+static inline _Bool mod__mc__json_begin_object(mod__mc__JsonReader* this) {
+    this->expect_comma = 0;
+    return mod__mc__json_expect(this, '{');
+}
+
+/** Moves to the next field of an object, storing its key; returns false at the end of the object */
+static inline _Bool mod__mc__json_next_field(mod__mc__JsonReader* this) {
+    if (this->failed) {
+        return 0;
+    }
+    mod__mc__json_skip_whitespace(this);
+    if (*this->cur == '}') {
+        this->cur++;
+        this->expect_comma = 1;
+        return 0;
+    }
+    if (this->expect_comma && !mod__mc__json_expect(this, ',')) {
+        return 0;
+    }
+    if (!mod__mc__json_read_string_raw(this, &this->key, &this->key_cap, 1)) {
+        return 0;
+    }
+    return mod__mc__json_expect(this, ':');
+}
+
+static inline _Bool mod__mc__json_field_is(mod__mc__JsonReader* this, const char* name) {
+    return strcmp(this->key, name) == 0;
+}
+
+/**
+ * Moves to the next element of an array, where `index` counts up from 0; returns false
+ * at the end of the array, and fails if there are more than `capacity` elements
+ */
+static inline _Bool mod__mc__json_next_element(mod__mc__JsonReader* this, size_t index, size_t capacity) {
+    if (index == 0) {
+        this->expect_comma = 0;
+        if (!mod__mc__json_expect(this, '[')) {
+            return 0;
+        }
+    }
+    if (this->failed) {
+        return 0;
+    }
+    mod__mc__json_skip_whitespace(this);
+    if (*this->cur == ']') {
+        this->cur++;
+        this->expect_comma = 1;
+        return 0;
+    }
+    if (this->expect_comma && !mod__mc__json_expect(this, ',')) {
+        return 0;
+    }
+    if (index >= capacity) {
+        return mod__mc__json_fail(this);
+    }
+    return 1;
+}
+
+static inline double mod__mc__json_read_double(mod__mc__JsonReader* this) {
+    if (this->failed) {
+        return 0;
+    }
+    mod__mc__json_skip_whitespace(this);
+    char* end;
+    double value = strtod(this->cur, &end);
+    if (end == this->cur) {
+        mod__mc__json_fail(this);
+        return 0;
+    }
+    this->cur = end;
+    this->expect_comma = 1;
+    return value;
+}
+
+// Whether `end` is the end of an integer that was read from `this->cur`, and it fit
+static inline _Bool mod__mc__json_integer_end(mod__mc__JsonReader* this, const char* end) {
+    if (end == this->cur || *end == '.' || *end == 'e' || *end == 'E' || errno == ERANGE) {
+        return mod__mc__json_fail(this);
+    }
+    this->cur = end;
+    this->expect_comma = 1;
+    return 1;
+}
+
+static inline long mod__mc__json_read_long(mod__mc__JsonReader* this) {
+    if (this->failed) {
+        return 0;
+    }
+    mod__mc__json_skip_whitespace(this);
+    char* end;
+    errno = 0;
+    long value = strtol(this->cur, &end, 10);
+    return mod__mc__json_integer_end(this, end) ? value : 0;
+}
+
+static inline long long mod__mc__json_read_long_long(mod__mc__JsonReader* this) {
+    if (this->failed) {
+        return 0;
+    }
+    mod__mc__json_skip_whitespace(this);
+    char* end;
+    errno = 0;
+    long long value = strtoll(this->cur, &end, 10);
+    return mod__mc__json_integer_end(this, end) ? value : 0;
+}
+
+static inline unsigned long long mod__mc__json_read_unsigned_long_long(mod__mc__JsonReader* this) {
+    if (this->failed) {
+        return 0;
+    }
+    mod__mc__json_skip_whitespace(this);
+    // `strtoull` would accept a negative number and wrap it around
+    if (*this->cur == '-') {
+        mod__mc__json_fail(this);
+        return 0;
+    }
+    char* end;
+    errno = 0;
+    unsigned long long value = strtoull(this->cur, &end, 10);
+    return mod__mc__json_integer_end(this, end) ? value : 0;
+}
+
+static inline _Bool mod__mc__json_read_bool(mod__mc__JsonReader* this) {
+    if (this->failed) {
+        return 0;
+    }
+    mod__mc__json_skip_whitespace(this);
+    if (mod__mc__json_match_literal(this, "true")) {
+        this->expect_comma = 1;
+        return 1;
+    }
+    if (!mod__mc__json_match_literal(this, "false")) {
+        mod__mc__json_fail(this);
+    }
+    this->expect_comma = 1;
+    return 0;
+}
+
+/** Reads a string or `null`; the result is allocated with malloc and owned by the caller */
+static inline char* mod__mc__json_read_string(mod__mc__JsonReader* this) {
+    if (this->failed || mod__mc__json_read_null(this)) {
+        return NULL;
+    }
+    char* value = NULL;
+    size_t cap = 0;
+    if (!mod__mc__json_read_string_raw(this, &value, &cap, 1)) {
+        free(value);
+        return NULL;
+    }
+    return value;
+}
+
+/** Reads a string into a fixed-size char array, failing if it doesn't fit */
+static inline void mod__mc__json_read_string_into(mod__mc__JsonReader* this, char* dst, size_t cap) {
+    mod__mc__json_read_string_raw(this, &dst, &cap, 0);
+}
+
+static inline char mod__mc__json_read_char(mod__mc__JsonReader* this) {
+    char value = '\0';
+    mod__mc__json_read_string_into(this, &value, 1);
+    return value;
+}
+
+/** Reads an enum variant by name, returning its index in `names` */
+static inline size_t mod__mc__json_read_enum(mod__mc__JsonReader* this, const char* const* names, size_t count) {
+    char* name = NULL;
+    size_t cap = 0;
+    if (!mod__mc__json_read_string_raw(this, &name, &cap, 1)) {
+        free(name);
+        return 0;
+    }
+    for (size_t i = 0; i < count; i++) {
+        if (strcmp(names[i], name) == 0) {
+            free(name);
+            return i;
+        }
+    }
+    free(name);
+    mod__mc__json_fail(this);
+    return 0;
+}
+
+/** How deeply objects and arrays in skipped values may be nested, so that untrusted input can't overflow the stack */
+#ifndef MC_JSON_MAX_DEPTH
+#define MC_JSON_MAX_DEPTH 64
+#endif
+
+static inline _Bool mod__mc__json_skip_nested(mod__mc__JsonReader* this, size_t depth);
+
+/** Skips over a value of any kind, e.g. for unknown fields. Fails if it is nested too deeply. */
+static inline _Bool mod__mc__json_skip_value(mod__mc__JsonReader* this) {
+    return mod__mc__json_skip_nested(this, 0);
+}
+
+/** Skips over a value inside `depth` objects or arrays */
+static inline _Bool mod__mc__json_skip_nested(mod__mc__JsonReader* this, size_t depth) {
+    if (this->failed) {
+        return 0;
+    }
+    mod__mc__json_skip_whitespace(this);
+    if ((*this->cur == '{' || *this->cur == '[') && depth >= MC_JSON_MAX_DEPTH) {
+        return mod__mc__json_fail(this);
+    }
+    switch (*this->cur) {
+        case '{':
+            mod__mc__json_begin_object(this);
+            while (mod__mc__json_next_field(this)) {
+                mod__mc__json_skip_nested(this, depth + 1);
+            }
+            break;
+        case '[':
+            for (size_t i = 0; mod__mc__json_next_element(this, i, (size_t)-1); i++) {
+                mod__mc__json_skip_nested(this, depth + 1);
+            }
+            break;
+        case '"':
+            free(mod__mc__json_read_string(this));
+            break;
+        case 't':
+        case 'f':
+            mod__mc__json_read_bool(this);
+            break;
+        case 'n':
+            if (!mod__mc__json_read_null(this)) {
+                mod__mc__json_fail(this);
+            }
+            break;
+        default:
+            mod__mc__json_read_double(this);
+            break;
+    }
+    return !this->failed;
+}
+
+/** Releases the reader's memory, returning whether everything was read successfully */
+static inline _Bool mod__mc__json_reader_finish(mod__mc__JsonReader* this) {
+    free(this->key);
+    this->key = NULL;
+    this->key_cap = 0;
+    if (!this->failed) {
+        mod__mc__json_skip_whitespace(this);
+        if (*this->cur != '\0') {
+            this->failed = 1;
+        }
+    }
+    return !this->failed;
+}
+
+#endif
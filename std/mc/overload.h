@@ -0,0 +1,24 @@
+/**
+ * Support for the macros that miniC generates for overloaded functions when asked to, which
+ * let C code call e.g. `print(x)` and get `print__int` or `print__double` depending on `x`.
+ * `MC_OVERLOAD(name, args...)` expands to `name__mc_N(args...)` for N arguments, which then
+ * chooses an overload with `_Generic`. Up to 8 arguments are supported.
+ */
+#ifndef MC_OVERLOAD_H
+#define MC_OVERLOAD_H
+
+#define MC_OVERLOAD_COUNT(...) MC_OVERLOAD_COUNT_(__VA_ARGS__, 8, 7, 6, 5, 4, 3, 2, 1, _)
+#define MC_OVERLOAD_COUNT_(_1, _2, _3, _4, _5, _6, _7, _8, n, ...) n
+
+#define MC_OVERLOAD_CAT(a, b) MC_OVERLOAD_CAT_(a, b)
+#define MC_OVERLOAD_CAT_(a, b) a##b
+
+#define MC_OVERLOAD(name, ...) MC_OVERLOAD_CAT(name##__mc_, MC_OVERLOAD_COUNT(__VA_ARGS__))(__VA_ARGS__)
+
+/**
+ * Every branch of a `_Generic` must compile, even ones that aren't chosen, so the nested ones
+ * for later arguments fall back to this. It is never defined, so calling it fails to link.
+ */
+void mc_overload_no_match();
+
+#endif
@@ -0,0 +1,32 @@
+/**
+ * Slice runtime for the `mc` module. Each slice type `T[]` is a struct of a pointer `ptr` to
+ * its first element and its length `len`, defined by the generated code along with helpers
+ * that check indices and ranges with the functions below.
+ */
+#ifndef MC_SLICE_H
+#define MC_SLICE_H
+
+#include <stddef.h>
+#include <stdio.h>
+#include <stdlib.h>
+
+// Aborts unless `index` is inside a slice of length `len`. `source` is the expression as it was
+// written, like `values[i] in sum`, while `file` and `line` point at the generated C, since that's
+// all the C preprocessor knows
+static inline size_t mod__mc__slice_check_index(size_t index, size_t len, const char* source, const char* file, int line) {
+    if (index >= len) {
+        fprintf(stderr, "%s:%d: index %zu is out of range for a slice of length %zu (%s)\n", file, line, index, len, source);
+        abort();
+    }
+    return index;
+}
+
+// Aborts unless `start..end` is a range inside a slice of length `len`
+static inline void mod__mc__slice_check_range(size_t start, size_t end, size_t len, const char* source, const char* file, int line) {
+    if (start > end || end > len) {
+        fprintf(stderr, "%s:%d: range %zu..%zu is out of range for a slice of length %zu (%s)\n", file, line, start, end, len, source);
+        abort();
+    }
+}
+
+#endif
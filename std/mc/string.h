@@ -0,0 +1,40 @@
+/**
+ * String runtime for the `mc` module, used by code generated for interpolated strings.
+ * miniC code can also call it directly as `mc::string(format, ...)`.
+ */
+#ifndef MC_STRING_H
+#define MC_STRING_H
+
+#include <stdarg.h>
+#include <stdio.h>
+#include <stdlib.h>
+
+// Lets GCC and Clang check the arguments against the format
+#ifndef MC_PRINTF_FORMAT
+#if defined(__GNUC__) || defined(__clang__)
+#define MC_PRINTF_FORMAT __attribute__((format(printf, 1, 2)))
+#else
+#define MC_PRINTF_FORMAT
+#endif
+#endif
+
+// Formats like `printf` into a new allocation, which the caller frees
+static inline MC_PRINTF_FORMAT char* mod__mc__string(const char* format, ...) {
+    va_list args;
+    va_start(args, format);
+    int len = vsnprintf(NULL, 0, format, args);
+    va_end(args);
+    if (len < 0) {
+        abort();
+    }
+    char* result = malloc((size_t)len + 1);
+    if (result == NULL) {
+        abort();
+    }
+    va_start(args, format);
+    vsnprintf(result, (size_t)len + 1, format, args);
+    va_end(args);
+    return result;
+}
+
+#endif
@@ -0,0 +1,63 @@
+/**
+ * Test runtime for the `mc` module, used by the `main` that test mode generates. Each `test`
+ * block becomes a function, which runs until an `assert` in it fails and jumps back to the
+ * runner, so that the remaining tests still run.
+ */
+#ifndef MC_TEST_H
+#define MC_TEST_H
+
+#include <setjmp.h>
+#include <stddef.h>
+#include <stdio.h>
+#include <string.h>
+
+typedef struct mod__mc__Test {
+    const char* name;
+    void (*run)(void);
+} mod__mc__Test;
+
+// Where a failed assertion jumps back to, and the name of the test that is running
+static jmp_buf mod__mc__test_failure;
+static const char* mod__mc__test_current;
+
+// `assert(condition)` in a test block, which also reports the condition as written
+#define mod__mc__test_assert(condition) mod__mc__test_check((condition) != 0, #condition, __FILE__, __LINE__)
+
+// Fails the running test unless `passed`
+static inline void mod__mc__test_check(int passed, const char* condition, const char* file, int line) {
+    if (!passed) {
+        printf("FAIL %s\n    %s:%d: assertion failed: %s\n", mod__mc__test_current, file, line, condition);
+        longjmp(mod__mc__test_failure, 1);
+    }
+}
+
+// Runs the tests whose names contain `filter`, or all of them if it is NULL, and prints a
+// summary. Returns the exit status, which is 1 if a test failed.
+static inline int mod__mc__run_tests(const mod__mc__Test* tests, size_t count, const char* filter) {
+    // Changed between `setjmp` and `longjmp`, so they have to be kept in memory
+    volatile size_t passed = 0;
+    volatile size_t failed = 0;
+    size_t skipped = 0;
+    for (size_t i = 0; i < count; i++) {
+        if (filter != NULL && strstr(tests[i].name, filter) == NULL) {
+            skipped++;
+            continue;
+        }
+        mod__mc__test_current = tests[i].name;
+        if (setjmp(mod__mc__test_failure) == 0) {
+            tests[i].run();
+            printf("PASS %s\n", tests[i].name);
+            passed++;
+        } else {
+            failed++;
+        }
+    }
+    printf("\n%zu passed, %zu failed", (size_t)passed, (size_t)failed);
+    if (skipped > 0) {
+        printf(", %zu filtered out", skipped);
+    }
+    printf("\n");
+    return failed == 0 ? 0 : 1;
+}
+
+#endif
@@ -0,0 +1,65 @@
+#include <stdio.h>
+typedef enum Flag__enum {
+    READ = 1,
+    WRITE = 2,
+    EXEC = 4
+} Flag;
+const char* classify(int code, int offset) {
+    switch (code+offset) {
+        case 0:
+            return "zero";
+        case EOF:
+            return "end";
+        case 1:
+        case 2:
+        case 3:
+            return "small";
+        case 8 ... 12:
+        case 'a' ... 'z':
+            return "medium";
+        case READ|WRITE|EXEC:
+        case 1<<5:
+            return "flags";
+        default:
+            return "other";
+    }
+
+}
+
+int weight(char grade, int bonus) {
+    int total = 0;
+    switch (grade) {
+        case 'A':
+            total = 4;
+            break;
+        case 'B':
+        case 'C':
+            total = 2;
+            if (bonus>0) {
+                total++;
+            }
+            break;
+        case 'D':
+        case 'F':
+            return 0;
+        default:
+            total = -1;
+    }
+
+    return total;
+}
+
+int is_vowel(char c) {
+    switch (c) {
+        case 'a':
+        case 'e':
+        case 'i':
+        case 'o':
+            return 1;
+        case 'y':
+        default:
+            return 0;
+    }
+
+}
+
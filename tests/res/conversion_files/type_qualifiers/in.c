@@ -0,0 +1,32 @@
+#include <stddef.h>
+
+typedef unsigned long long u64;
+typedef const char* const Name;
+typedef int structure_t;
+
+struct Buffer {
+    unsigned char bytes[16];
+    const char* label;
+    volatile unsigned short flags;
+    long double scale;
+    char* const* views;
+};
+
+static const int limit = 8;
+extern _Thread_local unsigned int counter;
+
+static inline unsigned long long widen(const unsigned int value) {
+    return (unsigned long long) value;
+}
+
+const char* first_label(const Buffer* restrict buffer, size_t count) {
+    register int i = 0;
+    char const* label = buffer->label;
+    const char* const fallback = "none";
+    unsigned long total = sizeof(unsigned long long) + sizeof(const Buffer*);
+    structure_t unused = (signed char) total;
+    if (count == 0) {
+        return fallback;
+    }
+    return label;
+}
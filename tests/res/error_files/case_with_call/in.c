@@ -0,0 +1,12 @@
+int limit() {
+    return 10;
+}
+
+int bucket(int x) {
+    switch (x) {
+        case 1 ... limit():
+            return 1;
+        default:
+            return 0;
+    }
+}
@@ -0,0 +1,7 @@
+// Under LLP64 longs are only 4 bytes, even though pointers take 8
+static_assert(sizeof(void*) == 8, "pointers are 64-bit");
+static_assert(sizeof(long) == 8, "long holds a pointer");
+
+int main() {
+    return 0;
+}
@@ -0,0 +1,6 @@
+// A closure can't be compared, so `eq` can't be derived
+@derive(eq)
+struct Button {
+    int id;
+    closure(int) => void on_click;
+};
@@ -0,0 +1,15 @@
+#include <stdio.h>
+
+int main() {
+    int tries = 0;
+retry:
+    tries++;
+    if (tries < 3) {
+        goto retry;
+    }
+    puts("done");
+done:
+    puts("still done");
+done:
+    return 0;
+}
@@ -0,0 +1,8 @@
+#include <stdint.h>
+
+// Each element would be initialized from a single byte of the file
+const uint32_t table[] = embed("table.bin");
+
+int main() {
+    return table[0];
+}
@@ -0,0 +1,6 @@
+closure(int) => int make_adder(int a) {
+    int local = a;
+    return [&local](int x) => int {
+        return x + local;
+    };
+}
@@ -0,0 +1,8 @@
+#include <string.h>
+
+void count_down(int n) {
+    // The string would have to be built and freed at every iteration
+    while (strlen($"{n}") > 1) {
+        n = n / 10;
+    }
+}
@@ -0,0 +1,17 @@
+macro max(a, b) {
+    a > b ? a : b;
+}
+
+int next_id(int* id) {
+    *id = *id + 1;
+    return *id;
+}
+
+int main() {
+    int id = 0;
+    // The condition runs again on every iteration, so the argument can't be evaluated before the loop
+    while (max!(next_id(&id), 2) < 5) {
+        id++;
+    }
+    return id;
+}
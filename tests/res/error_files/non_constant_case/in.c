@@ -0,0 +1,11 @@
+int matches(int x, int y) {
+    switch (x) {
+        case 0:
+            return 0;
+        // Only constants can label a case, even if C compilers accepted this one
+        case y:
+            return 1;
+        default:
+            return 2;
+    }
+}
@@ -0,0 +1,14 @@
+#include <stddef.h>
+
+int length(const char* text) {
+    int n = 0;
+    while (text[n] != 0) {
+        n++;
+    }
+    return n;
+}
+
+int main() {
+    // Plain pointers can't be null with non-null pointers turned on
+    return length(NULL);
+}
@@ -0,0 +1,12 @@
+#include <stddef.h>
+
+struct Node {
+    int value;
+    Node?* next;
+};
+
+Node* next_of(Node* node) {
+    // A fallback of `NULL` leaves the result nullable
+    Node* next = node->next ?? NULL;
+    return next;
+}
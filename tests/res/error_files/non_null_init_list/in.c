@@ -0,0 +1,18 @@
+#include <stddef.h>
+
+struct Range {
+    const char* start;
+    const char* end;
+};
+
+struct Range find(const char* text, char c) {
+    const char?* found = NULL;
+    for (const char* p = text; *p != 0; p++) {
+        if (*p == c) {
+            found = p;
+        }
+    }
+    // `found` sets the `end` field, which can't be null
+    struct Range range = {text, found};
+    return range;
+}
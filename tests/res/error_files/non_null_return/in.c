@@ -0,0 +1,8 @@
+#include <stddef.h>
+
+int* first(int?* values, int count) {
+    if (count == 0) {
+        return NULL;
+    }
+    return values;
+}
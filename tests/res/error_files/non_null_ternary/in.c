@@ -0,0 +1,13 @@
+#include <stddef.h>
+
+struct Node {
+    int value;
+    Node?* next;
+};
+
+Node* second(Node* head) {
+    Node?* next = head->next;
+    // Either branch may be taken, and the second one is null
+    Node* result = next ? next : NULL;
+    return result;
+}
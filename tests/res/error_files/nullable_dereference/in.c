@@ -0,0 +1,9 @@
+struct Node {
+    int value;
+    Node?* next;
+};
+
+int second(Node* head) {
+    // `next` may be null, and nothing checks it first
+    return head->next->value;
+}
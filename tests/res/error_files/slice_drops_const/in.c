@@ -0,0 +1,10 @@
+const int limits[4] = {10, 20, 30, 40};
+
+int first(int[] values) {
+    return values[0];
+}
+
+int main() {
+    const int[] all = limits;
+    return first(all[1..3]);
+}
@@ -0,0 +1,10 @@
+module sl {
+    enum Light {
+        OFF,
+        ON
+    };
+}
+
+int main() {
+    return sl::Light::LENGTH;
+}
@@ -0,0 +1,10 @@
+int count_positive(int* values, int count) {
+    int total = 0;
+    for (int i = 0; i < count; i++) {
+        if (values[i] > 0) {
+            total++;
+        }
+    }
+finish:
+    return total;
+}
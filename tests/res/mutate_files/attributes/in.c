@@ -0,0 +1,58 @@
+#include <stdint.h>
+#include <stdlib.h>
+
+@packed
+struct WireHeader {
+    uint8_t kind;
+    uint32_t length;
+    uint16_t checksum;
+};
+
+@aligned(16)
+struct Vector {
+    float x;
+    float y;
+    float z;
+};
+
+struct Slot {
+    char tag;
+    @aligned(8) int value;
+    @packed uint64_t stamp;
+};
+
+@packed
+union Register {
+    uint32_t word;
+    uint8_t bytes[3];
+};
+
+@derive(eq) @aligned(8)
+struct Pair {
+    int a;
+    int b;
+};
+
+static_assert(sizeof(WireHeader) == 7, "WireHeader has no padding");
+static_assert(sizeof(Vector) == 16, "Vector fills a 16 byte line");
+static_assert(sizeof(Slot) == 24, "Slot keeps its aligned value");
+static_assert(sizeof(Register) == 4, "Register is one word");
+
+@noreturn
+void fail(const char* message);
+
+@inline
+static int square(int x) {
+    return x * x;
+}
+
+@weak @section(".text.hooks")
+void on_start() {
+}
+
+module io {
+    @noreturn
+    void panic(int code) {
+        exit(code);
+    }
+}
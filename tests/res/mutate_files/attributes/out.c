@@ -0,0 +1,54 @@
+#include "mc/attributes.h"
+#include <string.h>
+#include <stdint.h>
+#include <stdlib.h>
+typedef struct MC_PACKED WireHeader__struct {
+    uint8_t kind;
+    uint32_t length;
+    uint16_t checksum;
+} WireHeader;
+typedef struct MC_ALIGNED(16) Vector__struct {
+    float x;
+    float y;
+    float z;
+} Vector;
+typedef struct Slot__struct {
+    char tag;
+    MC_ALIGNED(8) int value;
+    MC_PACKED uint64_t stamp;
+} Slot;
+typedef union MC_PACKED Register__union {
+    uint32_t word;
+    uint8_t bytes[3];
+} Register;
+typedef struct MC_ALIGNED(8) Pair__struct {
+    int a;
+    int b;
+} Pair;
+_Bool Pair__eq(Pair* this, Pair* other) {
+    if (this->a!=other->a) {
+        return 0;
+    }
+    if (this->b!=other->b) {
+        return 0;
+    }
+    return 1;
+}
+
+_Static_assert(sizeof(WireHeader)==7, "WireHeader has no padding");
+_Static_assert(sizeof(Vector)==16, "Vector fills a 16 byte line");
+_Static_assert(sizeof(Slot)==24, "Slot keeps its aligned value");
+_Static_assert(sizeof(Register)==4, "Register is one word");
+MC_NORETURN void fail(const char* message);
+MC_INLINE static inline int square(int x) {
+    return x*x;
+}
+
+MC_WEAK MC_SECTION(".text.hooks") void on_start() {
+}
+
+MC_NORETURN void mod__io__panic(int code) {
+    exit(code);
+}
+
+
@@ -0,0 +1,21 @@
+#include <stdio.h>
+
+int last(int[] values) {
+    return values[values.len - 1];
+}
+
+int sum(int[] values) {
+    int total = 0;
+    for (size_t i = 0; i < values.len; i++) {
+        total += values[i];
+    }
+    return total;
+}
+
+int main() {
+    int numbers[4] = {1, 2, 3, 4};
+    int[] tail = numbers[2..4];
+    printf("%d %d %d\n", last(numbers), sum(tail), numbers[1]);
+    // Out of range, so this aborts instead of reading past the array
+    return tail[2];
+}
@@ -0,0 +1,37 @@
+#include "mc/slice.h"
+#include <stdio.h>
+#ifndef mod__mc__Slice__int__defined
+#define mod__mc__Slice__int__defined
+typedef struct mod__mc__Slice__int__struct {
+    int* ptr;
+    size_t len;
+} mod__mc__Slice__int;
+static inline int* mod__mc__Slice__int__at(mod__mc__Slice__int s, size_t index, const char* source, const char* file, int line) {
+    return s.ptr+mod__mc__slice_check_index(index, s.len, source, file, line);
+}
+
+static inline mod__mc__Slice__int mod__mc__Slice__int__slice(mod__mc__Slice__int s, size_t start, size_t end, const char* source, const char* file, int line) {
+    mod__mc__slice_check_range(start, end, s.len, source, file, line);
+    return (mod__mc__Slice__int){s.ptr+start, end-start};
+}
+
+#endif
+int last(mod__mc__Slice__int values) {
+    return (*mod__mc__Slice__int__at(values, values.len-1, "values[values.len-1] in last", __FILE__, __LINE__));
+}
+
+int sum(mod__mc__Slice__int values) {
+    int total = 0;
+    for (size_t i = 0;i<values.len;i++) {
+        total += (*mod__mc__Slice__int__at(values, i, "values[i] in sum", __FILE__, __LINE__));
+    }
+    return total;
+}
+
+int main() {
+    int numbers[4] = {1, 2, 3, 4};
+    mod__mc__Slice__int tail = mod__mc__Slice__int__slice((mod__mc__Slice__int){numbers, 4}, 2, 4, "numbers[2..4] in main", __FILE__, __LINE__);
+    printf("%d %d %d\n", last((mod__mc__Slice__int){numbers, 4}), sum(tail), numbers[1]);
+    return (*mod__mc__Slice__int__at(tail, 2, "tail[2] in main", __FILE__, __LINE__));
+}
+
@@ -0,0 +1,53 @@
+#include <stdio.h>
+
+typedef closure(int) => void Handler;
+
+struct Button {
+    Handler on_click;
+};
+
+void each(int* values, int count, closure(int) => void f) {
+    for (int i in 0..count) {
+        f(values[i]);
+    }
+}
+
+Button make_button(int id) {
+    Button button = {[id](int clicks) => void {
+        printf("button %d clicked %d times\n", id, clicks);
+    }};
+    return button;
+}
+
+escaping closure(int) => int adder(int amount) {
+    return [amount](int x) => int {
+        return x + amount;
+    };
+}
+
+int sum(int* values, int count) {
+    int total = 0;
+    each(values, count, [&total](int v) => void {
+        total += v;
+    });
+    closure(int) => int twice = [](int x) => int {
+        return x * 2;
+    };
+    int scale = 3;
+    closure(int) => void add_scaled = [&total, scale, twice](int v) => void {
+        total = total + twice(v) * scale;
+    };
+    each(values, count, add_scaled);
+    return total;
+}
+
+module events {
+    void run(escaping closure() => void callback) {
+        callback();
+    }
+    void fire(int times) {
+        events::run([times]() => void {
+            printf("%d\n", times);
+        });
+    }
+}
@@ -0,0 +1,97 @@
+#include "mc/closure.h"
+#include <stdio.h>
+typedef mod__mc__Closure Handler;
+typedef struct Button__struct {
+    Handler on_click;
+} Button;
+void each(int* values, int count, mod__mc__Closure f) {
+    for (int i = 0, __mc_end_i = count; i < __mc_end_i; i++) {
+        ((void (*)(void*, int))f.fn)(f.env, values[i]);
+    }
+}
+
+typedef struct make_button__lambda0__env__struct {
+    int id;
+} make_button__lambda0__env;
+void make_button__lambda0(void* __mc_env, int clicks);
+Button make_button(int id) {
+    Button button = {((mod__mc__Closure){(mod__mc__ClosureFn)make_button__lambda0, mod__mc__closure_env(&(make_button__lambda0__env){id}, sizeof(make_button__lambda0__env))})};
+    return button;
+}
+
+void make_button__lambda0(void* __mc_env, int clicks) {
+    make_button__lambda0__env* __mc_self = __mc_env;
+    int id = __mc_self->id;
+    printf("button %d clicked %d times\n", id, clicks);
+}
+
+typedef struct adder__lambda0__env__struct {
+    int amount;
+} adder__lambda0__env;
+int adder__lambda0(void* __mc_env, int x);
+mod__mc__Closure adder(int amount) {
+    return (mod__mc__Closure){(mod__mc__ClosureFn)adder__lambda0, mod__mc__closure_env(&(adder__lambda0__env){amount}, sizeof(adder__lambda0__env))};
+}
+
+int adder__lambda0(void* __mc_env, int x) {
+    adder__lambda0__env* __mc_self = __mc_env;
+    int amount = __mc_self->amount;
+    return x+amount;
+}
+
+typedef struct sum__lambda0__env__struct {
+    int* total;
+} sum__lambda0__env;
+void sum__lambda0(void* __mc_env, int v);
+int sum__lambda1(void* __mc_env, int x);
+typedef struct sum__lambda2__env__struct {
+    int* total;
+    int scale;
+    mod__mc__Closure twice;
+} sum__lambda2__env;
+void sum__lambda2(void* __mc_env, int v);
+int sum(int* values, int count) {
+    int total = 0;
+    each(values, count, (mod__mc__Closure){(mod__mc__ClosureFn)sum__lambda0, &(sum__lambda0__env){&total}});
+    mod__mc__Closure twice = (mod__mc__Closure){(mod__mc__ClosureFn)sum__lambda1, 0};
+    int scale = 3;
+    mod__mc__Closure add_scaled = (mod__mc__Closure){(mod__mc__ClosureFn)sum__lambda2, &(sum__lambda2__env){&total, scale, twice}};
+    each(values, count, add_scaled);
+    return total;
+}
+
+void sum__lambda0(void* __mc_env, int v) {
+    sum__lambda0__env* __mc_self = __mc_env;
+    (*__mc_self->total) += v;
+}
+
+int sum__lambda1(void* __mc_env, int x) {
+    return x*2;
+}
+
+void sum__lambda2(void* __mc_env, int v) {
+    sum__lambda2__env* __mc_self = __mc_env;
+    int scale = __mc_self->scale;
+    mod__mc__Closure twice = __mc_self->twice;
+    (*__mc_self->total) = (*__mc_self->total)+((int (*)(void*, int))twice.fn)(twice.env, v)*scale;
+}
+
+void mod__events__run(mod__mc__Closure callback) {
+    ((void (*)(void*))callback.fn)(callback.env);
+}
+
+typedef struct fire__lambda0__env__struct {
+    int times;
+} mod__events__fire__lambda0__env;
+void mod__events__fire__lambda0(void* __mc_env);
+void mod__events__fire(int times) {
+    mod__events__run((mod__mc__Closure){(mod__mc__ClosureFn)mod__events__fire__lambda0, mod__mc__closure_env(&(mod__events__fire__lambda0__env){times}, sizeof(mod__events__fire__lambda0__env))});
+}
+
+void mod__events__fire__lambda0(void* __mc_env) {
+    mod__events__fire__lambda0__env* __mc_self = __mc_env;
+    int times = __mc_self->times;
+    printf("%d\n", times);
+}
+
+
@@ -0,0 +1,50 @@
+#include <stdint.h>
+
+const int KIB = 1024;
+const int BUFFER_SIZE = 4 * KIB + 16;
+const unsigned int MASK = ~(unsigned int)0;
+const double RATIO = 1 / 3.0;
+const long BIG = (long)KIB * KIB * KIB * 4;
+const int OFFSET = -KIB / 4;
+
+enum Flag {
+    READ = 1 << 0,
+    WRITE = 1 << 1,
+    EXEC = 1 << 2,
+    ALL = READ | WRITE | EXEC,
+    NEXT
+};
+
+typedef enum {
+    WIDTH = BUFFER_SIZE / 64,
+    HEIGHT = WIDTH / 2
+} Dimension;
+
+struct Header {
+    uint32_t magic;
+    uint8_t version;
+    uint64_t length;
+};
+
+struct Packet {
+    char kind;
+    Header header;
+    short checksums[3];
+    union {
+        int code;
+        char bytes[5];
+    };
+};
+
+static_assert(sizeof(Header) == 16, "Header must stay 16 bytes");
+static_assert(sizeof(Packet) == 40, "Packet layout changed");
+static_assert(Flag::COUNT == 5, "every flag is listed");
+static_assert(ALL == 7 && NEXT == 8, "flags are contiguous");
+static_assert(sizeof(long) == 8 ? BIG > 0 : 1, "longs hold large sizes");
+static_assert(OFFSET + 256 == 0 || OFFSET >= 0, "offset is a quarter KiB back");
+static_assert((unsigned char)300 == 44, "casts wrap");
+
+int area() {
+    static_assert(HEIGHT * 2 == WIDTH, "height is half the width");
+    return WIDTH * HEIGHT;
+}
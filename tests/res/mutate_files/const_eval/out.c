@@ -0,0 +1,63 @@
+#include <stdint.h>
+const int KIB = 1024;
+const int BUFFER_SIZE = 4112;
+const unsigned int MASK = 4294967295L;
+const double RATIO = 0.3333333333333333;
+const long BIG = 4294967296L;
+const int OFFSET = -256;
+typedef enum Flag__enum {
+    READ = 1,
+    WRITE = 2,
+    EXEC = 4,
+    ALL = 7,
+    NEXT
+} Flag;
+enum { Flag__COUNT = 5 };
+static const Flag Flag__values[Flag__COUNT] = { READ, WRITE, EXEC, ALL, NEXT };
+static const char* const Flag__names[Flag__COUNT] = { "READ", "WRITE", "EXEC", "ALL", "NEXT" };
+static inline const char* Flag__name(Flag value) {
+    for (int i = 0; i < Flag__COUNT; i++) {
+        if (Flag__values[i] == value) {
+            return Flag__names[i];
+        }
+    }
+    return 0;
+}
+static inline _Bool Flag__is_valid(int value) {
+    for (int i = 0; i < Flag__COUNT; i++) {
+        if ((int)Flag__values[i] == value) {
+            return 1;
+        }
+    }
+    return 0;
+}
+typedef enum {
+    WIDTH = 64,
+    HEIGHT = 32
+} Dimension;
+typedef struct Header__struct {
+    uint32_t magic;
+    uint8_t version;
+    uint64_t length;
+} Header;
+typedef struct Packet__struct {
+    char kind;
+    Header header;
+    short checksums[3];
+    union {
+        int code;
+        char bytes[5];
+    };
+} Packet;
+_Static_assert(sizeof(Header)==16, "Header must stay 16 bytes");
+_Static_assert(sizeof(Packet)==40, "Packet layout changed");
+_Static_assert(Flag__COUNT==5, "every flag is listed");
+_Static_assert(ALL==7&&NEXT==8, "flags are contiguous");
+_Static_assert(sizeof(long)==8 ? 4294967296L>0 : 1, "longs hold large sizes");
+_Static_assert((-256)+256==0||(-256)>=0, "offset is a quarter KiB back");
+_Static_assert((unsigned char)300==44, "casts wrap");
+int area() {
+    _Static_assert(HEIGHT*2==WIDTH, "height is half the width");
+    return WIDTH*HEIGHT;
+}
+
@@ -0,0 +1,25 @@
+#include <stdint.h>
+
+struct Node {
+    int value;
+    long count;
+    void* next;
+};
+
+struct Record {
+    char tag;
+    double weight;
+    uint64_t id;
+};
+
+// Under ILP32 longs and pointers take 4 bytes, and 8 byte fields only need 4 byte alignment
+static_assert(sizeof(long) == 4, "long is one word");
+static_assert(sizeof(void*) == 4, "pointers are one word");
+static_assert(sizeof(Node) == 12, "Node has no padding");
+static_assert(sizeof(Record) == 20, "Record aligns its fields to 4 bytes");
+
+const unsigned long WORDS = sizeof(Node) / sizeof(long);
+
+int main() {
+    return WORDS - 3;
+}
@@ -0,0 +1,20 @@
+#include <stdint.h>
+typedef struct Node__struct {
+    int value;
+    long count;
+    void* next;
+} Node;
+typedef struct Record__struct {
+    char tag;
+    double weight;
+    uint64_t id;
+} Record;
+_Static_assert(sizeof(long)==4, "long is one word");
+_Static_assert(sizeof(void*)==4, "pointers are one word");
+_Static_assert(sizeof(Node)==12, "Node has no padding");
+_Static_assert(sizeof(Record)==20, "Record aligns its fields to 4 bytes");
+const unsigned long WORDS = 3;
+int main() {
+    return WORDS-3;
+}
+
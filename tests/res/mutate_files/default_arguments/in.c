@@ -0,0 +1,52 @@
+#include <stdio.h>
+
+const int DEFAULT_MODE = 0644;
+
+typedef enum {
+    READ,
+    WRITE,
+    APPEND
+} Access;
+
+module fs {
+    const int NO_FLAGS = 0;
+
+    int default_timeout() {
+        return 30;
+    }
+
+    // Members of the module are qualified in the calls that use these defaults
+    int open(const char *path, Access access = READ, int flags = NO_FLAGS, int mode = DEFAULT_MODE);
+
+    int open(const char *path, Access access, int flags, int mode) {
+        printf("open %s access=%d flags=%d mode=%o\n", path, access, flags, mode);
+        return 3;
+    }
+
+    void close(int fd, _Bool sync = false, int timeout = default_timeout()) {
+        printf("close %d sync=%d timeout=%d\n", fd, sync, timeout);
+    }
+}
+
+int log_line(const char *fmt, int level = 1, ...);
+
+double scale(double value, double factor = 2.0, double offset = 0.0) {
+    return value * factor + offset;
+}
+
+int main() {
+    int fd = fs::open("a.txt");
+    fs::open("b.txt", WRITE);
+    fs::open("c.txt", mode: 0600);
+    fs::open("d.txt", flags: 2, access: APPEND);
+    fs::open(path: "e.txt", mode: 0400, flags: 1);
+    fs::close(fd, sync: true);
+    fs::close(fd);
+
+    let scaled = scale(3.0, offset: 1.0);
+    printf("%f %f\n", scaled, scale(scale(1.0), factor: scale(0.5)));
+
+    closure(int) => int twice = [](int x) => int { return x * 2; };
+    printf("%d\n", twice(4));
+    return 0;
+}
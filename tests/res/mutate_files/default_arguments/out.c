@@ -0,0 +1,49 @@
+#include "mc/closure.h"
+#include <stdio.h>
+const int DEFAULT_MODE = 0644;
+typedef enum {
+    READ,
+    WRITE,
+    APPEND
+} Access;
+const int mod__fs__NO_FLAGS = 0;
+int mod__fs__default_timeout() {
+    return 30;
+}
+
+int mod__fs__open(const char* path, Access access, int flags, int mode);
+int mod__fs__open(const char* path, Access access, int flags, int mode) {
+    printf("open %s access=%d flags=%d mode=%o\n", path, access, flags, mode);
+    return 3;
+}
+
+void mod__fs__close(int fd, _Bool sync, int timeout) {
+    printf("close %d sync=%d timeout=%d\n", fd, sync, timeout);
+}
+
+
+int log_line(const char* fmt, int level, ...);
+double scale(double value, double factor, double offset) {
+    return value*factor+offset;
+}
+
+int main__lambda0(void* __mc_env, int x);
+int main() {
+    int fd = mod__fs__open("a.txt", READ, mod__fs__NO_FLAGS, DEFAULT_MODE);
+    mod__fs__open("b.txt", WRITE, mod__fs__NO_FLAGS, DEFAULT_MODE);
+    mod__fs__open("c.txt", READ, mod__fs__NO_FLAGS, 0600);
+    mod__fs__open("d.txt", APPEND, 2, DEFAULT_MODE);
+    mod__fs__open("e.txt", READ, 1, 0400);
+    mod__fs__close(fd, 1, mod__fs__default_timeout());
+    mod__fs__close(fd, 0, mod__fs__default_timeout());
+    double scaled = scale(3.0, 2.0, 1.0);
+    printf("%f %f\n", scaled, scale(scale(1.0, 2.0, 0.0), scale(0.5, 2.0, 0.0), 0.0));
+    mod__mc__Closure twice = (mod__mc__Closure){(mod__mc__ClosureFn)main__lambda0, 0};
+    printf("%d\n", ((int (*)(void*, int))twice.fn)(twice.env, 4));
+    return 0;
+}
+
+int main__lambda0(void* __mc_env, int x) {
+    return x*2;
+}
+
@@ -0,0 +1,34 @@
+// The headers the helpers need are included automatically
+enum Shape {
+    CIRCLE,
+    SQUARE
+};
+
+module geo {
+    @derive(eq, hash, debug, clone)
+    struct Vec2 {
+        double x;
+        double y;
+    };
+}
+
+@derive(eq, hash, debug, clone)
+struct Sprite {
+    char *name;
+    int layer;
+    float scale;
+    bool visible;
+    Shape shape;
+    geo::Vec2 pos;
+    geo::Vec2 path[3];
+    int counts[2];
+    char label[8];
+    struct {
+        char tag;
+        void *data;
+    };
+    union {
+        int id;
+        float weight;
+    };
+};
@@ -0,0 +1,199 @@
+#include <stdint.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+typedef enum Shape__enum {
+    CIRCLE,
+    SQUARE
+} Shape;
+typedef struct Vec2__struct {
+    double x;
+    double y;
+} mod__geo__Vec2;
+_Bool mod__geo__Vec2__eq(mod__geo__Vec2* this, mod__geo__Vec2* other) {
+    if (this->x!=other->x) {
+        return 0;
+    }
+    if (this->y!=other->y) {
+        return 0;
+    }
+    return 1;
+}
+
+size_t mod__geo__Vec2__hash(mod__geo__Vec2* this) {
+    size_t hash = 17;
+    double __mc_double;
+    uint64_t __mc_double_bits;
+    __mc_double = this->x==0 ? 0 : this->x;
+    memcpy(&__mc_double_bits, &__mc_double, sizeof(uint64_t));
+    hash = hash*31+(size_t)(__mc_double_bits^(__mc_double_bits>>32));
+    __mc_double = this->y==0 ? 0 : this->y;
+    memcpy(&__mc_double_bits, &__mc_double, sizeof(uint64_t));
+    hash = hash*31+(size_t)(__mc_double_bits^(__mc_double_bits>>32));
+    return hash;
+}
+
+void mod__geo__Vec2__debug(mod__geo__Vec2* this, FILE* out) {
+    fprintf(out, "Vec2 {");
+    fprintf(out, " x: %f", (double)this->x);
+    fprintf(out, ", y: %f", (double)this->y);
+    fprintf(out, " }");
+}
+
+mod__geo__Vec2 mod__geo__Vec2__clone(mod__geo__Vec2* this) {
+    mod__geo__Vec2 copy = *this;
+    return copy;
+}
+
+
+typedef struct Sprite__struct {
+    char* name;
+    int layer;
+    float scale;
+    bool visible;
+    Shape shape;
+    mod__geo__Vec2 pos;
+    mod__geo__Vec2 path[3];
+    int counts[2];
+    char label[8];
+    struct {
+        char tag;
+        void* data;
+    };
+    union {
+        int id;
+        float weight;
+    };
+} Sprite;
+_Bool Sprite__eq(Sprite* this, Sprite* other) {
+    if (this->name!=other->name&&(this->name==NULL||other->name==NULL||strcmp(this->name, other->name)!=0)) {
+        return 0;
+    }
+    if (this->layer!=other->layer) {
+        return 0;
+    }
+    if (this->scale!=other->scale) {
+        return 0;
+    }
+    if (this->visible!=other->visible) {
+        return 0;
+    }
+    if (this->shape!=other->shape) {
+        return 0;
+    }
+    if (!mod__geo__Vec2__eq(&this->pos, &other->pos)) {
+        return 0;
+    }
+    for (size_t __mc_i = 0;__mc_i<3;__mc_i++) {
+        if (!mod__geo__Vec2__eq(&this->path[__mc_i], &other->path[__mc_i])) {
+            return 0;
+        }
+    }
+    for (size_t __mc_i = 0;__mc_i<2;__mc_i++) {
+        if (this->counts[__mc_i]!=other->counts[__mc_i]) {
+            return 0;
+        }
+    }
+    for (size_t __mc_i = 0;__mc_i<8;__mc_i++) {
+        if (this->label[__mc_i]!=other->label[__mc_i]) {
+            return 0;
+        }
+    }
+    if (this->tag!=other->tag) {
+        return 0;
+    }
+    if (this->data!=other->data) {
+        return 0;
+    }
+    if (memcmp(&this->id, &other->id, sizeof(int))!=0) {
+        return 0;
+    }
+    if (memcmp(&this->weight, &other->weight, sizeof(float))!=0) {
+        return 0;
+    }
+    return 1;
+}
+
+size_t Sprite__hash(Sprite* this) {
+    size_t hash = 17;
+    float __mc_float;
+    uint32_t __mc_float_bits;
+    for (const char* __mc_c = this->name;__mc_c!=NULL&&*__mc_c!=0;__mc_c++) {
+        hash = hash*31+(size_t)(unsigned char)*__mc_c;
+    }
+    hash = hash*31+(size_t)this->layer;
+    __mc_float = this->scale==0 ? 0 : this->scale;
+    memcpy(&__mc_float_bits, &__mc_float, sizeof(uint32_t));
+    hash = hash*31+(size_t)__mc_float_bits;
+    hash = hash*31+(size_t)this->visible;
+    hash = hash*31+(size_t)this->shape;
+    hash = hash*31+mod__geo__Vec2__hash(&this->pos);
+    for (size_t __mc_i = 0;__mc_i<3;__mc_i++) {
+        hash = hash*31+mod__geo__Vec2__hash(&this->path[__mc_i]);
+    }
+    for (size_t __mc_i = 0;__mc_i<2;__mc_i++) {
+        hash = hash*31+(size_t)this->counts[__mc_i];
+    }
+    for (size_t __mc_i = 0;__mc_i<8;__mc_i++) {
+        hash = hash*31+(size_t)this->label[__mc_i];
+    }
+    hash = hash*31+(size_t)this->tag;
+    hash = hash*31+(size_t)this->data;
+    for (size_t __mc_i = 0;__mc_i<sizeof(int);__mc_i++) {
+        hash = hash*31+(size_t)((char*)&this->id)[__mc_i];
+    }
+    for (size_t __mc_i = 0;__mc_i<sizeof(float);__mc_i++) {
+        hash = hash*31+(size_t)((char*)&this->weight)[__mc_i];
+    }
+    return hash;
+}
+
+void Sprite__debug(Sprite* this, FILE* out) {
+    fprintf(out, "Sprite {");
+    fprintf(out, " name: \"%s\"", this->name ? this->name : "(null)");
+    fprintf(out, ", layer: %ld", (long)this->layer);
+    fprintf(out, ", scale: %f", (double)this->scale);
+    fprintf(out, ", visible: %s", this->visible ? "true" : "false");
+    fprintf(out, ", shape: %ld", (long)this->shape);
+    fprintf(out, ", pos: ");
+    mod__geo__Vec2__debug(&this->pos, out);
+    fprintf(out, ", path: [");
+    for (size_t __mc_i = 0;__mc_i<3;__mc_i++) {
+        if (__mc_i>0) {
+            fprintf(out, ", ");
+        }
+        mod__geo__Vec2__debug(&this->path[__mc_i], out);
+    }
+    fprintf(out, "]");
+    fprintf(out, ", counts: [");
+    for (size_t __mc_i = 0;__mc_i<2;__mc_i++) {
+        if (__mc_i>0) {
+            fprintf(out, ", ");
+        }
+        fprintf(out, "%ld", (long)this->counts[__mc_i]);
+    }
+    fprintf(out, "]");
+    fprintf(out, ", label: \"%.*s\"", 8, this->label);
+    fprintf(out, ", tag: '%c'", this->tag);
+    fprintf(out, ", data: %p", (void*)this->data);
+    fprintf(out, ", id: %ld", (long)this->id);
+    fprintf(out, ", weight: %f", (double)this->weight);
+    fprintf(out, " }");
+}
+
+Sprite Sprite__clone(Sprite* this) {
+    Sprite copy = *this;
+    if (this->name!=NULL) {
+        char* __mc_copy = malloc(strlen(this->name)+1);
+        if (__mc_copy!=NULL) {
+            strcpy(__mc_copy, this->name);
+        }
+        copy.name = __mc_copy;
+    }
+    copy.pos = mod__geo__Vec2__clone(&this->pos);
+    for (size_t __mc_i = 0;__mc_i<3;__mc_i++) {
+        copy.path[__mc_i] = mod__geo__Vec2__clone(&this->path[__mc_i]);
+    }
+    return copy;
+}
+
@@ -0,0 +1,29 @@
+enum Role {
+    ADMIN,
+    EDITOR = 4,
+    VIEWER
+};
+
+module geo {
+    @derive(json, eq)
+    struct Vec2 {
+        double x;
+        double y;
+    };
+}
+
+@derive(json, eq)
+struct User {
+    char *name;
+    char initial;
+    int age;
+    bool active;
+    Role role;
+    float scores[3];
+    char code[4];
+    geo::Vec2 home;
+    struct {
+        size_t created;
+        long long offset;
+    };
+};
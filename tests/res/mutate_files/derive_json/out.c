@@ -0,0 +1,190 @@
+#include <string.h>
+#include "mc/json.h"
+typedef enum Role__enum {
+    ADMIN,
+    EDITOR = 4,
+    VIEWER
+} Role;
+enum { Role__COUNT = 3 };
+static const Role Role__values[Role__COUNT] = { ADMIN, EDITOR, VIEWER };
+static const char* const Role__names[Role__COUNT] = { "ADMIN", "EDITOR", "VIEWER" };
+static inline const char* Role__name(Role value) {
+    for (int i = 0; i < Role__COUNT; i++) {
+        if (Role__values[i] == value) {
+            return Role__names[i];
+        }
+    }
+    return 0;
+}
+static inline _Bool Role__is_valid(int value) {
+    for (int i = 0; i < Role__COUNT; i++) {
+        if ((int)Role__values[i] == value) {
+            return 1;
+        }
+    }
+    return 0;
+}
+typedef struct Vec2__struct {
+    double x;
+    double y;
+} mod__geo__Vec2;
+void mod__geo__Vec2__to_json(const mod__geo__Vec2* this, mod__mc__Buffer* out) {
+    mod__mc__json_write_raw(out, "{\"x\":");
+    mod__mc__json_write_double(out, (double)this->x);
+    mod__mc__json_write_raw(out, ",\"y\":");
+    mod__mc__json_write_double(out, (double)this->y);
+    mod__mc__json_write_raw(out, "}");
+}
+
+void mod__geo__Vec2__read_json(mod__geo__Vec2* this, mod__mc__JsonReader* reader) {
+    mod__mc__json_begin_object(reader);
+    while (mod__mc__json_next_field(reader)) {
+        if (mod__mc__json_field_is(reader, "x")) {
+            this->x = (double)mod__mc__json_read_double(reader);
+        }else if (mod__mc__json_field_is(reader, "y")) {
+            this->y = (double)mod__mc__json_read_double(reader);
+        }else {
+            mod__mc__json_skip_value(reader);
+        }
+    }
+}
+
+_Bool mod__geo__Vec2__from_json(const char* json, mod__geo__Vec2* this) {
+    mod__mc__JsonReader reader;
+    mod__mc__json_reader_init(&reader, json);
+    mod__geo__Vec2__read_json(this, &reader);
+    return mod__mc__json_reader_finish(&reader);
+}
+
+_Bool mod__geo__Vec2__eq(mod__geo__Vec2* this, mod__geo__Vec2* other) {
+    if (this->x!=other->x) {
+        return 0;
+    }
+    if (this->y!=other->y) {
+        return 0;
+    }
+    return 1;
+}
+
+
+typedef struct User__struct {
+    char* name;
+    char initial;
+    int age;
+    bool active;
+    Role role;
+    float scores[3];
+    char code[4];
+    mod__geo__Vec2 home;
+    struct {
+        size_t created;
+        long long offset;
+    };
+} User;
+void User__to_json(const User* this, mod__mc__Buffer* out) {
+    mod__mc__json_write_raw(out, "{\"name\":");
+    mod__mc__json_write_string(out, this->name);
+    mod__mc__json_write_raw(out, ",\"initial\":");
+    mod__mc__json_write_char(out, this->initial);
+    mod__mc__json_write_raw(out, ",\"age\":");
+    mod__mc__json_write_long(out, (long)this->age);
+    mod__mc__json_write_raw(out, ",\"active\":");
+    mod__mc__json_write_bool(out, this->active);
+    mod__mc__json_write_raw(out, ",\"role\":");
+    mod__mc__json_write_string(out, Role__name(this->role));
+    mod__mc__json_write_raw(out, ",\"scores\":");
+    mod__mc__json_write_raw(out, "[");
+    for (size_t __mc_i = 0;__mc_i<3;__mc_i++) {
+        if (__mc_i>0) {
+            mod__mc__json_write_raw(out, ",");
+        }
+        mod__mc__json_write_double(out, (double)this->scores[__mc_i]);
+    }
+    mod__mc__json_write_raw(out, "]");
+    mod__mc__json_write_raw(out, ",\"code\":");
+    mod__mc__json_write_string_n(out, this->code, 4);
+    mod__mc__json_write_raw(out, ",\"home\":");
+    mod__geo__Vec2__to_json(&this->home, out);
+    mod__mc__json_write_raw(out, ",\"created\":");
+    mod__mc__json_write_unsigned_long_long(out, (unsigned long long)this->created);
+    mod__mc__json_write_raw(out, ",\"offset\":");
+    mod__mc__json_write_long_long(out, (long long)this->offset);
+    mod__mc__json_write_raw(out, "}");
+}
+
+void User__read_json(User* this, mod__mc__JsonReader* reader) {
+    mod__mc__json_begin_object(reader);
+    while (mod__mc__json_next_field(reader)) {
+        if (mod__mc__json_field_is(reader, "name")) {
+            this->name = mod__mc__json_read_string(reader);
+        }else if (mod__mc__json_field_is(reader, "initial")) {
+            this->initial = mod__mc__json_read_char(reader);
+        }else if (mod__mc__json_field_is(reader, "age")) {
+            this->age = (int)mod__mc__json_read_long(reader);
+        }else if (mod__mc__json_field_is(reader, "active")) {
+            this->active = mod__mc__json_read_bool(reader);
+        }else if (mod__mc__json_field_is(reader, "role")) {
+            this->role = Role__values[mod__mc__json_read_enum(reader, Role__names, Role__COUNT)];
+        }else if (mod__mc__json_field_is(reader, "scores")) {
+            for (size_t __mc_i = 0;mod__mc__json_next_element(reader, __mc_i, 3);__mc_i++) {
+                this->scores[__mc_i] = (float)mod__mc__json_read_double(reader);
+            }
+        }else if (mod__mc__json_field_is(reader, "code")) {
+            mod__mc__json_read_string_into(reader, this->code, 4);
+        }else if (mod__mc__json_field_is(reader, "home")) {
+            mod__geo__Vec2__read_json(&this->home, reader);
+        }else if (mod__mc__json_field_is(reader, "created")) {
+            this->created = (size_t)mod__mc__json_read_unsigned_long_long(reader);
+        }else if (mod__mc__json_field_is(reader, "offset")) {
+            this->offset = (long long)mod__mc__json_read_long_long(reader);
+        }else {
+            mod__mc__json_skip_value(reader);
+        }
+    }
+}
+
+_Bool User__from_json(const char* json, User* this) {
+    mod__mc__JsonReader reader;
+    mod__mc__json_reader_init(&reader, json);
+    User__read_json(this, &reader);
+    return mod__mc__json_reader_finish(&reader);
+}
+
+_Bool User__eq(User* this, User* other) {
+    if (this->name!=other->name&&(this->name==NULL||other->name==NULL||strcmp(this->name, other->name)!=0)) {
+        return 0;
+    }
+    if (this->initial!=other->initial) {
+        return 0;
+    }
+    if (this->age!=other->age) {
+        return 0;
+    }
+    if (this->active!=other->active) {
+        return 0;
+    }
+    if (this->role!=other->role) {
+        return 0;
+    }
+    for (size_t __mc_i = 0;__mc_i<3;__mc_i++) {
+        if (this->scores[__mc_i]!=other->scores[__mc_i]) {
+            return 0;
+        }
+    }
+    for (size_t __mc_i = 0;__mc_i<4;__mc_i++) {
+        if (this->code[__mc_i]!=other->code[__mc_i]) {
+            return 0;
+        }
+    }
+    if (!mod__geo__Vec2__eq(&this->home, &other->home)) {
+        return 0;
+    }
+    if (this->created!=other->created) {
+        return 0;
+    }
+    if (this->offset!=other->offset) {
+        return 0;
+    }
+    return 1;
+}
+
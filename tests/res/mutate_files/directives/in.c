@@ -0,0 +1,47 @@
+#include <stdio.h>
+#pragma once
+#define MAX(a, b) ((a) > (b) ? (a) : (b))
+
+module config {
+    #define BUFFER_SIZE 64
+    #ifndef LOG_LEVEL
+    #define LOG_LEVEL 1
+    #endif
+    #define DOUBLED (BUFFER_SIZE * 2)
+    #define SCALE(BUFFER_SIZE) ((BUFFER_SIZE) * LOG_LEVEL)
+    #define NAME "BUFFER_SIZE"
+    #if defined(LOG_LEVEL) && LOG_LEVEL > 1
+    #define VERBOSE_LOG 1
+    #endif
+
+    int capacity() {
+        return config::BUFFER_SIZE * 2;
+    }
+}
+
+struct Stats {
+    int hits;
+#ifdef TRACK_MISSES
+    int misses;
+#endif
+};
+
+#if config::BUFFER_SIZE > 32
+#define LARGE_BUFFER config::DOUBLED
+#elif config::BUFFER_SIZE > 2
+#define LARGE_BUFFER 0
+#endif
+
+void report(Stats* stats) {
+#if VERBOSE > 1
+    printf("hits: %d\n", stats->hits);
+#else
+    printf("%d\n", MAX(stats->hits, config::LOG_LEVEL));
+#endif
+    for (int i = 0; i < config::capacity(); i++) {
+        #ifdef TRACK_MISSES
+        printf("checking %d of %d\n", i, stats->misses);
+        #endif
+    }
+}
+#undef MAX
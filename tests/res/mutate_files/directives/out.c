@@ -0,0 +1,43 @@
+#include <stdio.h>
+#pragma once
+#define MAX(a, b) ((a) > (b) ? (a) : (b))
+#define mod__config__BUFFER_SIZE 64
+#ifndef mod__config__LOG_LEVEL
+#define mod__config__LOG_LEVEL 1
+#endif
+#define mod__config__DOUBLED (mod__config__BUFFER_SIZE * 2)
+#define mod__config__SCALE(BUFFER_SIZE) ((BUFFER_SIZE) * mod__config__LOG_LEVEL)
+#define mod__config__NAME "BUFFER_SIZE"
+#if defined(mod__config__LOG_LEVEL) && mod__config__LOG_LEVEL > 1
+#define mod__config__VERBOSE_LOG 1
+#endif
+int mod__config__capacity() {
+    return mod__config__BUFFER_SIZE*2;
+}
+
+
+typedef struct Stats__struct {
+    int hits;
+#ifdef TRACK_MISSES
+    int misses;
+#endif
+} Stats;
+#if mod__config__BUFFER_SIZE > 32
+#define LARGE_BUFFER mod__config__DOUBLED
+#elif mod__config__BUFFER_SIZE > 2
+#define LARGE_BUFFER 0
+#endif
+void report(Stats* stats) {
+#if VERBOSE > 1
+    printf("hits: %d\n", stats->hits);
+#else
+    printf("%d\n", MAX(stats->hits, mod__config__LOG_LEVEL));
+#endif
+    for (int i = 0;i<mod__config__capacity();i++) {
+#ifdef TRACK_MISSES
+        printf("checking %d of %d\n", i, stats->misses);
+#endif
+    }
+}
+
+#undef MAX
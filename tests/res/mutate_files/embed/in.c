@@ -0,0 +1,16 @@
+#include <stdio.h>
+
+typedef unsigned char u8;
+
+// Paths are relative to this file
+const u8 icon[] = embed("assets/icon.bin");
+
+module config {
+    static const char defaults[] = embed("assets/defaults.conf");
+}
+
+int main() {
+    printf("%zu bytes, starting with %x\n", icon__len, icon[0]);
+    printf("%s(%zu characters)\n", config::defaults, config::defaults__len);
+    return 0;
+}
@@ -0,0 +1,21 @@
+#include <stddef.h>
+#include <stdio.h>
+typedef unsigned char u8;
+const u8 icon[18] = {
+    0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00, 0x00, 0x0d,
+    0x49, 0x48, 0x44, 0x52, 0x00, 0x01
+};
+const size_t icon__len = 18;
+static const char mod__config__defaults[26] = {
+    0x77, 0x69, 0x64, 0x74, 0x68, 0x20, 0x3d, 0x20, 0x36, 0x34, 0x30, 0x0a,
+    0x68, 0x65, 0x69, 0x67, 0x68, 0x74, 0x20, 0x3d, 0x20, 0x34, 0x38, 0x30,
+    0x0a, 0x00
+};
+static const size_t mod__config__defaults__len = 25;
+
+int main() {
+    printf("%zu bytes, starting with %x\n", icon__len, icon[0]);
+    printf("%s(%zu characters)\n", mod__config__defaults, mod__config__defaults__len);
+    return 0;
+}
+
@@ -0,0 +1,38 @@
+enum Color {
+    RED = 1,
+    GREEN = 4,
+    BLUE = 9
+};
+
+module sl {
+    enum Light {
+        OFF,
+        ON
+    };
+
+    enum Mode {
+        FAST,
+        SLOW
+    };
+}
+
+// Nothing uses its helpers, so they aren't generated
+enum Unused {
+    FIRST,
+    SECOND
+};
+
+void main() {
+    int n = Color::COUNT;
+    Color first = Color::values[0];
+    if (Color::is_valid(4)) {
+        n = sl::Light::COUNT;
+    }
+    const char* mode = sl::Mode::name(FAST);
+    for (Color c : Color) {
+        print(c);
+    }
+    for (sl::Light l : sl::Light) {
+        print(l);
+    }
+}
@@ -0,0 +1,92 @@
+typedef enum Color__enum {
+    RED = 1,
+    GREEN = 4,
+    BLUE = 9
+} Color;
+enum { Color__COUNT = 3 };
+static const Color Color__values[Color__COUNT] = { RED, GREEN, BLUE };
+static const char* const Color__names[Color__COUNT] = { "RED", "GREEN", "BLUE" };
+static inline const char* Color__name(Color value) {
+    for (int i = 0; i < Color__COUNT; i++) {
+        if (Color__values[i] == value) {
+            return Color__names[i];
+        }
+    }
+    return 0;
+}
+static inline _Bool Color__is_valid(int value) {
+    for (int i = 0; i < Color__COUNT; i++) {
+        if ((int)Color__values[i] == value) {
+            return 1;
+        }
+    }
+    return 0;
+}
+typedef enum Light__enum {
+    OFF,
+    ON
+} mod__sl__Light;
+enum { mod__sl__Light__COUNT = 2 };
+static const mod__sl__Light mod__sl__Light__values[mod__sl__Light__COUNT] = { OFF, ON };
+static const char* const mod__sl__Light__names[mod__sl__Light__COUNT] = { "OFF", "ON" };
+static inline const char* mod__sl__Light__name(mod__sl__Light value) {
+    for (int i = 0; i < mod__sl__Light__COUNT; i++) {
+        if (mod__sl__Light__values[i] == value) {
+            return mod__sl__Light__names[i];
+        }
+    }
+    return 0;
+}
+static inline _Bool mod__sl__Light__is_valid(int value) {
+    for (int i = 0; i < mod__sl__Light__COUNT; i++) {
+        if ((int)mod__sl__Light__values[i] == value) {
+            return 1;
+        }
+    }
+    return 0;
+}
+typedef enum Mode__enum {
+    FAST,
+    SLOW
+} mod__sl__Mode;
+enum { mod__sl__Mode__COUNT = 2 };
+static const mod__sl__Mode mod__sl__Mode__values[mod__sl__Mode__COUNT] = { FAST, SLOW };
+static const char* const mod__sl__Mode__names[mod__sl__Mode__COUNT] = { "FAST", "SLOW" };
+static inline const char* mod__sl__Mode__name(mod__sl__Mode value) {
+    for (int i = 0; i < mod__sl__Mode__COUNT; i++) {
+        if (mod__sl__Mode__values[i] == value) {
+            return mod__sl__Mode__names[i];
+        }
+    }
+    return 0;
+}
+static inline _Bool mod__sl__Mode__is_valid(int value) {
+    for (int i = 0; i < mod__sl__Mode__COUNT; i++) {
+        if ((int)mod__sl__Mode__values[i] == value) {
+            return 1;
+        }
+    }
+    return 0;
+}
+
+typedef enum Unused__enum {
+    FIRST,
+    SECOND
+} Unused;
+void main() {
+    int n = Color__COUNT;
+    Color first = Color__values[0];
+    if (Color__is_valid(4)) {
+        n = mod__sl__Light__COUNT;
+    }
+    const char* mode = mod__sl__Mode__name(FAST);
+    for (int __mc_i_c = 0; __mc_i_c < Color__COUNT; __mc_i_c++) {
+        Color c = Color__values[__mc_i_c];
+        print(c);
+    }
+    for (int __mc_i_l = 0; __mc_i_l < mod__sl__Light__COUNT; __mc_i_l++) {
+        mod__sl__Light l = mod__sl__Light__values[__mc_i_l];
+        print(l);
+    }
+}
+
@@ -0,0 +1,55 @@
+#include <stdio.h>
+
+int primes[5] = {2, 3, 5, 7, 11};
+
+module stats {
+    int samples[3];
+}
+
+struct Point {
+    int x;
+    int y;
+};
+
+int count(int n) {
+    int total = 0;
+    for (int i in 0..n) {
+        for (int j in i..n * 2) {
+            total += j;
+        }
+    }
+    for (const int k in 1..n) {
+        total -= k;
+    }
+    return total;
+}
+
+int sum_primes() {
+    int total = 0;
+    for (int p in primes) {
+        total += p;
+    }
+    for (int s in stats::samples) {
+        total += s;
+    }
+    return total;
+}
+
+void move_to(Point* p, int x, int y);
+
+void reset(int value) {
+    Point points[2];
+    for (Point* p in points) {
+        move_to(p, value, value);
+    }
+    char word[4] = {'a', 'b', 'c', 'd'};
+    for (char c in word) {
+        printf("%c", c);
+    }
+}
+
+void shadowed(int primes) {
+    for (int i in 0..primes) {
+        printf("%d", i);
+    }
+}
@@ -0,0 +1,54 @@
+#include <stdio.h>
+int primes[5] = {2, 3, 5, 7, 11};
+int mod__stats__samples[3];
+
+typedef struct Point__struct {
+    int x;
+    int y;
+} Point;
+int count(int n) {
+    int total = 0;
+    for (int i = 0, __mc_end_i = n; i < __mc_end_i; i++) {
+        for (int j = i, __mc_end_j = n*2; j < __mc_end_j; j++) {
+            total += j;
+        }
+    }
+    for (int k = 1, __mc_end_k = n; k < __mc_end_k; k++) {
+        total -= k;
+    }
+    return total;
+}
+
+int sum_primes() {
+    int total = 0;
+    for (size_t __mc_i_p = 0; __mc_i_p < 5; __mc_i_p++) {
+        int p = primes[__mc_i_p];
+        total += p;
+    }
+    for (size_t __mc_i_s = 0; __mc_i_s < 3; __mc_i_s++) {
+        int s = mod__stats__samples[__mc_i_s];
+        total += s;
+    }
+    return total;
+}
+
+void move_to(Point* p, int x, int y);
+void reset(int value) {
+    Point points[2];
+    for (size_t __mc_i_p = 0; __mc_i_p < 2; __mc_i_p++) {
+        Point* p = &points[__mc_i_p];
+        move_to(p, value, value);
+    }
+    char word[4] = {'a', 'b', 'c', 'd'};
+    for (size_t __mc_i_c = 0; __mc_i_c < 4; __mc_i_c++) {
+        char c = word[__mc_i_c];
+        printf("%c", c);
+    }
+}
+
+void shadowed(int primes) {
+    for (int i = 0, __mc_end_i = primes; i < __mc_end_i; i++) {
+        printf("%d", i);
+    }
+}
+
@@ -0,0 +1,45 @@
+#include <stdio.h>
+#include <stdarg.h>
+
+int add(int a, int b) {
+    return a + b;
+}
+
+void hello() {
+    puts("hello");
+}
+
+() => void pick() {
+    return hello;
+}
+
+int log_all(const char* fmt, ...) {
+    va_list args;
+    va_start(args, fmt);
+    int n = vprintf(fmt, args);
+    va_end(args);
+    return n;
+}
+
+typedef (int, int) => int BinaryOp;
+
+struct Handlers {
+    (const char*, ...) => int log;
+    BinaryOp op;
+};
+
+module math {
+    int apply((int, int) => int f, int a, int b) {
+        return f(a, b);
+    }
+}
+
+int main() {
+    (int, int) => int pointer = add;
+    () => () => void chained = pick;
+    chained()();
+    Handlers h = {log_all, add};
+    h.log("%d %d\n", pointer(1, 2), math::apply(h.op, 3, 4));
+    let inferred = pointer;
+    return inferred(0, 0);
+}
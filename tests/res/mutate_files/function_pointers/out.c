@@ -0,0 +1,58 @@
+#include <stdio.h>
+#include <stdarg.h>
+int add(int a, int b) {
+    return a+b;
+}
+
+void hello() {
+    puts("hello");
+}
+
+#ifndef mod__mc__Fn__4_void__defined
+#define mod__mc__Fn__4_void__defined
+typedef void (*mod__mc__Fn__4_void)(void);
+#endif
+mod__mc__Fn__4_void pick() {
+    return hello;
+}
+
+int log_all(const char* fmt, ...) {
+    va_list args;
+    va_start(args, fmt);
+    int n = vprintf(fmt, args);
+    va_end(args);
+    return n;
+}
+
+#ifndef mod__mc__Fn__3_int__3_int__3_int__defined
+#define mod__mc__Fn__3_int__3_int__3_int__defined
+typedef int (*mod__mc__Fn__3_int__3_int__3_int)(int, int);
+#endif
+typedef mod__mc__Fn__3_int__3_int__3_int BinaryOp;
+#ifndef mod__mc__Fn__3_int__10_constcharp__v__defined
+#define mod__mc__Fn__3_int__10_constcharp__v__defined
+typedef int (*mod__mc__Fn__3_int__10_constcharp__v)(const char*, ...);
+#endif
+typedef struct Handlers__struct {
+    mod__mc__Fn__3_int__10_constcharp__v log;
+    BinaryOp op;
+} Handlers;
+int mod__math__apply(mod__mc__Fn__3_int__3_int__3_int f, int a, int b) {
+    return f(a, b);
+}
+
+
+#ifndef mod__mc__Fn__19_mod__mc__Fn__4_void__defined
+#define mod__mc__Fn__19_mod__mc__Fn__4_void__defined
+typedef mod__mc__Fn__4_void (*mod__mc__Fn__19_mod__mc__Fn__4_void)(void);
+#endif
+int main() {
+    mod__mc__Fn__3_int__3_int__3_int pointer = add;
+    mod__mc__Fn__19_mod__mc__Fn__4_void chained = pick;
+    chained()();
+    Handlers h = {log_all, add};
+    h.log("%d %d\n", pointer(1, 2), mod__math__apply(h.op, 3, 4));
+    mod__mc__Fn__3_int__3_int__3_int inferred = pointer;
+    return inferred(0, 0);
+}
+
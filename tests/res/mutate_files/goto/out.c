@@ -0,0 +1,50 @@
+#include <stdio.h>
+#include <stdlib.h>
+int copy_file(char* from, char* to) {
+    int status = -1;
+    FILE* in = fopen(from, "r");
+    if (in==NULL) {
+        goto done;
+    }
+    FILE* out = fopen(to, "w");
+    if (out==NULL) {
+        goto close_in;
+    }
+    char* buffer = malloc(256);
+    if (buffer==NULL) {
+        goto close_out;
+    }
+    while (fgets(buffer, 256, in)!=NULL) {
+        if (buffer[0]=='#') {
+            continue;
+        }
+        if (fputs(buffer, out)<0) {
+            goto cleanup;
+        }
+    }
+    status = 0;
+    cleanup:
+    free(buffer);
+    close_out:
+    fclose(out);
+    close_in:
+    fclose(in);
+    done:
+    return status;
+}
+
+int search(int* values, int count, int target) {
+    int i = 0;
+    scan:
+    while (i<count) {
+        if (values[i]==target) {
+            break;
+        }
+        i++;
+        if (i==count) {
+            goto scan;
+        }
+    }
+    return i;
+}
+
@@ -0,0 +1,25 @@
+#include <stdio.h>
+#include <stdlib.h>
+#include <stdbool.h>
+
+struct Item {
+    char* name;
+    int count;
+    double price;
+};
+
+void describe(Item* item, bool in_stock) {
+    char line[64] = $"{item->name}: {item->count} at {item->price:%.2f} ({in_stock})";
+    puts(line);
+    line = $"100% of {{{item->name}}}";
+    puts(line);
+    char* summary = $"{item->count * 2} halves, first letter {item->name[0]}";
+    puts(summary);
+    free(summary);
+    // Allocated before the statement and freed after it
+    puts($"{item->name} costs {item->price * item->count}");
+}
+
+void sizes(long double total, long long big, size_t count) {
+    printf("%s\n", $"{total} {big} {count}");
+}
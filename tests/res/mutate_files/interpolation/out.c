@@ -0,0 +1,29 @@
+#include "mc/string.h"
+#include <stdio.h>
+#include <stdlib.h>
+#include <stdbool.h>
+typedef struct Item__struct {
+    char* name;
+    int count;
+    double price;
+} Item;
+void describe(Item* item, bool in_stock) {
+    char line[64];
+    snprintf(line, 64, "%s: %lld at %.2f (%s)", item->name, (long long)item->count, item->price, in_stock ? "true" : "false");
+    puts(line);
+    snprintf(line, 64, "100%% of {%s}", item->name);
+    puts(line);
+    char* summary = mod__mc__string("%lld halves, first letter %c", (long long)(item->count*2), item->name[0]);
+    puts(summary);
+    free(summary);
+    char* __mc_string0 = mod__mc__string("%s costs %g", item->name, item->price*item->count);
+    puts(__mc_string0);
+    free(__mc_string0);
+}
+
+void sizes(long double total, long long big, size_t count) {
+    char* __mc_string0 = mod__mc__string("%Lg %lld %llu", total, (long long)big, (unsigned long long)count);
+    printf("%s\n", __mc_string0);
+    free(__mc_string0);
+}
+
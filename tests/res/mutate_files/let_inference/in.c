@@ -0,0 +1,62 @@
+#include <stdio.h>
+
+module geo {
+    struct Point {
+        double x;
+        double y;
+    };
+    geo::Point origin() {
+        geo::Point p = {0.0, 0.0};
+        return p;
+    }
+}
+
+enum Color {
+    RED,
+    GREEN
+};
+
+@derive(eq)
+struct Item {
+    int counts[4];
+    char* name;
+    union {
+        float weight;
+        int units;
+    };
+};
+
+int scale(int value);
+
+void demo(Item* item, char small, size_t big) {
+    let p = geo::origin();
+    let x = p.x;
+    let sum = x + 1;
+    let promoted = small + small;
+    let widened = small * big;
+    auto count = item->counts[2];
+    let counts = item->counts;
+    let first = *counts;
+    let name = item->name;
+    let letter = name[0];
+    let weight = item->weight * 2;
+    let compare = 1 + 2 < 3.5;
+    let pick = count > 0 ? 1.5f : 2;
+    let same = Item__eq(item, item);
+    let color = GREEN;
+    let color_name = Color::name(color);
+    let n = Color::COUNT;
+    let scaled = scale(count) + 2L;
+    let size = sizeof(Item);
+    let where = &p;
+    let greeting = "hi";
+    let twice = [](int v) => int {
+        let doubled = v * 2;
+        return doubled;
+    };
+    let result = twice(3);
+    for (let i = 0; i < n; i++) {
+        let copy = i;
+        printf("%d %d\n", copy, result);
+    }
+}
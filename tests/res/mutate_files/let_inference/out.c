@@ -0,0 +1,99 @@
+#include <string.h>
+#include "mc/closure.h"
+#include <stdio.h>
+typedef struct Point__struct {
+    double x;
+    double y;
+} mod__geo__Point;
+mod__geo__Point mod__geo__origin() {
+    mod__geo__Point p = {0.0, 0.0};
+    return p;
+}
+
+
+typedef enum Color__enum {
+    RED,
+    GREEN
+} Color;
+enum { Color__COUNT = 2 };
+static const Color Color__values[Color__COUNT] = { RED, GREEN };
+static const char* const Color__names[Color__COUNT] = { "RED", "GREEN" };
+static inline const char* Color__name(Color value) {
+    for (int i = 0; i < Color__COUNT; i++) {
+        if (Color__values[i] == value) {
+            return Color__names[i];
+        }
+    }
+    return 0;
+}
+static inline _Bool Color__is_valid(int value) {
+    for (int i = 0; i < Color__COUNT; i++) {
+        if ((int)Color__values[i] == value) {
+            return 1;
+        }
+    }
+    return 0;
+}
+typedef struct Item__struct {
+    int counts[4];
+    char* name;
+    union {
+        float weight;
+        int units;
+    };
+} Item;
+_Bool Item__eq(Item* this, Item* other) {
+    for (size_t __mc_i = 0;__mc_i<4;__mc_i++) {
+        if (this->counts[__mc_i]!=other->counts[__mc_i]) {
+            return 0;
+        }
+    }
+    if (this->name!=other->name&&(this->name==NULL||other->name==NULL||strcmp(this->name, other->name)!=0)) {
+        return 0;
+    }
+    if (memcmp(&this->weight, &other->weight, sizeof(float))!=0) {
+        return 0;
+    }
+    if (memcmp(&this->units, &other->units, sizeof(int))!=0) {
+        return 0;
+    }
+    return 1;
+}
+
+int scale(int value);
+int demo__lambda0(void* __mc_env, int v);
+void demo(Item* item, char small, size_t big) {
+    mod__geo__Point p = mod__geo__origin();
+    double x = p.x;
+    double sum = x+1;
+    int promoted = small+small;
+    size_t widened = small*big;
+    int count = item->counts[2];
+    int* counts = item->counts;
+    int first = *counts;
+    char* name = item->name;
+    char letter = name[0];
+    float weight = item->weight*2;
+    int compare = 1+2<3.5;
+    float pick = count>0 ? 1.5f : 2;
+    _Bool same = Item__eq(item, item);
+    Color color = GREEN;
+    const char* color_name = Color__name(color);
+    int n = Color__COUNT;
+    long scaled = scale(count)+2L;
+    size_t size = sizeof(Item);
+    mod__geo__Point* where = &p;
+    char* greeting = "hi";
+    mod__mc__Closure twice = (mod__mc__Closure){(mod__mc__ClosureFn)demo__lambda0, 0};
+    int result = ((int (*)(void*, int))twice.fn)(twice.env, 3);
+    for (int i = 0;i<n;i++) {
+        int copy = i;
+        printf("%d %d\n", copy, result);
+    }
+}
+
+int demo__lambda0(void* __mc_env, int v) {
+    int doubled = v*2;
+    return doubled;
+}
+
@@ -0,0 +1,59 @@
+#include <stdio.h>
+
+enum Suit { HEARTS, SPADES };
+
+int find(int* grid, int target) {
+    int found = -1;
+    rows: for (int r = 0; r < 3; r++) {
+        for (int c = 0; c < 3; c++) {
+            if (grid[r * 3 + c] < 0) {
+                continue rows;
+            }
+            if (grid[r * 3 + c] == target) {
+                found = r * 3 + c;
+                break rows;
+            }
+        }
+    }
+    return found;
+}
+
+void walk(int limit) {
+    int values[4] = {1, 2, 3, 4};
+    outer: while (limit > 0) {
+        limit--;
+        inner: for (int v in values) {
+            switch (v) {
+                case 2:
+                    continue inner;
+                case 3:
+                    break inner;
+                default:
+                    if (v > limit) {
+                        break outer;
+                    }
+            }
+            printf("%d %d\n", limit, v);
+        }
+    }
+    outer: do {
+        limit++;
+        for (Suit s : Suit) {
+            if (s == SPADES) {
+                continue outer;
+            }
+        }
+    } while (limit < 3);
+    count: for (int i in 0..limit) {
+        let twice = [](int x) => int {
+            loop: while (x > 0) {
+                x--;
+                break loop;
+            }
+            return x * 2;
+        };
+        if (twice(i) > 4) {
+            break count;
+        }
+    }
+}
@@ -0,0 +1,92 @@
+#include "mc/closure.h"
+#include <stdio.h>
+typedef enum Suit__enum {
+    HEARTS,
+    SPADES
+} Suit;
+enum { Suit__COUNT = 2 };
+static const Suit Suit__values[Suit__COUNT] = { HEARTS, SPADES };
+static const char* const Suit__names[Suit__COUNT] = { "HEARTS", "SPADES" };
+static inline const char* Suit__name(Suit value) {
+    for (int i = 0; i < Suit__COUNT; i++) {
+        if (Suit__values[i] == value) {
+            return Suit__names[i];
+        }
+    }
+    return 0;
+}
+static inline _Bool Suit__is_valid(int value) {
+    for (int i = 0; i < Suit__COUNT; i++) {
+        if ((int)Suit__values[i] == value) {
+            return 1;
+        }
+    }
+    return 0;
+}
+int find(int* grid, int target) {
+    int found = -1;
+    for (int r = 0;r<3;r++) {
+        for (int c = 0;c<3;c++) {
+            if (grid[r*3+c]<0) {
+                goto __mc_continue_rows;
+            }
+            if (grid[r*3+c]==target) {
+                found = r*3+c;
+                goto __mc_break_rows;
+            }
+        }
+        __mc_continue_rows: ;
+    }
+    __mc_break_rows: ;
+    return found;
+}
+
+int walk__lambda0(void* __mc_env, int x);
+void walk(int limit) {
+    int values[4] = {1, 2, 3, 4};
+    while (limit>0) {
+        limit--;
+        for (size_t __mc_i_v = 0; __mc_i_v < 4; __mc_i_v++) {
+            int v = values[__mc_i_v];
+            switch (v) {
+                case 2:
+                    continue;
+                case 3:
+                    goto __mc_break_inner;
+                default:
+                    if (v>limit) {
+                        goto __mc_break_outer;
+                    }
+            }
+
+            printf("%d %d\n", limit, v);
+        }
+        __mc_break_inner: ;
+    }
+    __mc_break_outer: ;
+    do {
+        limit++;
+        for (int __mc_i_s = 0; __mc_i_s < Suit__COUNT; __mc_i_s++) {
+            Suit s = Suit__values[__mc_i_s];
+            if (s==SPADES) {
+                goto __mc_continue_outer_2;
+            }
+        }
+        __mc_continue_outer_2: ;
+    } while (limit<3);
+    for (int i = 0, __mc_end_i = limit; i < __mc_end_i; i++) {
+        mod__mc__Closure twice = (mod__mc__Closure){(mod__mc__ClosureFn)walk__lambda0, 0};
+        if (((int (*)(void*, int))twice.fn)(twice.env, i)>4) {
+            break;
+        }
+    }
+}
+
+int walk__lambda0(void* __mc_env, int x) {
+    while (x>0) {
+        x--;
+        break;
+    }
+    return x*2;
+}
+
@@ -0,0 +1,94 @@
+#include <stdio.h>
+
+// Locals get a hidden name for each expansion, so `tmp` can't clash with the caller's variables
+macro swap(type T, a, b) {
+    T tmp = a;
+    a = b;
+    b = tmp;
+}
+
+// A body that is a single expression can be used as a value
+macro max(a, b) {
+    a > b ? a : b;
+}
+
+macro square(x) {
+    x * x;
+}
+
+macro repeat(n, block body) {
+    for (int i = 0; i < n; i++) {
+        body;
+    }
+}
+
+macro counter(ident name, start) {
+    int name = start;
+}
+
+macro tick(ident name) {
+    name++;
+}
+
+macro tick_twice(ident name) {
+    tick!(name);
+    tick!(name);
+}
+
+macro scaled(type T, value) {
+    (T) (value * 2);
+}
+
+// Uses its argument's address twice, so the argument is stored as a pointer to it
+macro bump_twice(counter) {
+    increment(&counter);
+    increment(&counter);
+}
+
+void increment(int* value) {
+    *value = *value + 1;
+}
+
+int next_id(int* id) {
+    *id = *id + 1;
+    return *id;
+}
+
+int* slot_at(int* slots, int index) {
+    return &slots[index];
+}
+
+void swap_pointed(int* p, int* q) {
+    swap!(int, *p, *q);
+}
+
+int main() {
+    int tmp = 1;
+    int other = 2;
+    swap!(int, tmp, other);
+    char* first = "first";
+    char* second = "second";
+    swap!(char*, first, second);
+    swap_pointed(&tmp, &other);
+    printf("%d %d %s\n", tmp, other, first);
+
+    int i = 10;
+    repeat!(3, {
+        printf("i is still %d\n", i);
+    });
+    counter!(hits, 5);
+    tick_twice!(hits);
+    for (int j = 0; j < 2; tick!(j)) {
+        hits += max!(j + 1, square!(i - 9));
+    }
+    printf("%d %d\n", hits, scaled!(int, 1.5));
+
+    // Arguments used more than once are only evaluated once
+    int id = 0;
+    int biggest = max!(next_id(&id), 0);
+    int slots[3] = {1, 2, 3};
+    swap!(int, *slot_at(slots, next_id(&id)), tmp);
+    bump_twice!(slots[next_id(&id) - 3]);
+    printf("%d %d %d %d %d\n", biggest, id, tmp, slots[0], slots[2]);
+    return 0;
+}
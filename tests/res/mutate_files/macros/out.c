@@ -0,0 +1,59 @@
+#include <stdio.h>
+void increment(int* value) {
+    *value = *value+1;
+}
+
+int next_id(int* id) {
+    *id = *id+1;
+    return *id;
+}
+
+int* slot_at(int* slots, int index) {
+    return &slots[index];
+}
+
+void swap_pointed(int* p, int* q) {
+    int __mc_swap1_tmp = (*p);
+    *p = (*q);
+    *q = __mc_swap1_tmp;
+}
+
+int main() {
+    int tmp = 1;
+    int other = 2;
+    int __mc_swap2_tmp = tmp;
+    tmp = other;
+    other = __mc_swap2_tmp;
+    char* first = "first";
+    char* second = "second";
+    char* __mc_swap3_tmp = first;
+    first = second;
+    second = __mc_swap3_tmp;
+    swap_pointed(&tmp, &other);
+    printf("%d %d %s\n", tmp, other, first);
+    int i = 10;
+    for (int __mc_repeat4_i = 0;__mc_repeat4_i<3;__mc_repeat4_i++) {
+        printf("i is still %d\n", i);
+    }
+    int hits = 5;
+    hits++;
+    hits++;
+    for (int j = 0;j<2;j++) {
+        hits += ((j+1)>((i-9)*(i-9)) ? (j+1) : ((i-9)*(i-9)));
+    }
+    printf("%d %d\n", hits, ((int)(1.5*2)));
+    int id = 0;
+    int __mc_max13_a = next_id(&id);
+    int biggest = (__mc_max13_a>0 ? __mc_max13_a : 0);
+    int slots[3] = {1, 2, 3};
+    int* __mc_swap14_a = slot_at(slots, next_id(&id));
+    int __mc_swap14_tmp = (*__mc_swap14_a);
+    *__mc_swap14_a = tmp;
+    tmp = __mc_swap14_tmp;
+    int* __mc_bump_twice15_counter = &slots[next_id(&id)-3];
+    increment(&(*__mc_bump_twice15_counter));
+    increment(&(*__mc_bump_twice15_counter));
+    printf("%d %d %d %d %d\n", biggest, id, tmp, slots[0], slots[2]);
+    return 0;
+}
+
@@ -0,0 +1,42 @@
+#include <stdio.h>
+#include <stdlib.h>
+
+struct Buffer {
+    char* data;
+    size_t size;
+};
+
+// Plain pointers are non-null, so only `?*` parameters need checking
+size_t size_or_zero(Buffer?* buffer) {
+    if (buffer == NULL) {
+        return 0;
+    }
+    return buffer->size;
+}
+
+Buffer?* find(Buffer* buffers, size_t count, size_t size) {
+    for (size_t i = 0; i < count; i++) {
+        if (buffers[i].size == size) {
+            return &buffers[i];
+        }
+    }
+    return NULL;
+}
+
+int main() {
+    char?* memory = malloc(16);
+    if (memory == NULL) {
+        return 1;
+    }
+    Buffer buffers[2] = {{memory, 16}, {memory, 8}};
+    Buffer?* found = find(buffers, 2, 8);
+    printf("%zu\n", size_or_zero(found));
+    // Each branch of `?:` is checked with what its condition tells about the pointer
+    Buffer* chosen = found != NULL ? found : &buffers[0];
+    printf("%zu\n", chosen->size);
+    // A cast opts out of the check, e.g. for pointers known to be set
+    Buffer* first = (Buffer*)find(buffers, 2, 16);
+    printf("%zu\n", first->size);
+    free(memory);
+    return 0;
+}
@@ -0,0 +1,38 @@
+#include <stdio.h>
+#include <stdlib.h>
+typedef struct Buffer__struct {
+    char* data;
+    size_t size;
+} Buffer;
+size_t size_or_zero(Buffer* buffer) {
+    if (buffer==NULL) {
+        return 0;
+    }
+    return buffer->size;
+}
+
+Buffer* find(Buffer* buffers, size_t count, size_t size) {
+    for (size_t i = 0;i<count;i++) {
+        if (buffers[i].size==size) {
+            return &buffers[i];
+        }
+    }
+    return NULL;
+}
+
+int main() {
+    char* memory = malloc(16);
+    if (memory==NULL) {
+        return 1;
+    }
+    Buffer buffers[2] = {{memory, 16}, {memory, 8}};
+    Buffer* found = find(buffers, 2, 8);
+    printf("%zu\n", size_or_zero(found));
+    Buffer* chosen = found!=NULL ? found : &buffers[0];
+    printf("%zu\n", chosen->size);
+    Buffer* first = (Buffer*)find(buffers, 2, 16);
+    printf("%zu\n", first->size);
+    free(memory);
+    return 0;
+}
+
@@ -0,0 +1,59 @@
+#include <stdio.h>
+#include <stdlib.h>
+
+struct Item {
+    int value;
+};
+
+struct Slot {
+    int index;
+    Item?* item;
+};
+
+int?* find(int?* values, int count, int wanted) {
+    if (values == NULL) {
+        return NULL;
+    }
+    for (int i = 0; i < count; i++) {
+        if (values[i] == wanted) {
+            return &values[i];
+        }
+    }
+    return NULL;
+}
+
+int value_or(Slot?* slot, int fallback) {
+    if (slot == NULL) {
+        return fallback;
+    }
+    Item?* item = slot->item;
+    return item ? item->value : fallback;
+}
+
+int main() {
+    int values[3] = {1, 2, 3};
+    int?* found = find(values, 3, 2);
+    if (!found) {
+        return 1;
+    }
+    printf("%d\n", *found);
+    Item item = {5};
+    Slot slot = {0, &item};
+    Slot?* maybe = NULL;
+    if (maybe != NULL && maybe->index > 0) {
+        printf("positive\n");
+    }
+    if (maybe == NULL || maybe->item == NULL) {
+        printf("empty\n");
+    }
+    maybe = &slot;
+    let same = found;
+    printf("%d %d %d\n", *same, value_or(maybe, 7), value_or(NULL, 7));
+    let f = [found]() => int { return *found; };
+    printf("%d\n", f());
+    while (maybe) {
+        printf("%d\n", maybe->index);
+        maybe = NULL;
+    }
+    return 0;
+}
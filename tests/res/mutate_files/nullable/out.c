@@ -0,0 +1,68 @@
+#include "mc/closure.h"
+#include <stdio.h>
+#include <stdlib.h>
+typedef struct Item__struct {
+    int value;
+} Item;
+typedef struct Slot__struct {
+    int index;
+    Item* item;
+} Slot;
+int* find(int* values, int count, int wanted) {
+    if (values==NULL) {
+        return NULL;
+    }
+    for (int i = 0;i<count;i++) {
+        if (values[i]==wanted) {
+            return &values[i];
+        }
+    }
+    return NULL;
+}
+
+int value_or(Slot* slot, int fallback) {
+    if (slot==NULL) {
+        return fallback;
+    }
+    Item* item = slot->item;
+    return item ? item->value : fallback;
+}
+
+typedef struct main__lambda0__env__struct {
+    int* found;
+} main__lambda0__env;
+int main__lambda0(void* __mc_env);
+int main() {
+    int values[3] = {1, 2, 3};
+    int* found = find(values, 3, 2);
+    if (!found) {
+        return 1;
+    }
+    printf("%d\n", *found);
+    Item item = {5};
+    Slot slot = {0, &item};
+    Slot* maybe = NULL;
+    if (maybe!=NULL&&maybe->index>0) {
+        printf("positive\n");
+    }
+    if (maybe==NULL||maybe->item==NULL) {
+        printf("empty\n");
+    }
+    maybe = &slot;
+    int* same = found;
+    printf("%d %d %d\n", *same, value_or(maybe, 7), value_or(NULL, 7));
+    mod__mc__Closure f = (mod__mc__Closure){(mod__mc__ClosureFn)main__lambda0, &(main__lambda0__env){found}};
+    printf("%d\n", ((int (*)(void*))f.fn)(f.env));
+    while (maybe) {
+        printf("%d\n", maybe->index);
+        maybe = NULL;
+    }
+    return 0;
+}
+
+int main__lambda0(void* __mc_env) {
+    main__lambda0__env* __mc_self = __mc_env;
+    int* found = __mc_self->found;
+    return *found;
+}
+
@@ -0,0 +1,24 @@
+/**
+ * C code calling the generated overload macros with arguments that need the same implicit
+ * conversions miniC allows, compiled and run by `test_overload_macros_from_c`
+ */
+#define main overload_macros_main
+#include "out.c"
+#undef main
+
+int main(void) {
+    char c = 'A';
+    short s = -2;
+    unsigned char byte = 200;
+    float f = 0.5f;
+    mod__shape__Rect rect = {2.0, 4.0};
+    show(c);
+    show(s);
+    show(byte);
+    show(f);
+    show("literal", 1.5);
+    show("float", f);
+    show(mod__shape__area(&rect));
+    show(mod__shape__area(f));
+    return 0;
+}
@@ -0,0 +1,42 @@
+#include <stdio.h>
+
+module shape {
+    struct Rect {
+        double width;
+        double height;
+    };
+
+    double area(double side) {
+        return side * side;
+    }
+
+    double area(const shape::Rect* rect) {
+        return rect->width * rect->height;
+    }
+}
+
+void show(int value) {
+    printf("%d\n", value);
+}
+
+void show(double value) {
+    printf("%g\n", value);
+}
+
+void show(const char* label, double value) {
+    printf("%s %g\n", label, value);
+}
+
+// Only one overload, so it gets no macro
+void log_line(const char* line) {
+    printf("%s\n", line);
+}
+
+int main() {
+    shape::Rect rect = {2.0, 3.5};
+    show(shape::area(&rect));
+    show("square", shape::area(1.5));
+    show(7);
+    log_line("done");
+    return 0;
+}
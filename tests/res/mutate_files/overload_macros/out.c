@@ -0,0 +1,45 @@
+#include "mc/overload.h"
+#include <stdio.h>
+typedef struct Rect__struct {
+    double width;
+    double height;
+} mod__shape__Rect;
+double mod__shape__area__double(double side) {
+    return side*side;
+}
+
+double mod__shape__area__constmod__shape__Rectp(const mod__shape__Rect* rect) {
+    return rect->width*rect->height;
+}
+
+
+void show__int(int value) {
+    printf("%d\n", value);
+}
+
+void show__double(double value) {
+    printf("%g\n", value);
+}
+
+void show__constcharp_double(const char* label, double value) {
+    printf("%s %g\n", label, value);
+}
+
+void log_line(const char* line) {
+    printf("%s\n", line);
+}
+
+int main() {
+    mod__shape__Rect rect = {2.0, 3.5};
+    show__double(mod__shape__area__constmod__shape__Rectp(&rect));
+    show__constcharp_double("square", mod__shape__area__double(1.5));
+    show__int(7);
+    log_line("done");
+    return 0;
+}
+
+#define mod__shape__area(...) MC_OVERLOAD(mod__shape__area, __VA_ARGS__)
+#define mod__shape__area__mc_1(a1) _Generic((a1), double: mod__shape__area__double, const mod__shape__Rect*: mod__shape__area__constmod__shape__Rectp, float: mod__shape__area__double, mod__shape__Rect*: mod__shape__area__constmod__shape__Rectp)(a1)
+#define show(...) MC_OVERLOAD(show, __VA_ARGS__)
+#define show__mc_1(a1) _Generic((a1), int: show__int, double: show__double, _Bool: show__int, char: show__int, signed char: show__int, unsigned char: show__int, short: show__int, unsigned short: show__int, float: show__double)(a1)
+#define show__mc_2(a1, a2) _Generic((a1), const char*: _Generic((a2), double: show__constcharp_double, float: show__constcharp_double, default: mc_overload_no_match), char*: _Generic((a2), double: show__constcharp_double, float: show__constcharp_double, default: mc_overload_no_match))(a1, a2)
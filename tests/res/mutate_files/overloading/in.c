@@ -0,0 +1,74 @@
+#include <stdio.h>
+
+typedef enum {
+    RED,
+    GREEN
+} Color;
+
+struct Point {
+    int x;
+    int y;
+};
+
+void print(int value);
+void print(double value);
+void print(const char *value);
+void print(Point *p);
+void print(Color c);
+void print(int x, int y);
+
+void print(int value) {
+    printf("int %d\n", value);
+}
+
+void print(double value) {
+    printf("double %f\n", value);
+}
+
+void print(const char *value) {
+    printf("string %s\n", value);
+}
+
+void print(Point *p) {
+    printf("point %d %d\n", p->x, p->y);
+}
+
+void print(Color c) {
+    printf("color %d\n", c);
+}
+
+void print(int x, int y) {
+    printf("pair %d %d\n", x, y);
+}
+
+module geo {
+    int area(int w, int h) {
+        return w * h;
+    }
+
+    double area(double r) {
+        return 3.0 * r * r;
+    }
+}
+
+int main() {
+    char c = 'a';
+    float f = 1.5f;
+    Point p = {1, 2};
+    char *name = "miniC";
+    print(3);
+    print(c);
+    print(2.5);
+    print(f);
+    print("hello");
+    print(name);
+    print(&p);
+    print(GREEN);
+    print(1, 2);
+    let a = geo::area(2, 3);
+    let b = geo::area(1.5);
+    print(a);
+    print(b);
+    print(geo::area(geo::area(1, 2), 4));
+    return 0;
+}
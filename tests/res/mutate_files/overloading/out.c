@@ -0,0 +1,70 @@
+#include <stdio.h>
+typedef enum {
+    RED,
+    GREEN
+} Color;
+typedef struct Point__struct {
+    int x;
+    int y;
+} Point;
+void print__int(int value);
+void print__double(double value);
+void print__constcharp(const char* value);
+void print__Pointp(Point* p);
+void print__Color(Color c);
+void print__int_int(int x, int y);
+void print__int(int value) {
+    printf("int %d\n", value);
+}
+
+void print__double(double value) {
+    printf("double %f\n", value);
+}
+
+void print__constcharp(const char* value) {
+    printf("string %s\n", value);
+}
+
+void print__Pointp(Point* p) {
+    printf("point %d %d\n", p->x, p->y);
+}
+
+void print__Color(Color c) {
+    printf("color %d\n", c);
+}
+
+void print__int_int(int x, int y) {
+    printf("pair %d %d\n", x, y);
+}
+
+int mod__geo__area__int_int(int w, int h) {
+    return w*h;
+}
+
+double mod__geo__area__double(double r) {
+    return 3.0*r*r;
+}
+
+
+int main() {
+    char c = 'a';
+    float f = 1.5f;
+    Point p = {1, 2};
+    char* name = "miniC";
+    print__int(3);
+    print__int(c);
+    print__double(2.5);
+    print__double(f);
+    print__constcharp("hello");
+    print__constcharp(name);
+    print__Pointp(&p);
+    print__Color(GREEN);
+    print__int_int(1, 2);
+    int a = mod__geo__area__int_int(2, 3);
+    double b = mod__geo__area__double(1.5);
+    print__int(a);
+    print__double(b);
+    print__int(mod__geo__area__int_int(mod__geo__area__int_int(1, 2), 4));
+    return 0;
+}
+
@@ -0,0 +1,18 @@
+#include <stdio.h>
+
+char* pattern = r"^\d+\.\d+$";
+char* greeting = r#"She said "hi""#;
+char* columns = r"name	email";
+char* reset = r"[0m";
+char* alarm = r"Ready??!";
+
+void print_query(int id) {
+    char* query = """
+        SELECT name, "email"
+        FROM users
+        WHERE id = ?;	-- one row
+        -- ??= is not a #
+        """;
+    printf("%s -- %d\n", query, id);
+    puts("This literal is long enough that the generated C code writes it as adjacent literals over lines.");
+}
@@ -0,0 +1,16 @@
+#include <stdio.h>
+char* pattern = "^\\d+\\.\\d+$";
+char* greeting = "She said \"hi\"";
+char* columns = "name\temail";
+char* reset = "\033[0m";
+char* alarm = "Ready?\?!";
+void print_query(int id) {
+    char* query = "SELECT name, \"email\"\n"
+        "FROM users\n"
+        "WHERE id = ?;\t-- one row\n"
+        "-- ?\?= is not a #";
+    printf("%s -- %d\n", query, id);
+    puts("This literal is long enough that the generated C code writes it as adjacent "
+        "literals over lines.");
+}
+
@@ -0,0 +1,39 @@
+#include <stdio.h>
+#include <stdlib.h>
+
+struct City {
+    int zip;
+    char* name;
+};
+
+struct Address {
+    City?* city;
+};
+
+struct Person {
+    Address?* address;
+};
+
+Person?* lookup(Person* people, int count, int i) {
+    if (i < count) {
+        return &people[i];
+    }
+    return NULL;
+}
+
+int main() {
+    City paris = {75001, "Paris"};
+    Address home = {&paris};
+    Person people[2] = {{&home}, {NULL}};
+    Person?* first = &people[0];
+
+    int zip = first?->address?->city?->zip;
+    char* name = lookup(people, 2, 1)?->address?->city?->name ?? "unknown";
+    City?* city = lookup(people, 2, 0)?->address?->city;
+    printf("%d %s %d\n", zip, name, city?->zip ?? -1);
+    int count = 0;
+    while (lookup(people, 2, count)?->address) {
+        count++;
+    }
+    return count;
+}
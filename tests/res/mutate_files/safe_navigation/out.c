@@ -0,0 +1,44 @@
+#include <stdio.h>
+#include <stdlib.h>
+typedef struct City__struct {
+    int zip;
+    char* name;
+} City;
+typedef struct Address__struct {
+    City* city;
+} Address;
+typedef struct Person__struct {
+    Address* address;
+} Person;
+Person* lookup(Person* people, int count, int i) {
+    if (i<count) {
+        return &people[i];
+    }
+    return NULL;
+}
+
+int main() {
+    City paris = {75001, "Paris"};
+    Address home = {&paris};
+    Person people[2] = {{&home}, {NULL}};
+    Person* first = &people[0];
+    Address* __mc_nav0;
+    City* __mc_nav1;
+    int zip = (!first||!(__mc_nav0 = first->address)||!(__mc_nav1 = __mc_nav0->city) ? 0 : __mc_nav1->zip);
+    Person* __mc_nav2;
+    Address* __mc_nav3;
+    City* __mc_nav4;
+    char* __mc_nav5;
+    char* name = (!(__mc_nav2 = lookup(people, 2, 1))||!(__mc_nav3 = __mc_nav2->address)||!(__mc_nav4 = __mc_nav3->city)||!(__mc_nav5 = __mc_nav4->name) ? "unknown" : __mc_nav5);
+    Person* __mc_nav6;
+    Address* __mc_nav7;
+    City* city = (!(__mc_nav6 = lookup(people, 2, 0))||!(__mc_nav7 = __mc_nav6->address) ? 0 : __mc_nav7->city);
+    printf("%d %s %d\n", zip, name, (!city ? -1 : city->zip));
+    int count = 0;
+    Person* __mc_nav8;
+    while ((!(__mc_nav8 = lookup(people, 2, count)) ? 0 : __mc_nav8->address)) {
+        count++;
+    }
+    return count;
+}
+
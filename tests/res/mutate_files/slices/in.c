@@ -0,0 +1,38 @@
+#include <stdio.h>
+
+const int limits[3] = {10, 20, 30};
+
+int sum(int[] values) {
+    int total = 0;
+    for (int v in values) {
+        total += v;
+    }
+    return total;
+}
+
+void print_addresses(const int[] values) {
+    for (const int* v in values) {
+        printf("%d ", (int) (v - values.ptr));
+    }
+    printf("\n");
+}
+
+int main() {
+    int numbers[5] = {1, 2, 3, 4, 5};
+    int[] all = numbers;
+    int[] middle = numbers[1..4];
+    printf("%d %zu\n", sum(numbers), all.len);
+    printf("%d %zu\n", sum(middle), middle.len);
+    print_addresses(middle[1..3]);
+    for (int v in all[0..2]) {
+        printf("%d\n", v);
+    }
+    printf("%d %d\n", numbers[3], middle[2]);
+    const int[] bounds = limits[1..3];
+    print_addresses(bounds);
+    print_addresses((const int[]) numbers);
+    for (const int* limit in bounds) {
+        printf("%d\n", *limit);
+    }
+    return middle[0] - 2;
+}
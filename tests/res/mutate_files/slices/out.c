@@ -0,0 +1,79 @@
+#include "mc/slice.h"
+#include <stdio.h>
+const int limits[3] = {10, 20, 30};
+#ifndef mod__mc__Slice__int__defined
+#define mod__mc__Slice__int__defined
+typedef struct mod__mc__Slice__int__struct {
+    int* ptr;
+    size_t len;
+} mod__mc__Slice__int;
+static inline int* mod__mc__Slice__int__at(mod__mc__Slice__int s, size_t index, const char* source, const char* file, int line) {
+    return s.ptr+mod__mc__slice_check_index(index, s.len, source, file, line);
+}
+
+static inline mod__mc__Slice__int mod__mc__Slice__int__slice(mod__mc__Slice__int s, size_t start, size_t end, const char* source, const char* file, int line) {
+    mod__mc__slice_check_range(start, end, s.len, source, file, line);
+    return (mod__mc__Slice__int){s.ptr+start, end-start};
+}
+
+#endif
+int sum(mod__mc__Slice__int values) {
+    int total = 0;
+    for (size_t __mc_i_v = 0; __mc_i_v < values.len; __mc_i_v++) {
+        int v = values.ptr[__mc_i_v];
+        total += v;
+    }
+    return total;
+}
+
+#ifndef mod__mc__Slice__constint__defined
+#define mod__mc__Slice__constint__defined
+typedef struct mod__mc__Slice__constint__struct {
+    const int* ptr;
+    size_t len;
+} mod__mc__Slice__constint;
+static inline const int* mod__mc__Slice__constint__at(mod__mc__Slice__constint s, size_t index, const char* source, const char* file, int line) {
+    return s.ptr+mod__mc__slice_check_index(index, s.len, source, file, line);
+}
+
+static inline mod__mc__Slice__constint mod__mc__Slice__constint__slice(mod__mc__Slice__constint s, size_t start, size_t end, const char* source, const char* file, int line) {
+    mod__mc__slice_check_range(start, end, s.len, source, file, line);
+    return (mod__mc__Slice__constint){s.ptr+start, end-start};
+}
+
+static inline mod__mc__Slice__constint mod__mc__Slice__constint__from(mod__mc__Slice__int s) {
+    return (mod__mc__Slice__constint){s.ptr, s.len};
+}
+
+#endif
+void print_addresses(mod__mc__Slice__constint values) {
+    for (size_t __mc_i_v = 0; __mc_i_v < values.len; __mc_i_v++) {
+        const int* v = &values.ptr[__mc_i_v];
+        printf("%d ", (int)(v-values.ptr));
+    }
+    printf("\n");
+}
+
+int main() {
+    int numbers[5] = {1, 2, 3, 4, 5};
+    mod__mc__Slice__int all = (mod__mc__Slice__int){numbers, 5};
+    mod__mc__Slice__int middle = mod__mc__Slice__int__slice((mod__mc__Slice__int){numbers, 5}, 1, 4, "numbers[1..4] in main", __FILE__, __LINE__);
+    printf("%d %zu\n", sum((mod__mc__Slice__int){numbers, 5}), all.len);
+    printf("%d %zu\n", sum(middle), middle.len);
+    print_addresses(mod__mc__Slice__constint__from(mod__mc__Slice__int__slice(middle, 1, 3, "middle[1..3] in main", __FILE__, __LINE__)));
+    mod__mc__Slice__int __mc_slice0 = mod__mc__Slice__int__slice(all, 0, 2, "all[0..2] in main", __FILE__, __LINE__);
+    for (size_t __mc_i_v = 0; __mc_i_v < __mc_slice0.len; __mc_i_v++) {
+        int v = __mc_slice0.ptr[__mc_i_v];
+        printf("%d\n", v);
+    }
+    printf("%d %d\n", numbers[3], middle.ptr[2]);
+    mod__mc__Slice__constint bounds = mod__mc__Slice__constint__slice((mod__mc__Slice__constint){limits, 3}, 1, 3, "limits[1..3] in main", __FILE__, __LINE__);
+    print_addresses(bounds);
+    print_addresses((mod__mc__Slice__constint){numbers, 5});
+    for (size_t __mc_i_limit = 0; __mc_i_limit < bounds.len; __mc_i_limit++) {
+        const int* limit = &bounds.ptr[__mc_i_limit];
+        printf("%d\n", *limit);
+    }
+    return middle.ptr[0]-2;
+}
+
@@ -0,0 +1,34 @@
+#include <stdio.h>
+
+module bank {
+    void deposit(int* balance, int amount) {
+        *balance = *balance + amount;
+    }
+
+    test "deposit adds balance" {
+        int balance = 10;
+        bank::deposit(&balance, 5);
+        assert(balance == 15);
+    }
+}
+
+int square(int x) {
+    return x * x;
+}
+
+test "square" {
+    assert(square(3) == 9);
+    assert(square(-2) == 4);
+}
+
+// Fails on purpose, so that the runner's report and exit status can be checked
+test "square keeps the sign" {
+    assert(square(-3) == -9);
+    printf("unreachable\n");
+}
+
+// Replaced by the test runner
+int main() {
+    printf("%d\n", square(4));
+    return 0;
+}
@@ -0,0 +1,32 @@
+#include "mc/test.h"
+#include <stdio.h>
+void mod__bank__deposit(int* balance, int amount) {
+    *balance = *balance+amount;
+}
+
+static void mod__bank____mc_test0() {
+    int balance = 10;
+    mod__bank__deposit(&balance, 5);
+    mod__mc__test_assert(balance==15);
+}
+
+
+int square(int x) {
+    return x*x;
+}
+
+static void __mc_test1() {
+    mod__mc__test_assert(square(3)==9);
+    mod__mc__test_assert(square(-2)==4);
+}
+
+static void __mc_test2() {
+    mod__mc__test_assert(square(-3)==-9);
+    printf("unreachable\n");
+}
+
+static const mod__mc__Test __mc_tests[3] = {{"bank::deposit adds balance", mod__bank____mc_test0}, {"square", __mc_test1}, {"square keeps the sign", __mc_test2}};
+int main(int argc, char** argv) {
+    return mod__mc__run_tests(__mc_tests, 3, argc>1 ? argv[1] : NULL);
+}
+
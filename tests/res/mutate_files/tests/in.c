@@ -0,0 +1,29 @@
+#include <stdio.h>
+
+module bank {
+    void deposit(int* balance, int amount) {
+        *balance = *balance + amount;
+    }
+
+    test "deposit adds balance" {
+        int balance = 10;
+        bank::deposit(&balance, 5);
+        assert(balance == 15);
+    }
+}
+
+int square(int x) {
+    return x * x;
+}
+
+// Left out, since tests are only compiled in test mode
+test "square" {
+    assert(square(3) == 9);
+    assert(square(-2) == 4);
+}
+
+int main() {
+    int test = square(4);
+    printf("%d\n", test);
+    return 0;
+}
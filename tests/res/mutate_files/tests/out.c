@@ -0,0 +1,16 @@
+#include <stdio.h>
+void mod__bank__deposit(int* balance, int amount) {
+    *balance = *balance+amount;
+}
+
+
+int square(int x) {
+    return x*x;
+}
+
+int main() {
+    int test = square(4);
+    printf("%d\n", test);
+    return 0;
+}
+
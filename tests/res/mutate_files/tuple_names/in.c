@@ -0,0 +1,19 @@
+#include <stddef.h>
+
+typedef long size;
+typedef double t_int;
+
+// Both would be named `size_t_int` if the element names were only joined with `_`
+(size_t, int) first(size_t a, int b) {
+    return (a, b);
+}
+
+(size, t_int) second(size a, t_int b) {
+    return (a, b);
+}
+
+int main() {
+    let (count, flag) = first(3, 1);
+    let (length, ratio) = second(4, 0.5);
+    return (int)count + flag + (int)length + (int)ratio;
+}
@@ -0,0 +1,35 @@
+#include <stddef.h>
+typedef long size;
+typedef double t_int;
+#ifndef mod__mc__Tuple__6_size_t__3_int__defined
+#define mod__mc__Tuple__6_size_t__3_int__defined
+typedef struct mod__mc__Tuple__6_size_t__3_int__struct {
+    size_t _0;
+    int _1;
+} mod__mc__Tuple__6_size_t__3_int;
+#endif
+mod__mc__Tuple__6_size_t__3_int first(size_t a, int b) {
+    return (mod__mc__Tuple__6_size_t__3_int){a, b};
+}
+
+#ifndef mod__mc__Tuple__4_size__5_t_int__defined
+#define mod__mc__Tuple__4_size__5_t_int__defined
+typedef struct mod__mc__Tuple__4_size__5_t_int__struct {
+    size _0;
+    t_int _1;
+} mod__mc__Tuple__4_size__5_t_int;
+#endif
+mod__mc__Tuple__4_size__5_t_int second(size a, t_int b) {
+    return (mod__mc__Tuple__4_size__5_t_int){a, b};
+}
+
+int main() {
+    mod__mc__Tuple__6_size_t__3_int __mc_tuple0 = first(3, 1);
+    size_t count = __mc_tuple0._0;
+    int flag = __mc_tuple0._1;
+    mod__mc__Tuple__4_size__5_t_int __mc_tuple1 = second(4, 0.5);
+    size length = __mc_tuple1._0;
+    t_int ratio = __mc_tuple1._1;
+    return (int)count+flag+(int)length+(int)ratio;
+}
+
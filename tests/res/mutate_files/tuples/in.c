@@ -0,0 +1,65 @@
+#include <stdio.h>
+#include <stdbool.h>
+
+struct Point {
+    int x;
+    int y;
+};
+
+typedef (int, bool) Checked;
+
+(int, bool) divmod(int a, int b) {
+    if (b == 0) {
+        return (0, false);
+    }
+    return (a / b, true);
+}
+
+Checked parse_digit(char c) {
+    if (c >= '0' && c <= '9') {
+        return (c - '0', true);
+    }
+    return (0, false);
+}
+
+module geo {
+    (Point, double) farthest(Point *points, int count) {
+        Point best = points[0];
+        double best_distance = 0.0;
+        for (int i = 0; i < count; i++) {
+            double d = points[i].x * points[i].x + points[i].y * points[i].y;
+            if (d > best_distance) {
+                best = points[i];
+                best_distance = d;
+            }
+        }
+        return (best, best_distance);
+    }
+}
+
+((int, int), const char*) labeled_pair() {
+    (int, int) pair = {1, 2};
+    return (pair, "pair");
+}
+
+int main() {
+    let (q, ok) = divmod(17, 5);
+    printf("%d %d\n", q, ok);
+    let (_, failed) = divmod(1, 0);
+    printf("%d\n", failed);
+    let (digit, is_digit) = parse_digit('7');
+    printf("%d %d\n", digit, is_digit);
+    Point points[3] = {{1, 2}, {3, 4}, {0, 1}};
+    let (p, distance) = geo::farthest(points, 3);
+    printf("%d %d %f\n", p.x, p.y, distance);
+    let (pair, label) = labeled_pair();
+    printf("%s %d %d\n", label, pair._0, pair._1);
+    (int, bool) result = divmod(9, 3);
+    printf("%d %d\n", result._0, result._1);
+    closure(int) => (int, int) twice = [](int v) => (int, int) {
+        return (v, v * 2);
+    };
+    let (a, b) = twice(21);
+    printf("%d %d\n", a, b);
+    return 0;
+}
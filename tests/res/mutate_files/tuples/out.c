@@ -0,0 +1,105 @@
+#include "mc/closure.h"
+#include <stdio.h>
+#include <stdbool.h>
+typedef struct Point__struct {
+    int x;
+    int y;
+} Point;
+#ifndef mod__mc__Tuple__3_int__4_bool__defined
+#define mod__mc__Tuple__3_int__4_bool__defined
+typedef struct mod__mc__Tuple__3_int__4_bool__struct {
+    int _0;
+    bool _1;
+} mod__mc__Tuple__3_int__4_bool;
+#endif
+typedef mod__mc__Tuple__3_int__4_bool Checked;
+mod__mc__Tuple__3_int__4_bool divmod(int a, int b) {
+    if (b==0) {
+        return (mod__mc__Tuple__3_int__4_bool){0, 0};
+    }
+    return (mod__mc__Tuple__3_int__4_bool){a/b, 1};
+}
+
+Checked parse_digit(char c) {
+    if (c>='0'&&c<='9') {
+        return (Checked){c-'0', 1};
+    }
+    return (Checked){0, 0};
+}
+
+#ifndef mod__mc__Tuple__5_Point__6_double__defined
+#define mod__mc__Tuple__5_Point__6_double__defined
+typedef struct mod__mc__Tuple__5_Point__6_double__struct {
+    Point _0;
+    double _1;
+} mod__mc__Tuple__5_Point__6_double;
+#endif
+mod__mc__Tuple__5_Point__6_double mod__geo__farthest(Point* points, int count) {
+    Point best = points[0];
+    double best_distance = 0.0;
+    for (int i = 0;i<count;i++) {
+        double d = points[i].x*points[i].x+points[i].y*points[i].y;
+        if (d>best_distance) {
+            best = points[i];
+            best_distance = d;
+        }
+    }
+    return (mod__mc__Tuple__5_Point__6_double){best, best_distance};
+}
+
+
+#ifndef mod__mc__Tuple__3_int__3_int__defined
+#define mod__mc__Tuple__3_int__3_int__defined
+typedef struct mod__mc__Tuple__3_int__3_int__struct {
+    int _0;
+    int _1;
+} mod__mc__Tuple__3_int__3_int;
+#endif
+#ifndef mod__mc__Tuple__28_mod__mc__Tuple__3_int__3_int__10_constcharp__defined
+#define mod__mc__Tuple__28_mod__mc__Tuple__3_int__3_int__10_constcharp__defined
+typedef struct mod__mc__Tuple__28_mod__mc__Tuple__3_int__3_int__10_constcharp__struct {
+    mod__mc__Tuple__3_int__3_int _0;
+    const char* _1;
+} mod__mc__Tuple__28_mod__mc__Tuple__3_int__3_int__10_constcharp;
+#endif
+mod__mc__Tuple__28_mod__mc__Tuple__3_int__3_int__10_constcharp labeled_pair() {
+    mod__mc__Tuple__3_int__3_int pair = {1, 2};
+    return (mod__mc__Tuple__28_mod__mc__Tuple__3_int__3_int__10_constcharp){pair, "pair"};
+}
+
+mod__mc__Tuple__3_int__3_int main__lambda0(void* __mc_env, int v);
+int main() {
+    mod__mc__Tuple__3_int__4_bool __mc_tuple0 = divmod(17, 5);
+    int q = __mc_tuple0._0;
+    bool ok = __mc_tuple0._1;
+    printf("%d %d\n", q, ok);
+    mod__mc__Tuple__3_int__4_bool __mc_tuple1 = divmod(1, 0);
+    bool failed = __mc_tuple1._1;
+    printf("%d\n", failed);
+    Checked __mc_tuple2 = parse_digit('7');
+    int digit = __mc_tuple2._0;
+    bool is_digit = __mc_tuple2._1;
+    printf("%d %d\n", digit, is_digit);
+    Point points[3] = {{1, 2}, {3, 4}, {0, 1}};
+    mod__mc__Tuple__5_Point__6_double __mc_tuple3 = mod__geo__farthest(points, 3);
+    Point p = __mc_tuple3._0;
+    double distance = __mc_tuple3._1;
+    printf("%d %d %f\n", p.x, p.y, distance);
+    mod__mc__Tuple__28_mod__mc__Tuple__3_int__3_int__10_constcharp __mc_tuple4 = labeled_pair();
+    mod__mc__Tuple__3_int__3_int pair = __mc_tuple4._0;
+    const char* label = __mc_tuple4._1;
+    printf("%s %d %d\n", label, pair._0, pair._1);
+    mod__mc__Tuple__3_int__4_bool result = divmod(9, 3);
+    printf("%d %d\n", result._0, result._1);
+    mod__mc__Closure twice = (mod__mc__Closure){(mod__mc__ClosureFn)main__lambda0, 0};
+    mod__mc__Tuple__3_int__3_int __mc_tuple5 = ((mod__mc__Tuple__3_int__3_int (*)(void*, int))twice.fn)(twice.env, 21);
+    int a = __mc_tuple5._0;
+    int b = __mc_tuple5._1;
+    printf("%d %d\n", a, b);
+    return 0;
+}
+
+mod__mc__Tuple__3_int__3_int main__lambda0(void* __mc_env, int v) {
+    return (mod__mc__Tuple__3_int__3_int){v, v*2};
+}
+
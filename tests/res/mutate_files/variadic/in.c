@@ -0,0 +1,69 @@
+#include <stdarg.h>
+#include <stdio.h>
+
+module io {
+    int log(const char *level, const char *fmt, ...);
+
+    int log(const char *level, const char *fmt, ...) {
+        va_list args;
+        va_start(args, fmt);
+        printf("[%s] ", level);
+        int written = vfprintf(stdout, fmt, args);
+        va_end(args);
+        return written;
+    }
+}
+
+int sum(int count, ...) {
+    va_list args;
+    va_start(args, count);
+    int total = 0;
+    for (int i = 0; i < count; i++) {
+        total += va_arg(args, int);
+    }
+    va_end(args);
+    return total;
+}
+
+double average(int count, ...) {
+    va_list args;
+    va_start(args, count);
+    let total = 0.0;
+    for (int i = 0; i < count; i++) {
+        let value = va_arg(args, double);
+        total += value;
+    }
+    va_end(args);
+    return total / count;
+}
+
+void print_all(const char *first, ...) {
+    va_list args;
+    va_list copy;
+    va_start(args, first);
+    va_copy(copy, args);
+    for (const char *s = first; s != NULL; s = va_arg(args, const char *)) {
+        printf("%s\n", s);
+    }
+    va_end(copy);
+    va_end(args);
+}
+
+int main() {
+    io::log("info", "%d items, average %f\n", sum(3, 1, 2, 3), average(2, 1.5, 2.5));
+    print_all("a", "b", (char *)NULL);
+
+    int base = 10;
+    closure(int, ...) => int add_all = [base](int count, ...) => int {
+        va_list args;
+        va_start(args, count);
+        int total = base;
+        for (int i = 0; i < count; i++) {
+            total += va_arg(args, int);
+        }
+        va_end(args);
+        return total;
+    };
+    printf("%d\n", add_all(2, 5, 6));
+    return 0;
+}
@@ -0,0 +1,75 @@
+#include "mc/closure.h"
+#include <stdarg.h>
+#include <stdio.h>
+int mod__io__log(const char* level, const char* fmt, ...);
+int mod__io__log(const char* level, const char* fmt, ...) {
+    va_list args;
+    va_start(args, fmt);
+    printf("[%s] ", level);
+    int written = vfprintf(stdout, fmt, args);
+    va_end(args);
+    return written;
+}
+
+
+int sum(int count, ...) {
+    va_list args;
+    va_start(args, count);
+    int total = 0;
+    for (int i = 0;i<count;i++) {
+        total += va_arg(args, int);
+    }
+    va_end(args);
+    return total;
+}
+
+double average(int count, ...) {
+    va_list args;
+    va_start(args, count);
+    double total = 0.0;
+    for (int i = 0;i<count;i++) {
+        double value = va_arg(args, double);
+        total += value;
+    }
+    va_end(args);
+    return total/count;
+}
+
+void print_all(const char* first, ...) {
+    va_list args;
+    va_list copy;
+    va_start(args, first);
+    va_copy(copy, args);
+    for (const char* s = first;s!=NULL;s = va_arg(args, const char*)) {
+        printf("%s\n", s);
+    }
+    va_end(copy);
+    va_end(args);
+}
+
+typedef struct main__lambda0__env__struct {
+    int base;
+} main__lambda0__env;
+int main__lambda0(void* __mc_env, int count, ...);
+int main() {
+    mod__io__log("info", "%d items, average %f\n", sum(3, 1, 2, 3), average(2, 1.5, 2.5));
+    print_all("a", "b", (char*)NULL);
+    int base = 10;
+    mod__mc__Closure add_all = (mod__mc__Closure){(mod__mc__ClosureFn)main__lambda0, &(main__lambda0__env){base}};
+    printf("%d\n", ((int (*)(void*, int, ...))add_all.fn)(add_all.env, 2, 5, 6));
+    return 0;
+}
+
+int main__lambda0(void* __mc_env, int count, ...) {
+    main__lambda0__env* __mc_self = __mc_env;
+    int base = __mc_self->base;
+    va_list args;
+    va_start(args, count);
+    int total = base;
+    for (int i = 0;i<count;i++) {
+        total += va_arg(args, int);
+    }
+    va_end(args);
+    return total;
+}
+
@@ -5,9 +5,9 @@
  */
 #[cfg(test)]
 mod tests {
-    use std::{fs::{self, File}, io::Read, path::Path};
+    use std::{fs::{self, File}, io::Read, path::{Path, PathBuf}, process::Command};
     use anyhow::Result;
-    use mini_c::{codegen::full::CodeGenerator, parsing::parser::MyMiniCParser};
+    use mini_c::{analysis::consteval::DataModel, codegen::full::CodeGenerator, parsing::parser::MyMiniCParser};
 
     #[test]
     fn test_return_to_c() -> Result<()> {
@@ -36,9 +36,8 @@ mod tests {
     fn test_mutations() -> Result<()> {
         let paths = fs::read_dir("./tests/res/mutate_files").expect("Could not read test files");
         for entry in paths {
-            let mut generator: CodeGenerator = CodeGenerator::new();
-
             let path = entry.unwrap().path();
+            let mut generator: CodeGenerator = generator_for(&path);
             let in_path = path.join("in.c");
             let out_path = path.join("out.c");
             let out_content = read_file_to_string(&out_path).replace("\r\n", "\n");
@@ -51,10 +50,118 @@ mod tests {
                 let generated = generator.code_gen(program.unwrap())?;
                 assert_eq!(out_content, generated);
             }
+
+            // Fixtures that read other files list them in `out.d`, relative to the fixture
+            let depfile_path = path.join("out.d");
+            if depfile_path.exists() {
+                let prefix = format!("{}/", path.to_str().unwrap());
+                assert_eq!(read_file_to_string(&depfile_path).replace("\r\n", "\n"), generator.depfile("out.c").replace(&prefix, ""));
+            }
         }
         Ok(())
     }
 
+    #[test]
+    fn test_errors() -> Result<()> {
+        let paths = fs::read_dir("./tests/res/error_files").expect("Could not read test files");
+        for entry in paths {
+            let path = entry.unwrap().path();
+            let mut generator: CodeGenerator = generator_for(&path);
+            let in_path = path.join("in.c");
+            let error_path = path.join("error.txt");
+            let error_content = read_file_to_string(&error_path).replace("\r\n", "\n");
+
+            println!("Trying: {}", path.to_str().unwrap());
+            let error = match MyMiniCParser::parse_file(String::from(in_path.to_str().unwrap())) {
+                Err(e) => e,
+                Ok(program) => match generator.code_gen(program) {
+                    Err(e) => e.to_string(),
+                    Ok(generated) => panic!("Expected an error, but generated:\n{}", generated),
+                },
+            };
+            assert_eq!(error_content.trim_end(), error);
+        }
+        Ok(())
+    }
+
+    /// Compiles the test_mode fixture with the system's C compiler, if there is one, and checks
+    /// what the generated runner prints and returns
+    #[test]
+    fn test_generated_runner() -> Result<()> {
+        let Some(binary_path) = compile_c(Path::new("./tests/res/mutate_files/test_mode/out.c"), "test_mode") else {
+            return Ok(());
+        };
+
+        let all = Command::new(&binary_path).output()?;
+        let stdout = String::from_utf8(all.stdout)?;
+        assert_eq!(Some(1), all.status.code());
+        assert!(stdout.contains("PASS bank::deposit adds balance\n"));
+        assert!(stdout.contains("PASS square\n"));
+        assert!(stdout.contains("FAIL square keeps the sign\n"));
+        assert!(!stdout.contains("unreachable"));
+        assert!(stdout.ends_with("2 passed, 1 failed\n"));
+
+        let filtered = Command::new(&binary_path).arg("deposit").output()?;
+        let stdout = String::from_utf8(filtered.stdout)?;
+        assert_eq!(Some(0), filtered.status.code());
+        assert_eq!("PASS bank::deposit adds balance\n\n1 passed, 0 failed, 2 filtered out\n", stdout);
+        Ok(())
+    }
+
+    /// Compiles C code that calls the overload_macros fixture's macros with arguments that need
+    /// promotions or added qualifiers, and checks that each call reaches the right overload
+    #[test]
+    fn test_overload_macros_from_c() -> Result<()> {
+        let Some(binary_path) = compile_c(Path::new("./tests/res/mutate_files/overload_macros/caller.c"), "overload_macros") else {
+            return Ok(());
+        };
+        let output = Command::new(&binary_path).output()?;
+        assert!(output.status.success());
+        assert_eq!("65\n-2\n200\n0.5\nliteral 1.5\nfloat 0.5\n8\n0.25\n", String::from_utf8(output.stdout)?);
+        Ok(())
+    }
+
+    /// Compiles a C file with the system's C compiler, with warnings as errors and the file's
+    /// directory and `std` on the include path. Returns `None` if there is no C compiler.
+    fn compile_c(source: &Path, name: &str) -> Option<PathBuf> {
+        let binary_path = Path::new(env!("CARGO_TARGET_TMPDIR")).join(name);
+        let compiled = Command::new("cc")
+            .args(["-std=c11", "-Wall", "-Werror", "-I", "./std", "-I", source.parent().unwrap().to_str().unwrap()])
+            .args(["-o", binary_path.to_str().unwrap(), source.to_str().unwrap()])
+            .status();
+        match compiled {
+            Ok(status) => assert!(status.success(), "Could not compile {}", source.to_str().unwrap()),
+            Err(_) => {
+                println!("Skipping, since there is no C compiler");
+                return None;
+            }
+        }
+        Some(binary_path)
+    }
+
+    /// A generator configured by the fixture's `options` file, if it has one, which holds
+    /// one option per line
+    fn generator_for(path: &Path) -> CodeGenerator {
+        let mut generator = CodeGenerator::new();
+        let options_path = path.join("options");
+        if !options_path.exists() {
+            return generator;
+        }
+        for option in read_file_to_string(&options_path).lines().map(|l| l.trim()).filter(|l| !l.is_empty()) {
+            match option {
+                "non_null_pointers" => generator.set_non_null_pointers(true),
+                "overload_macros" => generator.set_overload_macros(true),
+                "bounds_checks" => generator.set_bounds_checks(true),
+                "test_mode" => generator.set_test_mode(true),
+                "data_model ilp32" => generator.set_data_model(DataModel::Ilp32),
+                "data_model llp64" => generator.set_data_model(DataModel::Llp64),
+                "data_model lp64" => generator.set_data_model(DataModel::Lp64),
+                _ => panic!("Unknown option '{}' in {}", option, options_path.to_str().unwrap()),
+            }
+        }
+        generator
+    }
+
     fn read_file_to_string(path: &Path) ->String {
         let mut file = File::open(path).unwrap();
         let mut content = String::new();